@@ -0,0 +1,74 @@
+//! Contract address checks: EVM addresses are `0x` + 40 hex digits, with a
+//! valid EIP-55 checksum if written in mixed case; other chains use base58.
+
+use crate::chains::ChainInfo;
+use crate::config::is_evm_address;
+use anyhow::Result;
+use sha3::{Digest, Keccak256};
+
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// `address` (`0x` + 40 hex digits) in EIP-55 mixed case.
+pub fn to_checksum(address: &str) -> String {
+    let hex = address[2..].to_ascii_lowercase();
+    let hash = Keccak256::digest(hex.as_bytes());
+    let mut checksummed = String::from("0x");
+    for (i, c) in hex.chars().enumerate() {
+        let nibble = if i % 2 == 0 {
+            hash[i / 2] >> 4
+        } else {
+            hash[i / 2] & 0x0f
+        };
+        checksummed.push(if nibble >= 8 {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        });
+    }
+    checksummed
+}
+
+/// A Solana-style address: 32 to 44 base58 characters.
+pub fn is_base58_address(address: &str) -> bool {
+    (32..=44).contains(&address.len()) && address.chars().all(|c| BASE58_ALPHABET.contains(c))
+}
+
+/// Check `address` against `chain`'s format: a 0x address on EVM chains,
+/// base58 on the rest. An all-lowercase or all-uppercase 0x address carries
+/// no checksum.
+pub fn check(chain: &ChainInfo, address: &str) -> Result<()> {
+    if chain.evm_chain_id.is_none() {
+        if !is_base58_address(address) {
+            anyhow::bail!("contract '{}' is not a base58 address", address);
+        }
+        return Ok(());
+    }
+    if !is_evm_address(address) {
+        anyhow::bail!("contract '{}' is not a 0x address", address);
+    }
+    let hex = &address[2..];
+    let mixed_case =
+        hex.chars().any(|c| c.is_ascii_uppercase()) && hex.chars().any(|c| c.is_ascii_lowercase());
+    if mixed_case {
+        let expected = to_checksum(address);
+        if expected != address {
+            anyhow::bail!(
+                "contract '{}' has an invalid EIP-55 checksum; did you mean '{}'?",
+                address,
+                expected
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The form sources see: 0x addresses lowercased, so every source matches
+/// them the same way. Base58 is case-sensitive and kept as written.
+pub fn normalize(address: &str) -> String {
+    let address = address.trim();
+    if is_evm_address(address) {
+        address.to_ascii_lowercase()
+    } else {
+        address.to_string()
+    }
+}
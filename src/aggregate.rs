@@ -1,82 +1,410 @@
-use crate::types::{AggregatedResult, TokenData};
+use crate::config::PriceBounds;
+use crate::types::{AggregatedResult, SourceWeight, TokenData, UnitKey};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-const DEVIATION_THRESHOLD: f64 = 0.03; // 3%
+/// Default cross-source tolerance when neither config nor the unit sets one.
+pub const DEFAULT_DEVIATION_THRESHOLD: f64 = 0.01; // 1%
 
-pub fn aggregate(unit_index: u32, data: Vec<TokenData>) -> AggregatedResult {
+/// How the per-source prices are reduced to one central price.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationStrategy {
+    #[default]
+    Mean,
+    Median,
+    /// Mean without the single highest and lowest price (3+ sources).
+    TrimmedMean,
+    /// Mean weighted by 24h volume, else liquidity, else equal weights.
+    Weighted,
+}
+
+/// What a `weighted` average weighted the sources by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightBasis {
+    Volume,
+    Liquidity,
+    Equal,
+}
+
+impl AggregationStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Mean => "mean",
+            Self::Median => "median",
+            Self::TrimmedMean => "trimmed_mean",
+            Self::Weighted => "weighted",
+        }
+    }
+
+    /// Central price of `data`; `Weighted` also returns the weights it used.
+    fn central(self, data: &[&TokenData]) -> (f64, Vec<SourceWeight>) {
+        if self == Self::Weighted {
+            return weighted_mean(data);
+        }
+        let prices: Vec<f64> = data.iter().map(|d| d.price_usd).collect();
+        (self.central_value(&prices), Vec::new())
+    }
+
+    /// Central value of non-empty `values`; `Weighted` has nothing to weigh
+    /// plain values by and takes the mean.
+    pub fn central_value(self, values: &[f64]) -> f64 {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let n = sorted.len();
+        match self {
+            Self::Median => median(&sorted),
+            Self::TrimmedMean if n >= 3 => mean(&sorted[1..n - 1]),
+            _ => mean(&sorted),
+        }
+    }
+}
+
+/// Weights by volume when every source reports it, else by liquidity when
+/// every source reports that, else equally. Mixing the two would compare
+/// unlike quantities.
+fn weighted_mean(data: &[&TokenData]) -> (f64, Vec<SourceWeight>) {
+    let all_positive = |f: fn(&TokenData) -> Option<f64>| -> Option<Vec<f64>> {
+        data.iter()
+            .map(|d| f(d).filter(|v| *v > 0.0))
+            .collect::<Option<Vec<f64>>>()
+    };
+    let (basis, raw) = match all_positive(|d| d.volume_24h) {
+        Some(w) => (WeightBasis::Volume, w),
+        None => match all_positive(|d| d.liquidity) {
+            Some(w) => (WeightBasis::Liquidity, w),
+            None => (WeightBasis::Equal, vec![1.0; data.len()]),
+        },
+    };
+    let total: f64 = raw.iter().sum();
+    let weights: Vec<SourceWeight> = data
+        .iter()
+        .zip(&raw)
+        .map(|(d, w)| SourceWeight {
+            source: d.source.clone(),
+            weight: w / total,
+            basis,
+        })
+        .collect();
+    let price = data
+        .iter()
+        .zip(&weights)
+        .map(|(d, w)| d.price_usd * w.weight)
+        .sum();
+    (price, weights)
+}
+
+/// Middle of an already-sorted, non-empty slice.
+fn median(sorted: &[f64]) -> f64 {
+    let n = sorted.len();
+    if n.is_multiple_of(2) {
+        (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0
+    } else {
+        sorted[n / 2]
+    }
+}
+
+/// Per-unit knobs for [`aggregate`], resolved from config.
+#[derive(Debug, Clone)]
+pub struct AggregateSettings {
+    /// Largest fraction any surviving source may differ from the central
+    /// price before the unit is marked invalid.
+    pub deviation_threshold: f64,
+    pub strategy: AggregationStrategy,
+    /// Sources further than this fraction from the median are dropped
+    /// before the cross-check.
+    pub outlier_threshold: f64,
+    /// Fewest sources that must survive outlier rejection.
+    pub min_sources: usize,
+    /// Quotes whose timestamp is older than this are dropped first.
+    pub max_age_secs: Option<u64>,
+    /// Quotes outside these USD bounds are dropped as implausible, and a
+    /// central price outside them invalidates the unit.
+    pub bounds: PriceBounds,
+    /// Symbol every source that reports one should give.
+    pub expected_symbol: Option<String>,
+    /// Invalidate the unit on a symbol mismatch instead of only warning.
+    pub strict_identity: bool,
+}
+
+/// `key` names the unit or reference in logs and in the result. `now` is
+/// compared against each quote's timestamp for `max_age_secs`.
+pub fn aggregate(
+    key: &UnitKey,
+    data: Vec<TokenData>,
+    settings: AggregateSettings,
+    now: DateTime<Utc>,
+) -> AggregatedResult {
+    let AggregateSettings {
+        deviation_threshold,
+        strategy,
+        outlier_threshold,
+        min_sources,
+        max_age_secs,
+        bounds,
+        expected_symbol,
+        strict_identity,
+    } = settings;
+    let (unit_index, reference) = (key.index(), key.reference());
     let name = data.first().map(|d| d.name.clone()).unwrap_or_default();
     let contract = data.first().map(|d| d.contract.clone()).unwrap_or_default();
-    let sources: Vec<String> = data.iter().map(|d| d.source.clone()).collect();
+    let age_secs = |d: &TokenData| (now - d.timestamp).num_seconds();
 
-    if data.is_empty() {
-        return AggregatedResult {
+    let (fresh, stale): (Vec<&TokenData>, Vec<&TokenData>) = match max_age_secs {
+        Some(max) => data.iter().partition(|d| age_secs(d) <= max as i64),
+        None => (data.iter().collect(), Vec::new()),
+    };
+    for d in &stale {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            source = %d.source,
+            age_secs = age_secs(d),
+            max_age_secs = max_age_secs.unwrap_or_default(),
+            "dropping stale quote"
+        );
+    }
+
+    let (fresh, implausible): (Vec<&TokenData>, Vec<&TokenData>) = fresh
+        .into_iter()
+        .partition(|d| bounds.contains(d.price_usd));
+    for d in &implausible {
+        warn!(
             unit_index,
+            reference,
+            unit = %name,
+            source = %d.source,
+            price_usd = d.price_usd,
+            "dropping price outside configured bounds"
+        );
+    }
+
+    let median = (fresh.len() >= 3)
+        .then(|| {
+            let mut prices: Vec<f64> = fresh.iter().map(|d| d.price_usd).collect();
+            prices.sort_by(f64::total_cmp);
+            median(&prices)
+        })
+        .filter(|median| *median > 0.0);
+    let (survivors, rejected): (Vec<&TokenData>, Vec<&TokenData>) = match median {
+        // Fewer than 3 quotes give no meaningful median, and a zero one no
+        // relative distance; leave it to the cross-check.
+        None => (fresh.clone(), Vec::new()),
+        Some(median) => fresh
+            .iter()
+            .partition(|d| (d.price_usd - median).abs() / median <= outlier_threshold),
+    };
+    for d in &rejected {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            source = %d.source,
+            price_usd = d.price_usd,
+            threshold_pct = outlier_threshold * 100.0,
+            "rejecting outlier: too far from median"
+        );
+    }
+    let sources: Vec<String> = survivors.iter().map(|d| d.source.clone()).collect();
+    let rejected_sources: Vec<String> = rejected.iter().map(|d| d.source.clone()).collect();
+
+    if survivors.is_empty() {
+        let reason = if data.is_empty() {
+            "no source data"
+        } else if stale.len() == data.len() {
+            "stale data"
+        } else if fresh.is_empty() {
+            "price outside configured bounds"
+        } else {
+            "sources disagree"
+        };
+        return AggregatedResult {
+            unit_index: unit_index.unwrap_or(0),
+            reference_id: reference.map(String::from),
             name,
             contract,
             avg_price_usd: 0.0,
             volume_24h: None,
             price_change_24h: None,
             sources,
+            rejected_sources,
             valid: false,
+            reason: Some(reason.to_string()),
+            strategy,
+            weights: Vec::new(),
+            oldest_quote_age_secs: None,
+            cached_at: None,
             per_source: data,
         };
     }
 
-    let avg_price: f64 = data.iter().map(|d| d.price_usd).sum::<f64>() / data.len() as f64;
+    let (avg_price, weights) = strategy.central(&survivors);
+    let oldest_quote_age_secs = survivors.iter().map(|d| age_secs(d)).max();
 
-    let valid = if data.len() < 2 {
+    let reason = if avg_price <= 0.0 || !avg_price.is_finite() {
         warn!(
-            "unit {} ({}): only {} source — skipping cross-check",
             unit_index,
-            name,
-            data.len()
+            reference,
+            unit = %name,
+            strategy = strategy.as_str(),
+            price_usd = avg_price,
+            "aggregated price is not positive — invalid"
+        );
+        Some("price not positive".to_string())
+    } else if !bounds.contains(avg_price) {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            strategy = strategy.as_str(),
+            price_usd = avg_price,
+            "aggregated price outside configured bounds — invalid"
+        );
+        Some("price outside configured bounds".to_string())
+    } else if survivors.len() < min_sources {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            sources = survivors.len(),
+            min_sources,
+            "too few sources left — invalid"
+        );
+        Some(format!("fewer than {} sources", min_sources))
+    } else if survivors.len() < 2 && !rejected.is_empty() {
+        // Several sources answered and disagreed; the one left standing
+        // is not a single-source result.
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            rejected = rejected.len(),
+            "outlier rejection left one source — invalid"
         );
-        true
+        Some("sources disagree".to_string())
+    } else if survivors.len() < 2 {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            sources = survivors.len(),
+            "single source — skipping cross-check"
+        );
+        None
     } else {
-        let all_within = data.iter().all(|d| {
+        let all_within = survivors.iter().all(|d| {
             let deviation = (d.price_usd - avg_price).abs() / avg_price;
-            if deviation > DEVIATION_THRESHOLD {
+            if deviation > deviation_threshold {
                 warn!(
-                    "unit {} ({}): source '{}' price {:.8} deviates {:.2}% from average {:.8}",
                     unit_index,
-                    name,
-                    d.source,
-                    d.price_usd,
-                    deviation * 100.0,
-                    avg_price,
+                    reference,
+                    unit = %name,
+                    source = %d.source,
+                    price_usd = d.price_usd,
+                    deviation_pct = deviation * 100.0,
+                    threshold_pct = deviation_threshold * 100.0,
+                    strategy = strategy.as_str(),
+                    aggregate_usd = avg_price,
+                    "source price deviates"
                 );
             }
-            deviation <= DEVIATION_THRESHOLD
+            deviation <= deviation_threshold
         });
         if all_within {
             info!(
-                "unit {} ({}): all {} sources within 1% — valid (avg {:.8})",
                 unit_index,
-                name,
-                data.len(),
-                avg_price
+                reference,
+                unit = %name,
+                sources = survivors.len(),
+                threshold_pct = deviation_threshold * 100.0,
+                strategy = strategy.as_str(),
+                price_usd = avg_price,
+                "all sources agree — valid"
             );
+            None
+        } else {
+            Some("sources disagree".to_string())
         }
-        all_within
     };
 
-    let volume_24h = aggregate_optional(&data, |d| d.volume_24h);
-    let price_change_24h = aggregate_optional(&data, |d| d.price_change_24h);
+    let identity = identity_mismatch(&fresh, expected_symbol.as_deref());
+    if let Some((_, symbols)) = &identity {
+        warn!(
+            unit_index,
+            reference,
+            unit = %name,
+            symbols = %symbols,
+            expected_symbol = expected_symbol.as_deref().unwrap_or("—"),
+            strict_identity,
+            "sources report a different token"
+        );
+    }
+    let reason = reason.or_else(|| {
+        identity
+            .filter(|_| strict_identity)
+            .map(|(reason, _)| reason.to_string())
+    });
+
+    let volume_24h = aggregate_optional(&survivors, |d| d.volume_24h);
+    let price_change_24h = aggregate_optional(&survivors, |d| d.price_change_24h);
 
     AggregatedResult {
-        unit_index,
+        unit_index: unit_index.unwrap_or(0),
+        reference_id: reference.map(String::from),
         name,
         contract,
         avg_price_usd: avg_price,
         volume_24h,
         price_change_24h,
         sources,
-        valid,
+        rejected_sources,
+        valid: reason.is_none(),
+        reason,
+        strategy,
+        weights,
+        oldest_quote_age_secs,
+        cached_at: None,
         per_source: data,
     }
 }
 
-fn aggregate_optional(data: &[TokenData], f: fn(&TokenData) -> Option<f64>) -> Option<f64> {
+/// Why `quotes` may not all be the same token: the symbols they report
+/// differ from each other (case-insensitively) or from `expected`. Returns
+/// the reason and the `source=SYMBOL` list; quotes without a symbol are
+/// left out.
+fn identity_mismatch(
+    quotes: &[&TokenData],
+    expected: Option<&str>,
+) -> Option<(&'static str, String)> {
+    let reported: Vec<(&str, &str)> = quotes
+        .iter()
+        .filter_map(|d| Some((d.source.as_str(), d.reported_symbol.as_deref()?)))
+        .collect();
+    let (_, first) = reported.first()?;
+    let reason =
+        if expected.is_some_and(|e| reported.iter().any(|(_, s)| !s.eq_ignore_ascii_case(e))) {
+            "symbol differs from expected_symbol"
+        } else if reported.iter().any(|(_, s)| !s.eq_ignore_ascii_case(first)) {
+            "sources disagree on symbol"
+        } else {
+            return None;
+        };
+    let symbols = reported
+        .iter()
+        .map(|(source, symbol)| format!("{}={}", source, symbol))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some((reason, symbols))
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn aggregate_optional(data: &[&TokenData], f: fn(&TokenData) -> Option<f64>) -> Option<f64> {
     let vals: Vec<f64> = data.iter().filter_map(|d| f(d)).collect();
     if vals.is_empty() {
         None
@@ -84,3 +412,428 @@ fn aggregate_optional(data: &[TokenData], f: fn(&TokenData) -> Option<f64>) -> O
         Some(vals.iter().sum::<f64>() / vals.len() as f64)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use proptest::prelude::*;
+
+    fn quote(source: &str, price_usd: f64, timestamp: DateTime<Utc>) -> TokenData {
+        TokenData {
+            name: "TKN".to_string(),
+            chain: "ethereum".to_string(),
+            contract: "0x6c6ee5e31d828de241282b9606c8e98ea48526e2".to_string(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: None,
+            price_change_24h: None,
+            source: source.to_string(),
+            timestamp,
+            reported_symbol: None,
+            reported_name: None,
+        }
+    }
+
+    fn settings(strategy: AggregationStrategy, deviation_threshold: f64) -> AggregateSettings {
+        AggregateSettings {
+            deviation_threshold,
+            strategy,
+            outlier_threshold: 0.05,
+            min_sources: 1,
+            max_age_secs: None,
+            bounds: PriceBounds::default(),
+            expected_symbol: None,
+            strict_identity: false,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    fn strategies() -> impl Strategy<Value = AggregationStrategy> {
+        prop_oneof![
+            Just(AggregationStrategy::Mean),
+            Just(AggregationStrategy::Median),
+            Just(AggregationStrategy::TrimmedMean),
+            Just(AggregationStrategy::Weighted),
+        ]
+    }
+
+    /// Quotes from distinct sources at positive prices spanning several
+    /// orders of magnitude, some with volume for `weighted`.
+    fn quotes() -> impl Strategy<Value = Vec<TokenData>> {
+        prop::collection::vec((1e-9f64..1e6, prop::option::of(1.0f64..1e9)), 0..8).prop_map(
+            |entries| {
+                entries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (price, volume))| TokenData {
+                        volume_24h: volume,
+                        ..quote(&format!("s{}", i), price, now())
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    fn key() -> UnitKey {
+        UnitKey::Index(1)
+    }
+
+    /// Everything but `per_source`, for comparing results of different inputs.
+    fn outcome(result: &AggregatedResult) -> serde_json::Value {
+        let mut value = serde_json::to_value(result).unwrap();
+        value.as_object_mut().unwrap().remove("per_source");
+        value
+    }
+
+    proptest! {
+        #[test]
+        fn price_lies_within_included_quotes(
+            data in quotes(),
+            strategy in strategies(),
+            threshold in 0.0f64..1.0,
+        ) {
+            let result = aggregate(&key(), data.clone(), settings(strategy, threshold), now());
+            let included: Vec<f64> = data
+                .iter()
+                .filter(|d| result.sources.contains(&d.source))
+                .map(|d| d.price_usd)
+                .collect();
+            prop_assume!(!included.is_empty());
+            let min = included.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = included.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            let slack = max * 1e-12;
+            prop_assert!(
+                result.avg_price_usd >= min - slack && result.avg_price_usd <= max + slack,
+                "{} outside [{}, {}]",
+                result.avg_price_usd,
+                min,
+                max
+            );
+        }
+
+        #[test]
+        fn validity_is_monotone_in_the_threshold(
+            data in quotes(),
+            strategy in strategies(),
+            low in 0.0f64..1.0,
+            extra in 0.0f64..1.0,
+        ) {
+            let tight = aggregate(&key(), data.clone(), settings(strategy, low), now());
+            let loose = aggregate(&key(), data, settings(strategy, (low + extra).min(1.0)), now());
+            prop_assert!(!tight.valid || loose.valid);
+        }
+
+        #[test]
+        fn excluded_quotes_do_not_change_the_result(
+            data in quotes(),
+            stale_prices in prop::collection::vec(1e-9f64..1e6, 1..4),
+            strategy in strategies(),
+        ) {
+            let mut settings = settings(strategy, 0.03);
+            settings.max_age_secs = Some(60);
+            let old = now() - chrono::Duration::seconds(3600);
+            let mut with_stale = data.clone();
+            with_stale.extend(
+                stale_prices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| quote(&format!("stale{}", i), *p, old)),
+            );
+            let without = aggregate(&key(), data.clone(), settings.clone(), now());
+            let with = aggregate(&key(), with_stale, settings, now());
+            if data.is_empty() {
+                prop_assert_eq!(with.reason.as_deref(), Some("stale data"));
+            } else {
+                prop_assert_eq!(outcome(&without), outcome(&with));
+            }
+        }
+
+        #[test]
+        fn per_source_is_the_input(data in quotes(), strategy in strategies()) {
+            let result = aggregate(&key(), data.clone(), settings(strategy, 0.03), now());
+            prop_assert_eq!(
+                serde_json::to_value(&result.per_source).unwrap(),
+                serde_json::to_value(&data).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn source_ten_percent_off_is_rejected_and_the_unit_stays_valid() {
+        let data = vec![
+            quote("a", 1.0, now()),
+            quote("b", 1.001, now()),
+            quote("c", 1.1, now()),
+        ];
+
+        let result = aggregate(
+            &key(),
+            data,
+            settings(AggregationStrategy::Mean, 0.01),
+            now(),
+        );
+
+        assert!(result.valid, "{:?}", result.reason);
+        assert_eq!(result.sources, ["a", "b"]);
+        assert_eq!(result.rejected_sources, ["c"]);
+        assert!((result.avg_price_usd - 1.0005).abs() < 1e-12);
+        assert_eq!(result.per_source.len(), 3);
+    }
+
+    #[test]
+    fn rejection_leaving_too_few_sources_is_invalid() {
+        let data = vec![
+            quote("a", 1.0, now()),
+            quote("b", 1.001, now()),
+            quote("c", 1.1, now()),
+        ];
+        let settings = AggregateSettings {
+            min_sources: 3,
+            ..settings(AggregationStrategy::Mean, 0.01)
+        };
+
+        let result = aggregate(&key(), data, settings, now());
+
+        assert!(!result.valid);
+        assert_eq!(result.reason.as_deref(), Some("fewer than 3 sources"));
+        assert_eq!(result.rejected_sources, ["c"]);
+    }
+
+    #[test]
+    fn one_survivor_of_outlier_rejection_is_invalid() {
+        let data = vec![
+            quote("a", 1.0, now()),
+            quote("b", 1.5, now()),
+            quote("c", 2.0, now()),
+        ];
+
+        let result = aggregate(
+            &key(),
+            data,
+            settings(AggregationStrategy::Mean, 0.01),
+            now(),
+        );
+
+        assert!(!result.valid);
+        assert_eq!(result.reason.as_deref(), Some("sources disagree"));
+        assert_eq!(result.sources, ["b"]);
+        assert_eq!(result.rejected_sources, ["a", "c"]);
+    }
+
+    #[test]
+    fn zero_median_rejects_nothing() {
+        let data = vec![
+            quote("a", 0.0, now()),
+            quote("b", 0.0, now()),
+            quote("c", 1.0, now()),
+        ];
+
+        let result = aggregate(
+            &key(),
+            data,
+            settings(AggregationStrategy::Mean, 0.01),
+            now(),
+        );
+
+        assert!(!result.valid);
+        assert!(result.rejected_sources.is_empty());
+        assert_eq!(result.sources, ["a", "b", "c"]);
+        assert_eq!(result.reason.as_deref(), Some("sources disagree"));
+    }
+
+    #[test]
+    fn zero_prices_are_invalid() {
+        let data = vec![
+            quote("a", 0.0, now()),
+            quote("b", 0.0, now()),
+            quote("c", 0.0, now()),
+        ];
+
+        let result = aggregate(
+            &key(),
+            data,
+            settings(AggregationStrategy::Median, 0.01),
+            now(),
+        );
+
+        assert!(!result.valid);
+        assert!(result.rejected_sources.is_empty());
+        assert_eq!(result.reason.as_deref(), Some("price not positive"));
+    }
+
+    #[test]
+    fn empty_input_is_invalid_with_zero_price() {
+        for strategy in [
+            AggregationStrategy::Mean,
+            AggregationStrategy::Median,
+            AggregationStrategy::TrimmedMean,
+            AggregationStrategy::Weighted,
+        ] {
+            let result = aggregate(&key(), Vec::new(), settings(strategy, 0.03), now());
+            assert!(!result.valid);
+            assert_eq!(result.avg_price_usd, 0.0);
+            assert_eq!(result.reason.as_deref(), Some("no source data"));
+        }
+    }
+
+    fn reporting(source: &str, symbol: Option<&str>) -> TokenData {
+        TokenData {
+            reported_symbol: symbol.map(str::to_string),
+            ..quote(source, 1.0, now())
+        }
+    }
+
+    fn mismatch(quotes: &[TokenData], expected: Option<&str>) -> Option<(&'static str, String)> {
+        identity_mismatch(&quotes.iter().collect::<Vec<_>>(), expected)
+    }
+
+    #[test]
+    fn sources_agreeing_on_the_symbol_pass() {
+        let quotes = [reporting("a", Some("USDC")), reporting("b", Some("usdc"))];
+
+        assert_eq!(mismatch(&quotes, None), None);
+        assert_eq!(mismatch(&quotes, Some("Usdc")), None);
+    }
+
+    #[test]
+    fn sources_disagreeing_on_the_symbol_are_reported() {
+        let quotes = [reporting("a", Some("USDC")), reporting("b", Some("USDC.e"))];
+
+        assert_eq!(
+            mismatch(&quotes, None),
+            Some(("sources disagree on symbol", "a=USDC, b=USDC.e".to_string()))
+        );
+        assert_eq!(
+            mismatch(&quotes[..1], Some("DAI")),
+            Some(("symbol differs from expected_symbol", "a=USDC".to_string()))
+        );
+    }
+
+    #[test]
+    fn quotes_without_a_symbol_are_left_out_of_the_check() {
+        let quotes = [
+            reporting("a", Some("USDC")),
+            reporting("b", None),
+            reporting("c", Some("usdc")),
+        ];
+
+        assert_eq!(mismatch(&quotes, Some("USDC")), None);
+        assert_eq!(
+            mismatch(&[reporting("a", None), reporting("b", None)], Some("USDC")),
+            None
+        );
+    }
+
+    #[test]
+    fn strict_identity_makes_a_symbol_mismatch_invalid() {
+        let data = vec![reporting("a", Some("USDC")), reporting("b", Some("USDC.e"))];
+        let lenient = settings(AggregationStrategy::Mean, 0.01);
+        let strict = AggregateSettings {
+            strict_identity: true,
+            ..settings(AggregationStrategy::Mean, 0.01)
+        };
+
+        let warned = aggregate(&key(), data.clone(), lenient, now());
+        let refused = aggregate(&key(), data, strict, now());
+
+        assert!(warned.valid, "{:?}", warned.reason);
+        assert!(!refused.valid);
+        assert_eq!(
+            refused.reason.as_deref(),
+            Some("sources disagree on symbol")
+        );
+        assert_eq!(refused.avg_price_usd, 1.0);
+
+        let unreported = vec![reporting("a", Some("USDC")), reporting("b", None)];
+        let strict = AggregateSettings {
+            strict_identity: true,
+            expected_symbol: Some("USDC".to_string()),
+            ..settings(AggregationStrategy::Mean, 0.01)
+        };
+        assert!(aggregate(&key(), unreported, strict, now()).valid);
+    }
+
+    #[test]
+    fn quotes_go_stale_as_the_clock_passes_max_age() {
+        let clock = ManualClock::new(now());
+        let data = vec![
+            quote("a", 1.0, now()),
+            quote("b", 1.002, now() - chrono::Duration::seconds(10)),
+        ];
+        let mut settings = settings(AggregationStrategy::Mean, 0.01);
+        settings.max_age_secs = Some(60);
+        let run =
+            |clock: &ManualClock| aggregate(&key(), data.clone(), settings.clone(), clock.now());
+
+        clock.advance(chrono::Duration::seconds(50));
+        let result = run(&clock);
+        assert_eq!(result.sources, ["a", "b"]);
+        assert_eq!(result.oldest_quote_age_secs, Some(60));
+
+        clock.advance(chrono::Duration::seconds(1));
+        let result = run(&clock);
+        assert!(result.valid, "{:?}", result.reason);
+        assert_eq!(result.sources, ["a"]);
+
+        clock.advance(chrono::Duration::seconds(10));
+        let result = run(&clock);
+        assert!(!result.valid);
+        assert_eq!(result.reason.as_deref(), Some("stale data"));
+    }
+
+    #[test]
+    fn three_percent_spread_needs_a_looser_threshold_than_the_default() {
+        let data = vec![quote("a", 1.0, now()), quote("b", 1.03, now())];
+
+        let default = settings(AggregationStrategy::Mean, DEFAULT_DEVIATION_THRESHOLD);
+        let result = aggregate(&key(), data.clone(), default, now());
+        assert!(!result.valid);
+        assert_eq!(result.reason.as_deref(), Some("sources disagree"));
+        assert!(result.rejected_sources.is_empty());
+
+        let loose = settings(AggregationStrategy::Mean, 0.05);
+        let result = aggregate(&key(), data, loose, now());
+        assert!(result.valid, "{:?}", result.reason);
+        assert_eq!(result.sources, ["a", "b"]);
+    }
+
+    #[test]
+    fn weighted_without_volume_falls_back_to_liquidity_then_equal_weights() {
+        let with_liquidity = |source: &str, price: f64, liquidity: Option<f64>| TokenData {
+            liquidity,
+            ..quote(source, price, now())
+        };
+        let weighted = || settings(AggregationStrategy::Weighted, 0.01);
+
+        // Volume from only one source is not enough to weigh by volume.
+        let data = vec![
+            TokenData {
+                volume_24h: Some(5e6),
+                ..with_liquidity("a", 1.0, Some(3e6))
+            },
+            with_liquidity("b", 1.004, Some(1e6)),
+        ];
+        let result = aggregate(&key(), data, weighted(), now());
+        assert!(result.valid, "{:?}", result.reason);
+        assert!(result
+            .weights
+            .iter()
+            .all(|w| w.basis == WeightBasis::Liquidity));
+        assert_eq!(result.weights[0].weight, 0.75);
+        assert!((result.avg_price_usd - 1.001).abs() < 1e-12);
+
+        let data = vec![
+            with_liquidity("a", 1.0, Some(3e6)),
+            with_liquidity("b", 1.004, None),
+        ];
+        let result = aggregate(&key(), data, weighted(), now());
+        assert!(result.valid, "{:?}", result.reason);
+        assert!(result.weights.iter().all(|w| w.basis == WeightBasis::Equal));
+        assert!((result.avg_price_usd - 1.002).abs() < 1e-12);
+    }
+}
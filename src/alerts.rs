@@ -0,0 +1,722 @@
+use crate::clock::Clock;
+use crate::config::{
+    AlertDestination, AlertFormat, AlertRule, AlertRules, AlertSeverity, AlertsConfig,
+};
+use crate::oracle::{PipelineOutput, RunTotals};
+use crate::output::OmittedEntry;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Discord rejects messages longer than 2000 characters.
+const DISCORD_MAX_CHARS: usize = 1900;
+
+impl AlertRule {
+    const ALL: [AlertRule; 7] = [
+        AlertRule::InvalidUnit,
+        AlertRule::SourceFailure,
+        AlertRule::SubmitFailure,
+        AlertRule::PriceJump,
+        AlertRule::ForexSymbolDropped,
+        AlertRule::IpfsFailure,
+        AlertRule::Submit,
+    ];
+
+    /// The rule an alert key such as `invalid_unit:3` belongs to.
+    fn of_key(key: &str) -> Option<AlertRule> {
+        let name = key.split(':').next()?;
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            AlertRule::InvalidUnit => "invalid_unit",
+            AlertRule::SourceFailure => "source_failure",
+            AlertRule::SubmitFailure => "submit_failure",
+            AlertRule::PriceJump => "price_jump",
+            AlertRule::ForexSymbolDropped => "forex_symbol_dropped",
+            AlertRule::IpfsFailure => "ipfs_failure",
+            AlertRule::Submit => "submit",
+        }
+    }
+
+    fn severity(self) -> AlertSeverity {
+        match self {
+            AlertRule::SubmitFailure => AlertSeverity::Critical,
+            AlertRule::InvalidUnit
+            | AlertRule::SourceFailure
+            | AlertRule::PriceJump
+            | AlertRule::IpfsFailure => AlertSeverity::Warning,
+            AlertRule::ForexSymbolDropped | AlertRule::Submit => AlertSeverity::Info,
+        }
+    }
+
+    /// Events are sent every time they happen; the other rules are
+    /// conditions, held back by the cool-down and reported once resolved.
+    fn is_event(self) -> bool {
+        matches!(self, AlertRule::Submit)
+    }
+
+    fn enabled(self, rules: &AlertRules) -> bool {
+        match self {
+            AlertRule::InvalidUnit => rules.invalid_unit,
+            AlertRule::SourceFailure => rules.source_failure,
+            AlertRule::SubmitFailure => rules.submit_failure,
+            AlertRule::PriceJump => rules.price_jump,
+            AlertRule::ForexSymbolDropped => rules.forex_symbol_dropped,
+            AlertRule::IpfsFailure => rules.ipfs_failure,
+            AlertRule::Submit => rules.submit,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub rule: AlertRule,
+    /// Stable identity used for cool-down, e.g. `invalid_unit:3`.
+    pub key: String,
+    pub message: String,
+}
+
+/// How the steps after `Oracle::run` (submission, publishing) went.
+#[derive(Debug, Default)]
+pub struct PostRun<'a> {
+    /// ActionHash of the submitted table.
+    pub action_hash: Option<&'a str>,
+    pub submit: Option<&'a anyhow::Error>,
+    pub ipfs: Option<&'a anyhow::Error>,
+}
+
+/// What the `json` format sends along with the alerts.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunReport {
+    pub summary: RunTotals,
+    /// Units and forex symbols left out of the table, with the reason.
+    pub omitted: Vec<OmittedEntry>,
+}
+
+/// Persisted between runs so repeated alerts can be rate-limited.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AlertState {
+    #[serde(default)]
+    last_sent: HashMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    last_prices: HashMap<u32, f64>,
+}
+
+impl AlertState {
+    fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!(
+                    "alert state {} unreadable ({}); starting fresh",
+                    path.display(),
+                    e
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).context("serializing alert state")?;
+        std::fs::write(path, json).with_context(|| format!("writing {}", path.display()))
+    }
+}
+
+/// Every alert condition present in this run, before filtering and cool-down.
+pub fn collect_alerts(
+    cfg: &AlertsConfig,
+    outcome: &PipelineOutput,
+    forex_symbols: &[String],
+    previous_prices: &HashMap<u32, f64>,
+    post_run: &PostRun<'_>,
+) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+
+    for r in outcome.aggregated.iter().filter(|r| !r.valid) {
+        alerts.push(Alert {
+            rule: AlertRule::InvalidUnit,
+            key: format!("invalid_unit:{}", r.unit_index),
+            message: format!(
+                "unit {} ({}) invalid — sources: [{}]",
+                r.unit_index,
+                r.name,
+                r.sources.join(", ")
+            ),
+        });
+    }
+
+    for (source, (count, first_error)) in down_sources(outcome) {
+        alerts.push(Alert {
+            rule: AlertRule::SourceFailure,
+            key: format!("source_failure:{}", source),
+            message: format!(
+                "source '{}' failed for all {} request(s): {}",
+                source, count, first_error
+            ),
+        });
+    }
+
+    if let Some(action_hash) = post_run.action_hash {
+        alerts.push(Alert {
+            rule: AlertRule::Submit,
+            key: "submit".to_string(),
+            message: format!("ConversionTable submitted: {}", action_hash),
+        });
+    }
+    if let Some(e) = post_run.submit {
+        alerts.push(Alert {
+            rule: AlertRule::SubmitFailure,
+            key: "submit_failure".to_string(),
+            message: format!("ConversionTable submission failed: {:#}", e),
+        });
+    }
+    if let Some(e) = post_run.ipfs {
+        alerts.push(Alert {
+            rule: AlertRule::IpfsFailure,
+            key: "ipfs_failure".to_string(),
+            message: format!("IPFS publish failed (submission unaffected): {:#}", e),
+        });
+    }
+
+    for r in outcome.aggregated.iter().filter(|r| r.valid) {
+        let Some(prev) = previous_prices.get(&r.unit_index) else {
+            continue;
+        };
+        if *prev <= 0.0 {
+            continue;
+        }
+        let change_pct = (r.avg_price_usd - prev) / prev * 100.0;
+        if change_pct.abs() > cfg.price_jump_percent {
+            alerts.push(Alert {
+                rule: AlertRule::PriceJump,
+                key: format!("price_jump:{}", r.unit_index),
+                message: format!(
+                    "unit {} ({}) moved {:+.2}% ({:.8} → {:.8})",
+                    r.unit_index, r.name, change_pct, prev, r.avg_price_usd
+                ),
+            });
+        }
+    }
+
+    let resolved: HashSet<&str> = outcome.forex.iter().map(|f| f.symbol.as_str()).collect();
+    for symbol in forex_symbols {
+        if !resolved.contains(symbol.as_str()) {
+            alerts.push(Alert {
+                rule: AlertRule::ForexSymbolDropped,
+                key: format!("forex_symbol_dropped:{}", symbol),
+                message: format!("forex symbol {} omitted from ConversionTable", symbol),
+            });
+        }
+    }
+
+    alerts
+}
+
+/// Sources that failed and never contributed a price this run, with their
+/// failure count and first error.
+pub fn down_sources(outcome: &PipelineOutput) -> BTreeMap<&str, (usize, &str)> {
+    let succeeded: HashSet<&str> = outcome
+        .aggregated
+        .iter()
+        .flat_map(|a| a.sources.iter().map(String::as_str))
+        .collect();
+    let mut failures: BTreeMap<&str, (usize, &str)> = BTreeMap::new();
+    for e in &outcome.source_errors {
+        if succeeded.contains(e.source.as_str()) {
+            continue;
+        }
+        let entry = failures
+            .entry(e.source.as_str())
+            .or_insert((0, e.error.as_str()));
+        entry.0 += 1;
+    }
+    failures
+}
+
+/// Collect, filter, rate-limit and deliver alerts for this run. Never fails
+/// the run: delivery and state-file problems are only logged.
+pub async fn send_alerts(
+    cfg: &AlertsConfig,
+    outcome: &PipelineOutput,
+    forex_symbols: &[String],
+    post_run: &PostRun<'_>,
+    clock: &dyn Clock,
+) {
+    let mut state = AlertState::load(&cfg.state_file);
+    let now = clock.now();
+    let cooldown = chrono::Duration::minutes(cfg.cooldown_minutes as i64);
+    // Destinations filter differently, so keep what any of them wants.
+    let alerts: Vec<Alert> =
+        collect_alerts(cfg, outcome, forex_symbols, &state.last_prices, post_run)
+            .into_iter()
+            .filter(|a| {
+                a.rule.enabled(&cfg.rules) && cfg.destinations.iter().any(|d| wants(cfg, d, a.rule))
+            })
+            .collect();
+
+    let active: HashSet<&str> = alerts.iter().map(|a| a.key.as_str()).collect();
+    let mut recovered: Vec<String> = state
+        .last_sent
+        .keys()
+        .filter(|k| !active.contains(k.as_str()))
+        .cloned()
+        .collect();
+    recovered.sort();
+    for key in &recovered {
+        state.last_sent.remove(key);
+    }
+
+    let due: Vec<&Alert> = alerts
+        .iter()
+        .filter(|a| {
+            a.rule.is_event()
+                || state
+                    .last_sent
+                    .get(&a.key)
+                    .map(|sent| now - *sent >= cooldown)
+                    .unwrap_or(true)
+        })
+        .collect();
+
+    if !due.is_empty() || !recovered.is_empty() {
+        let report = RunReport {
+            summary: RunTotals::new(outcome, post_run.action_hash),
+            omitted: outcome.omissions(forex_symbols),
+        };
+        deliver(cfg, &due, &recovered, &report).await;
+        for alert in due.iter().filter(|a| !a.rule.is_event()) {
+            state.last_sent.insert(alert.key.clone(), now);
+        }
+    }
+    if alerts.len() > due.len() {
+        info!(
+            "{} alert(s) suppressed by {}m cool-down",
+            alerts.len() - due.len(),
+            cfg.cooldown_minutes
+        );
+    }
+
+    for r in outcome.aggregated.iter().filter(|r| r.valid) {
+        state.last_prices.insert(r.unit_index, r.avg_price_usd);
+    }
+    if let Err(e) = state.save(&cfg.state_file) {
+        warn!("failed to save alert state: {:#}", e);
+    }
+}
+
+/// `notify-test`: post a made-up submit and invalid unit to every
+/// destination, whatever its `min_severity`. One result per destination,
+/// in config order.
+pub async fn send_test(cfg: &AlertsConfig) -> Vec<Result<()>> {
+    let alerts = [
+        Alert {
+            rule: AlertRule::Submit,
+            key: "submit".to_string(),
+            message: "ConversionTable submitted: uhCkkTEST (test alert)".to_string(),
+        },
+        Alert {
+            rule: AlertRule::InvalidUnit,
+            key: "invalid_unit:3".to_string(),
+            message: "unit 3 (EXAMPLE) invalid — sources: [] (test alert)".to_string(),
+        },
+    ];
+    let report = RunReport {
+        summary: RunTotals {
+            units_ok: 12,
+            units_invalid: 1,
+            units_missing: 0,
+            units_cached: 0,
+            forex_ok: 28,
+            forex_missing: 0,
+            submitted: true,
+            global_definition: "uhCkkTEST".to_string(),
+            action: Some("uhCkkTEST".to_string()),
+        },
+        omitted: vec![OmittedEntry {
+            entry: "unit 3 (EXAMPLE)".to_string(),
+            error: "sources disagree".to_string(),
+        }],
+    };
+    let due: Vec<&Alert> = alerts.iter().collect();
+    let text = format_message(&due, &[], cfg.run_link.as_deref());
+    let client = reqwest::Client::new();
+    let mut results = Vec::with_capacity(cfg.destinations.len());
+    for dest in &cfg.destinations {
+        let payload = payload(dest.format, &text, &due, &[], &report);
+        results.push(post(&client, dest, &payload).await);
+    }
+    results
+}
+
+fn format_message(due: &[&Alert], recovered: &[String], run_link: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    if !due.is_empty() {
+        lines.push(format!("pricing-oracle: {} alert(s)", due.len()));
+        for alert in due {
+            let severity = match alert.rule.severity() {
+                AlertSeverity::Info => "info",
+                AlertSeverity::Warning => "warning",
+                AlertSeverity::Critical => "CRITICAL",
+            };
+            lines.push(format!("[{}] {}", severity, alert.message));
+        }
+    }
+    if !recovered.is_empty() {
+        lines.push(format!("resolved: {}", recovered.join(", ")));
+    }
+    if let Some(link) = run_link {
+        lines.push(format!("details: {}", link));
+    }
+    lines.join("\n")
+}
+
+#[derive(Serialize)]
+struct JsonPayload<'a> {
+    text: &'a str,
+    alerts: Vec<JsonAlert<'a>>,
+    resolved: &'a [String],
+    #[serde(flatten)]
+    report: &'a RunReport,
+}
+
+#[derive(Serialize)]
+struct JsonAlert<'a> {
+    rule: AlertRule,
+    severity: AlertSeverity,
+    key: &'a str,
+    message: &'a str,
+}
+
+fn payload(
+    format: AlertFormat,
+    text: &str,
+    due: &[&Alert],
+    recovered: &[String],
+    report: &RunReport,
+) -> serde_json::Value {
+    match format {
+        AlertFormat::Slack => serde_json::json!({ "text": text }),
+        AlertFormat::Discord => {
+            let content: String = text.chars().take(DISCORD_MAX_CHARS).collect();
+            serde_json::json!({ "content": content })
+        }
+        AlertFormat::Json => serde_json::to_value(JsonPayload {
+            text,
+            alerts: due
+                .iter()
+                .map(|a| JsonAlert {
+                    rule: a.rule,
+                    severity: a.rule.severity(),
+                    key: &a.key,
+                    message: &a.message,
+                })
+                .collect(),
+            resolved: recovered,
+            report,
+        })
+        .unwrap_or_default(),
+    }
+}
+
+/// Whether `dest` takes alerts of `rule`: the rules in its `on` list, or
+/// else everything at or above its `min_severity`.
+fn wants(cfg: &AlertsConfig, dest: &AlertDestination, rule: AlertRule) -> bool {
+    match &dest.on {
+        Some(on) => on.contains(&rule),
+        None => rule.severity() >= dest.min_severity.unwrap_or(cfg.min_severity),
+    }
+}
+
+/// Each destination gets the alerts it [`wants`], plus whatever resolved
+/// (only for its own rules when it sets `on`); one with nothing to say is
+/// skipped.
+async fn deliver(cfg: &AlertsConfig, due: &[&Alert], recovered: &[String], report: &RunReport) {
+    let client = reqwest::Client::new();
+    // URLs often embed a token, so only their position is logged.
+    for (i, dest) in cfg.destinations.iter().enumerate() {
+        let alerts: Vec<&Alert> = due
+            .iter()
+            .copied()
+            .filter(|a| wants(cfg, dest, a.rule))
+            .collect();
+        let recovered: Vec<String> = recovered
+            .iter()
+            .filter(|key| match (&dest.on, AlertRule::of_key(key)) {
+                (Some(on), Some(rule)) => on.contains(&rule),
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        if alerts.is_empty() && recovered.is_empty() {
+            continue;
+        }
+        let text = format_message(&alerts, &recovered, cfg.run_link.as_deref());
+        let payload = payload(dest.format, &text, &alerts, &recovered, report);
+        if let Err(e) = post(&client, dest, &payload).await {
+            warn!("alert destination {}: {:#}", i + 1, e);
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    dest: &AlertDestination,
+    payload: &serde_json::Value,
+) -> Result<()> {
+    let url = match (&dest.url, &dest.url_env) {
+        (Some(url), _) => url.clone(),
+        (None, Some(var)) => {
+            std::env::var(var).with_context(|| format!("alert webhook env var {} not set", var))?
+        }
+        (None, None) => anyhow::bail!("no url or url_env"),
+    };
+    let resp = client
+        .post(&url)
+        .timeout(std::time::Duration::from_secs(10))
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("delivery failed: {}", e.without_url()))?;
+    if !resp.status().is_success() {
+        anyhow::bail!("webhook returned HTTP {}", resp.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::oracle::{RunStats, SourceError};
+    use crate::output::{self, ReferenceCurrency, TableFormat};
+    use serde_json::Value;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    /// A run where `dead` failed every request and nothing else happened.
+    fn outcome(dead: &str) -> PipelineOutput {
+        let built = output::build_conversion_table(
+            &[],
+            &[],
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            None,
+            None,
+        );
+        PipelineOutput {
+            aggregated: Vec::new(),
+            forex: Vec::new(),
+            table: built.table,
+            omitted: Vec::new(),
+            reference: ReferenceCurrency::usd(),
+            stats: RunStats {
+                started_at: now(),
+                finished_at: now(),
+                units_valid: 0,
+                units_invalid: 0,
+                proxies_unresolved: 0,
+                units_unencodable: 0,
+                units_cached: 0,
+                forex_requested: 0,
+                forex_ok: 0,
+                forex_missing: 0,
+            },
+            source_errors: vec![SourceError {
+                source: dead.to_string(),
+                subject: "unit 1".to_string(),
+                error: "HTTP 503".to_string(),
+            }],
+            source_timings: Vec::new(),
+        }
+    }
+
+    /// A `json` destination that wants everything and a Slack one at the
+    /// default `warning`, both on `server`.
+    fn config(server: &MockServer, name: &str) -> AlertsConfig {
+        let state_file = std::env::temp_dir().join(format!(
+            "pricing-oracle-alerts-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&state_file);
+        serde_yaml::from_str(&format!(
+            r#"
+destinations:
+  - url: "{uri}/json"
+    format: json
+    min_severity: info
+  - url: "{uri}/slack"
+state_file: "{state}"
+"#,
+            uri = server.uri(),
+            state = state_file.display()
+        ))
+        .unwrap()
+    }
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Bodies posted to `route` so far, oldest first.
+    async fn posted(server: &MockServer, route: &str) -> Vec<Value> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|r| r.url.path() == route)
+            .map(|r| serde_json::from_slice(&r.body).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn each_destination_gets_the_alerts_at_its_severity() {
+        let server = server().await;
+        let cfg = config(&server, "severity");
+        let post_run = PostRun {
+            action_hash: Some("uhCkkABC"),
+            ..Default::default()
+        };
+
+        send_alerts(
+            &cfg,
+            &outcome("kraken"),
+            &[],
+            &post_run,
+            &ManualClock::new(now()),
+        )
+        .await;
+
+        let json = posted(&server, "/json").await;
+        assert_eq!(json.len(), 1);
+        let rules: Vec<_> = json[0]["alerts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|a| a["rule"].as_str().unwrap())
+            .collect();
+        assert_eq!(rules, ["source_failure", "submit"]);
+        assert_eq!(json[0]["alerts"][1]["severity"], "info");
+        assert_eq!(json[0]["summary"]["action"], "uhCkkABC");
+        assert!(json[0]["omitted"].as_array().unwrap().is_empty());
+
+        let slack = posted(&server, "/slack").await;
+        assert_eq!(slack.len(), 1);
+        let text = slack[0]["text"].as_str().unwrap();
+        assert!(text.contains("source 'kraken' failed"), "{}", text);
+        assert!(!text.contains("submitted"), "{}", text);
+        let _ = std::fs::remove_file(&cfg.state_file);
+    }
+
+    #[tokio::test]
+    async fn submits_are_sent_every_run_while_conditions_wait_for_the_cooldown() {
+        let server = server().await;
+        let cfg = config(&server, "cooldown");
+        let clock = ManualClock::new(now());
+        let post_run = PostRun {
+            action_hash: Some("uhCkkABC"),
+            ..Default::default()
+        };
+
+        for _ in 0..2 {
+            send_alerts(&cfg, &outcome("kraken"), &[], &post_run, &clock).await;
+            clock.advance(chrono::Duration::minutes(15));
+        }
+        send_alerts(&cfg, &outcome("okx"), &[], &PostRun::default(), &clock).await;
+
+        let json = posted(&server, "/json").await;
+        let keys: Vec<Vec<&str>> = json
+            .iter()
+            .map(|body| {
+                body["alerts"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|a| a["key"].as_str().unwrap())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                vec!["source_failure:kraken", "submit"],
+                vec!["submit"],
+                vec!["source_failure:okx"],
+            ]
+        );
+        // The submit is never listed as resolved; the recovered source is.
+        assert_eq!(
+            json[2]["resolved"],
+            serde_json::json!(["source_failure:kraken"])
+        );
+        let _ = std::fs::remove_file(&cfg.state_file);
+    }
+
+    #[tokio::test]
+    async fn test_alerts_reach_every_destination_and_report_failures() {
+        let server = MockServer::start().await;
+        Mock::given(path("/json"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(path("/slack"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let cfg = config(&server, "test");
+
+        let results = send_test(&cfg).await;
+
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            "webhook returned HTTP 404 Not Found"
+        );
+        let json = posted(&server, "/json").await;
+        assert_eq!(json[0]["summary"]["action"], "uhCkkTEST");
+        assert_eq!(json[0]["omitted"][0]["entry"], "unit 3 (EXAMPLE)");
+    }
+
+    #[tokio::test]
+    async fn a_destination_with_on_gets_only_those_rules() {
+        let server = server().await;
+        let mut cfg = config(&server, "on");
+        cfg.destinations = serde_yaml::from_str(&format!(
+            "- url: \"{}/submits\"\n  format: json\n  on: [submit]",
+            server.uri()
+        ))
+        .unwrap();
+        let clock = ManualClock::new(now());
+        let post_run = PostRun {
+            action_hash: Some("uhCkkABC"),
+            ..Default::default()
+        };
+
+        send_alerts(&cfg, &outcome("kraken"), &[], &post_run, &clock).await;
+        clock.advance(chrono::Duration::minutes(15));
+        // kraken recovers and okx goes down, with no submit: nothing for it.
+        send_alerts(&cfg, &outcome("okx"), &[], &PostRun::default(), &clock).await;
+
+        let posts = posted(&server, "/submits").await;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0]["alerts"][0]["rule"], "submit");
+        assert_eq!(posts[0]["alerts"].as_array().unwrap().len(), 1);
+        let _ = std::fs::remove_file(&cfg.state_file);
+    }
+}
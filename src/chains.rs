@@ -0,0 +1,259 @@
+//! Chain naming shared by the sources: the [`ChainRegistry`] of what each
+//! source calls a chain, and the native coins.
+
+use crate::sources::NotConfigured;
+use serde::Deserialize;
+
+/// Sources that address tokens by chain and contract, and so need a
+/// [`ChainInfo`] entry for the unit's chain.
+pub const CHAIN_SOURCES: &[&str] = &[
+    "coingecko",
+    "coinmarketcap",
+    "geckoterminal",
+    "dexscreener",
+    "mobula",
+    "moralis",
+    "curve",
+    "1inch",
+];
+
+/// One chain and the identifier each source knows it by. A source whose
+/// field is unset does not support the chain.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainInfo {
+    /// Canonical name, as used in a unit's `chain`.
+    pub name: String,
+    /// Other names accepted in `chain`.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// CoinGecko asset platform id for `simple/token_price`.
+    pub coingecko_platform: Option<String>,
+    /// CoinMarketCap platform slug a contract's quote must belong to.
+    pub cmc_slug: Option<String>,
+    /// GeckoTerminal network id.
+    pub geckoterminal_network: Option<String>,
+    /// DexScreener `chainId`.
+    pub dexscreener_id: Option<String>,
+    /// Mobula `blockchain` name.
+    pub mobula_blockchain: Option<String>,
+    /// Moralis `chain` parameter.
+    pub moralis_chain: Option<String>,
+    /// Curve API blockchain id.
+    pub curve_network: Option<String>,
+    /// EVM chain id, used by 1inch. Unset for non-EVM chains, whose
+    /// contracts are checked as base58 instead of 0x addresses.
+    pub evm_chain_id: Option<u64>,
+}
+
+impl ChainInfo {
+    fn matches(&self, chain: &str) -> bool {
+        self.name.eq_ignore_ascii_case(chain)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(chain))
+    }
+
+    /// This chain's identifier for `source`, if the source supports it.
+    pub fn source_id(&self, source: &str) -> Option<String> {
+        match source {
+            "coingecko" => self.coingecko_platform.clone(),
+            "coinmarketcap" => self.cmc_slug.clone(),
+            "geckoterminal" => self.geckoterminal_network.clone(),
+            "dexscreener" => self.dexscreener_id.clone(),
+            "mobula" => self.mobula_blockchain.clone(),
+            "moralis" => self.moralis_chain.clone(),
+            "curve" => self.curve_network.clone(),
+            "1inch" => self.evm_chain_id.map(|id| id.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Fields set in `other` replace ours; its aliases are added.
+    fn merge(&mut self, other: &ChainInfo) {
+        for alias in &other.aliases {
+            if !self.matches(alias) {
+                self.aliases.push(alias.clone());
+            }
+        }
+        let fields = [
+            (&mut self.coingecko_platform, &other.coingecko_platform),
+            (&mut self.cmc_slug, &other.cmc_slug),
+            (
+                &mut self.geckoterminal_network,
+                &other.geckoterminal_network,
+            ),
+            (&mut self.dexscreener_id, &other.dexscreener_id),
+            (&mut self.mobula_blockchain, &other.mobula_blockchain),
+            (&mut self.moralis_chain, &other.moralis_chain),
+            (&mut self.curve_network, &other.curve_network),
+        ];
+        for (ours, theirs) in fields {
+            if theirs.is_some() {
+                ours.clone_from(theirs);
+            }
+        }
+        if other.evm_chain_id.is_some() {
+            self.evm_chain_id = other.evm_chain_id;
+        }
+    }
+}
+
+/// Chains the sources can address: the built-in table plus the config's
+/// `chains` section.
+#[derive(Debug, Clone)]
+pub struct ChainRegistry {
+    chains: Vec<ChainInfo>,
+}
+
+/// Name, aliases, source ids and EVM chain id of a [`BUILTIN`] chain.
+type BuiltinChain = (
+    &'static str,
+    &'static [&'static str],
+    [&'static str; 7],
+    Option<u64>,
+);
+
+/// Built-in chains: name, aliases, then the ids for coingecko,
+/// coinmarketcap, geckoterminal, dexscreener, mobula, moralis and curve
+/// (empty where the source has none), and the EVM chain id if any. Sepolia tokens
+/// are priced by their mainnet listings where a source has no testnet.
+#[rustfmt::skip]
+const BUILTIN: &[BuiltinChain] = &[
+    ("ethereum", &["eth"],
+     ["ethereum", "ethereum", "eth", "ethereum", "Ethereum", "eth", "ethereum"], Some(1)),
+    ("sepolia", &[],
+     ["ethereum", "ethereum", "eth", "ethereum", "Ethereum", "sepolia", ""], Some(11155111)),
+    ("polygon", &["matic", "polygon-pos"],
+     ["polygon-pos", "polygon", "polygon_pos", "polygon", "Polygon", "polygon", "polygon"], Some(137)),
+    ("bsc", &["bnb", "binance-smart-chain"],
+     ["binance-smart-chain", "bnb", "bsc", "bsc", "BNB Smart Chain (BEP20)", "bsc", "bsc"], Some(56)),
+    ("arbitrum", &["arbitrum-one", "arb"],
+     ["arbitrum-one", "arbitrum", "arbitrum", "arbitrum", "Arbitrum", "arbitrum", "arbitrum"], Some(42161)),
+    ("optimism", &["op", "optimistic-ethereum"],
+     ["optimistic-ethereum", "optimism-ethereum", "optimism", "optimism", "", "optimism", "optimism"], Some(10)),
+    ("base", &[],
+     ["base", "base", "base", "base", "Base", "base", "base"], Some(8453)),
+    ("avalanche", &["avax"],
+     ["avalanche", "avalanche", "avax", "avalanche", "Avalanche C-Chain", "avalanche", "avalanche"], Some(43114)),
+    ("gnosis", &["xdai"],
+     ["xdai", "gnosis-chain", "xdai", "gnosischain", "", "gnosis", "xdai"], Some(100)),
+    ("solana", &["sol"],
+     ["solana", "solana", "solana", "solana", "Solana", "", ""], None),
+];
+
+impl Default for ChainRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl ChainRegistry {
+    /// The chains known without any config.
+    pub fn builtin() -> Self {
+        let chains = BUILTIN
+            .iter()
+            .map(|(name, aliases, ids, evm_chain_id)| {
+                let [coingecko, cmc, geckoterminal, dexscreener, mobula, moralis, curve] =
+                    ids.map(|id| (!id.is_empty()).then(|| id.to_string()));
+                ChainInfo {
+                    name: name.to_string(),
+                    aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                    coingecko_platform: coingecko,
+                    cmc_slug: cmc,
+                    geckoterminal_network: geckoterminal,
+                    dexscreener_id: dexscreener,
+                    mobula_blockchain: mobula,
+                    moralis_chain: moralis,
+                    curve_network: curve,
+                    evm_chain_id: *evm_chain_id,
+                }
+            })
+            .collect();
+        Self { chains }
+    }
+
+    /// Apply the config's `chains` entries: one naming a known chain (or
+    /// one of its aliases) updates it field by field, any other is added.
+    pub fn with_overrides(mut self, extra: &[ChainInfo]) -> Self {
+        for entry in extra {
+            match self.chains.iter_mut().find(|c| c.matches(&entry.name)) {
+                Some(known) => known.merge(entry),
+                None => self.chains.push(entry.clone()),
+            }
+        }
+        self
+    }
+
+    /// The chain a unit's `chain` names, by canonical name or alias.
+    pub fn resolve(&self, chain: &str) -> Option<&ChainInfo> {
+        self.chains.iter().find(|c| c.matches(chain))
+    }
+
+    /// `chain`'s identifier for `source`, or a [`NotConfigured`] error
+    /// saying the source does not support it.
+    pub fn id_for(&self, chain: &str, source: &str) -> anyhow::Result<String> {
+        self.resolve(chain)
+            .and_then(|c| c.source_id(source))
+            .ok_or_else(|| {
+                NotConfigured(format!(
+                    "chain {} not supported by source {}",
+                    chain, source
+                ))
+                .into()
+            })
+    }
+
+    /// Canonical names, for error messages.
+    pub fn names(&self) -> Vec<&str> {
+        self.chains.iter().map(|c| c.name.as_str()).collect()
+    }
+}
+
+/// A chain's native coin, as the sources that price it by id or by its
+/// wrapped ERC-20 know it.
+#[derive(Debug, Clone, Copy)]
+pub struct NativeCoin {
+    pub symbol: &'static str,
+    /// CoinGecko coin id for `simple/price`.
+    pub coingecko_id: &'static str,
+    /// CoinMarketCap slug for `quotes/latest`.
+    pub cmc_slug: &'static str,
+    /// Wrapped token address that DEX and contract-based sources look up.
+    pub wrapped: &'static str,
+}
+
+/// Native coin of `chain` for units with `native: true`. Testnets are not
+/// mapped since their native coin has no market price.
+pub fn native_coin(chain: &str) -> Option<NativeCoin> {
+    let eth = |wrapped| NativeCoin {
+        symbol: "ETH",
+        coingecko_id: "ethereum",
+        cmc_slug: "ethereum",
+        wrapped,
+    };
+    match chain {
+        "ethereum" | "eth" => Some(eth("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")),
+        "arbitrum" | "arbitrum-one" | "arb" => {
+            Some(eth("0x82af49447d8a07e3bd95bd0d56f35241523fbab1"))
+        }
+        "base" => Some(eth("0x4200000000000000000000000000000000000006")),
+        "polygon" | "matic" | "polygon-pos" => Some(NativeCoin {
+            symbol: "POL",
+            coingecko_id: "polygon-ecosystem-token",
+            cmc_slug: "polygon-ecosystem-token",
+            wrapped: "0x0d500b1d8e8ef31e21c99d1db9a6444d3adf1270",
+        }),
+        "bsc" | "bnb" | "binance-smart-chain" => Some(NativeCoin {
+            symbol: "BNB",
+            coingecko_id: "binancecoin",
+            cmc_slug: "bnb",
+            wrapped: "0xbb4cdb9cbd36b01bd1cbaebf2de08d9173bc095c",
+        }),
+        "avalanche" | "avax" => Some(NativeCoin {
+            symbol: "AVAX",
+            coingecko_id: "avalanche-2",
+            cmc_slug: "avalanche",
+            wrapped: "0xb31f66aa3c1e785363f0875a1b74e27b85fd66c7",
+        }),
+        _ => None,
+    }
+}
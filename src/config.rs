@@ -1,7 +1,11 @@
+use crate::aggregate::{AggregateSettings, AggregationStrategy};
+use crate::chains::{ChainInfo, ChainRegistry, CHAIN_SOURCES};
+use crate::forex_aggregate::ForexAggregateSettings;
+use crate::types::UnitKey;
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
@@ -9,10 +13,451 @@ pub struct Config {
     pub price_references: Vec<PriceReference>,
     #[serde(default)]
     pub forex: ForexConfig,
+    /// Price sources defined entirely in config (generic REST endpoints).
+    #[serde(default)]
+    pub custom_sources: Vec<CustomSourceConfig>,
+    /// Chains added to, or overriding ids in, the built-in chain table.
+    #[serde(default)]
+    pub chains: Vec<ChainInfo>,
+    /// Largest fraction a source may differ from a unit's average price; units
+    /// may override it with their own `deviation_threshold`.
+    #[serde(default = "default_deviation_threshold")]
+    pub deviation_threshold: f64,
+    /// How source prices are combined; units may override it with `aggregation`.
+    #[serde(default)]
+    pub aggregation: AggregationStrategy,
+    /// Sources further than this fraction from a unit's median are dropped
+    /// before the cross-check (only with 3 or more sources).
+    #[serde(default = "default_outlier_threshold")]
+    pub outlier_threshold: f64,
+    /// A unit is invalid when fewer sources than this survive outlier rejection;
+    /// units may override it with their own `min_sources`.
+    #[serde(default = "default_min_sources")]
+    pub min_sources: usize,
+    /// Quotes whose source timestamp is older than this are dropped before
+    /// aggregation; no age limit when unset.
+    #[serde(default)]
+    pub max_age_seconds: Option<u64>,
+    /// Mark a unit invalid, instead of only warning, when its sources report
+    /// different token symbols or one other than its `expected_symbol`.
+    #[serde(default)]
+    pub strict_identity: bool,
+    /// `--submit` refuses when a unit's price moved more than this percent
+    /// since the last published table (override with `--force`).
+    #[serde(default = "default_max_change_percent")]
+    pub max_change_percent: f64,
+    /// `--submit` refuses when more than this fraction of valid units had to
+    /// be left out of the table (override with `--force`).
+    #[serde(default = "default_max_omitted_fraction")]
+    pub max_omitted_fraction: f64,
+    /// Each submitted table is saved here, and the change guard and
+    /// `--submit-if-changed` compare against it when the conductor read fails.
+    #[serde(default)]
+    pub last_table_file: Option<PathBuf>,
+    /// Forex sources further than this fraction from a symbol's average are flagged.
+    #[serde(default = "default_forex_deviation_threshold")]
+    pub forex_deviation_threshold: f64,
+    /// How per-source forex rates are combined (`weighted` is not supported).
+    #[serde(default)]
+    pub forex_aggregation: AggregationStrategy,
+    /// Symbols whose sources still differ from the central rate by more than
+    /// this fraction are omitted; no limit when unset.
+    #[serde(default)]
+    pub forex_max_deviation: Option<f64>,
+    /// Last valid price per unit, used when no source answers for it.
+    #[serde(default)]
+    pub cache_file: Option<PathBuf>,
+    /// Cached prices older than this are not used; `0` never uses them.
+    #[serde(default)]
+    pub max_cache_age_seconds: u64,
+    /// Last aggregated rate per symbol, used when a run gets no valid quote.
+    #[serde(default)]
+    pub forex_cache_file: Option<PathBuf>,
+    /// Cached rates older than this are not used.
+    #[serde(default = "default_forex_max_cache_age_seconds")]
+    pub forex_max_cache_age_seconds: u64,
+    /// Timeouts and proxy for the HTTP client shared by price and forex sources.
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// Base URL per source or forex provider name, replacing the production
+    /// API root, e.g. for an egress proxy or a mock server.
+    #[serde(default)]
+    pub source_endpoints: HashMap<String, String>,
+    /// Fiat currency the ConversionTable is denominated in (default `USD`).
+    /// Any other currency must be listed in `forex.symbols`.
+    #[serde(default = "default_reference_currency")]
+    pub reference_currency: String,
+    /// Most fractional digits written for a price or forex rate in the table.
+    #[serde(default = "default_price_decimals")]
+    pub price_decimals: u32,
+    /// Decimal places for `volume` in the table; values below 0.01 get
+    /// this many significant figures instead.
+    #[serde(default = "default_volume_decimals")]
+    pub volume_decimals: u32,
+    /// Decimal places for `net_change`; values below 0.01 get this many
+    /// significant figures instead.
+    #[serde(default = "default_change_decimals")]
+    pub change_decimals: u32,
+    /// Embed a `RunMetadata` JSON blob in the table's `additional_data`
+    /// (also enabled by `--embed-metadata`).
+    #[serde(default)]
+    pub embed_metadata: bool,
+    /// Seconds one price source may take for one unit before it counts as failed.
+    #[serde(default = "default_source_timeout_secs")]
+    pub source_timeout_secs: u64,
+    /// Retries after a 5xx, 429, timeout or connection error on any source
+    /// request; 0 disables retrying.
+    #[serde(default = "default_http_retries")]
+    pub http_retries: u32,
+    /// Chainlink answers older than this are rejected as stale.
+    #[serde(default = "default_chainlink_max_age_secs")]
+    pub chainlink_max_age_secs: u64,
+    /// Pyth quotes published longer ago than this are rejected as stale.
+    #[serde(default = "default_pyth_max_age_secs")]
+    pub pyth_max_age_secs: u64,
+    /// Pyth quotes with `conf / price` above this ratio are rejected.
+    #[serde(default = "default_pyth_max_conf_ratio")]
+    pub pyth_max_conf_ratio: f64,
+    #[serde(default)]
+    pub alerts: Option<AlertsConfig>,
+    /// The old webhook section, only read to point at `alerts` instead.
+    #[serde(default)]
+    notifications: Option<serde::de::IgnoredAny>,
+    /// S3-compatible bucket that receives each run's artifacts.
+    #[serde(default)]
+    pub storage: Option<StorageConfig>,
+    /// IPFS node that pins each submitted table.
+    #[serde(default)]
+    pub ipfs: Option<IpfsConfig>,
+    /// Postgres connection string; each run's results are inserted after it finishes.
+    #[serde(default)]
+    pub postgres_url: Option<String>,
+    /// Rows that could not be inserted wait here until the next successful connection.
+    #[serde(default = "default_postgres_spill_file")]
+    pub postgres_spill_file: PathBuf,
+    /// OTLP/HTTP traces endpoint, e.g. `http://localhost:4318/v1/traces`
+    /// (needs the `otel` build feature).
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// InfluxDB v2 bucket that receives each run as line-protocol points.
+    #[serde(default)]
+    pub influx: Option<InfluxConfig>,
+    /// Local JSONL file each run is appended to; read by `history`.
+    #[serde(default)]
+    pub history: Option<HistoryConfig>,
+    /// Conductor connection for `--submit`. Each field is optional and the
+    /// matching `HOLOCHAIN_*` env var wins over it.
+    #[serde(default)]
+    pub holochain: Option<HolochainSection>,
+    /// Named conductors the table can be submitted to with `--target`;
+    /// when set, they replace the `holochain` section for `--submit`.
+    #[serde(default)]
+    pub targets: Vec<TargetConfig>,
     pub units: Vec<UnitConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+/// Webhook alerts sent at the end of a run.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertsConfig {
+    pub destinations: Vec<AlertDestination>,
+    /// Alerts below this severity are not sent, unless a destination sets
+    /// its own `min_severity`.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: AlertSeverity,
+    /// An identical alert is not re-sent until this many minutes have passed
+    /// (or the condition recovered in between).
+    #[serde(default = "default_cooldown_minutes")]
+    pub cooldown_minutes: u64,
+    /// Where cool-down timestamps and last prices are kept between runs.
+    #[serde(default = "default_alert_state_file")]
+    pub state_file: PathBuf,
+    #[serde(default)]
+    pub rules: AlertRules,
+    /// Price move (percent, vs. the previous run) that triggers `price_jump`.
+    #[serde(default = "default_price_jump_percent")]
+    pub price_jump_percent: f64,
+    /// Optional link or path to the run artifact, appended to every message.
+    #[serde(default)]
+    pub run_link: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertDestination {
+    /// Webhook URL; prefer `url_env` so the URL stays out of config.yaml.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Env var holding the webhook URL.
+    #[serde(default)]
+    pub url_env: Option<String>,
+    #[serde(default)]
+    pub format: AlertFormat,
+    /// Overrides the section's `min_severity` for this destination, e.g.
+    /// `info` on a channel that should also hear about every submit.
+    #[serde(default)]
+    pub min_severity: Option<AlertSeverity>,
+    /// Only these rules are sent here, whatever their severity, e.g.
+    /// `[submit]` for a channel that announces submissions only.
+    #[serde(default)]
+    pub on: Option<Vec<AlertRule>>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertFormat {
+    #[default]
+    Slack,
+    Discord,
+    /// The message plus the run summary and omitted entries as JSON.
+    Json,
+}
+
+/// What an alert is about; the names match the `rules` switches.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertRule {
+    InvalidUnit,
+    SourceFailure,
+    SubmitFailure,
+    PriceJump,
+    ForexSymbolDropped,
+    IpfsFailure,
+    /// A table was submitted.
+    Submit,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertRules {
+    #[serde(default = "default_true")]
+    pub invalid_unit: bool,
+    #[serde(default = "default_true")]
+    pub source_failure: bool,
+    #[serde(default = "default_true")]
+    pub submit_failure: bool,
+    #[serde(default = "default_true")]
+    pub price_jump: bool,
+    #[serde(default = "default_true")]
+    pub forex_symbol_dropped: bool,
+    #[serde(default = "default_true")]
+    pub ipfs_failure: bool,
+    #[serde(default = "default_true")]
+    pub submit: bool,
+}
+
+impl Default for AlertRules {
+    fn default() -> Self {
+        Self {
+            invalid_unit: true,
+            source_failure: true,
+            submit_failure: true,
+            price_jump: true,
+            forex_symbol_dropped: true,
+            ipfs_failure: true,
+            submit: true,
+        }
+    }
+}
+
+fn default_min_severity() -> AlertSeverity {
+    AlertSeverity::Warning
+}
+
+fn default_cooldown_minutes() -> u64 {
+    360
+}
+
+fn default_alert_state_file() -> PathBuf {
+    PathBuf::from(".pricing-oracle-alerts.json")
+}
+
+fn default_price_jump_percent() -> f64 {
+    10.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InfluxConfig {
+    /// Base URL, e.g. `http://localhost:8086`.
+    pub url: String,
+    pub org: String,
+    pub bucket: String,
+    /// Env var holding the API token.
+    #[serde(default = "default_influx_token_env")]
+    pub token_env: String,
+    /// Attempts for the write request; only 5xx and connection errors are retried.
+    #[serde(default = "default_upload_attempts")]
+    pub max_attempts: u32,
+}
+
+/// Local run history (`history` subcommand).
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryConfig {
+    pub path: PathBuf,
+    /// Runs older than this are pruned on each write; `0` keeps every run.
+    #[serde(default = "default_history_keep_days")]
+    pub keep_days: u32,
+}
+
+pub fn default_history_keep_days() -> u32 {
+    90
+}
+
+/// The `holochain:` section; unset fields fall back to env vars, then to
+/// the built-in defaults. Setting `admin_port` selects an admin connection;
+/// `signing_key_path` (with `HOLOCHAIN_APP_TOKEN`) selects app-only.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HolochainSection {
+    pub admin_port: Option<u16>,
+    pub signing_key_path: Option<PathBuf>,
+    pub app_port: Option<u16>,
+    pub app_id: Option<String>,
+    pub role_name: Option<String>,
+    pub connect_timeout_ms: Option<u64>,
+    pub call_timeout_ms: Option<u64>,
+    pub retry_attempts: Option<u32>,
+    pub retry_backoff_ms: Option<u64>,
+}
+
+/// One submission target, e.g. a testnet and a production alliance.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TargetConfig {
+    pub name: String,
+    /// Prefix for this target's env overrides: `TESTNET_` reads
+    /// `TESTNET_HOLOCHAIN_APP_PORT` and so on. Empty reads plain `HOLOCHAIN_*`.
+    #[serde(default)]
+    pub env_prefix: String,
+    #[serde(flatten)]
+    pub holochain: HolochainSection,
+}
+
+fn default_influx_token_env() -> String {
+    "INFLUX_TOKEN".to_string()
+}
+
+/// S3-compatible object storage for run artifacts (table, report, receipt).
+#[derive(Debug, Clone, Deserialize)]
+pub struct StorageConfig {
+    /// Custom endpoint for MinIO, R2, etc.; AWS S3 if omitted.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    pub bucket: String,
+    /// Key prefix, e.g. `pricing-oracle/prod/`.
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_storage_region")]
+    pub region: String,
+    #[serde(default = "default_access_key_env")]
+    pub access_key_env: String,
+    #[serde(default = "default_secret_key_env")]
+    pub secret_key_env: String,
+    /// Address buckets as `endpoint/bucket/key` (needed by most non-AWS stores).
+    #[serde(default = "default_true")]
+    pub path_style: bool,
+    /// Attempts per object, with exponential backoff between them.
+    #[serde(default = "default_upload_attempts")]
+    pub max_attempts: u32,
+}
+
+/// Kubo-compatible RPC API used to add and pin submitted tables.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IpfsConfig {
+    /// Base URL of the RPC API, e.g. `http://127.0.0.1:5001`.
+    pub api_url: String,
+    /// Env var holding a bearer token (pinning services); none for a local node.
+    #[serde(default)]
+    pub token_env: Option<String>,
+    /// Put the previous table's CID into the next submission's `additional_data`.
+    #[serde(default)]
+    pub link_previous: bool,
+    /// Where the last published CID is kept between runs.
+    #[serde(default = "default_ipfs_state_file")]
+    pub state_file: PathBuf,
+}
+
+fn default_postgres_spill_file() -> PathBuf {
+    PathBuf::from(".pricing-oracle-pg-spill.jsonl")
+}
+
+fn default_ipfs_state_file() -> PathBuf {
+    PathBuf::from(".pricing-oracle-ipfs-cid")
+}
+
+fn default_storage_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".to_string()
+}
+
+fn default_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".to_string()
+}
+
+fn default_upload_attempts() -> u32 {
+    3
+}
+
+/// A generic REST price source: URL template plus JSON pointers into the response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomSourceConfig {
+    pub name: String,
+    /// URL with `{contract}` / `{chain}` placeholders.
+    pub url: String,
+    /// Extra request headers; values may reference env vars as `${VAR}`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    pub price_pointer: String,
+    #[serde(default)]
+    pub volume_pointer: Option<String>,
+    #[serde(default)]
+    pub market_cap_pointer: Option<String>,
+    /// Multiplier applied to the extracted price (e.g. 1e-6 for micro-dollar APIs).
+    #[serde(default)]
+    pub scale: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpConfig {
+    /// Whole-request timeout, from connecting to reading the body.
+    #[serde(default = "default_http_timeout_seconds")]
+    pub timeout_seconds: u64,
+    #[serde(default = "default_http_connect_timeout_seconds")]
+    pub connect_timeout_seconds: u64,
+    /// Send all source traffic through this http(s) proxy.
+    #[serde(default)]
+    pub proxy_url: Option<String>,
+    /// `timeout_seconds` overrides by source or forex provider name.
+    #[serde(default)]
+    pub source_timeouts: HashMap<String, u64>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_seconds: default_http_timeout_seconds(),
+            connect_timeout_seconds: default_http_connect_timeout_seconds(),
+            proxy_url: None,
+            source_timeouts: HashMap::new(),
+        }
+    }
+}
+
+fn default_http_timeout_seconds() -> u64 {
+    15
+}
+
+fn default_http_connect_timeout_seconds() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct ForexConfig {
     #[serde(default)]
     pub symbols: Vec<String>,
@@ -20,11 +465,151 @@ pub struct ForexConfig {
     pub use_twelve_data: bool,
     #[serde(default = "default_true")]
     pub use_coinapi: bool,
+    #[serde(default = "default_true")]
+    pub use_open_exchange_rates: bool,
+    #[serde(default = "default_true")]
+    pub use_currencyapi: bool,
+    #[serde(default = "default_true")]
+    pub use_alpha_vantage: bool,
+    /// Most Alpha Vantage requests in one run (one per symbol; free tier allows 25 a day).
+    #[serde(default = "default_alpha_vantage_call_budget")]
+    pub alpha_vantage_call_budget: u32,
+    /// European Central Bank reference rates; keyless, so off unless enabled.
+    #[serde(default)]
+    pub use_ecb: bool,
+    /// Frankfurter `/latest`; keyless, so off unless enabled.
+    #[serde(default)]
+    pub use_frankfurter: bool,
     #[serde(default = "default_max_symbols_per_run")]
     pub max_symbols_per_run: usize,
     /// Seconds to wait between batches when iterating (e.g. 65 for Twelve Data free tier per-minute limit).
     #[serde(default)]
     pub delay_between_batches_secs: u64,
+    /// Seconds one forex provider may take for one batch before it counts as failed.
+    #[serde(default = "default_source_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Plausible range per symbol (foreign units per USD); rates outside are dropped.
+    #[serde(default)]
+    pub bounds: HashMap<String, PriceBounds>,
+    /// Display names per symbol, overriding the built-in currency names.
+    #[serde(default)]
+    pub names: HashMap<String, String>,
+}
+
+/// Optional lower/upper limits on a price or rate.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct PriceBounds {
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+}
+
+impl PriceBounds {
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+
+    fn validate(&self, what: &str) -> Result<()> {
+        for v in [self.min, self.max].into_iter().flatten() {
+            if !v.is_finite() || v <= 0.0 {
+                anyhow::bail!("{}: bound {} must be a positive number", what, v);
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min >= max {
+                anyhow::bail!("{}: minimum {} must be below maximum {}", what, min, max);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Same values as an empty `forex:` block, so omitting the section entirely
+/// does not zero out the batch size or timeout.
+impl Default for ForexConfig {
+    fn default() -> Self {
+        Self {
+            symbols: Vec::new(),
+            use_twelve_data: true,
+            use_coinapi: true,
+            use_open_exchange_rates: true,
+            use_currencyapi: true,
+            use_alpha_vantage: true,
+            alpha_vantage_call_budget: default_alpha_vantage_call_budget(),
+            use_ecb: false,
+            use_frankfurter: false,
+            max_symbols_per_run: default_max_symbols_per_run(),
+            delay_between_batches_secs: 0,
+            timeout_secs: default_source_timeout_secs(),
+            bounds: HashMap::new(),
+            names: HashMap::new(),
+        }
+    }
+}
+
+fn default_deviation_threshold() -> f64 {
+    crate::aggregate::DEFAULT_DEVIATION_THRESHOLD
+}
+
+fn default_max_omitted_fraction() -> f64 {
+    0.1
+}
+
+fn default_max_change_percent() -> f64 {
+    20.0
+}
+
+fn default_outlier_threshold() -> f64 {
+    0.05
+}
+
+fn default_min_sources() -> usize {
+    1
+}
+
+fn default_forex_max_cache_age_seconds() -> u64 {
+    86_400
+}
+
+fn default_forex_deviation_threshold() -> f64 {
+    crate::forex_aggregate::DEFAULT_FOREX_DEVIATION_THRESHOLD
+}
+
+fn default_pyth_max_age_secs() -> u64 {
+    60
+}
+
+fn default_pyth_max_conf_ratio() -> f64 {
+    0.01
+}
+
+fn default_chainlink_max_age_secs() -> u64 {
+    3600
+}
+
+fn default_http_retries() -> u32 {
+    crate::http::DEFAULT_RETRIES
+}
+
+fn default_reference_currency() -> String {
+    "USD".to_string()
+}
+
+fn default_price_decimals() -> u32 {
+    crate::output::DEFAULT_PRICE_DECIMALS
+}
+
+fn default_volume_decimals() -> u32 {
+    crate::output::DEFAULT_VOLUME_DECIMALS
+}
+
+fn default_change_decimals() -> u32 {
+    crate::output::DEFAULT_CHANGE_DECIMALS
+}
+
+fn default_source_timeout_secs() -> u64 {
+    30
 }
 
 fn default_true() -> bool {
@@ -35,26 +620,121 @@ fn default_max_symbols_per_run() -> usize {
     8
 }
 
+fn default_alpha_vantage_call_budget() -> u32 {
+    25
+}
+
 /// Token fetched for price only; not in ConversionTable, no unit_index.
 #[derive(Debug, Clone, Deserialize)]
 pub struct PriceReference {
     pub id: String,
     pub name: String,
     pub chain: String,
+    /// Token address; leave out for the chain's native coin (`native: true`).
+    #[serde(default)]
     pub contract: String,
+    /// Price the chain's native coin (e.g. ETH) instead of a token contract.
+    #[serde(default)]
+    pub native: bool,
     #[serde(default)]
     pub decimals: Option<u8>,
+    /// Kraken trading pair (e.g. `XBTUSD`); Kraken skips the token without it.
+    #[serde(default)]
+    pub kraken_pair: Option<String>,
+    /// Coinbase Exchange product (e.g. `ETH-USD`); Coinbase skips the token without it.
+    #[serde(default)]
+    pub coinbase_product: Option<String>,
+    /// CoinPaprika coin id (e.g. `hot-holo`); CoinPaprika skips the token without it.
+    #[serde(default)]
+    pub coinpaprika_id: Option<String>,
+    /// Chainlink aggregator address on Ethereum mainnet.
+    #[serde(default)]
+    pub chainlink_feed: Option<String>,
+    /// Decimals of the feed's answer (default 8).
+    #[serde(default)]
+    pub chainlink_decimals: Option<u8>,
+    /// Pyth price feed id (hex, e.g. the ETH/USD feed id).
+    #[serde(default)]
+    pub pyth_price_id: Option<String>,
+    /// OKX spot instrument quoted in USDT (e.g. `HOT-USDT`); OKX skips the token without it.
+    #[serde(default)]
+    pub okx_inst_id: Option<String>,
+    /// Bybit USDT spot symbol (e.g. `WBTCUSDT`); Bybit skips the token without it.
+    #[serde(default)]
+    pub bybit_symbol: Option<String>,
+    /// Curve pool address holding this token; Curve skips the token without it.
+    #[serde(default)]
+    pub curve_pool: Option<String>,
+    /// Index of this token among the pool's coins (default 0).
+    #[serde(default)]
+    pub curve_index: Option<u8>,
+    /// GeckoTerminal pool to price this token from instead of the token's blended price.
+    #[serde(default)]
+    pub geckoterminal_pool: Option<String>,
+    /// Let CoinMarketCap use an entry on the right platform whose contract
+    /// address is missing from the response; logged as a warning when used.
+    #[serde(default)]
+    pub allow_loose_match: bool,
+    /// Per-source symbol or id keyed by source name, e.g. `{ kraken: XBTUSD }`.
+    /// Takes precedence over the dedicated fields such as `kraken_pair`.
+    #[serde(default)]
+    pub source_ids: HashMap<String, String>,
+    /// Only query these sources (by name); all registered sources when absent.
+    #[serde(default)]
+    pub sources: Option<Vec<String>>,
+    /// Overrides the top-level `deviation_threshold` for this entry.
+    #[serde(default)]
+    pub deviation_threshold: Option<f64>,
+    /// Overrides the top-level `aggregation` strategy for this entry.
+    #[serde(default)]
+    pub aggregation: Option<AggregationStrategy>,
+    /// Overrides the top-level `min_sources` for this entry.
+    #[serde(default)]
+    pub min_sources: Option<usize>,
+    /// Quotes below this USD price are dropped as implausible.
+    #[serde(default)]
+    pub min_price_usd: Option<f64>,
+    /// Quotes above this USD price are dropped as implausible.
+    #[serde(default)]
+    pub max_price_usd: Option<f64>,
+    /// Symbol the sources should report for this token (case-insensitive).
+    #[serde(default)]
+    pub expected_symbol: Option<String>,
 }
 
 impl PriceReference {
-    /// Build a UnitConfig-shaped value for use with SourceRegistry::fetch_all (same fields needed for API calls).
+    /// Build a UnitConfig-shaped value for use with SourceRegistry::fetch_all
+    /// (same fields needed for API calls). It carries the reference's `id`,
+    /// so logs and the aggregated result name the reference, not unit 0.
     pub fn to_unit_config_for_fetch(&self) -> UnitConfig {
         UnitConfig {
             unit_index: 0,
+            reference_id: Some(self.id.clone()),
             name: self.name.clone(),
             chain: self.chain.clone(),
             contract: self.contract.clone(),
+            native: self.native,
             decimals: self.decimals,
+            kraken_pair: self.kraken_pair.clone(),
+            coinbase_product: self.coinbase_product.clone(),
+            coinpaprika_id: self.coinpaprika_id.clone(),
+            chainlink_feed: self.chainlink_feed.clone(),
+            chainlink_decimals: self.chainlink_decimals,
+            pyth_price_id: self.pyth_price_id.clone(),
+            okx_inst_id: self.okx_inst_id.clone(),
+            bybit_symbol: self.bybit_symbol.clone(),
+            curve_pool: self.curve_pool.clone(),
+            curve_index: self.curve_index,
+            geckoterminal_pool: self.geckoterminal_pool.clone(),
+            allow_loose_match: self.allow_loose_match,
+            source_ids: self.source_ids.clone(),
+            sources: self.sources.clone(),
+            deviation_threshold: self.deviation_threshold,
+            aggregation: self.aggregation,
+            min_sources: self.min_sources,
+            min_price_usd: self.min_price_usd,
+            max_price_usd: self.max_price_usd,
+            expected_symbol: self.expected_symbol.clone(),
             price_proxy: None,
         }
     }
@@ -63,17 +743,130 @@ impl PriceReference {
 #[derive(Debug, Clone, Deserialize)]
 pub struct UnitConfig {
     pub unit_index: u32,
+    /// Set when this was built from a price reference; never read from YAML.
+    #[serde(skip)]
+    pub reference_id: Option<String>,
     pub name: String,
     pub chain: String,
+    /// Token address; leave out for the chain's native coin (`native: true`).
+    #[serde(default)]
     pub contract: String,
+    /// Price the chain's native coin (e.g. ETH) instead of a token contract.
+    #[serde(default)]
+    pub native: bool,
     pub decimals: Option<u8>,
+    /// Kraken trading pair (e.g. `XBTUSD`); Kraken skips the unit without it.
+    #[serde(default)]
+    pub kraken_pair: Option<String>,
+    /// Coinbase Exchange product (e.g. `ETH-USD`); Coinbase skips the unit without it.
+    #[serde(default)]
+    pub coinbase_product: Option<String>,
+    /// CoinPaprika coin id (e.g. `hot-holo`); CoinPaprika skips the unit without it.
+    #[serde(default)]
+    pub coinpaprika_id: Option<String>,
+    /// Chainlink aggregator address on Ethereum mainnet; read via `ETH_RPC_URL`.
+    #[serde(default)]
+    pub chainlink_feed: Option<String>,
+    /// Decimals of the feed's answer (default 8).
+    #[serde(default)]
+    pub chainlink_decimals: Option<u8>,
+    /// Pyth price feed id (hex, e.g. the ETH/USD feed id).
+    #[serde(default)]
+    pub pyth_price_id: Option<String>,
+    /// OKX spot instrument quoted in USDT (e.g. `HOT-USDT`); OKX skips the unit without it.
+    #[serde(default)]
+    pub okx_inst_id: Option<String>,
+    /// Bybit USDT spot symbol (e.g. `WBTCUSDT`); Bybit skips the unit without it.
+    #[serde(default)]
+    pub bybit_symbol: Option<String>,
+    /// Curve pool address holding this unit's token; Curve skips the unit without it.
+    #[serde(default)]
+    pub curve_pool: Option<String>,
+    /// Index of this token among the pool's coins (default 0).
+    #[serde(default)]
+    pub curve_index: Option<u8>,
+    /// GeckoTerminal pool to price this unit from instead of the token's blended price.
+    #[serde(default)]
+    pub geckoterminal_pool: Option<String>,
+    /// Let CoinMarketCap use an entry on the right platform whose contract
+    /// address is missing from the response; logged as a warning when used.
+    #[serde(default)]
+    pub allow_loose_match: bool,
+    /// Per-source symbol or id keyed by source name, e.g. `{ kraken: XBTUSD }`.
+    /// Takes precedence over the dedicated fields such as `kraken_pair`.
+    #[serde(default)]
+    pub source_ids: HashMap<String, String>,
+    /// Only query these sources (by name); all registered sources when absent.
+    #[serde(default)]
+    pub sources: Option<Vec<String>>,
+    /// Overrides the top-level `deviation_threshold` for this entry.
+    #[serde(default)]
+    pub deviation_threshold: Option<f64>,
+    /// Overrides the top-level `aggregation` strategy for this entry.
+    #[serde(default)]
+    pub aggregation: Option<AggregationStrategy>,
+    /// Overrides the top-level `min_sources` for this entry.
+    #[serde(default)]
+    pub min_sources: Option<usize>,
+    /// Quotes below this USD price are dropped as implausible.
+    #[serde(default)]
+    pub min_price_usd: Option<f64>,
+    /// Quotes above this USD price are dropped as implausible.
+    #[serde(default)]
+    pub max_price_usd: Option<f64>,
+    /// Symbol the sources should report for this token (case-insensitive).
+    #[serde(default)]
+    pub expected_symbol: Option<String>,
     pub price_proxy: Option<PriceProxy>,
 }
 
+impl UnitConfig {
+    /// The unit's index, or the reference's id for a price reference.
+    pub fn key(&self) -> UnitKey {
+        match &self.reference_id {
+            Some(id) => UnitKey::Reference(id.clone()),
+            None => UnitKey::Index(self.unit_index),
+        }
+    }
+
+    /// Symbol or id `source` looks this entry up by: `source_ids` first,
+    /// then the source's dedicated field, if it has one.
+    pub fn source_id(&self, source: &str) -> Option<&str> {
+        if let Some(id) = self.source_ids.get(source) {
+            return Some(id);
+        }
+        match source {
+            "kraken" => self.kraken_pair.as_deref(),
+            "coinbase" => self.coinbase_product.as_deref(),
+            "coinpaprika" => self.coinpaprika_id.as_deref(),
+            "chainlink" => self.chainlink_feed.as_deref(),
+            "pyth" => self.pyth_price_id.as_deref(),
+            "okx" => self.okx_inst_id.as_deref(),
+            "bybit" => self.bybit_symbol.as_deref(),
+            "curve" => self.curve_pool.as_deref(),
+            "geckoterminal" => self.geckoterminal_pool.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Address that contract-based sources look up: the wrapped token for a
+    /// native coin, otherwise `contract`.
+    pub fn lookup_contract(&self) -> &str {
+        if self.native {
+            crate::chains::native_coin(&self.chain).map_or("", |n| n.wrapped)
+        } else {
+            &self.contract
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PriceProxy {
     pub use_unit: Option<u32>,
     pub use_reference: Option<String>,
+    /// Scale the copied price by this factor, e.g. `0.001` for a milli-unit.
+    #[serde(default)]
+    pub multiplier: Option<f64>,
 }
 
 #[derive(Debug, Clone)]
@@ -84,15 +877,72 @@ pub enum ProxySource {
 
 impl Config {
     pub fn load(path: &Path) -> Result<Self> {
-        let contents =
-            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
-        let config: Config = serde_yaml::from_str(&contents)
-            .with_context(|| format!("parsing {}", path.display()))?;
+        let mut config = Self::parse(path)?;
         config.validate()?;
+        config.normalize_contracts();
         Ok(config)
     }
 
-    fn validate(&self) -> Result<()> {
+    /// Lowercase every 0x contract once validated, so sources compare
+    /// addresses without re-normalizing them.
+    fn normalize_contracts(&mut self) {
+        let contracts = self
+            .units
+            .iter_mut()
+            .map(|u| &mut u.contract)
+            .chain(self.price_references.iter_mut().map(|r| &mut r.contract));
+        for contract in contracts {
+            *contract = crate::address::normalize(contract);
+        }
+    }
+
+    /// The built-in chain table with this config's `chains` applied.
+    pub fn chain_registry(&self) -> ChainRegistry {
+        ChainRegistry::builtin().with_overrides(&self.chains)
+    }
+
+    /// Read and deserialize `path` without validating it. `path` may be a
+    /// file, whose `include:` list pulls in more files, or a directory whose
+    /// `*.yaml` files are merged in name order. `${VAR}` and
+    /// `${VAR:-default}` in string values are replaced from the environment
+    /// first; `$$` is a literal `$`.
+    pub fn parse(path: &Path) -> Result<Self> {
+        let value = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("reading {}", path.display()))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<_>>()
+                .with_context(|| format!("reading {}", path.display()))?;
+            files.retain(|f| f.extension().is_some_and(|e| e == "yaml"));
+            files.sort();
+            if files.is_empty() {
+                anyhow::bail!("{} contains no .yaml files", path.display());
+            }
+            let mut merged = serde_yaml::Value::Mapping(Default::default());
+            for file in &files {
+                let value = load_yaml(file, &mut Vec::new())?;
+                merge_yaml(&mut merged, value)
+                    .with_context(|| format!("merging {}", file.display()))?;
+            }
+            merged
+        } else {
+            load_yaml(path, &mut Vec::new())?
+        };
+        let mut config: Config =
+            serde_yaml::from_value(value).with_context(|| format!("parsing {}", path.display()))?;
+        let contracts = config
+            .units
+            .iter_mut()
+            .map(|u| &mut u.contract)
+            .chain(config.price_references.iter_mut().map(|r| &mut r.contract));
+        for contract in contracts {
+            *contract = contract.trim().to_string();
+        }
+        Ok(config)
+    }
+
+    /// Checks every run depends on; stops at the first problem.
+    pub fn validate(&self) -> Result<()> {
         let mut seen_forex: HashMap<&str, ()> = HashMap::new();
         for symbol in &self.forex.symbols {
             if symbol.trim().is_empty() {
@@ -107,10 +957,440 @@ impl Config {
             if seen_forex.insert(symbol.as_str(), ()).is_some() {
                 anyhow::bail!("forex.symbols contains duplicate '{}'", symbol);
             }
+            if !self.forex.names.contains_key(symbol)
+                && !crate::forex_aggregate::is_known_currency(symbol)
+            {
+                tracing::warn!(
+                    "forex symbol '{}' has no built-in name; add forex.names.{} to avoid 'Unknown Currency'",
+                    symbol,
+                    symbol
+                );
+            }
+        }
+        for (symbol, name) in &self.forex.names {
+            if !self.forex.symbols.contains(symbol) {
+                anyhow::bail!("forex.names has '{}' which is not in forex.symbols", symbol);
+            }
+            if name.trim().is_empty() {
+                anyhow::bail!("forex.names.{} must not be empty", symbol);
+            }
+        }
+        if self.reference_currency != "USD"
+            && !self.forex.symbols.contains(&self.reference_currency)
+        {
+            anyhow::bail!(
+                "reference_currency '{}' must be listed in forex.symbols so its rate is fetched",
+                self.reference_currency
+            );
+        }
+        let max_decimals = crate::output::MAX_DECIMALS;
+        for (key, value) in [
+            ("price_decimals", self.price_decimals),
+            ("volume_decimals", self.volume_decimals),
+            ("change_decimals", self.change_decimals),
+        ] {
+            if value > max_decimals {
+                anyhow::bail!("{} must be at most {}", key, max_decimals);
+            }
+        }
+        if self.source_timeout_secs == 0 || self.forex.timeout_secs == 0 {
+            anyhow::bail!("source_timeout_secs and forex.timeout_secs must be greater than 0");
+        }
+        if self.chainlink_max_age_secs == 0 || self.pyth_max_age_secs == 0 {
+            anyhow::bail!("chainlink_max_age_secs and pyth_max_age_secs must be greater than 0");
+        }
+        if !self.pyth_max_conf_ratio.is_finite() || self.pyth_max_conf_ratio <= 0.0 {
+            anyhow::bail!("pyth_max_conf_ratio must be a positive number");
+        }
+        // References as units, so `source_id` sees their overrides too.
+        let references: Vec<UnitConfig> = self
+            .price_references
+            .iter()
+            .map(PriceReference::to_unit_config_for_fetch)
+            .collect();
+        for entry in self.units.iter().chain(&references) {
+            if entry
+                .expected_symbol
+                .as_deref()
+                .is_some_and(|s| s.trim().is_empty())
+            {
+                anyhow::bail!("'{}': expected_symbol must not be empty", entry.name);
+            }
+            if entry.min_sources == Some(0) {
+                anyhow::bail!("'{}': min_sources must be at least 1", entry.name);
+            }
+            for (field, source) in [("chainlink_feed", "chainlink"), ("curve_pool", "curve")] {
+                if let Some(address) = entry.source_id(source) {
+                    if !is_evm_address(address) {
+                        anyhow::bail!(
+                            "'{}': {} '{}' is not a 0x address",
+                            entry.name,
+                            field,
+                            address
+                        );
+                    }
+                }
+            }
+        }
+        if let Some(chain) = self.chains.iter().find(|c| c.name.trim().is_empty()) {
+            anyhow::bail!(
+                "chains has an entry with no name (aliases {:?})",
+                chain.aliases
+            );
+        }
+        let registry = self.chain_registry();
+        // Proxies copy their price, so only fetched entries need an address.
+        let tokens = self
+            .units
+            .iter()
+            .filter(|u| u.price_proxy.is_none())
+            .map(|u| (u.name.as_str(), u.chain.as_str(), &u.contract, u.native))
+            .chain(
+                self.price_references
+                    .iter()
+                    .map(|r| (r.name.as_str(), r.chain.as_str(), &r.contract, r.native)),
+            );
+        for (name, chain, contract, native) in tokens {
+            if native {
+                if !contract.is_empty() {
+                    anyhow::bail!("'{}': set either contract or native: true, not both", name);
+                }
+                if crate::chains::native_coin(chain).is_none() {
+                    anyhow::bail!("'{}': no native coin known for chain '{}'", name, chain);
+                }
+            } else if contract.is_empty() {
+                anyhow::bail!("'{}': needs a contract or native: true", name);
+            } else if let Some(info) = registry.resolve(chain) {
+                crate::address::check(info, contract)
+                    .with_context(|| format!("'{}' on {}", name, chain))?;
+            }
+        }
+        let fetched = self
+            .units
+            .iter()
+            .filter(|u| u.price_proxy.is_none())
+            .chain(&references);
+        for entry in fetched {
+            // Only sources that look tokens up by chain need to know it.
+            let selected: Vec<&str> = CHAIN_SOURCES
+                .iter()
+                .copied()
+                .filter(|s| {
+                    entry
+                        .sources
+                        .as_ref()
+                        .is_none_or(|names| names.iter().any(|n| n == s))
+                })
+                .collect();
+            if selected.is_empty() {
+                continue;
+            }
+            let Some(chain) = registry.resolve(&entry.chain) else {
+                anyhow::bail!(
+                    "'{}': chain '{}' is not known (known: {}); add it under chains",
+                    entry.name,
+                    entry.chain,
+                    registry.names().join(", ")
+                );
+            };
+            if !selected.iter().any(|s| chain.source_id(s).is_some()) {
+                anyhow::bail!(
+                    "'{}': chain '{}' is not supported by any of its sources ({})",
+                    entry.name,
+                    entry.chain,
+                    selected.join(", ")
+                );
+            }
+        }
+        // USDT-quoted exchanges are converted through the USDT reference.
+        for entry in self.units.iter().chain(&references) {
+            for (field, source, suffix) in [
+                ("okx_inst_id", "okx", "-USDT"),
+                ("bybit_symbol", "bybit", "USDT"),
+            ] {
+                if let Some(pair) = entry.source_id(source) {
+                    if !pair.ends_with(suffix) {
+                        anyhow::bail!("'{}': {} '{}' must be a USDT pair", entry.name, field, pair);
+                    }
+                }
+            }
         }
         if self.forex.max_symbols_per_run == 0 {
             anyhow::bail!("forex.max_symbols_per_run must be greater than 0");
         }
+        if self.forex_max_cache_age_seconds == 0 {
+            anyhow::bail!("forex_max_cache_age_seconds must be greater than 0");
+        }
+        if self.forex.alpha_vantage_call_budget == 0 {
+            anyhow::bail!(
+                "forex.alpha_vantage_call_budget must be greater than 0; set use_alpha_vantage: false to disable it"
+            );
+        }
+
+        if self.notifications.is_some() {
+            anyhow::bail!(
+                "the notifications section was replaced by alerts; move each webhook to an alerts destination with the same on and format"
+            );
+        }
+        if let Some(alerts) = &self.alerts {
+            if alerts.destinations.is_empty() {
+                anyhow::bail!("alerts.destinations must list at least one webhook");
+            }
+            for dest in &alerts.destinations {
+                if dest.url.is_some() == dest.url_env.is_some() {
+                    anyhow::bail!("each alerts destination needs exactly one of url or url_env");
+                }
+                match &dest.on {
+                    Some(on) if on.is_empty() => {
+                        anyhow::bail!("an alerts destination's `on` must list at least one rule")
+                    }
+                    Some(_) if dest.min_severity.is_some() => {
+                        anyhow::bail!(
+                            "an alerts destination takes `on` or `min_severity`, not both"
+                        )
+                    }
+                    _ => {}
+                }
+            }
+            if !alerts.price_jump_percent.is_finite() || alerts.price_jump_percent <= 0.0 {
+                anyhow::bail!("alerts.price_jump_percent must be a positive number");
+            }
+        }
+
+        if self.http.timeout_seconds == 0 || self.http.connect_timeout_seconds == 0 {
+            anyhow::bail!(
+                "http.timeout_seconds and http.connect_timeout_seconds must be greater than 0"
+            );
+        }
+        if let Some(proxy) = &self.http.proxy_url {
+            // Never echo the URL: it may carry proxy credentials.
+            if !proxy.starts_with("http://") && !proxy.starts_with("https://") {
+                anyhow::bail!("http.proxy_url must be an http(s) URL");
+            }
+        }
+        for (source, secs) in &self.http.source_timeouts {
+            let known = crate::sources::BUILTIN_SOURCE_NAMES.contains(&source.as_str())
+                || crate::forex::BUILTIN_FOREX_NAMES.contains(&source.as_str())
+                || self.custom_sources.iter().any(|c| c.name == *source);
+            if !known {
+                anyhow::bail!(
+                    "http.source_timeouts has '{}', which is not a known source",
+                    source
+                );
+            }
+            if *secs == 0 {
+                anyhow::bail!("http.source_timeouts.{} must be greater than 0", source);
+            }
+        }
+        for (source, url) in &self.source_endpoints {
+            if source == "chainlink" {
+                anyhow::bail!("source_endpoints.chainlink: Chainlink reads ETH_RPC_URL instead");
+            }
+            if !crate::sources::BUILTIN_SOURCE_NAMES.contains(&source.as_str())
+                && !crate::forex::BUILTIN_FOREX_NAMES.contains(&source.as_str())
+            {
+                anyhow::bail!(
+                    "source_endpoints has '{}', which is not a built-in price source or forex provider",
+                    source
+                );
+            }
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("source_endpoints.{} must be an http(s) URL", source);
+            }
+        }
+
+        if let Some(ipfs) = &self.ipfs {
+            if !ipfs.api_url.starts_with("http://") && !ipfs.api_url.starts_with("https://") {
+                anyhow::bail!("ipfs.api_url must be an http(s) URL");
+            }
+        }
+
+        if let Some(influx) = &self.influx {
+            if influx.org.trim().is_empty() || influx.bucket.trim().is_empty() {
+                anyhow::bail!("influx.org and influx.bucket must not be empty");
+            }
+            if influx.max_attempts == 0 {
+                anyhow::bail!("influx.max_attempts must be greater than 0");
+            }
+        }
+
+        if let Some(storage) = &self.storage {
+            if storage.bucket.trim().is_empty() {
+                anyhow::bail!("storage.bucket must not be empty");
+            }
+            if storage.max_attempts == 0 {
+                anyhow::bail!("storage.max_attempts must be greater than 0");
+            }
+        }
+
+        let mut target_names: HashMap<&str, ()> = HashMap::new();
+        let mut env_prefixes: HashMap<&str, &str> = HashMap::new();
+        for t in &self.targets {
+            if t.name.trim().is_empty() || t.name == "all" {
+                anyhow::bail!("targets entry needs a name other than 'all'");
+            }
+            if target_names.insert(t.name.as_str(), ()).is_some() {
+                anyhow::bail!("duplicate target name '{}'", t.name);
+            }
+            if let Some(other) = env_prefixes.insert(t.env_prefix.as_str(), t.name.as_str()) {
+                anyhow::bail!(
+                    "targets '{}' and '{}' share env_prefix '{}'",
+                    other,
+                    t.name,
+                    t.env_prefix
+                );
+            }
+        }
+
+        let mut custom_names: HashMap<&str, ()> = HashMap::new();
+        for c in &self.custom_sources {
+            if c.name.trim().is_empty() {
+                anyhow::bail!("custom_sources entry has an empty name");
+            }
+            if crate::sources::BUILTIN_SOURCE_NAMES.contains(&c.name.as_str()) {
+                anyhow::bail!(
+                    "custom source '{}' clashes with a built-in source name",
+                    c.name
+                );
+            }
+            if custom_names.insert(c.name.as_str(), ()).is_some() {
+                anyhow::bail!("duplicate custom source name '{}'", c.name);
+            }
+            validate_url_template(&c.url)
+                .with_context(|| format!("custom source '{}' url", c.name))?;
+            let pointers = std::iter::once(&c.price_pointer)
+                .chain(c.volume_pointer.iter())
+                .chain(c.market_cap_pointer.iter());
+            for pointer in pointers {
+                validate_json_pointer(pointer)
+                    .with_context(|| format!("custom source '{}'", c.name))?;
+            }
+            if let Some(scale) = c.scale {
+                if !scale.is_finite() || scale <= 0.0 {
+                    anyhow::bail!(
+                        "custom source '{}' scale must be a positive finite number",
+                        c.name
+                    );
+                }
+            }
+        }
+
+        let thresholds = [
+            ("deviation_threshold", Some(self.deviation_threshold)),
+            (
+                "forex_deviation_threshold",
+                Some(self.forex_deviation_threshold),
+            ),
+            ("outlier_threshold", Some(self.outlier_threshold)),
+            ("forex_max_deviation", self.forex_max_deviation),
+        ]
+        .into_iter()
+        .chain(
+            self.units
+                .iter()
+                .map(|u| (u.name.as_str(), u.deviation_threshold)),
+        )
+        .chain(
+            self.price_references
+                .iter()
+                .map(|r| (r.name.as_str(), r.deviation_threshold)),
+        );
+        for (name, threshold) in thresholds {
+            if let Some(t) = threshold {
+                if !(t > 0.0 && t < 1.0) {
+                    anyhow::bail!("'{}': threshold {} must be between 0 and 1", name, t);
+                }
+            }
+        }
+        if self.forex_aggregation == AggregationStrategy::Weighted {
+            anyhow::bail!(
+                "forex_aggregation: 'weighted' needs volumes, which forex rates don't have"
+            );
+        }
+
+        if self.min_sources == 0 {
+            anyhow::bail!("min_sources must be at least 1");
+        }
+        if !(0.0..=1.0).contains(&self.max_omitted_fraction) {
+            anyhow::bail!("max_omitted_fraction must be between 0 and 1");
+        }
+        if !self.max_change_percent.is_finite() || self.max_change_percent <= 0.0 {
+            anyhow::bail!("max_change_percent must be a positive number");
+        }
+        if self.max_age_seconds == Some(0) {
+            anyhow::bail!("max_age_seconds must be greater than 0");
+        }
+        let price_bounds = self
+            .units
+            .iter()
+            .map(|u| (u.name.as_str(), u.min_price_usd, u.max_price_usd))
+            .chain(
+                self.price_references
+                    .iter()
+                    .map(|r| (r.name.as_str(), r.min_price_usd, r.max_price_usd)),
+            );
+        for (name, min, max) in price_bounds {
+            PriceBounds { min, max }.validate(&format!("'{}' min/max_price_usd", name))?;
+        }
+        for (symbol, bounds) in &self.forex.bounds {
+            if !self.forex.symbols.contains(symbol) {
+                anyhow::bail!(
+                    "forex.bounds has '{}' which is not in forex.symbols",
+                    symbol
+                );
+            }
+            bounds.validate(&format!("forex.bounds.{}", symbol))?;
+        }
+
+        let mut valid_sources: Vec<&str> = crate::sources::BUILTIN_SOURCE_NAMES.to_vec();
+        valid_sources.extend(self.custom_sources.iter().map(|c| c.name.as_str()));
+        for (name, source_ids) in self
+            .units
+            .iter()
+            .map(|u| (u.name.as_str(), &u.source_ids))
+            .chain(
+                self.price_references
+                    .iter()
+                    .map(|r| (r.name.as_str(), &r.source_ids)),
+            )
+        {
+            for source in source_ids
+                .keys()
+                .filter(|s| !valid_sources.contains(&s.as_str()))
+            {
+                tracing::warn!(
+                    "'{}': source_ids has '{}', which is not a known source; it is ignored",
+                    name,
+                    source
+                );
+            }
+        }
+        let selections = self
+            .units
+            .iter()
+            .map(|u| (u.name.as_str(), &u.sources))
+            .chain(
+                self.price_references
+                    .iter()
+                    .map(|r| (r.name.as_str(), &r.sources)),
+            );
+        for (name, sources) in selections {
+            let Some(sources) = sources else { continue };
+            if sources.is_empty() {
+                anyhow::bail!("'{}': sources must not be empty (omit it to use all)", name);
+            }
+            if let Some(unknown) = sources
+                .iter()
+                .find(|s| !valid_sources.contains(&s.as_str()))
+            {
+                anyhow::bail!(
+                    "'{}': unknown source '{}' in sources (valid: {})",
+                    name,
+                    unknown,
+                    valid_sources.join(", ")
+                );
+            }
+        }
 
         let mut ref_ids: HashMap<&str, &str> = HashMap::new();
         for r in &self.price_references {
@@ -164,11 +1444,49 @@ impl Config {
                         );
                     }
                 }
+                if let Some(m) = proxy.multiplier {
+                    if !m.is_finite() || m <= 0.0 {
+                        anyhow::bail!(
+                            "unit '{}' has price_proxy.multiplier {} (must be a positive number)",
+                            unit.name,
+                            m
+                        );
+                    }
+                }
             }
         }
+        for unit in self.proxy_units() {
+            crate::proxy::chain(self, unit)?;
+        }
         Ok(())
     }
 
+    /// Forex aggregation knobs from the top-level `forex_*` settings.
+    pub fn forex_aggregate_settings(&self) -> ForexAggregateSettings {
+        ForexAggregateSettings {
+            strategy: self.forex_aggregation,
+            deviation_threshold: self.forex_deviation_threshold,
+            max_deviation: self.forex_max_deviation,
+        }
+    }
+
+    /// Aggregation knobs for `unit`: its own overrides, else the global values.
+    pub fn aggregate_settings_for(&self, unit: &UnitConfig) -> AggregateSettings {
+        AggregateSettings {
+            deviation_threshold: unit.deviation_threshold.unwrap_or(self.deviation_threshold),
+            strategy: unit.aggregation.unwrap_or(self.aggregation),
+            outlier_threshold: self.outlier_threshold,
+            min_sources: unit.min_sources.unwrap_or(self.min_sources),
+            max_age_secs: self.max_age_seconds,
+            bounds: PriceBounds {
+                min: unit.min_price_usd,
+                max: unit.max_price_usd,
+            },
+            expected_symbol: unit.expected_symbol.clone(),
+            strict_identity: self.strict_identity,
+        }
+    }
+
     pub fn real_units(&self) -> Vec<&UnitConfig> {
         self.units
             .iter()
@@ -197,3 +1515,328 @@ impl Config {
         anyhow::bail!("price_proxy must have use_unit or use_reference");
     }
 }
+
+/// Config list keys that included files append to; any other top-level key
+/// may be set by only one file.
+const MERGED_LISTS: &[&str] = &["units", "price_references"];
+
+/// Parse and interpolate `path`, then merge in its `include:` files (paths
+/// relative to `path`). `stack` holds the files being loaded, to report
+/// include cycles.
+fn load_yaml(path: &Path, stack: &mut Vec<PathBuf>) -> Result<serde_yaml::Value> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("reading {}", path.display()))?;
+    if let Some(start) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<String> = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        anyhow::bail!("circular include: {}", cycle.join(" -> "));
+    }
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut value: serde_yaml::Value =
+        serde_yaml::from_str(&contents).with_context(|| format!("parsing {}", path.display()))?;
+    interpolate_value(&mut value, "")
+        .with_context(|| format!("interpolating {}", path.display()))?;
+
+    let includes = match value.as_mapping_mut().and_then(|m| m.remove("include")) {
+        None => Vec::new(),
+        Some(includes) => serde_yaml::from_value::<Vec<PathBuf>>(includes)
+            .with_context(|| format!("{}: include must be a list of paths", path.display()))?,
+    };
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    for include in includes {
+        let included = load_yaml(&dir.join(&include), stack)?;
+        merge_yaml(&mut value, included)
+            .with_context(|| format!("{}: including {}", path.display(), include.display()))?;
+    }
+    stack.pop();
+    Ok(value)
+}
+
+/// Append `other`'s `units` / `price_references` to `base`; copy any other
+/// key unless `base` already sets it.
+fn merge_yaml(base: &mut serde_yaml::Value, other: serde_yaml::Value) -> Result<()> {
+    let serde_yaml::Value::Mapping(other) = other else {
+        anyhow::bail!("top level must be a mapping");
+    };
+    let base = base
+        .as_mapping_mut()
+        .context("top level must be a mapping")?;
+    for (key, value) in other {
+        let name = key.as_str().unwrap_or("?").to_string();
+        match base.get_mut(&key) {
+            None => {
+                base.insert(key, value);
+            }
+            Some(serde_yaml::Value::Sequence(items)) if MERGED_LISTS.contains(&name.as_str()) => {
+                let serde_yaml::Value::Sequence(more) = value else {
+                    anyhow::bail!("'{}' must be a list", name);
+                };
+                items.extend(more);
+            }
+            Some(_) => anyhow::bail!("'{}' is set in more than one file", name),
+        }
+    }
+    Ok(())
+}
+
+/// Interpolate every string scalar under `value`. Errors name the YAML path
+/// and variable, never the value, since interpolated strings may be secrets.
+fn interpolate_value(value: &mut serde_yaml::Value, path: &str) -> Result<()> {
+    match value {
+        serde_yaml::Value::String(s) => {
+            *s = interpolate(s).with_context(|| format!("at {}", path))?;
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                interpolate_value(item, &format!("{}[{}]", path, i))?;
+            }
+        }
+        serde_yaml::Value::Mapping(map) => {
+            for (key, item) in map.iter_mut() {
+                let key = key.as_str().unwrap_or("?");
+                let child = if path.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                interpolate_value(item, &child)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => interpolate_value(&mut tagged.value, path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}`, `${VAR:-default}` (defaults may nest) and `$$` in `input`.
+/// A `$` not followed by `{` or `$` is kept as is.
+fn interpolate(input: &str) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(tail) = after.strip_prefix('$') {
+            out.push('$');
+            rest = tail;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = closing_brace(body).context("unterminated '${' in config value")?;
+            out.push_str(&expand(&body[..end])?);
+            rest = &body[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Index of the `}` closing a `${`, skipping nested `${...}` in defaults.
+fn closing_brace(body: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `VAR` or `VAR:-default`; an unset or empty variable falls back to the default.
+fn expand(expr: &str) -> Result<String> {
+    let (name, default) = match expr.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (expr, None),
+    };
+    let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        anyhow::bail!("invalid variable name '{}' in '${{...}}'", name);
+    }
+    match std::env::var(name) {
+        Ok(value) if !value.is_empty() => Ok(value),
+        _ => match default {
+            Some(default) => interpolate(default),
+            None => anyhow::bail!(
+                "environment variable '{}' is not set and has no default",
+                name
+            ),
+        },
+    }
+}
+
+/// `0x` followed by 40 hex digits.
+pub fn is_evm_address(address: &str) -> bool {
+    let hex = address.strip_prefix("0x").unwrap_or("");
+    hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// URL template placeholders understood by custom REST sources.
+const URL_PLACEHOLDERS: &[&str] = &["contract", "chain"];
+
+/// Errors never include `url` itself: it has been through `${VAR}`
+/// interpolation and may carry an API key.
+fn validate_url_template(url: &str) -> Result<()> {
+    let mut offset = 0;
+    let mut rest = url;
+    while let Some(start) = rest.find('{') {
+        if let Some(close) = rest[..start].find('}') {
+            anyhow::bail!("unbalanced '}}' at position {}", offset + close);
+        }
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            anyhow::bail!("unterminated placeholder at position {}", offset + start);
+        };
+        let name = &after[..end];
+        if !URL_PLACEHOLDERS.contains(&name) {
+            anyhow::bail!(
+                "unknown placeholder '{{{}}}' at position {} (expected one of: {})",
+                name,
+                offset + start,
+                URL_PLACEHOLDERS.join(", ")
+            );
+        }
+        offset += start + end + 2;
+        rest = &after[end + 1..];
+    }
+    if let Some(close) = rest.find('}') {
+        anyhow::bail!("unbalanced '}}' at position {}", offset + close);
+    }
+    Ok(())
+}
+
+/// RFC 6901 syntax check: empty, or `/`-prefixed with `~` only as `~0` / `~1`.
+fn validate_json_pointer(pointer: &str) -> Result<()> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        anyhow::bail!("JSON pointer '{}' must start with '/'", pointer);
+    }
+    let mut chars = pointer.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0') | Some('1')) {
+            anyhow::bail!("JSON pointer '{}' has an invalid '~' escape", pointer);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_expands_variables_and_nested_defaults() {
+        std::env::set_var("PRICING_ORACLE_TEST_HOST", "rpc.example");
+        std::env::remove_var("PRICING_ORACLE_TEST_UNSET");
+        std::env::set_var("PRICING_ORACLE_TEST_EMPTY", "");
+
+        assert_eq!(
+            interpolate("https://${PRICING_ORACLE_TEST_HOST}/v1").unwrap(),
+            "https://rpc.example/v1"
+        );
+        assert_eq!(
+            interpolate("${PRICING_ORACLE_TEST_UNSET:-${PRICING_ORACLE_TEST_HOST}}").unwrap(),
+            "rpc.example"
+        );
+        assert_eq!(
+            interpolate(
+                "${PRICING_ORACLE_TEST_UNSET:-${PRICING_ORACLE_TEST_EMPTY:-fallback}}:8545"
+            )
+            .unwrap(),
+            "fallback:8545"
+        );
+        assert_eq!(interpolate("${PRICING_ORACLE_TEST_UNSET:-}").unwrap(), "");
+    }
+
+    #[test]
+    fn double_dollar_is_a_literal_dollar() {
+        std::env::set_var("PRICING_ORACLE_TEST_PRICE", "secret");
+
+        assert_eq!(
+            interpolate("$${PRICING_ORACLE_TEST_PRICE} costs $5 and $$").unwrap(),
+            "${PRICING_ORACLE_TEST_PRICE} costs $5 and $"
+        );
+    }
+
+    #[test]
+    fn missing_variable_is_named_without_a_value() {
+        std::env::remove_var("PRICING_ORACLE_TEST_MISSING");
+
+        let err = interpolate("key=${PRICING_ORACLE_TEST_MISSING}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "environment variable 'PRICING_ORACLE_TEST_MISSING' is not set and has no default"
+        );
+        assert_eq!(
+            interpolate("${PRICING_ORACLE_TEST_MISSING")
+                .unwrap_err()
+                .to_string(),
+            "unterminated '${' in config value"
+        );
+        assert!(interpolate("${1BAD}").is_err());
+    }
+
+    #[test]
+    fn url_template_errors_leave_the_url_out() {
+        let url = "https://api.example/SECRETKEY/{contrat}?key=SECRETKEY";
+
+        let err = validate_url_template(url).unwrap_err().to_string();
+
+        assert_eq!(
+            err,
+            "unknown placeholder '{contrat}' at position 30 (expected one of: contract, chain)"
+        );
+        for url in [
+            "https://api.example/SECRETKEY/{contract",
+            "https://api.example/SECRETKEY/}{chain}",
+            "https://api.example/{chain}/SECRETKEY}",
+        ] {
+            let err = validate_url_template(url).unwrap_err().to_string();
+            assert!(!err.contains("SECRETKEY"), "{}", err);
+        }
+        assert!(validate_url_template("https://api.example/{chain}/{contract}?k=1").is_ok());
+    }
+
+    fn unit(yaml: &str) -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: WBTC\nchain: ethereum\ncontract: \"0x2260fac5e5542a773aa44fbcfedf7c193bc2c599\"\n{}",
+            yaml
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn source_ids_take_precedence_over_dedicated_fields() {
+        let unit = unit(
+            "kraken_pair: XBTUSD\ncoinpaprika_id: btc-bitcoin\nsource_ids:\n  kraken: XXBTZUSD\n  binance: BTCUSDT",
+        );
+
+        assert_eq!(unit.source_id("kraken"), Some("XXBTZUSD"));
+        assert_eq!(unit.source_id("binance"), Some("BTCUSDT"));
+        assert_eq!(unit.source_id("coinpaprika"), Some("btc-bitcoin"));
+        assert_eq!(unit.source_id("coinbase"), None);
+        assert_eq!(unit.source_id("not-a-source"), None);
+    }
+
+    #[test]
+    fn unknown_source_ids_key_is_only_a_warning() {
+        let mut config: Config = serde_yaml::from_str("units: []").unwrap();
+        config.units.push(unit("source_ids:\n  binanse: BTCUSDT"));
+
+        config.validate().unwrap();
+        assert_eq!(config.units[0].source_id("binanse"), Some("BTCUSDT"));
+    }
+}
@@ -0,0 +1,310 @@
+//! Entry-by-entry comparison of two ConversionTables, used by
+//! `--submit-if-changed` to skip submissions when nothing moved and by the
+//! `diff` subcommand.
+
+use crate::guard::zfuel_to_f64;
+use crate::output::read_json_file;
+use crate::types::ConversionTable;
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// One unit price or forex rate that differs between two tables.
+#[derive(Debug, Clone)]
+pub struct TableChange {
+    /// `unit 3` or `forex EUR`.
+    pub entry: String,
+    pub previous: Option<f64>,
+    pub current: Option<f64>,
+}
+
+impl TableChange {
+    /// Percent move from `previous`; `None` unless both values exist.
+    pub fn change_percent(&self) -> Option<f64> {
+        match (self.previous, self.current) {
+            (Some(prev), Some(cur)) if prev != 0.0 => Some((cur - prev) / prev * 100.0),
+            _ => None,
+        }
+    }
+}
+
+/// Unit prices and forex rates that moved more than `tolerance_percent`,
+/// or exist in only one of the tables. Empty when the tables match.
+pub fn changed_entries(
+    previous: &ConversionTable,
+    current: &ConversionTable,
+    tolerance_percent: f64,
+) -> Result<Vec<TableChange>> {
+    let previous = entry_values(previous)?;
+    let current = entry_values(current)?;
+    let keys: BTreeSet<&EntryKey> = previous.keys().chain(current.keys()).collect();
+    Ok(keys
+        .into_iter()
+        .map(|key| TableChange {
+            entry: key.2.clone(),
+            previous: previous.get(key).copied(),
+            current: current.get(key).copied(),
+        })
+        .filter(|c| c.exceeds(tolerance_percent))
+        .collect())
+}
+
+impl TableChange {
+    /// Moved more than `tolerance_percent`, or present in only one table.
+    pub fn exceeds(&self, tolerance_percent: f64) -> bool {
+        match (self.previous, self.current) {
+            (Some(prev), Some(cur)) => match self.change_percent() {
+                Some(pct) => pct.abs() > tolerance_percent,
+                None => prev != cur,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// A unit whose `sources` list differs between two tables.
+#[derive(Debug, Clone)]
+pub struct SourcesChange {
+    pub unit: String,
+    pub previous: Vec<String>,
+    pub current: Vec<String>,
+}
+
+/// Units present in both tables whose sources differ, ignoring order.
+pub fn changed_sources(
+    previous: &ConversionTable,
+    current: &ConversionTable,
+) -> Vec<SourcesChange> {
+    let mut changes: Vec<SourcesChange> = previous
+        .data
+        .iter()
+        .filter_map(|(key, prev)| {
+            let cur = current.data.get(key)?;
+            let mut previous = prev.sources.clone();
+            let mut current = cur.sources.clone();
+            previous.sort();
+            current.sort();
+            (previous != current).then(|| SourcesChange {
+                unit: key.clone(),
+                previous,
+                current,
+            })
+        })
+        .collect();
+    changes.sort_by_key(|c| c.unit.parse::<u32>().unwrap_or(u32::MAX));
+    changes
+}
+
+/// `diff` subcommand: print every entry that differs between two table
+/// JSON files, and fail if any moved beyond `tolerance_percent` or exists
+/// on one side only. Source list changes are reported but never fail.
+pub fn run(old: &Path, new: &Path, tolerance_percent: f64) -> Result<()> {
+    let previous = read_json_file(old)?;
+    let current = read_json_file(new)?;
+    let changes = changed_entries(&previous, &current, 0.0)?;
+    let sources = changed_sources(&previous, &current);
+
+    let fmt = |v: Option<f64>| v.map_or_else(|| "—".to_string(), |p| format!("{:.8e}", p));
+    if !changes.is_empty() {
+        println!(
+            "{:<14} {:<16} {:<16} {:<16} {:<10} Status",
+            "Entry", "Old", "New", "Abs diff", "Change"
+        );
+        println!("{}", "-".repeat(82));
+    }
+    for c in &changes {
+        let abs = match (c.previous, c.current) {
+            (Some(prev), Some(cur)) => format!("{:+.8e}", cur - prev),
+            _ => "—".to_string(),
+        };
+        let change = c
+            .change_percent()
+            .map_or_else(|| "—".to_string(), |p| format!("{:+.4}%", p));
+        let status = match (c.previous, c.current) {
+            (None, _) => "only in new",
+            (_, None) => "only in old",
+            _ if c.exceeds(tolerance_percent) => "CHANGED",
+            _ => "ok",
+        };
+        println!(
+            "{:<14} {:<16} {:<16} {:<16} {:<10} {}",
+            c.entry,
+            fmt(c.previous),
+            fmt(c.current),
+            abs,
+            change,
+            status
+        );
+    }
+    for s in &sources {
+        println!(
+            "unit {}: sources [{}] -> [{}]",
+            s.unit,
+            s.previous.join(", "),
+            s.current.join(", ")
+        );
+    }
+
+    let failed = changes
+        .iter()
+        .filter(|c| c.exceeds(tolerance_percent))
+        .count();
+    if failed > 0 {
+        anyhow::bail!(
+            "{} entr{} differ by more than {}%",
+            failed,
+            if failed == 1 { "y" } else { "ies" },
+            tolerance_percent
+        );
+    }
+    println!(
+        "Tables match within {}% ({} entr{} moved, {} source list change(s))",
+        tolerance_percent,
+        changes.len(),
+        if changes.len() == 1 { "y" } else { "ies" },
+        sources.len()
+    );
+    Ok(())
+}
+
+/// Units first by index, then forex by symbol; the label is what gets logged.
+type EntryKey = (u8, u32, String);
+
+fn entry_values(table: &ConversionTable) -> Result<BTreeMap<EntryKey, f64>> {
+    let mut values = BTreeMap::new();
+    for (key, data) in &table.data {
+        let price = zfuel_to_f64(&data.current_price).with_context(|| format!("unit {}", key))?;
+        let index = key.parse().unwrap_or(u32::MAX);
+        values.insert((0, index, format!("unit {}", key)), price);
+    }
+    for rate in &table.forex_rates {
+        let value = zfuel_to_f64(&rate.rate).with_context(|| format!("forex {}", rate.symbol))?;
+        values.insert((1, 0, format!("forex {}", rate.symbol)), value);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{decimal_string, DEFAULT_PRICE_DECIMALS};
+    use crate::types::{ConversionData, ForexRate, ReferenceUnit};
+    use holo_hash::ActionHash;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use zfuel::fuel::ZFuel;
+
+    fn zfuel(value: f64) -> ZFuel {
+        ZFuel::from_str(&decimal_string(value, DEFAULT_PRICE_DECIMALS).unwrap()).unwrap()
+    }
+
+    fn table(units: &[(&str, f64)], forex: &[(&str, f64)]) -> ConversionTable {
+        ConversionTable {
+            reference_unit: ReferenceUnit {
+                symbol: "$".to_string(),
+                name: "US Dollar".to_string(),
+            },
+            data: units
+                .iter()
+                .map(|(key, price)| {
+                    let data = ConversionData {
+                        current_price: zfuel(*price),
+                        volume: "0.00".to_string(),
+                        net_change: "0.0000".to_string(),
+                        sources: vec!["kraken".to_string()],
+                        contract: None,
+                    };
+                    (key.to_string(), data)
+                })
+                .collect::<HashMap<_, _>>(),
+            forex_rates: forex
+                .iter()
+                .map(|(symbol, rate)| ForexRate {
+                    symbol: symbol.to_string(),
+                    name: symbol.to_string(),
+                    rate: zfuel(*rate),
+                })
+                .collect(),
+            additional_data: None,
+            global_definition: ActionHash::from_raw_36(vec![0; 36]),
+        }
+    }
+
+    fn entries(changes: &[TableChange]) -> Vec<&str> {
+        changes.iter().map(|c| c.entry.as_str()).collect()
+    }
+
+    #[test]
+    fn prices_round_trip_through_zfuel_unchanged() {
+        for value in [
+            0.00123,
+            0.000000123456789,
+            1.0875,
+            162.5,
+            2500.5,
+            3150.25,
+            98765.4321,
+        ] {
+            assert_eq!(zfuel_to_f64(&zfuel(value)).unwrap(), value, "{}", value);
+        }
+    }
+
+    #[test]
+    fn identical_tables_have_no_changes() {
+        let previous = table(&[("1", 0.00123), ("2", 3150.25)], &[("EUR", 0.92)]);
+        let current = table(&[("2", 3150.25), ("1", 0.00123)], &[("EUR", 0.92)]);
+
+        assert!(changed_entries(&previous, &current, 0.0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn move_of_exactly_the_tolerance_does_not_count() {
+        // 2 -> 2.5 is +25% and 4 -> 3 is -25%, both exact in binary.
+        let previous = table(&[("1", 2.0)], &[("EUR", 4.0)]);
+        let current = table(&[("1", 2.5)], &[("EUR", 3.0)]);
+
+        assert!(changed_entries(&previous, &current, 25.0)
+            .unwrap()
+            .is_empty());
+        let changes = changed_entries(&previous, &current, 24.99).unwrap();
+        assert_eq!(entries(&changes), ["unit 1", "forex EUR"]);
+        assert_eq!(changes[0].change_percent(), Some(25.0));
+        assert_eq!(changes[1].change_percent(), Some(-25.0));
+    }
+
+    #[test]
+    fn float_noise_below_the_encoded_digits_is_not_a_change() {
+        let previous = table(&[("1", 0.3)], &[]);
+        let current = table(&[("1", 0.1 + 0.2)], &[]);
+
+        assert!(changed_entries(&previous, &current, 0.0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn entries_on_one_side_count_at_any_tolerance() {
+        let previous = table(&[("2", 1.0), ("10", 1.0)], &[("EUR", 0.92)]);
+        let current = table(&[("2", 1.0), ("3", 1.0)], &[("JPY", 150.0)]);
+
+        let changes = changed_entries(&previous, &current, 1000.0).unwrap();
+        assert_eq!(
+            entries(&changes),
+            ["unit 3", "unit 10", "forex EUR", "forex JPY"]
+        );
+        assert_eq!((changes[0].previous, changes[0].current), (None, Some(1.0)));
+        assert_eq!(changes[0].change_percent(), None);
+    }
+
+    #[test]
+    fn move_away_from_zero_always_counts() {
+        let previous = table(&[("1", 0.0)], &[]);
+        let current = table(&[("1", 0.000001)], &[]);
+
+        let changes = changed_entries(&previous, &current, 1000.0).unwrap();
+        assert_eq!(entries(&changes), ["unit 1"]);
+        assert_eq!(changes[0].change_percent(), None);
+    }
+}
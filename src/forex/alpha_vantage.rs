@@ -0,0 +1,150 @@
+use super::ForexSource;
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tracing::warn;
+
+/// Production API root; `source_endpoints` or `ALPHA_VANTAGE_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://www.alphavantage.co";
+
+/// Alpha Vantage `CURRENCY_EXCHANGE_RATE`: one request per symbol. Calls
+/// are counted across every batch of the run and stop at `call_budget`.
+pub struct AlphaVantage {
+    client: HttpClient,
+    base_url: String,
+    api_key: String,
+    call_budget: u32,
+    calls: AtomicU32,
+}
+
+impl AlphaVantage {
+    pub fn new(client: HttpClient, base_url: String, api_key: String, call_budget: u32) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            call_budget,
+            calls: AtomicU32::new(0),
+        }
+    }
+
+    /// Take one call from the budget; `false` once it is spent.
+    fn take_call(&self) -> bool {
+        self.calls
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.call_budget).then_some(n + 1)
+            })
+            .is_ok()
+    }
+}
+
+/// What one request answered.
+enum Quote {
+    Rate(f64),
+    /// `Note` / `Information`: the per-minute or daily limit was hit.
+    Throttled(String),
+    /// Logged and skipped; the other symbols still count.
+    Failed(String),
+}
+
+#[async_trait]
+impl ForexSource for AlphaVantage {
+    fn name(&self) -> &str {
+        "alpha_vantage"
+    }
+
+    /// Symbols one at a time. A throttle message or a spent budget returns
+    /// the rates fetched so far.
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        let mut rates = HashMap::new();
+        if symbols.iter().any(|s| s == "USD") {
+            rates.insert("USD".to_string(), 1.0);
+        }
+        for symbol in symbols.iter().filter(|s| *s != "USD") {
+            if !self.take_call() {
+                warn!(
+                    "Alpha Vantage call budget of {} used up at {}; returning {} partial rate(s)",
+                    self.call_budget,
+                    symbol,
+                    rates.len()
+                );
+                break;
+            }
+
+            let request = self
+                .client
+                .get(&format!("{}/query", self.base_url))
+                .query(&[
+                    ("function", "CURRENCY_EXCHANGE_RATE"),
+                    ("from_currency", "USD"),
+                    ("to_currency", symbol.as_str()),
+                    ("apikey", self.api_key.as_str()),
+                ]);
+            let resp = self
+                .client
+                .send(request)
+                .await
+                .with_context(|| format!("Alpha Vantage request failed for {}", symbol))?;
+            let status = resp.status();
+            if !status.is_success() {
+                warn!(
+                    "Alpha Vantage USD/{} failed (HTTP {}): {} — ignored",
+                    symbol,
+                    status,
+                    resp.text()
+                );
+                continue;
+            }
+            let body: serde_json::Value = resp
+                .json()
+                .with_context(|| format!("Alpha Vantage parse failed for {}", symbol))?;
+
+            match parse_quote(&body) {
+                Quote::Rate(rate) => {
+                    rates.insert(symbol.clone(), rate);
+                }
+                Quote::Throttled(message) => {
+                    warn!(
+                        "Alpha Vantage quota reached at {} ({}); returning {} partial rate(s)",
+                        symbol,
+                        message,
+                        rates.len()
+                    );
+                    break;
+                }
+                Quote::Failed(reason) => {
+                    warn!("Alpha Vantage USD/{} failed ({}) — ignored", symbol, reason);
+                }
+            }
+        }
+
+        if rates.is_empty() {
+            anyhow::bail!("Alpha Vantage did not return any forex rates");
+        }
+        Ok(rates)
+    }
+}
+
+fn parse_quote(body: &serde_json::Value) -> Quote {
+    for key in ["Note", "Information"] {
+        if let Some(message) = body.get(key).and_then(|v| v.as_str()) {
+            return Quote::Throttled(message.to_string());
+        }
+    }
+    if let Some(message) = body.get("Error Message").and_then(|v| v.as_str()) {
+        return Quote::Failed(format!("API error: {}", message));
+    }
+    let Some(rate_str) = body
+        .get("Realtime Currency Exchange Rate")
+        .and_then(|q| q.get("5. Exchange Rate"))
+        .and_then(|v| v.as_str())
+    else {
+        return Quote::Failed("missing exchange rate".to_string());
+    };
+    match rate_str.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Quote::Rate(rate),
+        _ => Quote::Failed(format!("invalid rate '{}'", rate_str)),
+    }
+}
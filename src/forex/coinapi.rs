@@ -1,17 +1,113 @@
 use super::ForexSource;
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
 use std::collections::HashMap;
 use tracing::warn;
 
+/// Production API root; `source_endpoints` or `COINAPI_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://rest.coinapi.io";
+
 pub struct CoinApi {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
     api_key: String,
 }
 
 impl CoinApi {
-    pub fn new(client: reqwest::Client, api_key: String) -> Self {
-        Self { client, api_key }
+    pub fn new(client: HttpClient, base_url: String, api_key: String) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+}
+
+/// Per-symbol requests in flight at once, for symbols the all-rates call
+/// did not cover.
+const PER_SYMBOL_CONCURRENCY: usize = 4;
+
+/// One per-symbol lookup.
+enum Lookup {
+    Rate(String, f64),
+    Skipped,
+    Quota(String),
+}
+
+impl CoinApi {
+    /// Every USD rate in one call (`/v1/exchangerate/USD`), keyed by quote
+    /// asset. `None` when the call fails or hits the quota, so the
+    /// per-symbol path can take over.
+    async fn fetch_all_rates(&self) -> Result<Option<HashMap<String, f64>>> {
+        let url = format!("{}/v1/exchangerate/USD", self.base_url);
+        let request = self
+            .client
+            .get(&url)
+            .query(&[("invert", "false")])
+            .header("X-CoinAPI-Key", &self.api_key);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("CoinAPI request failed for USD rates")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            warn!(
+                "CoinAPI USD rates failed (HTTP {}): {} — trying per symbol",
+                status,
+                resp.text()
+            );
+            return Ok(None);
+        }
+        let body: serde_json::Value = resp.json().context("CoinAPI parse failed for USD rates")?;
+        let Some(entries) = body.get("rates").and_then(|v| v.as_array()) else {
+            warn!("CoinAPI USD rates failed (missing rates) — trying per symbol");
+            return Ok(None);
+        };
+        let rates = entries
+            .iter()
+            .filter_map(|e| {
+                let quote = e.get("asset_id_quote")?.as_str()?;
+                let rate = e.get("rate")?.as_f64()?;
+                Some((quote.to_string(), rate))
+            })
+            .collect();
+        Ok(Some(rates))
+    }
+
+    async fn fetch_rate(&self, symbol: &str) -> Result<Lookup> {
+        let url = format!("{}/v1/exchangerate/USD/{}", self.base_url, symbol);
+        let request = self.client.get(&url).header("X-CoinAPI-Key", &self.api_key);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .with_context(|| format!("CoinAPI request failed for USD/{}", symbol))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            if is_quota_error(&body) {
+                return Ok(Lookup::Quota(symbol.to_string()));
+            }
+            warn!(
+                "CoinAPI USD/{} failed (HTTP {}): {} — ignored",
+                symbol, status, body
+            );
+            return Ok(Lookup::Skipped);
+        }
+
+        let body: serde_json::Value = resp
+            .json()
+            .with_context(|| format!("CoinAPI parse failed for USD/{}", symbol))?;
+        let Some(rate) = body.get("rate").and_then(|v| v.as_f64()) else {
+            warn!("CoinAPI USD/{} failed (missing rate) — ignored", symbol);
+            return Ok(Lookup::Skipped);
+        };
+        Ok(Lookup::Rate(symbol.to_string(), rate))
     }
 }
 
@@ -21,28 +117,44 @@ impl ForexSource for CoinApi {
         "coinapi"
     }
 
+    /// One all-rates call, then per-symbol requests (a few at a time) for
+    /// anything it lacked. A quota error stops the per-symbol requests and
+    /// returns the rates fetched so far.
     async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
         let mut rates = HashMap::new();
+        let mut missing = Vec::new();
+        let foreign: Vec<&String> = symbols.iter().filter(|s| *s != "USD").collect();
+        if foreign.len() < symbols.len() {
+            rates.insert("USD".to_string(), 1.0);
+        }
 
-        for symbol in symbols {
-            if symbol == "USD" {
-                rates.insert(symbol.clone(), 1.0);
-                continue;
+        let all = match foreign.len() {
+            0 => Some(HashMap::new()),
+            // A single symbol is one request either way.
+            1 => None,
+            _ => self.fetch_all_rates().await?,
+        };
+        for symbol in foreign {
+            match all.as_ref().and_then(|all| all.get(symbol.as_str())) {
+                Some(rate) => {
+                    rates.insert(symbol.clone(), *rate);
+                }
+                None => missing.push(symbol.as_str()),
             }
+        }
 
-            let url = format!("https://rest.coinapi.io/v1/exchangerate/USD/{}", symbol);
-            let resp = self
-                .client
-                .get(&url)
-                .header("X-CoinAPI-Key", &self.api_key)
-                .send()
-                .await
-                .with_context(|| format!("CoinAPI request failed for USD/{}", symbol))?;
-
-            let status = resp.status();
-            if !status.is_success() {
-                let body = resp.text().await.unwrap_or_default();
-                if is_quota_error(&body) {
+        let lookups: Vec<_> = missing
+            .iter()
+            .map(|symbol| self.fetch_rate(symbol))
+            .collect();
+        let mut lookups = stream::iter(lookups).buffer_unordered(PER_SYMBOL_CONCURRENCY);
+        while let Some(lookup) = lookups.next().await {
+            match lookup? {
+                Lookup::Rate(symbol, rate) => {
+                    rates.insert(symbol, rate);
+                }
+                Lookup::Skipped => {}
+                Lookup::Quota(symbol) => {
                     warn!(
                         "CoinAPI quota reached at USD/{}; returning {} partial rate(s)",
                         symbol,
@@ -50,22 +162,7 @@ impl ForexSource for CoinApi {
                     );
                     break;
                 }
-                warn!(
-                    "CoinAPI USD/{} failed (HTTP {}): {} — ignored",
-                    symbol, status, body
-                );
-                continue;
             }
-
-            let body: serde_json::Value = resp
-                .json()
-                .await
-                .with_context(|| format!("CoinAPI parse failed for USD/{}", symbol))?;
-            let Some(rate) = body.get("rate").and_then(|v| v.as_f64()) else {
-                warn!("CoinAPI USD/{} failed (missing rate) — ignored", symbol);
-                continue;
-            };
-            rates.insert(symbol.clone(), rate);
         }
 
         if rates.is_empty() {
@@ -83,3 +180,133 @@ fn is_quota_error(message: &str) -> bool {
         || msg.contains("subscription")
         || msg.contains("forbidden")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Without retries, so the 5xx and 429 answers are final.
+    fn source(server: &MockServer) -> CoinApi {
+        CoinApi::new(
+            HttpClient::new(reqwest::Client::new()).with_retries(0),
+            server.uri(),
+            "test-key".to_string(),
+        )
+    }
+
+    async fn mount(server: &MockServer, route: &str, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .and(header("X-CoinAPI-Key", "test-key"))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    fn symbols(symbols: &[&str]) -> Vec<String> {
+        symbols.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn all_rates_call_covers_most_and_the_rest_is_fetched_per_symbol() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            "/v1/exchangerate/USD",
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "asset_id_base": "USD",
+                "rates": [
+                    { "time": "2026-10-16T11:59:00.0000000Z", "asset_id_quote": "EUR", "rate": 0.92 },
+                    { "time": "2026-10-16T11:59:00.0000000Z", "asset_id_quote": "GBP", "rate": 0.79 },
+                    { "time": "2026-10-16T11:59:00.0000000Z", "asset_id_quote": "BTC" }
+                ]
+            })),
+        )
+        .await;
+        mount(
+            &server,
+            "/v1/exchangerate/USD/JPY",
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "time": "2026-10-16T11:59:00.0000000Z",
+                "asset_id_base": "USD",
+                "asset_id_quote": "JPY",
+                "rate": 150.1
+            })),
+        )
+        .await;
+
+        let rates = source(&server)
+            .fetch_rates(&symbols(&["USD", "EUR", "JPY"]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rates,
+            HashMap::from([
+                ("USD".to_string(), 1.0),
+                ("EUR".to_string(), 0.92),
+                ("JPY".to_string(), 150.1),
+            ])
+        );
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_all_rates_call_falls_back_to_per_symbol_requests() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            "/v1/exchangerate/USD",
+            ResponseTemplate::new(500).set_body_string("internal error"),
+        )
+        .await;
+        for (symbol, rate) in [("EUR", 0.92), ("GBP", 0.79)] {
+            mount(
+                &server,
+                &format!("/v1/exchangerate/USD/{}", symbol),
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "rate": rate })),
+            )
+            .await;
+        }
+
+        let rates = source(&server)
+            .fetch_rates(&symbols(&["EUR", "GBP"]))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rates,
+            HashMap::from([("EUR".to_string(), 0.92), ("GBP".to_string(), 0.79)])
+        );
+    }
+
+    #[tokio::test]
+    async fn quota_error_on_the_fallback_returns_what_was_fetched() {
+        let server = MockServer::start().await;
+        mount(
+            &server,
+            "/v1/exchangerate/USD",
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "rates": [{ "asset_id_quote": "EUR", "rate": 0.92 }]
+            })),
+        )
+        .await;
+        mount(
+            &server,
+            "/v1/exchangerate/USD/JPY",
+            ResponseTemplate::new(429)
+                .set_body_json(serde_json::json!({ "error": "Quota exceeded: daily limit" })),
+        )
+        .await;
+
+        let rates = source(&server)
+            .fetch_rates(&symbols(&["EUR", "JPY"]))
+            .await
+            .unwrap();
+
+        assert_eq!(rates, HashMap::from([("EUR".to_string(), 0.92)]));
+    }
+}
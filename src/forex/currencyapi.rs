@@ -0,0 +1,109 @@
+use super::ForexSource;
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Production API root; `source_endpoints` or `CURRENCYAPI_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.currencyapi.com";
+
+/// currencyapi.com `/v3/latest` with `base_currency=USD`: every symbol in
+/// one request.
+pub struct CurrencyApi {
+    client: HttpClient,
+    base_url: String,
+    api_key: String,
+}
+
+impl CurrencyApi {
+    pub fn new(client: HttpClient, base_url: String, api_key: String) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+}
+
+/// `{"data": {"EUR": {"code": "EUR", "value": 0.92}, ...}}`
+#[derive(Deserialize)]
+struct LatestResponse {
+    data: HashMap<String, Quote>,
+}
+
+#[derive(Deserialize)]
+struct Quote {
+    value: f64,
+}
+
+#[async_trait]
+impl ForexSource for CurrencyApi {
+    fn name(&self) -> &str {
+        "currencyapi"
+    }
+
+    /// A 429 with `X-RateLimit-Remaining: 0` returns the rates fetched so far.
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        let mut rates = HashMap::new();
+        if symbols.iter().any(|s| s == "USD") {
+            rates.insert("USD".to_string(), 1.0);
+        }
+        let foreign: Vec<&str> = symbols
+            .iter()
+            .map(String::as_str)
+            .filter(|s| *s != "USD")
+            .collect();
+
+        if !foreign.is_empty() {
+            let currencies = foreign.join(",");
+            let request = self
+                .client
+                .get(&format!("{}/v3/latest", self.base_url))
+                .query(&[
+                    ("apikey", self.api_key.as_str()),
+                    ("base_currency", "USD"),
+                    ("currencies", currencies.as_str()),
+                ]);
+            let resp = self
+                .client
+                .send(request)
+                .await
+                .with_context(|| format!("currencyapi request failed for {}", currencies))?;
+
+            let status = resp.status();
+            if status.as_u16() == 429 && resp.header("x-ratelimit-remaining") == Some("0") {
+                warn!(
+                    "currencyapi quota reached at {}; returning {} partial rate(s)",
+                    currencies,
+                    rates.len()
+                );
+            } else if !status.is_success() {
+                anyhow::bail!("currencyapi returned HTTP {}: {}", status, resp.text());
+            } else {
+                let body: LatestResponse = resp.json().context("currencyapi parse failed")?;
+                let mut missing = Vec::new();
+                for symbol in foreign {
+                    match body.data.get(symbol) {
+                        Some(quote) if quote.value.is_finite() && quote.value > 0.0 => {
+                            rates.insert(symbol.to_string(), quote.value);
+                        }
+                        _ => missing.push(symbol),
+                    }
+                }
+                if !missing.is_empty() {
+                    warn!(
+                        "currencyapi returned no rate for {} — ignored",
+                        missing.join(", ")
+                    );
+                }
+            }
+        }
+
+        if rates.is_empty() {
+            anyhow::bail!("currencyapi did not return any forex rates");
+        }
+        Ok(rates)
+    }
+}
@@ -0,0 +1,246 @@
+use super::ForexSource;
+use crate::clock::Clock;
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+/// Production root; `source_endpoints` or `ECB_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://www.ecb.europa.eu";
+
+/// Oldest reference date still accepted. The ECB publishes on TARGET working
+/// days only, so a Friday document is current until Monday afternoon, and
+/// until Tuesday over Easter.
+const MAX_AGE_DAYS: i64 = 4;
+
+/// European Central Bank daily reference rates. Keyless; one request covers
+/// every symbol. The rates are EUR-based and converted to foreign units per
+/// USD through the USD rate in the same document.
+pub struct Ecb {
+    client: HttpClient,
+    base_url: String,
+    clock: Arc<dyn Clock>,
+}
+
+impl Ecb {
+    pub fn new(client: HttpClient, base_url: String, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            client,
+            base_url,
+            clock,
+        }
+    }
+}
+
+#[async_trait]
+impl ForexSource for Ecb {
+    fn name(&self) -> &str {
+        "ecb"
+    }
+
+    /// Symbols the ECB does not publish are left out, so other providers
+    /// still cover them.
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        let url = format!("{}/stats/eurofxref/eurofxref-daily.xml", self.base_url);
+        let resp = self
+            .client
+            .send(self.client.get(&url))
+            .await
+            .context("ECB request failed")?;
+        let status = resp.status();
+        if !status.is_success() {
+            anyhow::bail!("ECB returned HTTP {}", status);
+        }
+
+        let (date, eur_rates) = parse_daily(&resp.text()).context("ECB parse failed")?;
+        let age = (self.clock.now().date_naive() - date).num_days();
+        if age > MAX_AGE_DAYS {
+            anyhow::bail!("ECB reference rates are from {} ({} days old)", date, age);
+        }
+
+        let rates = usd_rates(&eur_rates, symbols)?;
+        for symbol in symbols.iter().filter(|s| !rates.contains_key(*s)) {
+            debug!("ECB does not publish {}; skipped", symbol);
+        }
+        if rates.is_empty() {
+            anyhow::bail!("ECB did not return any forex rates");
+        }
+        Ok(rates)
+    }
+}
+
+/// The reference date and `currency -> units per EUR` from
+/// `eurofxref-daily.xml`.
+fn parse_daily(xml: &str) -> Result<(NaiveDate, HashMap<String, f64>)> {
+    let mut date = None;
+    let mut rates = HashMap::new();
+    for tag in xml.split("<Cube").skip(1) {
+        let tag = tag.split('>').next().unwrap_or_default();
+        if let Some(time) = attribute(tag, "time") {
+            date = Some(
+                NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                    .with_context(|| format!("invalid time '{}'", time))?,
+            );
+        }
+        if let (Some(currency), Some(rate)) = (attribute(tag, "currency"), attribute(tag, "rate")) {
+            let value: f64 = rate
+                .parse()
+                .with_context(|| format!("invalid rate '{}' for {}", rate, currency))?;
+            rates.insert(currency.to_string(), value);
+        }
+    }
+    let date = date.context("no reference date in document")?;
+    if rates.is_empty() {
+        anyhow::bail!("no rates in document");
+    }
+    Ok((date, rates))
+}
+
+/// Value of `name='...'` or `name="..."` inside one tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = tag;
+    while let Some(pos) = rest.find(name) {
+        let preceded_by_space = rest[..pos].ends_with(char::is_whitespace);
+        rest = &rest[pos + name.len()..];
+        if !preceded_by_space {
+            continue;
+        }
+        let Some(value) = rest.trim_start().strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = value.chars().next().filter(|c| *c == '\'' || *c == '"') else {
+            continue;
+        };
+        return value[1..].split(quote).next();
+    }
+    None
+}
+
+/// Foreign units per USD for the requested `symbols`: `rate_X / rate_USD`,
+/// with EUR at `1 / rate_USD`.
+fn usd_rates(eur_rates: &HashMap<String, f64>, symbols: &[String]) -> Result<HashMap<String, f64>> {
+    let usd = *eur_rates
+        .get("USD")
+        .context("ECB document has no USD rate")?;
+    if !usd.is_finite() || usd <= 0.0 {
+        anyhow::bail!("ECB USD rate {} is not positive", usd);
+    }
+    let mut rates = HashMap::new();
+    for symbol in symbols {
+        let per_eur = match symbol.as_str() {
+            "EUR" => 1.0,
+            s => match eur_rates.get(s) {
+                Some(rate) if rate.is_finite() && *rate > 0.0 => *rate,
+                _ => continue,
+            },
+        };
+        rates.insert(symbol.clone(), per_eur / usd);
+    }
+    Ok(rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use chrono::{DateTime, Utc};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// `eurofxref-daily.xml` for Friday 2026-10-16, trimmed to a few
+    /// currencies.
+    const DAILY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<gesmes:Sender>
+		<gesmes:name>European Central Bank</gesmes:name>
+	</gesmes:Sender>
+	<Cube>
+		<Cube time='2026-10-16'>
+			<Cube currency='USD' rate='1.0875'/>
+			<Cube currency='JPY' rate='162.50'/>
+			<Cube currency='GBP' rate='0.8350'/>
+		</Cube>
+	</Cube>
+</gesmes:Envelope>
+"#;
+
+    fn symbols(symbols: &[&str]) -> Vec<String> {
+        symbols.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_the_reference_date_and_rates() {
+        let (date, rates) = parse_daily(DAILY).unwrap();
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2026, 10, 16).unwrap());
+        assert_eq!(
+            rates,
+            HashMap::from([
+                ("USD".to_string(), 1.0875),
+                ("JPY".to_string(), 162.5),
+                ("GBP".to_string(), 0.835),
+            ])
+        );
+    }
+
+    #[test]
+    fn document_without_rates_is_an_error() {
+        let xml = "<gesmes:Envelope><Cube><Cube time='2026-10-16'></Cube></Cube></gesmes:Envelope>";
+        assert_eq!(
+            parse_daily(xml).unwrap_err().to_string(),
+            "no rates in document"
+        );
+    }
+
+    #[test]
+    fn eur_rates_are_converted_through_usd() {
+        let (_, eur_rates) = parse_daily(DAILY).unwrap();
+
+        let rates = usd_rates(&eur_rates, &symbols(&["EUR", "JPY", "CHF"])).unwrap();
+
+        assert_eq!(rates.len(), 2, "CHF is not published: {:?}", rates);
+        assert!((rates["EUR"] - 1.0 / 1.0875).abs() < 1e-12);
+        assert!((rates["JPY"] - 162.5 / 1.0875).abs() < 1e-9);
+    }
+
+    async fn fetch_on(today: &str) -> Result<HashMap<String, f64>> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/stats/eurofxref/eurofxref-daily.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(DAILY))
+            .mount(&server)
+            .await;
+        let now: DateTime<Utc> = format!("{}T15:00:00Z", today).parse().unwrap();
+        Ecb::new(
+            HttpClient::new(reqwest::Client::new()),
+            server.uri(),
+            Arc::new(ManualClock::new(now)),
+        )
+        .fetch_rates(&symbols(&["USD", "GBP"]))
+        .await
+    }
+
+    #[tokio::test]
+    async fn friday_rates_last_over_the_weekend() {
+        // Saturday, Monday, and a Tuesday after a Monday holiday.
+        for today in ["2026-10-17", "2026-10-19", "2026-10-20"] {
+            let rates = fetch_on(today).await.unwrap();
+            assert_eq!(rates["USD"], 1.0, "{}", today);
+            assert!((rates["GBP"] - 0.835 / 1.0875).abs() < 1e-12, "{}", today);
+        }
+    }
+
+    #[tokio::test]
+    async fn rates_older_than_four_days_are_stale() {
+        let err = fetch_on("2026-10-21").await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "ECB reference rates are from 2026-10-16 (5 days old)"
+        );
+    }
+}
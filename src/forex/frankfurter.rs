@@ -0,0 +1,87 @@
+use super::ForexSource;
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Production API root; `source_endpoints` or `FRANKFURTER_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.frankfurter.app";
+
+/// Frankfurter `/latest`: keyless, every symbol in one request.
+pub struct Frankfurter {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Frankfurter {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[derive(Deserialize)]
+struct LatestResponse {
+    rates: HashMap<String, f64>,
+}
+
+#[async_trait]
+impl ForexSource for Frankfurter {
+    fn name(&self) -> &str {
+        "frankfurter"
+    }
+
+    /// Rates come back USD-based, so they map directly. Symbols Frankfurter
+    /// does not know are dropped from the response and logged.
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        let mut rates = HashMap::new();
+        if symbols.iter().any(|s| s == "USD") {
+            rates.insert("USD".to_string(), 1.0);
+        }
+        let foreign: Vec<&str> = symbols
+            .iter()
+            .map(String::as_str)
+            .filter(|s| *s != "USD")
+            .collect();
+
+        if !foreign.is_empty() {
+            let to = foreign.join(",");
+            let request = self
+                .client
+                .get(&format!("{}/latest", self.base_url))
+                .query(&[("from", "USD"), ("to", to.as_str())]);
+            let resp = self
+                .client
+                .send(request)
+                .await
+                .with_context(|| format!("Frankfurter request failed for {}", to))?;
+            let status = resp.status();
+            if !status.is_success() {
+                anyhow::bail!("Frankfurter returned HTTP {}: {}", status, resp.text());
+            }
+            let body: LatestResponse = resp.json().context("Frankfurter parse failed")?;
+
+            let mut missing = Vec::new();
+            for symbol in foreign {
+                match body.rates.get(symbol) {
+                    Some(rate) if rate.is_finite() && *rate > 0.0 => {
+                        rates.insert(symbol.to_string(), *rate);
+                    }
+                    _ => missing.push(symbol),
+                }
+            }
+            if !missing.is_empty() {
+                warn!(
+                    "Frankfurter returned no rate for {} — ignored",
+                    missing.join(", ")
+                );
+            }
+        }
+
+        if rates.is_empty() {
+            anyhow::bail!("Frankfurter did not return any forex rates");
+        }
+        Ok(rates)
+    }
+}
@@ -0,0 +1,58 @@
+//! Scripted forex source for exercising the pipeline without network access.
+//! Built for tests and with the `testing` feature.
+
+use super::ForexSource;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Returns fixed USD-based rates, or fails every call.
+pub struct MockForexSource {
+    name: String,
+    rates: Result<HashMap<String, f64>, String>,
+    delay: Duration,
+}
+
+impl MockForexSource {
+    /// Answer with `rates` (foreign units per USD); symbols not in the map
+    /// are left out, as a provider with partial coverage would.
+    pub fn new(name: &str, rates: HashMap<String, f64>) -> Self {
+        Self {
+            name: name.to_string(),
+            rates: Ok(rates),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Fail every `fetch_rates` with `message`.
+    pub fn failing(name: &str, message: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            rates: Err(message.to_string()),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Sleep this long before answering each call, like a slow API.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[async_trait]
+impl ForexSource for MockForexSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        tokio::time::sleep(self.delay).await;
+        let rates = self.rates.as_ref().map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(symbols
+            .iter()
+            .filter_map(|s| rates.get(s).map(|rate| (s.clone(), *rate)))
+            .collect())
+    }
+}
@@ -1,9 +1,35 @@
+pub mod alpha_vantage;
 pub mod coinapi;
+pub mod currencyapi;
+pub mod ecb;
+pub mod frankfurter;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+pub mod open_exchange_rates;
 pub mod twelve_data;
 
+use crate::clock::{Clock, SystemClock};
+use crate::config::Config;
+use crate::http::HttpClient;
+use crate::sources::{client_for, endpoint, DEFAULT_SOURCE_TIMEOUT};
+use crate::types::SourceResult;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
+
+/// Names of the built-in forex providers, as returned by `ForexSource::name`.
+pub const BUILTIN_FOREX_NAMES: &[&str] = &[
+    "twelve_data",
+    "coinapi",
+    "ecb",
+    "frankfurter",
+    "open_exchange_rates",
+    "currencyapi",
+    "alpha_vantage",
+];
 
 #[async_trait]
 pub trait ForexSource: Send + Sync {
@@ -13,51 +39,308 @@ pub trait ForexSource: Send + Sync {
 
 pub struct ForexSourceRegistry {
     sources: Vec<Box<dyn ForexSource>>,
+    clock: Arc<dyn Clock>,
+    /// Per-provider limit for one `fetch_rates`.
+    timeout: Duration,
 }
 
 impl ForexSourceRegistry {
-    pub fn new(
-        client: reqwest::Client,
-        twelve_data_api_key: Option<String>,
-        coinapi_api_key: Option<String>,
-        use_twelve_data: bool,
-        use_coinapi: bool,
-    ) -> Self {
-        let mut sources: Vec<Box<dyn ForexSource>> = Vec::new();
-
-        if use_twelve_data {
-            if let Some(key) = twelve_data_api_key {
-                sources.push(Box::new(twelve_data::TwelveData::new(client.clone(), key)));
-            } else {
-                tracing::warn!("TWELVE_DATA_API_KEY not set; Twelve Data forex source disabled");
+    pub fn builder(client: HttpClient) -> ForexSourceRegistryBuilder {
+        ForexSourceRegistryBuilder {
+            client,
+            endpoints: HashMap::new(),
+            request_timeouts: HashMap::new(),
+            registry: ForexSourceRegistry {
+                sources: Vec::new(),
+                clock: Arc::new(SystemClock),
+                timeout: DEFAULT_SOURCE_TIMEOUT,
+            },
+        }
+    }
+
+    /// Config/env-driven registry used by `main`: each provider is added when
+    /// its `forex.use_*` toggle is on and its API key env var is set. The ECB
+    /// and Frankfurter need no key and are added on their toggle alone.
+    pub fn from_config(client: HttpClient, cfg: &Config, clock: Arc<dyn Clock>) -> Self {
+        let forex = &cfg.forex;
+        let mut builder = Self::builder(client)
+            .with_clock(clock)
+            .with_timeout(Duration::from_secs(forex.timeout_secs))
+            .with_endpoints(cfg.source_endpoints.clone())
+            .with_request_timeouts(cfg.http.source_timeouts.clone());
+
+        if forex.use_twelve_data {
+            match std::env::var("TWELVE_DATA_API_KEY") {
+                Ok(key) => builder = builder.with_twelve_data(key),
+                Err(_) => {
+                    tracing::warn!("TWELVE_DATA_API_KEY not set; Twelve Data forex source disabled")
+                }
+            }
+        }
+
+        if forex.use_coinapi {
+            match std::env::var("COINAPI_API_KEY") {
+                Ok(key) => builder = builder.with_coinapi(key),
+                Err(_) => tracing::warn!("COINAPI_API_KEY not set; CoinAPI forex source disabled"),
             }
         }
 
-        if use_coinapi {
-            if let Some(key) = coinapi_api_key {
-                sources.push(Box::new(coinapi::CoinApi::new(client, key)));
-            } else {
-                tracing::warn!("COINAPI_API_KEY not set; CoinAPI forex source disabled");
+        if forex.use_open_exchange_rates {
+            match std::env::var("OXR_APP_ID") {
+                Ok(app_id) => builder = builder.with_open_exchange_rates(app_id),
+                Err(_) => {
+                    tracing::warn!("OXR_APP_ID not set; Open Exchange Rates forex source disabled")
+                }
             }
         }
 
-        Self { sources }
+        if forex.use_currencyapi {
+            match std::env::var("CURRENCYAPI_KEY") {
+                Ok(key) => builder = builder.with_currencyapi(key),
+                Err(_) => {
+                    tracing::warn!("CURRENCYAPI_KEY not set; currencyapi forex source disabled")
+                }
+            }
+        }
+
+        if forex.use_alpha_vantage {
+            match std::env::var("ALPHAVANTAGE_API_KEY") {
+                Ok(key) => {
+                    builder = builder.with_alpha_vantage(key, forex.alpha_vantage_call_budget)
+                }
+                Err(_) => tracing::warn!(
+                    "ALPHAVANTAGE_API_KEY not set; Alpha Vantage forex source disabled"
+                ),
+            }
+        }
+
+        if forex.use_ecb {
+            builder = builder.with_ecb();
+        }
+
+        if forex.use_frankfurter {
+            builder = builder.with_frankfurter();
+        }
+
+        builder.build()
+    }
+
+    /// Registry over exactly `sources`, on the system clock with the default
+    /// timeout. Use [`ForexSourceRegistry::builder`] to change either.
+    pub fn with_sources(sources: Vec<Box<dyn ForexSource>>) -> Self {
+        Self {
+            sources,
+            clock: Arc::new(SystemClock),
+            timeout: DEFAULT_SOURCE_TIMEOUT,
+        }
+    }
+
+    /// Add a provider; it is queried alongside the others.
+    pub fn register(&mut self, source: Box<dyn ForexSource>) {
+        self.sources.push(source);
     }
 
     pub fn source_count(&self) -> usize {
         self.sources.len()
     }
 
-    pub async fn fetch_all(
-        &self,
-        symbols: &[String],
-    ) -> Vec<(String, Result<HashMap<String, f64>>)> {
-        let mut results = Vec::new();
-        for source in &self.sources {
+    /// Query every provider concurrently; results keep registration order.
+    /// A provider slower than the registry timeout yields an `Err`.
+    pub async fn fetch_all(&self, symbols: &[String]) -> Vec<SourceResult<HashMap<String, f64>>> {
+        let fetches = self.sources.iter().map(|source| {
             let name = source.name().to_string();
-            let result = source.fetch_rates(symbols).await;
-            results.push((name, result));
-        }
-        results
+            let span = tracing::info_span!("forex_fetch", source = %name);
+            async move {
+                let started = self.clock.now();
+                let fetch = source.fetch_rates(symbols);
+                let result = match tokio::time::timeout(self.timeout, fetch).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow::anyhow!(
+                        "timed out after {}s",
+                        self.timeout.as_secs()
+                    )),
+                };
+                let elapsed_ms = (self.clock.now() - started).num_milliseconds();
+                tracing::debug!("forex source '{}' took {}ms", name, elapsed_ms);
+                SourceResult {
+                    source: name,
+                    result,
+                    elapsed_ms,
+                }
+            }
+            .instrument(span)
+        });
+        futures::future::join_all(fetches).await
+    }
+}
+
+pub struct ForexSourceRegistryBuilder {
+    client: HttpClient,
+    /// Base URL overrides by provider name (`source_endpoints`).
+    endpoints: HashMap<String, String>,
+    /// Request timeout overrides in seconds by provider name.
+    request_timeouts: HashMap<String, u64>,
+    registry: ForexSourceRegistry,
+}
+
+impl ForexSourceRegistryBuilder {
+    pub fn with(mut self, source: Box<dyn ForexSource>) -> Self {
+        self.registry.register(source);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.registry.clock = clock;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.registry.timeout = timeout;
+        self
+    }
+
+    /// Base URLs for providers added after this call, keyed by provider name.
+    pub fn with_endpoints(mut self, endpoints: HashMap<String, String>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// HTTP request timeouts in seconds for providers added after this call,
+    /// keyed by provider name.
+    pub fn with_request_timeouts(mut self, timeouts: HashMap<String, u64>) -> Self {
+        self.request_timeouts = timeouts;
+        self
+    }
+
+    pub fn with_twelve_data(self, api_key: String) -> Self {
+        let source = twelve_data::TwelveData::new(
+            client_for(&self.client, &self.request_timeouts, "twelve_data"),
+            endpoint(
+                &self.endpoints,
+                "twelve_data",
+                "TWELVE_DATA_BASE_URL",
+                twelve_data::DEFAULT_BASE_URL,
+            ),
+            api_key,
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_coinapi(self, api_key: String) -> Self {
+        let source = coinapi::CoinApi::new(
+            client_for(&self.client, &self.request_timeouts, "coinapi"),
+            endpoint(
+                &self.endpoints,
+                "coinapi",
+                "COINAPI_BASE_URL",
+                coinapi::DEFAULT_BASE_URL,
+            ),
+            api_key,
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_open_exchange_rates(self, app_id: String) -> Self {
+        let source = open_exchange_rates::OpenExchangeRates::new(
+            client_for(&self.client, &self.request_timeouts, "open_exchange_rates"),
+            endpoint(
+                &self.endpoints,
+                "open_exchange_rates",
+                "OPEN_EXCHANGE_RATES_BASE_URL",
+                open_exchange_rates::DEFAULT_BASE_URL,
+            ),
+            app_id,
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_currencyapi(self, api_key: String) -> Self {
+        let source = currencyapi::CurrencyApi::new(
+            client_for(&self.client, &self.request_timeouts, "currencyapi"),
+            endpoint(
+                &self.endpoints,
+                "currencyapi",
+                "CURRENCYAPI_BASE_URL",
+                currencyapi::DEFAULT_BASE_URL,
+            ),
+            api_key,
+        );
+        self.with(Box::new(source))
+    }
+
+    /// `call_budget` caps the requests this provider makes over the whole run.
+    pub fn with_alpha_vantage(self, api_key: String, call_budget: u32) -> Self {
+        let source = alpha_vantage::AlphaVantage::new(
+            client_for(&self.client, &self.request_timeouts, "alpha_vantage"),
+            endpoint(
+                &self.endpoints,
+                "alpha_vantage",
+                "ALPHA_VANTAGE_BASE_URL",
+                alpha_vantage::DEFAULT_BASE_URL,
+            ),
+            api_key,
+            call_budget,
+        );
+        self.with(Box::new(source))
+    }
+
+    /// Uses the clock set so far; call [`Self::with_clock`] first.
+    pub fn with_ecb(self) -> Self {
+        let source = ecb::Ecb::new(
+            client_for(&self.client, &self.request_timeouts, "ecb"),
+            endpoint(
+                &self.endpoints,
+                "ecb",
+                "ECB_BASE_URL",
+                ecb::DEFAULT_BASE_URL,
+            ),
+            self.registry.clock.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_frankfurter(self) -> Self {
+        let source = frankfurter::Frankfurter::new(
+            client_for(&self.client, &self.request_timeouts, "frankfurter"),
+            endpoint(
+                &self.endpoints,
+                "frankfurter",
+                "FRANKFURTER_BASE_URL",
+                frankfurter::DEFAULT_BASE_URL,
+            ),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn build(self) -> ForexSourceRegistry {
+        self.registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockForexSource;
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_provider_times_out_and_the_fast_one_answers() {
+        let rates = HashMap::from([("EUR".to_string(), 0.92)]);
+        let registry = ForexSourceRegistry::builder(HttpClient::new(reqwest::Client::new()))
+            .with_timeout(Duration::from_secs(2))
+            .with(Box::new(
+                MockForexSource::new("slow", rates.clone()).with_delay(Duration::from_secs(60)),
+            ))
+            .with(Box::new(MockForexSource::new("fast", rates)))
+            .build();
+
+        let started = tokio::time::Instant::now();
+        let results = registry.fetch_all(&["EUR".to_string()]).await;
+
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        assert_eq!(
+            results[0].result.as_ref().unwrap_err().to_string(),
+            "timed out after 2s"
+        );
+        assert_eq!(results[1].result.as_ref().unwrap()["EUR"], 0.92);
     }
 }
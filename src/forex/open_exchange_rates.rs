@@ -0,0 +1,224 @@
+use super::ForexSource;
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Production API root; `source_endpoints` or `OPEN_EXCHANGE_RATES_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://openexchangerates.org";
+
+/// Open Exchange Rates `/api/latest.json`. Rates are USD-based on every
+/// plan, and only the requested symbols are asked for.
+pub struct OpenExchangeRates {
+    client: HttpClient,
+    base_url: String,
+    app_id: String,
+}
+
+impl OpenExchangeRates {
+    pub fn new(client: HttpClient, base_url: String, app_id: String) -> Self {
+        Self {
+            client,
+            base_url,
+            app_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct LatestResponse {
+    #[serde(default)]
+    rates: HashMap<String, f64>,
+}
+
+/// The body of a failed request, e.g. `{"error": true, "status": 403,
+/// "message": "access_restricted", "description": "..."}`.
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    message: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[async_trait]
+impl ForexSource for OpenExchangeRates {
+    fn name(&self) -> &str {
+        "open_exchange_rates"
+    }
+
+    async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
+        let joined = symbols.join(",");
+        let request = self
+            .client
+            .get(&format!("{}/api/latest.json", self.base_url))
+            .query(&[
+                ("app_id", self.app_id.as_str()),
+                ("symbols", joined.as_str()),
+            ]);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .with_context(|| format!("Open Exchange Rates request failed for {}", joined))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            let Ok(error) = serde_json::from_str::<ErrorEnvelope>(&body) else {
+                anyhow::bail!("Open Exchange Rates returned HTTP {}: {}", status, body);
+            };
+            if is_plan_error(status.as_u16(), &error.message) {
+                anyhow::bail!(
+                    "Open Exchange Rates quota or plan limit reached ({}): {}",
+                    error.message,
+                    error.description
+                );
+            }
+            anyhow::bail!(
+                "Open Exchange Rates returned HTTP {} ({}): {}",
+                status,
+                error.message,
+                error.description
+            );
+        }
+
+        let body: LatestResponse = resp.json().context("Open Exchange Rates parse failed")?;
+        let mut rates = HashMap::new();
+        let mut missing = Vec::new();
+        for symbol in symbols {
+            match body.rates.get(symbol) {
+                Some(rate) if rate.is_finite() && *rate > 0.0 => {
+                    rates.insert(symbol.clone(), *rate);
+                }
+                _ if symbol == "USD" => {
+                    rates.insert(symbol.clone(), 1.0);
+                }
+                _ => missing.push(symbol.as_str()),
+            }
+        }
+        if !missing.is_empty() {
+            warn!(
+                "Open Exchange Rates returned no rate for {} — ignored",
+                missing.join(", ")
+            );
+        }
+
+        if rates.is_empty() {
+            anyhow::bail!("Open Exchange Rates did not return any forex rates");
+        }
+        Ok(rates)
+    }
+}
+
+/// A request the plan does not cover (`access_restricted`) or the monthly
+/// allowance is used up, as opposed to a bad key or an outage.
+fn is_plan_error(status: u16, message: &str) -> bool {
+    matches!(status, 403 | 429)
+        && matches!(
+            message,
+            "access_restricted" | "not_allowed" | "too_many_requests"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn fetch(response: ResponseTemplate) -> Result<HashMap<String, f64>> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/latest.json"))
+            .and(query_param("app_id", "test-app"))
+            .and(query_param("symbols", "USD,EUR,JPY"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        let symbols = ["USD", "EUR", "JPY"].map(String::from);
+        OpenExchangeRates::new(
+            HttpClient::new(reqwest::Client::new()).with_retries(0),
+            server.uri(),
+            "test-app".to_string(),
+        )
+        .fetch_rates(&symbols)
+        .await
+    }
+
+    fn envelope(status: u16, message: &str, description: &str) -> ResponseTemplate {
+        ResponseTemplate::new(status).set_body_json(serde_json::json!({
+            "error": true,
+            "status": status,
+            "message": message,
+            "description": description
+        }))
+    }
+
+    #[tokio::test]
+    async fn parses_the_requested_rates() {
+        let rates = fetch(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "disclaimer": "Usage subject to terms: https://openexchangerates.org/terms",
+            "license": "https://openexchangerates.org/license",
+            "timestamp": 1791201600,
+            "base": "USD",
+            "rates": { "EUR": 0.92, "JPY": 150.1 }
+        })))
+        .await
+        .unwrap();
+
+        assert_eq!(
+            rates,
+            HashMap::from([
+                ("USD".to_string(), 1.0),
+                ("EUR".to_string(), 0.92),
+                ("JPY".to_string(), 150.1),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn plan_limit_envelope_is_reported_as_such() {
+        let err = fetch(envelope(
+            429,
+            "too_many_requests",
+            "Access restricted for repeated over-use (status: 429).",
+        ))
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Open Exchange Rates quota or plan limit reached (too_many_requests): Access restricted for repeated over-use (status: 429)."
+        );
+    }
+
+    #[tokio::test]
+    async fn other_error_envelope_keeps_status_and_message() {
+        let err = fetch(envelope(
+            401,
+            "invalid_app_id",
+            "Invalid App ID provided. Please sign up at https://openexchangerates.org/signup.",
+        ))
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Open Exchange Rates returned HTTP 401 Unauthorized (invalid_app_id): Invalid App ID provided. Please sign up at https://openexchangerates.org/signup."
+        );
+    }
+
+    #[tokio::test]
+    async fn body_that_is_not_an_envelope_is_shown_as_is() {
+        let err = fetch(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Open Exchange Rates returned HTTP 502 Bad Gateway: Bad Gateway"
+        );
+    }
+}
@@ -1,96 +1,136 @@
 use super::ForexSource;
+use crate::http::HttpClient;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use std::collections::HashMap;
 use tracing::warn;
 
+/// Production API root; `source_endpoints` or `TWELVE_DATA_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.twelvedata.com";
+
+/// Most pairs Twelve Data accepts in one `/price` request. Each pair still
+/// costs a credit; `forex.max_symbols_per_run` keeps a batch within the
+/// per-minute allowance.
+const MAX_PAIRS_PER_REQUEST: usize = 120;
+
 pub struct TwelveData {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
     api_key: String,
 }
 
 impl TwelveData {
-    pub fn new(client: reqwest::Client, api_key: String) -> Self {
-        Self { client, api_key }
+    pub fn new(client: HttpClient, base_url: String, api_key: String) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+        }
     }
 }
 
+/// What one batch answered for one pair.
+enum Quote {
+    Rate(f64),
+    /// Logged and skipped; the rest of the batch still counts.
+    Failed(String),
+}
+
 #[async_trait]
 impl ForexSource for TwelveData {
     fn name(&self) -> &str {
         "twelve_data"
     }
 
+    /// Every pair in one request (comma-separated `symbol`). A quota error
+    /// returns the rates fetched so far.
     async fn fetch_rates(&self, symbols: &[String]) -> Result<HashMap<String, f64>> {
         let mut rates = HashMap::new();
+        if symbols.iter().any(|s| s == "USD") {
+            rates.insert("USD".to_string(), 1.0);
+        }
+        let foreign: Vec<&String> = symbols.iter().filter(|s| *s != "USD").collect();
 
-        for symbol in symbols {
-            if symbol == "USD" {
-                rates.insert(symbol.clone(), 1.0);
-                continue;
-            }
-
-            let pair = format!("USD/{}", symbol);
+        for batch in foreign.chunks(MAX_PAIRS_PER_REQUEST) {
+            let pairs: Vec<String> = batch.iter().map(|s| format!("USD/{}", s)).collect();
+            let joined = pairs.join(",");
+            let request = self
+                .client
+                .get(&format!("{}/price", self.base_url))
+                .query(&[
+                    ("symbol", joined.as_str()),
+                    ("apikey", self.api_key.as_str()),
+                ]);
             let resp = self
                 .client
-                .get("https://api.twelvedata.com/price")
-                .query(&[("symbol", pair.as_str()), ("apikey", self.api_key.as_str())])
-                .send()
+                .send(request)
                 .await
-                .with_context(|| format!("Twelve Data request failed for {}", pair))?;
+                .with_context(|| format!("Twelve Data request failed for {}", joined))?;
 
             let status = resp.status();
             if !status.is_success() {
-                let body = resp.text().await.unwrap_or_default();
+                let body = resp.text();
                 if is_quota_error(&body) {
                     warn!(
                         "Twelve Data quota reached at {}; returning {} partial rate(s)",
-                        pair,
+                        joined,
                         rates.len()
                     );
                     break;
                 }
                 warn!(
-                    "Twelve Data USD/{} failed (HTTP {}): {} — ignored",
-                    symbol, status, body
+                    "Twelve Data {} failed (HTTP {}): {} — ignored",
+                    joined, status, body
                 );
                 continue;
             }
 
             let body: serde_json::Value = resp
                 .json()
-                .await
-                .with_context(|| format!("Twelve Data parse failed for {}", pair))?;
+                .with_context(|| format!("Twelve Data parse failed for {}", joined))?;
 
+            // An error for the whole request rather than for one pair.
             if let Some(message) = body.get("message").and_then(|v| v.as_str()) {
                 if is_quota_error(message) {
                     warn!(
                         "Twelve Data quota reached at {}; returning {} partial rate(s)",
-                        pair,
+                        joined,
                         rates.len()
                     );
                     break;
                 }
                 warn!(
-                    "Twelve Data USD/{} failed (API error): {} — ignored",
-                    symbol, message
+                    "Twelve Data {} failed (API error): {} — ignored",
+                    joined, message
                 );
                 continue;
             }
 
-            let Some(rate_str) = body.get("price").and_then(|v| v.as_str()) else {
-                warn!("Twelve Data USD/{} failed (missing price) — ignored", symbol);
-                continue;
-            };
-            let Ok(rate) = rate_str.parse::<f64>() else {
+            let mut quota_reached = false;
+            for (symbol, pair) in batch.iter().zip(&pairs) {
+                // A single pair comes back flat; several come keyed by pair.
+                let entry = if pairs.len() == 1 {
+                    &body
+                } else {
+                    &body[pair.as_str()]
+                };
+                match parse_quote(entry) {
+                    Quote::Rate(rate) => {
+                        rates.insert((*symbol).clone(), rate);
+                    }
+                    Quote::Failed(reason) => {
+                        quota_reached |= is_quota_error(&reason);
+                        warn!("Twelve Data {} failed ({}) — ignored", pair, reason);
+                    }
+                }
+            }
+            if quota_reached {
                 warn!(
-                    "Twelve Data USD/{} failed (invalid rate '{}') — ignored",
-                    symbol, rate_str
+                    "Twelve Data quota reached; returning {} partial rate(s)",
+                    rates.len()
                 );
-                continue;
-            };
-
-            rates.insert(symbol.clone(), rate);
+                break;
+            }
         }
 
         if rates.is_empty() {
@@ -101,6 +141,19 @@ impl ForexSource for TwelveData {
     }
 }
 
+fn parse_quote(entry: &serde_json::Value) -> Quote {
+    if let Some(message) = entry.get("message").and_then(|v| v.as_str()) {
+        return Quote::Failed(format!("API error: {}", message));
+    }
+    let Some(rate_str) = entry.get("price").and_then(|v| v.as_str()) else {
+        return Quote::Failed("missing price".to_string());
+    };
+    match rate_str.parse::<f64>() {
+        Ok(rate) => Quote::Rate(rate),
+        Err(_) => Quote::Failed(format!("invalid rate '{}'", rate_str)),
+    }
+}
+
 fn is_quota_error(message: &str) -> bool {
     let msg = message.to_lowercase();
     msg.contains("run out of api credits")
@@ -108,3 +161,97 @@ fn is_quota_error(message: &str) -> bool {
         || msg.contains("quota")
         || msg.contains("credits")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn fetch(
+        symbols: &[&str],
+        pairs: &str,
+        body: serde_json::Value,
+    ) -> Result<HashMap<String, f64>> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/price"))
+            .and(query_param("symbol", pairs))
+            .and(query_param("apikey", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let symbols: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
+        TwelveData::new(
+            HttpClient::new(reqwest::Client::new()),
+            server.uri(),
+            "test-key".to_string(),
+        )
+        .fetch_rates(&symbols)
+        .await
+    }
+
+    #[tokio::test]
+    async fn batch_is_keyed_by_pair_and_a_failed_pair_is_skipped() {
+        let rates = fetch(
+            &["USD", "EUR", "JPY", "XYZ"],
+            "USD/EUR,USD/JPY,USD/XYZ",
+            serde_json::json!({
+                "USD/EUR": { "price": "0.92000" },
+                "USD/JPY": { "price": "150.10000" },
+                "USD/XYZ": {
+                    "code": 400,
+                    "message": "**symbol** not found: USD/XYZ",
+                    "status": "error"
+                }
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            rates,
+            HashMap::from([
+                ("USD".to_string(), 1.0),
+                ("EUR".to_string(), 0.92),
+                ("JPY".to_string(), 150.1),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn single_pair_comes_back_flat() {
+        let rates = fetch(
+            &["EUR"],
+            "USD/EUR",
+            serde_json::json!({ "price": "0.92000" }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(rates, HashMap::from([("EUR".to_string(), 0.92)]));
+    }
+
+    #[tokio::test]
+    async fn quota_error_for_the_whole_batch_returns_what_was_fetched() {
+        let body = serde_json::json!({
+            "code": 429,
+            "message": "You have run out of API credits for the current minute.",
+            "status": "error"
+        });
+
+        let rates = fetch(&["USD", "EUR", "JPY"], "USD/EUR,USD/JPY", body.clone())
+            .await
+            .unwrap();
+        assert_eq!(rates, HashMap::from([("USD".to_string(), 1.0)]));
+
+        let err = fetch(&["EUR", "JPY"], "USD/EUR,USD/JPY", body)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Twelve Data did not return any forex rates"
+        );
+    }
+}
@@ -1,19 +1,56 @@
+use crate::aggregate::AggregationStrategy;
+use crate::config::PriceBounds;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use std::collections::HashMap;
 use tracing::warn;
 
-const FOREX_DEVIATION_THRESHOLD: f64 = 0.01;
+/// Default tolerance for flagging a forex source that differs from the average.
+pub const DEFAULT_FOREX_DEVIATION_THRESHOLD: f64 = 0.01;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AggregatedForexRate {
     pub symbol: String,
     pub name: String,
     pub foreign_per_usd: f64,
+    /// Set when no source gave a valid rate this run and the rate came
+    /// from `forex_cache_file`: when it was originally fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+    /// Each source's rate that went into `foreign_per_usd`, after bounds and
+    /// inverted-rate checks; empty for a cached rate.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub per_source: Vec<ForexSourceRate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForexSourceRate {
+    pub source: String,
+    pub foreign_per_usd: f64,
+}
+
+/// A rate within this fraction of the reciprocal of the other sources'
+/// consensus is taken as quoted the wrong way round (USD per foreign unit).
+const INVERTED_TOLERANCE: f64 = 0.02;
+
+/// How per-source forex rates are combined, resolved from config.
+#[derive(Debug, Clone, Copy)]
+pub struct ForexAggregateSettings {
+    pub strategy: AggregationStrategy,
+    /// Sources further than this fraction from the central rate are flagged.
+    pub deviation_threshold: f64,
+    /// A symbol whose sources still differ from the central rate by more
+    /// than this fraction is omitted; no limit when unset.
+    pub max_deviation: Option<f64>,
 }
 
 pub fn aggregate_forex_rates(
     symbols: &[String],
     source_results: Vec<(String, Result<HashMap<String, f64>>)>,
+    settings: ForexAggregateSettings,
+    bounds: &HashMap<String, PriceBounds>,
+    names: &HashMap<String, String>,
 ) -> Vec<AggregatedForexRate> {
     let mut by_symbol: HashMap<String, Vec<(String, f64)>> = HashMap::new();
 
@@ -23,6 +60,15 @@ pub fn aggregate_forex_rates(
                 for symbol in symbols {
                     if let Some(rate) = rates.get(symbol) {
                         if let Some(normalized) = normalize_foreign_per_usd(*rate) {
+                            if bounds.get(symbol).is_some_and(|b| !b.contains(normalized)) {
+                                warn!(
+                                    symbol = %symbol,
+                                    source = %source_name,
+                                    rate = normalized,
+                                    "forex rate outside configured bounds, dropped"
+                                );
+                                continue;
+                            }
                             by_symbol
                                 .entry(symbol.clone())
                                 .or_default()
@@ -32,8 +78,9 @@ pub fn aggregate_forex_rates(
                 }
             }
             Err(e) => warn!(
-                "forex source '{}' failed: {} — any symbols only from this source will be ignored, omitted from ConversionTable",
-                source_name, e
+                source = %source_name,
+                error = %e,
+                "forex source failed — any symbols only from this source will be ignored, omitted from ConversionTable"
             ),
         }
     }
@@ -42,45 +89,106 @@ pub fn aggregate_forex_rates(
     for symbol in symbols {
         let Some(values) = by_symbol.get(symbol) else {
             warn!(
-                "forex symbol '{}' failed (missing from all sources) — ignored, omitted from ConversionTable",
-                symbol
+                symbol = %symbol,
+                "forex symbol missing from all sources — ignored, omitted from ConversionTable"
             );
             continue;
         };
+        let values = drop_inverted(symbol, values, settings.strategy);
         if values.is_empty() {
             warn!(
-                "forex symbol '{}' failed (no valid rates) — ignored, omitted from ConversionTable",
-                symbol
+                symbol = %symbol,
+                "forex symbol has no valid rates — ignored, omitted from ConversionTable"
             );
             continue;
         }
 
-        let avg = values.iter().map(|(_, rate)| *rate).sum::<f64>() / values.len() as f64;
+        let rates: Vec<f64> = values.iter().map(|(_, rate)| *rate).collect();
+        let central = settings.strategy.central_value(&rates);
+        let mut max_deviation: f64 = 0.0;
         if values.len() > 1 {
-            for (source, rate) in values {
-                let deviation = (rate - avg).abs() / avg;
-                if deviation > FOREX_DEVIATION_THRESHOLD {
+            for (source, rate) in &values {
+                let deviation = (rate - central).abs() / central;
+                max_deviation = max_deviation.max(deviation);
+                if deviation > settings.deviation_threshold {
                     warn!(
-                        "forex {} source '{}' deviates {:.2}% from average {:.8}",
-                        symbol,
-                        source,
-                        deviation * 100.0,
-                        avg
+                        symbol = %symbol,
+                        source = %source,
+                        rate = *rate,
+                        deviation_pct = deviation * 100.0,
+                        threshold_pct = settings.deviation_threshold * 100.0,
+                        central = central,
+                        "forex source rate deviates"
                     );
                 }
             }
         }
+        if let Some(limit) = settings.max_deviation.filter(|l| max_deviation > *l) {
+            warn!(
+                symbol = %symbol,
+                deviation_pct = max_deviation * 100.0,
+                limit_pct = limit * 100.0,
+                "forex sources disagree beyond forex_max_deviation — ignored, omitted from ConversionTable"
+            );
+            continue;
+        }
 
         aggregated.push(AggregatedForexRate {
             symbol: symbol.clone(),
-            name: currency_name(symbol).to_string(),
-            foreign_per_usd: avg,
+            name: display_name(symbol, names),
+            foreign_per_usd: central,
+            cached_at: None,
+            per_source: values
+                .into_iter()
+                .map(|(source, foreign_per_usd)| ForexSourceRate {
+                    source,
+                    foreign_per_usd,
+                })
+                .collect(),
         });
     }
 
     aggregated
 }
 
+/// `values` without rates that look inverted: within [`INVERTED_TOLERANCE`]
+/// of the reciprocal of the other sources' central rate, and not already
+/// close to that rate itself (which a symbol trading near 1 would be).
+fn drop_inverted(
+    symbol: &str,
+    values: &[(String, f64)],
+    strategy: AggregationStrategy,
+) -> Vec<(String, f64)> {
+    if values.len() < 2 {
+        return values.to_vec();
+    }
+    let mut kept = Vec::new();
+    for (i, (source, rate)) in values.iter().enumerate() {
+        let others: Vec<f64> = values
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, (_, r))| *r)
+            .collect();
+        let consensus = strategy.central_value(&others);
+        let reciprocal = 1.0 / consensus;
+        let near_reciprocal = (rate - reciprocal).abs() / reciprocal <= INVERTED_TOLERANCE;
+        let near_consensus = (rate - consensus).abs() / consensus <= INVERTED_TOLERANCE;
+        if near_reciprocal && !near_consensus {
+            warn!(
+                symbol = %symbol,
+                source = %source,
+                rate = *rate,
+                consensus = consensus,
+                "forex rate looks inverted (USD per unit rather than units per USD), dropped"
+            );
+            continue;
+        }
+        kept.push((source.clone(), *rate));
+    }
+    kept
+}
+
 fn normalize_foreign_per_usd(rate: f64) -> Option<f64> {
     if rate.is_finite() && rate > 0.0 {
         Some(rate)
@@ -89,7 +197,46 @@ fn normalize_foreign_per_usd(rate: f64) -> Option<f64> {
     }
 }
 
-fn currency_name(symbol: &str) -> &'static str {
+/// `names` entry for `symbol`, else the built-in currency name.
+pub fn display_name(symbol: &str, names: &HashMap<String, String>) -> String {
+    names
+        .get(symbol)
+        .cloned()
+        .unwrap_or_else(|| currency_name(symbol).to_string())
+}
+
+/// True if `symbol` is one of the currencies this oracle has a name for.
+pub fn is_known_currency(symbol: &str) -> bool {
+    currency_name(symbol) != UNKNOWN_CURRENCY
+}
+
+const UNKNOWN_CURRENCY: &str = "Unknown Currency";
+
+/// Sign used for `symbol` in a ReferenceUnit, falling back to the code.
+pub fn currency_sign(symbol: &str) -> &str {
+    match symbol {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" | "CNY" => "¥",
+        "INR" => "₹",
+        "KRW" => "₩",
+        "ILS" => "₪",
+        "UAH" => "₴",
+        "TRY" => "₺",
+        "RUB" => "₽",
+        "NGN" => "₦",
+        "PHP" => "₱",
+        "VND" => "₫",
+        "THB" => "฿",
+        "PLN" => "zł",
+        "BRL" => "R$",
+        _ => symbol,
+    }
+}
+
+/// Display name for `symbol`, or `Unknown Currency`.
+pub fn currency_name(symbol: &str) -> &'static str {
     match symbol {
         "USD" => "US Dollar",
         "EUR" => "Euro",
@@ -140,6 +287,226 @@ fn currency_name(symbol: &str) -> &'static str {
         "COP" => "Colombian Peso",
         "PEN" => "Peruvian Sol",
         "UYU" => "Uruguayan Peso",
-        _ => "Unknown Currency",
+        _ => UNKNOWN_CURRENCY,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const SYMBOLS: [&str; 3] = ["EUR", "JPY", "GBP"];
+
+    fn settings(strategy: AggregationStrategy) -> ForexAggregateSettings {
+        ForexAggregateSettings {
+            strategy,
+            deviation_threshold: DEFAULT_FOREX_DEVIATION_THRESHOLD,
+            max_deviation: None,
+        }
+    }
+
+    fn symbols() -> Vec<String> {
+        SYMBOLS.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn strategies() -> impl Strategy<Value = AggregationStrategy> {
+        prop_oneof![
+            Just(AggregationStrategy::Mean),
+            Just(AggregationStrategy::Median),
+            Just(AggregationStrategy::TrimmedMean),
+        ]
+    }
+
+    /// Up to five sources, each quoting any subset of [`SYMBOLS`] (plus
+    /// one symbol nobody asked for) at positive rates, or failing.
+    fn source_maps() -> impl Strategy<Value = Vec<Option<HashMap<String, f64>>>> {
+        let rates = prop::collection::hash_map(
+            prop::sample::select(vec!["EUR", "JPY", "GBP", "XAU"]),
+            1e-3f64..1e4,
+            0..4,
+        )
+        .prop_map(|m| {
+            m.into_iter()
+                .map(|(s, r)| (s.to_string(), r))
+                .collect::<HashMap<_, _>>()
+        });
+        prop::collection::vec(prop::option::weighted(0.9, rates), 0..5)
+    }
+
+    fn results(
+        maps: &[Option<HashMap<String, f64>>],
+    ) -> Vec<(String, Result<HashMap<String, f64>>)> {
+        maps.iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let result = match m {
+                    Some(rates) => Ok(rates.clone()),
+                    None => Err(anyhow::anyhow!("source down")),
+                };
+                (format!("fx{}", i), result)
+            })
+            .collect()
+    }
+
+    fn run(
+        maps: &[Option<HashMap<String, f64>>],
+        strategy: AggregationStrategy,
+    ) -> Vec<AggregatedForexRate> {
+        aggregate_forex_rates(
+            &symbols(),
+            results(maps),
+            settings(strategy),
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn rate_lies_within_its_sources(maps in source_maps(), strategy in strategies()) {
+            for rate in run(&maps, strategy) {
+                let quoted: Vec<f64> = rate.per_source.iter().map(|s| s.foreign_per_usd).collect();
+                prop_assert!(!quoted.is_empty());
+                let min = quoted.iter().cloned().fold(f64::INFINITY, f64::min);
+                let max = quoted.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                let slack = max * 1e-12;
+                prop_assert!(rate.foreign_per_usd >= min - slack && rate.foreign_per_usd <= max + slack);
+            }
+        }
+
+        #[test]
+        fn per_source_rates_come_from_the_input(maps in source_maps(), strategy in strategies()) {
+            for rate in run(&maps, strategy) {
+                for used in &rate.per_source {
+                    let index: usize = used.source[2..].parse().unwrap();
+                    let quoted = maps[index].as_ref().and_then(|m| m.get(&rate.symbol));
+                    prop_assert_eq!(quoted, Some(&used.foreign_per_usd));
+                }
+            }
+        }
+
+        #[test]
+        fn output_follows_requested_symbols(maps in source_maps(), strategy in strategies()) {
+            let out: Vec<String> = run(&maps, strategy).into_iter().map(|r| r.symbol).collect();
+            let expected: Vec<String> = symbols()
+                .into_iter()
+                .filter(|s| out.contains(s))
+                .collect();
+            prop_assert_eq!(&out, &expected);
+            for symbol in &out {
+                prop_assert!(maps.iter().flatten().any(|m| m.contains_key(symbol)));
+            }
+        }
+
+        #[test]
+        fn failed_sources_change_nothing(maps in source_maps(), strategy in strategies()) {
+            let mut with_failure = maps.clone();
+            with_failure.push(None);
+            let rates = |out: Vec<AggregatedForexRate>| -> Vec<(String, f64)> {
+                out.into_iter().map(|r| (r.symbol, r.foreign_per_usd)).collect()
+            };
+            prop_assert_eq!(rates(run(&maps, strategy)), rates(run(&with_failure, strategy)));
+        }
+    }
+
+    fn quotes(rates: &[f64]) -> Vec<Option<HashMap<String, f64>>> {
+        rates
+            .iter()
+            .map(|r| Some(HashMap::from([("JPY".to_string(), *r)])))
+            .collect()
+    }
+
+    fn jpy(out: &[AggregatedForexRate]) -> Option<&AggregatedForexRate> {
+        out.iter().find(|r| r.symbol == "JPY")
+    }
+
+    #[test]
+    fn inverted_jpy_rate_is_dropped_before_averaging() {
+        for strategy in [AggregationStrategy::Mean, AggregationStrategy::Median] {
+            let out = run(&quotes(&[150.0, 0.0066, 150.3]), strategy);
+
+            let rate = jpy(&out).unwrap();
+            let used: Vec<_> = rate.per_source.iter().map(|s| s.source.as_str()).collect();
+            assert_eq!(used, ["fx0", "fx2"], "{:?}", strategy);
+            assert!(
+                (rate.foreign_per_usd - 150.15).abs() < 1e-9,
+                "{:?}",
+                strategy
+            );
+        }
+    }
+
+    #[test]
+    fn two_sources_inverse_to_each_other_leave_the_symbol_out() {
+        let out = run(&quotes(&[150.0, 0.0066]), AggregationStrategy::Median);
+
+        assert!(jpy(&out).is_none());
+    }
+
+    #[test]
+    fn rates_near_one_are_not_taken_for_inverted() {
+        let out = run(&quotes(&[0.995, 1.005]), AggregationStrategy::Mean);
+
+        let rate = jpy(&out).unwrap();
+        assert_eq!(rate.per_source.len(), 2);
+        assert!((rate.foreign_per_usd - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn strategy_picks_the_central_rate() {
+        let maps = quotes(&[150.0, 150.3, 160.0]);
+
+        let median = run(&maps, AggregationStrategy::Median);
+        let mean = run(&maps, AggregationStrategy::Mean);
+
+        assert_eq!(jpy(&median).unwrap().foreign_per_usd, 150.3);
+        assert!((jpy(&mean).unwrap().foreign_per_usd - 460.3 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn disagreement_beyond_max_deviation_leaves_the_symbol_out() {
+        let mut strict = settings(AggregationStrategy::Median);
+        strict.max_deviation = Some(0.02);
+        let run_with = |rates: &[f64]| {
+            aggregate_forex_rates(
+                &symbols(),
+                results(&quotes(rates)),
+                strict,
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+        };
+
+        assert!(jpy(&run_with(&[150.0, 160.0])).is_none());
+        assert!(jpy(&run_with(&[150.0, 151.0])).is_some());
+    }
+
+    #[test]
+    fn rate_outside_the_bounds_is_dropped_before_aggregation() {
+        let bounds = HashMap::from([(
+            "JPY".to_string(),
+            PriceBounds {
+                min: Some(100.0),
+                max: Some(200.0),
+            },
+        )]);
+        let run_with = |rates: &[f64]| {
+            aggregate_forex_rates(
+                &symbols(),
+                results(&quotes(rates)),
+                settings(AggregationStrategy::Mean),
+                &bounds,
+                &HashMap::new(),
+            )
+        };
+
+        let out = run_with(&[150.0, 1500.0, 150.3]);
+        let rate = jpy(&out).unwrap();
+        let used: Vec<_> = rate.per_source.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(used, ["fx0", "fx2"]);
+        assert!((rate.foreign_per_usd - 150.15).abs() < 1e-9);
+
+        assert!(jpy(&run_with(&[1500.0, 15.0])).is_none());
     }
 }
@@ -0,0 +1,190 @@
+//! Last-known-good forex rates (`forex_cache_file`): each symbol's most
+//! recent aggregated rate and when it was fetched, used when a later run
+//! gets no valid quote for it.
+
+use crate::forex_aggregate::AggregatedForexRate;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Bumped whenever the file layout changes; other versions are ignored.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRate {
+    pub foreign_per_usd: f64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedRate {
+    /// Whether the rate is at most `max_age` old at `now`.
+    pub fn is_fresh(&self, now: DateTime<Utc>, max_age: chrono::Duration) -> bool {
+        now - self.fetched_at <= max_age
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Keyed by forex symbol.
+    rates: BTreeMap<String, CachedRate>,
+}
+
+/// Rates by symbol; an absent file is an empty cache.
+pub fn load(path: &Path) -> Result<BTreeMap<String, CachedRate>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading forex cache {}", path.display())),
+    };
+    let file: CacheFile = serde_json::from_str(&contents)
+        .with_context(|| format!("parsing forex cache {}", path.display()))?;
+    if file.version != FORMAT_VERSION {
+        anyhow::bail!(
+            "forex cache {} has version {}, expected {}",
+            path.display(),
+            file.version,
+            FORMAT_VERSION
+        );
+    }
+    Ok(file.rates)
+}
+
+/// Store the freshly fetched `rates` at `fetched_at`, keeping entries for
+/// other symbols. Rates that came from the cache are not rewritten, so
+/// they keep their original fetch time and still expire.
+pub fn save(path: &Path, rates: &[AggregatedForexRate], fetched_at: DateTime<Utc>) -> Result<()> {
+    let mut cached = load(path).unwrap_or_else(|e| {
+        tracing::warn!("{:#}; starting a new forex cache", e);
+        BTreeMap::new()
+    });
+    for rate in rates.iter().filter(|r| r.cached_at.is_none()) {
+        cached.insert(
+            rate.symbol.clone(),
+            CachedRate {
+                foreign_per_usd: rate.foreign_per_usd,
+                fetched_at,
+            },
+        );
+    }
+    let file = CacheFile {
+        version: FORMAT_VERSION,
+        rates: cached,
+    };
+    let contents = serde_json::to_string_pretty(&file).context("serializing forex cache")?;
+    // Replace via rename so a crash mid-write can't truncate the cache.
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents).with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use std::path::PathBuf;
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pricing-oracle-forex-cache-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn rate(
+        symbol: &str,
+        foreign_per_usd: f64,
+        cached_at: Option<DateTime<Utc>>,
+    ) -> AggregatedForexRate {
+        AggregatedForexRate {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            foreign_per_usd,
+            cached_at,
+            per_source: Vec::new(),
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn saved_rates_load_back_under_the_current_version() {
+        let path = cache_path("round-trip");
+
+        save(&path, &[rate("EUR", 0.92, None)], start()).unwrap();
+
+        let file: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(file["version"], FORMAT_VERSION);
+        let cache = load(&path).unwrap();
+        assert_eq!(cache["EUR"].foreign_per_usd, 0.92);
+        assert_eq!(cache["EUR"].fetched_at, start());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn rates_served_from_the_cache_keep_their_fetch_time() {
+        let path = cache_path("keep");
+        let clock = ManualClock::new(start());
+        save(
+            &path,
+            &[rate("EUR", 0.92, None), rate("JPY", 150.0, None)],
+            clock.now(),
+        )
+        .unwrap();
+
+        // An hour later JPY is fresh again but EUR only came from the cache.
+        clock.advance(chrono::Duration::hours(1));
+        let run = [rate("EUR", 0.92, Some(start())), rate("JPY", 151.0, None)];
+        save(&path, &run, clock.now()).unwrap();
+
+        let cache = load(&path).unwrap();
+        assert_eq!(cache["EUR"].fetched_at, start());
+        assert_eq!(cache["JPY"].fetched_at, clock.now());
+        assert_eq!(cache["JPY"].foreign_per_usd, 151.0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn cached_rate_is_a_hit_until_max_age_then_stale() {
+        let path = cache_path("age");
+        let clock = ManualClock::new(start());
+        save(&path, &[rate("EUR", 0.92, None)], clock.now()).unwrap();
+        let max_age = chrono::Duration::seconds(86_400);
+
+        clock.advance(max_age);
+        let cache = load(&path).unwrap();
+        assert!(cache["EUR"].is_fresh(clock.now(), max_age));
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(!cache["EUR"].is_fresh(clock.now(), max_age));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn other_versions_and_unversioned_files_are_refused() {
+        let path = cache_path("version");
+        let cached = r#"{"EUR": {"foreign_per_usd": 0.92, "fetched_at": "2026-10-16T12:00:00Z"}}"#;
+
+        std::fs::write(&path, format!(r#"{{"version": 2, "rates": {}}}"#, cached)).unwrap();
+        let error = load(&path).unwrap_err().to_string();
+        assert!(error.ends_with("has version 2, expected 1"), "{}", error);
+
+        std::fs::write(&path, cached).unwrap();
+        assert!(load(&path).is_err());
+
+        // Saving over either starts a new file.
+        save(&path, &[rate("JPY", 150.0, None)], start()).unwrap();
+        let cache = load(&path).unwrap();
+        assert_eq!(cache.keys().collect::<Vec<_>>(), ["JPY"]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
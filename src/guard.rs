@@ -0,0 +1,127 @@
+//! Pre-submit comparison against the last published ConversionTable.
+
+use crate::types::ConversionTable;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use tracing::warn;
+use zfuel::fuel::ZFuel;
+
+/// One unit's price in the previous and the new table.
+#[derive(Debug, Clone)]
+pub struct PriceChange {
+    pub unit: String,
+    pub previous: Option<f64>,
+    pub current: Option<f64>,
+}
+
+impl PriceChange {
+    /// Percent move from `previous`; `None` unless both prices exist.
+    pub fn change_percent(&self) -> Option<f64> {
+        match (self.previous, self.current) {
+            (Some(prev), Some(cur)) if prev != 0.0 => Some((cur - prev) / prev * 100.0),
+            _ => None,
+        }
+    }
+
+    /// Moved more than `max_change_percent`, or disappeared from the table.
+    pub fn trips(&self, max_change_percent: f64) -> bool {
+        match (self.previous, self.current) {
+            (Some(_), None) => true,
+            _ => self
+                .change_percent()
+                .is_some_and(|c| c.abs() > max_change_percent),
+        }
+    }
+}
+
+/// Every unit in either table, ordered by unit index.
+pub fn compare(previous: &ConversionTable, current: &ConversionTable) -> Result<Vec<PriceChange>> {
+    let mut units: BTreeSet<(u32, &str)> = BTreeSet::new();
+    for key in previous.data.keys().chain(current.data.keys()) {
+        units.insert((key.parse().unwrap_or(u32::MAX), key.as_str()));
+    }
+    units
+        .into_iter()
+        .map(|(_, key)| {
+            let price = |table: &ConversionTable| {
+                table
+                    .data
+                    .get(key)
+                    .map(|d| zfuel_to_f64(&d.current_price))
+                    .transpose()
+                    .with_context(|| format!("unit {}", key))
+            };
+            Ok(PriceChange {
+                unit: key.to_string(),
+                previous: price(previous)?,
+                current: price(current)?,
+            })
+        })
+        .collect()
+}
+
+pub fn zfuel_to_f64(value: &ZFuel) -> Result<f64> {
+    let text = value.to_string();
+    text.parse()
+        .with_context(|| format!("ZFuel '{}' is not a number", text))
+}
+
+pub fn print_diff(changes: &[PriceChange], max_change_percent: f64) {
+    let fmt = |v: Option<f64>| v.map_or_else(|| "—".to_string(), |p| format!("{:.8}", p));
+    println!(
+        "\n{:<8} {:<18} {:<18} {:<12} Guard",
+        "Unit", "Previous", "New", "Change"
+    );
+    println!("{}", "-".repeat(66));
+    for c in changes {
+        let change = c
+            .change_percent()
+            .map_or_else(|| "—".to_string(), |p| format!("{:+.2}%", p));
+        let guard = if c.trips(max_change_percent) {
+            "BLOCK"
+        } else {
+            "ok"
+        };
+        println!(
+            "{:<8} {:<18} {:<18} {:<12} {}",
+            c.unit,
+            fmt(c.previous),
+            fmt(c.current),
+            change,
+            guard
+        );
+    }
+    println!();
+}
+
+/// Fails when any unit moved more than `max_change_percent` since
+/// `previous` or went missing, unless `force` is set.
+pub fn check(
+    previous: &ConversionTable,
+    current: &ConversionTable,
+    max_change_percent: f64,
+    force: bool,
+) -> Result<()> {
+    let changes = compare(previous, current)?;
+    print_diff(&changes, max_change_percent);
+    let tripped = changes
+        .iter()
+        .filter(|c| c.trips(max_change_percent))
+        .count();
+    if tripped == 0 {
+        return Ok(());
+    }
+    if force {
+        warn!(
+            "[guard] {} unit(s) moved more than {}% or went missing — submitting anyway (--force)",
+            tripped, max_change_percent
+        );
+        return Ok(());
+    }
+    anyhow::bail!(
+        "change guard: {} unit(s) moved more than {}% since the last ConversionTable or went missing; \
+         rerun with --force to submit anyway",
+        tripped,
+        max_change_percent
+    )
+}
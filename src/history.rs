@@ -0,0 +1,172 @@
+//! Local run history: one JSON line per run, appended after the run and
+//! read back by the `history` subcommand.
+
+use crate::forex_aggregate::AggregatedForexRate;
+use crate::oracle::{PipelineOutput, RunStats};
+use crate::types::{AggregatedResult, ConversionTable};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Bumped whenever a stored field changes meaning or is removed.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// What each line holds. Units carry their per-source `TokenData` in
+/// `per_source`.
+#[derive(Serialize)]
+struct Entry<'a> {
+    version: u32,
+    run_id: String,
+    action_hash: Option<&'a str>,
+    stats: &'a RunStats,
+    units: &'a [AggregatedResult],
+    forex: &'a [AggregatedForexRate],
+    table: &'a ConversionTable,
+}
+
+/// The fields `history list` and pruning read back from a line.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunSummary {
+    pub version: u32,
+    pub run_id: String,
+    pub action_hash: Option<String>,
+    pub stats: StatsSummary,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StatsSummary {
+    pub started_at: DateTime<Utc>,
+    pub units_valid: usize,
+    pub units_invalid: usize,
+}
+
+/// Run id derived from the start time, e.g. `20261016T092443.109Z`.
+pub fn run_id(started_at: DateTime<Utc>) -> String {
+    started_at.format("%Y%m%dT%H%M%S%.3fZ").to_string()
+}
+
+/// Append `outcome` to the history file at `path`, first dropping runs that
+/// started more than `keep_days` before `now` (`0` keeps every run).
+pub fn append(
+    path: &Path,
+    keep_days: u32,
+    outcome: &PipelineOutput,
+    action_hash: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<String> {
+    let run_id = run_id(outcome.stats.started_at);
+    let entry = Entry {
+        version: SCHEMA_VERSION,
+        run_id: run_id.clone(),
+        action_hash,
+        stats: &outcome.stats,
+        units: &outcome.aggregated,
+        forex: &outcome.forex,
+        table: &outcome.table,
+    };
+    let line = serde_json::to_string(&entry).context("serializing history entry")?;
+
+    let existing = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    let cutoff = now - chrono::Duration::days(i64::from(keep_days));
+    // Lines that don't parse are kept: pruning never destroys what it can't read.
+    let expired = |l: &str| {
+        keep_days > 0
+            && serde_json::from_str::<RunSummary>(l).is_ok_and(|s| s.stats.started_at < cutoff)
+    };
+    let lines: Vec<&str> = existing.lines().filter(|l| !l.is_empty()).collect();
+    let pruned = lines.iter().filter(|l| expired(l)).count();
+
+    if pruned == 0 {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening {}", path.display()))?;
+        writeln!(file, "{}", line).with_context(|| format!("writing {}", path.display()))?;
+    } else {
+        let mut contents = String::new();
+        for kept in lines.iter().filter(|l| !expired(l)) {
+            contents.push_str(kept);
+            contents.push('\n');
+        }
+        contents.push_str(&line);
+        contents.push('\n');
+        // Replace via rename so a crash mid-write can't truncate the history.
+        let tmp = path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp, contents).with_context(|| format!("writing {}", tmp.display()))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+        tracing::debug!(
+            "Pruned {} run(s) older than {} days from history",
+            pruned,
+            keep_days
+        );
+    }
+    Ok(run_id)
+}
+
+/// The most recent `limit` runs in `path`, newest first.
+pub fn list(path: &Path, limit: usize) -> Result<Vec<RunSummary>> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut runs = Vec::new();
+    for (n, line) in contents.lines().enumerate().filter(|(_, l)| !l.is_empty()) {
+        let run: RunSummary = serde_json::from_str(line)
+            .with_context(|| format!("{}:{}: invalid history entry", path.display(), n + 1))?;
+        runs.push(run);
+    }
+    runs.reverse();
+    runs.truncate(limit);
+    Ok(runs)
+}
+
+/// Everything stored for `run_id`; the latest entry if a replay stored it twice.
+pub fn show(path: &Path, run_id: &str) -> Result<serde_json::Value> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    for line in contents.lines().rev().filter(|l| !l.is_empty()) {
+        let Ok(run) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if run["run_id"].as_str() == Some(run_id) {
+            return Ok(run);
+        }
+    }
+    anyhow::bail!("no run '{}' in {}", run_id, path.display())
+}
+
+/// `history list`: one line per run.
+pub fn print_list(path: &Path, limit: usize) -> Result<()> {
+    let runs = list(path, limit)?;
+    if runs.is_empty() {
+        println!("{}: no runs recorded", path.display());
+        return Ok(());
+    }
+    println!("Run                    Started               Valid  Invalid  Submitted");
+    for run in &runs {
+        println!(
+            "{:<22} {:<20} {:>6} {:>8}  {}",
+            run.run_id,
+            run.stats
+                .started_at
+                .format("%Y-%m-%d %H:%M:%SZ")
+                .to_string(),
+            run.stats.units_valid,
+            run.stats.units_invalid,
+            run.action_hash.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+/// `history show`: the stored entry as pretty JSON.
+pub fn print_show(path: &Path, run_id: &str) -> Result<()> {
+    let run = show(path, run_id)?;
+    println!("{}", serde_json::to_string_pretty(&run)?);
+    Ok(())
+}
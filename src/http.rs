@@ -0,0 +1,758 @@
+use crate::clock::Clock;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, Instrument};
+
+/// Replaces secret header values and query parameters in recordings.
+const REDACTED: &str = "REDACTED";
+
+/// Header / query-parameter name fragments treated as secrets.
+const SECRET_MARKERS: &[&str] = &["key", "token", "secret", "auth", "app_id", "password"];
+
+/// Run metadata written next to the exchanges by `--record`.
+const RECORDING_INFO_FILE: &str = "run.json";
+
+/// Retries after the first attempt when `with_retries` is not called.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// First backoff delay; doubles on each retry.
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Longest `Retry-After` a 429 may ask us to wait before we give up instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// HTTP client shared by every price and forex source. Live by default; can
+/// record each exchange to disk or serve exchanges back from a recording.
+#[derive(Clone)]
+pub struct HttpClient {
+    inner: reqwest::Client,
+    mode: Arc<Mode>,
+    retries: u32,
+    /// Per-request timeout replacing the `reqwest::Client` one.
+    timeout: Option<Duration>,
+    /// Name of the source using this client, kept in recordings.
+    source: Option<String>,
+    /// URL prefixes whose path carries a secret, such as an `ETH_RPC_URL`
+    /// with an Infura or Alchemy key in it.
+    secret_urls: Arc<Vec<String>>,
+}
+
+enum Mode {
+    Live,
+    Record {
+        dir: PathBuf,
+        seq: AtomicUsize,
+        clock: Arc<dyn Clock>,
+    },
+    Replay {
+        dir: PathBuf,
+        exchanges: Mutex<Vec<(RecordedExchange, bool)>>,
+        recorded_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// What `--record` writes to `run.json` before the first exchange.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    /// Clock time when recording started; replays run at this time.
+    pub recorded_at: DateTime<Utc>,
+    /// `pricing-oracle` version that made the recording.
+    pub version: String,
+}
+
+/// One request/response pair as written by `--record`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub seq: usize,
+    pub timestamp: DateTime<Utc>,
+    /// Source that made the request; absent in older recordings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub method: String,
+    /// Request URL with secret query parameters redacted, and the whole
+    /// path for URLs registered with `with_secret_url`.
+    pub url: String,
+    pub request_headers: BTreeMap<String, String>,
+    /// Request body, e.g. the `eth_call` payload of a JSON-RPC POST;
+    /// absent for requests without one and in older recordings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub response_headers: BTreeMap<String, String>,
+    pub body: String,
+}
+
+/// Fully-buffered response; what sources read status, headers and body from.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).context("invalid JSON body")
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// `Retry-After` in seconds; the HTTP-date form is not supported.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.header("retry-after")?
+            .trim()
+            .parse()
+            .ok()
+            .map(Duration::from_secs)
+    }
+}
+
+impl HttpClient {
+    pub fn new(inner: reqwest::Client) -> Self {
+        Self {
+            inner,
+            mode: Arc::new(Mode::Live),
+            retries: DEFAULT_RETRIES,
+            timeout: None,
+            source: None,
+            secret_urls: Arc::default(),
+        }
+    }
+
+    /// Hit the network and write every exchange to `dir` as
+    /// `NNNN-<source>.json`, plus `run.json` with the recording time.
+    pub fn recording(inner: reqwest::Client, dir: &Path, clock: Arc<dyn Clock>) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating record dir {}", dir.display()))?;
+        let info = RecordingInfo {
+            recorded_at: clock.now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
+        let path = dir.join(RECORDING_INFO_FILE);
+        let json = serde_json::to_string_pretty(&info).context("serializing recording info")?;
+        std::fs::write(&path, json).with_context(|| format!("writing {}", path.display()))?;
+        Ok(Self {
+            inner,
+            mode: Arc::new(Mode::Record {
+                dir: dir.to_path_buf(),
+                seq: AtomicUsize::new(0),
+                clock,
+            }),
+            retries: DEFAULT_RETRIES,
+            timeout: None,
+            source: None,
+            secret_urls: Arc::default(),
+        })
+    }
+
+    /// Serve every request from the exchanges recorded in `dir`; never touches the network.
+    pub fn replaying(inner: reqwest::Client, dir: &Path) -> Result<Self> {
+        let mut exchanges = Vec::new();
+        let mut recorded_at = None;
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("reading replay dir {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            if path.file_name().and_then(|n| n.to_str()) == Some(RECORDING_INFO_FILE) {
+                let info: RecordingInfo = serde_json::from_str(&contents)
+                    .with_context(|| format!("parsing {}", path.display()))?;
+                recorded_at = Some(info.recorded_at);
+                continue;
+            }
+            let exchange: RecordedExchange = serde_json::from_str(&contents)
+                .with_context(|| format!("parsing {}", path.display()))?;
+            exchanges.push((exchange, false));
+        }
+        exchanges.sort_by_key(|(e, _)| e.seq);
+        // Recordings made before run.json existed: first exchange's time.
+        let recorded_at = recorded_at.or_else(|| exchanges.first().map(|(e, _)| e.timestamp));
+        debug!(
+            "Loaded {} recorded HTTP exchange(s) from {}",
+            exchanges.len(),
+            dir.display()
+        );
+        Ok(Self {
+            inner,
+            mode: Arc::new(Mode::Replay {
+                dir: dir.to_path_buf(),
+                exchanges: Mutex::new(exchanges),
+                recorded_at,
+            }),
+            retries: DEFAULT_RETRIES,
+            timeout: None,
+            source: None,
+            secret_urls: Arc::default(),
+        })
+    }
+
+    /// When the replayed recording was made; `None` when live or recording.
+    pub fn recorded_at(&self) -> Option<DateTime<Utc>> {
+        match self.mode.as_ref() {
+            Mode::Replay { recorded_at, .. } => *recorded_at,
+            _ => None,
+        }
+    }
+
+    /// Tag recorded exchanges with the source making them.
+    pub fn for_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Treat the path of every URL starting with `url` as secret: logs and
+    /// recordings show only its scheme and host, and request errors leave
+    /// the URL out.
+    pub fn with_secret_url(mut self, url: &str) -> Self {
+        Arc::make_mut(&mut self.secret_urls).push(url.to_string());
+        self
+    }
+
+    /// Retry transient failures (5xx, 429, timeouts, connection errors) up
+    /// to `retries` times after the first attempt; 0 disables retrying.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Give every request built from this client its own timeout, e.g. a
+    /// longer one for a slow source.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.with_request_timeout(self.inner.get(url))
+    }
+
+    pub fn post(&self, url: &str) -> reqwest::RequestBuilder {
+        self.with_request_timeout(self.inner.post(url))
+    }
+
+    fn with_request_timeout(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+
+    /// Execute a request built from `get`/`post` and buffer the response,
+    /// retrying transient failures with backoff.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        self.retry_request(request).await
+    }
+
+    /// Exponential backoff with jitter on 5xx, 429, timeouts and connection
+    /// errors. A 429 with `Retry-After` waits that long instead, unless it
+    /// exceeds `MAX_RETRY_AFTER`. Other 4xx are returned immediately.
+    async fn retry_request(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        let mut delay = BASE_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            // Bodies that can't be cloned (streams) get a single attempt.
+            let Some(next) = request.try_clone().filter(|_| attempt < self.retries) else {
+                return self.send_once(request).await;
+            };
+            attempt += 1;
+            let wait = match self.send_once(next).await {
+                Ok(response) if is_retryable_status(response.status) => {
+                    let retry_after = (response.status == StatusCode::TOO_MANY_REQUESTS)
+                        .then(|| response.retry_after())
+                        .flatten();
+                    if retry_after.is_some_and(|d| d > MAX_RETRY_AFTER) {
+                        debug!(
+                            "HTTP {} asks to retry after {:?}; not waiting that long",
+                            response.status, retry_after
+                        );
+                        return Ok(response);
+                    }
+                    debug!(
+                        "HTTP {} on attempt {}/{}, retrying",
+                        response.status,
+                        attempt,
+                        self.retries + 1
+                    );
+                    retry_after.unwrap_or_else(|| jitter(delay))
+                }
+                Ok(response) => {
+                    if attempt > 1 {
+                        debug!("HTTP {} after {} attempt(s)", response.status, attempt);
+                    }
+                    return Ok(response);
+                }
+                Err(e) if is_retryable_error(&e) => {
+                    debug!(
+                        "HTTP request failed on attempt {}/{}, retrying: {:#}",
+                        attempt,
+                        self.retries + 1,
+                        e
+                    );
+                    jitter(delay)
+                }
+                Err(e) => return Err(e),
+            };
+            // Replayed exchanges come back instantly; don't sleep between them.
+            if !matches!(self.mode.as_ref(), Mode::Replay { .. }) {
+                tokio::time::sleep(wait).await;
+            }
+            delay *= 2;
+        }
+    }
+
+    async fn send_once(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        #[allow(unused_mut)]
+        let mut request = request.build().context("building HTTP request")?;
+        let span = tracing::info_span!(
+            "http_request",
+            http.method = %request.method(),
+            url = %self.redact_url(request.url()),
+            http.status = tracing::field::Empty,
+        );
+        #[cfg(feature = "otel")]
+        crate::telemetry::inject_trace_context(&span, request.headers_mut());
+        let response = self.dispatch(request).instrument(span.clone()).await;
+        if let Ok(response) = &response {
+            span.record("http.status", response.status.as_u16());
+        }
+        response
+    }
+
+    fn has_secret_path(&self, url: &reqwest::Url) -> bool {
+        self.secret_urls
+            .iter()
+            .any(|prefix| url.as_str().starts_with(prefix.as_str()))
+    }
+
+    fn redact_url(&self, url: &reqwest::Url) -> String {
+        redact_url(url, self.has_secret_path(url))
+    }
+
+    async fn execute(&self, request: reqwest::Request) -> Result<HttpResponse> {
+        let hide_url = self.has_secret_path(request.url());
+        match self.inner.execute(request).await {
+            Ok(response) => buffer(response).await,
+            Err(e) if hide_url => Err(e.without_url().into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn dispatch(&self, request: reqwest::Request) -> Result<HttpResponse> {
+        match self.mode.as_ref() {
+            Mode::Live => self.execute(request).await,
+            Mode::Record { dir, seq, clock } => {
+                let method = request.method().to_string();
+                let url = self.redact_url(request.url());
+                let request_headers = header_map(request.headers());
+                let request_body = request_body(&request);
+                let response = self.execute(request).await?;
+                let exchange = RecordedExchange {
+                    seq: seq.fetch_add(1, Ordering::SeqCst),
+                    timestamp: clock.now(),
+                    source: self.source.clone(),
+                    method,
+                    url,
+                    request_headers,
+                    request_body,
+                    status: response.status.as_u16(),
+                    response_headers: header_map(&response.headers),
+                    body: response.text(),
+                };
+                let path = match &exchange.source {
+                    Some(source) => dir.join(format!("{:04}-{}.json", exchange.seq, source)),
+                    None => dir.join(format!("{:04}.json", exchange.seq)),
+                };
+                let json = serde_json::to_string_pretty(&exchange)
+                    .context("serializing recorded exchange")?;
+                std::fs::write(&path, json)
+                    .with_context(|| format!("writing {}", path.display()))?;
+                Ok(response)
+            }
+            Mode::Replay { dir, exchanges, .. } => {
+                let method = request.method().to_string();
+                let url = self.redact_url(request.url());
+                let body = request_body(&request);
+                // Older recordings have no request body; they match any.
+                let matches = |e: &RecordedExchange| {
+                    e.method == method
+                        && e.url == url
+                        && (e.request_body.is_none() || e.request_body == body)
+                };
+                let mut exchanges = exchanges.lock().unwrap();
+                // Prefer an unused match so repeated requests replay in order;
+                // fall back to the last match once all have been served.
+                let index = exchanges
+                    .iter()
+                    .position(|(e, used)| !used && matches(e))
+                    .or_else(|| exchanges.iter().rposition(|(e, _)| matches(e)))
+                    .with_context(|| {
+                        format!(
+                            "replay: no recorded response for {} {} in {}",
+                            method,
+                            url,
+                            dir.display()
+                        )
+                    })?;
+                exchanges[index].1 = true;
+                let exchange = &exchanges[index].0;
+                let mut headers = HeaderMap::new();
+                for (name, value) in &exchange.response_headers {
+                    if let (Ok(name), Ok(value)) = (
+                        HeaderName::from_bytes(name.as_bytes()),
+                        HeaderValue::from_str(value),
+                    ) {
+                        headers.insert(name, value);
+                    }
+                }
+                Ok(HttpResponse {
+                    status: StatusCode::from_u16(exchange.status)
+                        .context("replay: invalid recorded status")?,
+                    headers,
+                    body: exchange.body.clone().into_bytes(),
+                })
+            }
+        }
+    }
+}
+
+async fn buffer(response: reqwest::Response) -> Result<HttpResponse> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.bytes().await.context("reading response body")?;
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: body.to_vec(),
+    })
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+fn is_retryable_error(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_timeout() || e.is_connect())
+}
+
+/// `delay` plus up to 50% more, so concurrent sources don't retry in lockstep.
+fn jitter(delay: Duration) -> Duration {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    delay + delay.mul_f64(f64::from(nanos % 1000) / 2000.0)
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_MARKERS.iter().any(|m| name.contains(m))
+}
+
+/// The body of a request built from `get`/`post`, as text.
+fn request_body(request: &reqwest::Request) -> Option<String> {
+    request
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| String::from_utf8_lossy(b).into_owned())
+}
+
+/// `url` with secret query parameters replaced; with `hide_path`, also its
+/// path and any user info, since the secret is in the path itself.
+fn redact_url(url: &reqwest::Url, hide_path: bool) -> String {
+    let mut url = url.clone();
+    if hide_path {
+        url.set_path(REDACTED);
+        let _ = url.set_username("");
+        let _ = url.set_password(None);
+    }
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let v = if is_secret(&k) {
+                REDACTED.to_string()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), v)
+        })
+        .collect();
+    if !pairs.is_empty() {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+fn header_map(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_secret(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                value.to_str().unwrap_or_default().to_string()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use wiremock::matchers::{body_partial_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "pricing-oracle-http-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn eth_call(client: &HttpClient, url: &str, to: &str) -> String {
+        let payload = serde_json::json!({ "method": "eth_call", "params": [{ "to": to }] });
+        let response = client.send(client.post(url).json(&payload)).await.unwrap();
+        response.text()
+    }
+
+    #[test]
+    fn redacts_secret_query_parameters_and_paths() {
+        let url = reqwest::Url::parse("https://api.example.com/v1/price?apikey=abc&id=7").unwrap();
+        assert_eq!(
+            redact_url(&url, false),
+            "https://api.example.com/v1/price?apikey=REDACTED&id=7"
+        );
+        let rpc = reqwest::Url::parse("https://user:pw@mainnet.infura.io/v3/0123abcd").unwrap();
+        assert_eq!(redact_url(&rpc, true), "https://mainnet.infura.io/REDACTED");
+    }
+
+    #[tokio::test]
+    async fn replay_tells_posts_to_the_same_url_apart_by_body() {
+        let server = MockServer::start().await;
+        for (feed, answer) in [("0xfeed1", "one"), ("0xfeed2", "two")] {
+            Mock::given(method("POST"))
+                .and(body_partial_json(
+                    serde_json::json!({ "params": [{ "to": feed }] }),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_string(answer))
+                .mount(&server)
+                .await;
+        }
+        let rpc_url = format!("{}/v3/secretkey", server.uri());
+        let dir = scratch_dir("replay-body");
+        let clock = Arc::new(ManualClock::new(Utc::now()));
+
+        let recorder = HttpClient::recording(reqwest::Client::new(), &dir, clock)
+            .unwrap()
+            .for_source("chainlink")
+            .with_secret_url(&rpc_url);
+        assert_eq!(eth_call(&recorder, &rpc_url, "0xfeed1").await, "one");
+        assert_eq!(eth_call(&recorder, &rpc_url, "0xfeed2").await, "two");
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let contents = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!contents.contains("secretkey"), "{}", contents);
+        }
+
+        // Asked in the opposite order, concurrently, with the server gone.
+        drop(server);
+        let replayer = HttpClient::replaying(reqwest::Client::new(), &dir)
+            .unwrap()
+            .with_secret_url(&rpc_url);
+        let (two, one) = tokio::join!(
+            eth_call(&replayer, &rpc_url, "0xfeed2"),
+            eth_call(&replayer, &rpc_url, "0xfeed1"),
+        );
+        assert_eq!((one.as_str(), two.as_str()), ("one", "two"));
+
+        let payload = serde_json::json!({ "method": "eth_call", "params": [{ "to": "0xfeed3" }] });
+        let err = replayer
+            .send(replayer.post(&rpc_url).json(&payload))
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("no recorded response"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Answer `first` `times` times, then 200 `ok`.
+    async fn flaky_server(first: ResponseTemplate, times: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(first)
+            .up_to_n_times(times)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn get(client: &HttpClient, server: &MockServer) -> HttpResponse {
+        let url = format!("{}/price", server.uri());
+        client.send(client.get(&url)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn server_error_then_success_is_retried() {
+        let server = flaky_server(ResponseTemplate::new(502), 1).await;
+        let client = HttpClient::new(reqwest::Client::new());
+
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text(), "ok");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_error_is_not_retried() {
+        let server = flaky_server(ResponseTemplate::new(400), 1).await;
+        let client = HttpClient::new(reqwest::Client::new());
+
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_stop_at_the_limit() {
+        let server = flaky_server(ResponseTemplate::new(503), 5).await;
+        let client = HttpClient::new(reqwest::Client::new()).with_retries(1);
+
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_honours_a_short_retry_after() {
+        let server = flaky_server(
+            ResponseTemplate::new(429).insert_header("Retry-After", "1"),
+            1,
+        )
+        .await;
+        let client = HttpClient::new(reqwest::Client::new());
+
+        let started = std::time::Instant::now();
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_with_a_long_retry_after_is_returned() {
+        let server = flaky_server(
+            ResponseTemplate::new(429).insert_header("Retry-After", "120"),
+            1,
+        )
+        .await;
+        let client = HttpClient::new(reqwest::Client::new());
+
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.retry_after(), Some(Duration::from_secs(120)));
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    /// Answers 200 `ok`, but only after `delay`.
+    async fn slow_server(delay: Duration) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("ok")
+                    .set_delay(delay),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn response_slower_than_the_timeout_is_a_timeout_error() {
+        let server = slow_server(Duration::from_secs(5)).await;
+        let client = HttpClient::new(reqwest::Client::new())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(0);
+
+        let started = std::time::Instant::now();
+        let url = format!("{}/price", server.uri());
+        let err = client.send(client.get(&url)).await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(
+            err.downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_timeout()),
+            "{:#}",
+            err
+        );
+        assert!(is_retryable_error(&err));
+    }
+
+    #[tokio::test]
+    async fn timed_out_request_is_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("ok"))
+            .mount(&server)
+            .await;
+        let client = HttpClient::new(reqwest::Client::new())
+            .with_timeout(Duration::from_millis(200))
+            .with_retries(1);
+
+        let response = get(&client, &server).await;
+
+        assert_eq!(response.text(), "ok");
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn response_inside_the_timeout_answers() {
+        let server = slow_server(Duration::from_millis(100)).await;
+        let client = HttpClient::new(reqwest::Client::new())
+            .with_timeout(Duration::from_secs(2))
+            .with_retries(0);
+
+        assert_eq!(get(&client, &server).await.text(), "ok");
+    }
+}
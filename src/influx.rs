@@ -0,0 +1,315 @@
+use crate::config::InfluxConfig;
+use crate::oracle::PipelineOutput;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Render a run as InfluxDB line protocol, every point stamped with the run's
+/// start time (nanoseconds). Line order follows the outcome, so the same run
+/// always renders identically.
+pub fn line_protocol(outcome: &PipelineOutput) -> String {
+    let ts = outcome
+        .stats
+        .started_at
+        .timestamp_nanos_opt()
+        .unwrap_or_default();
+    let mut out = String::new();
+
+    for r in &outcome.aggregated {
+        let unit = r.unit_index.to_string();
+        let unit_tags = [("unit", unit.as_str()), ("name", r.name.as_str())];
+        for d in &r.per_source {
+            let tags = [unit_tags[0], unit_tags[1], ("source", d.source.as_str())];
+            push_float(&mut out, "oracle_price", &tags, d.price_usd, ts);
+        }
+        let tags = [unit_tags[0], unit_tags[1], ("source", "aggregate")];
+        push_float(&mut out, "oracle_price", &tags, r.avg_price_usd, ts);
+        push_int(&mut out, "oracle_valid", &unit_tags, r.valid as i64, ts);
+    }
+
+    for f in &outcome.forex {
+        let tags = [("symbol", f.symbol.as_str()), ("name", f.name.as_str())];
+        push_float(&mut out, "oracle_forex", &tags, f.foreign_per_usd, ts);
+    }
+
+    for t in &outcome.source_timings {
+        let tags = [
+            ("source", t.source.as_str()),
+            ("subject", t.subject.as_str()),
+        ];
+        push_int(
+            &mut out,
+            "oracle_source_latency_ms",
+            &tags,
+            t.elapsed_ms,
+            ts,
+        );
+    }
+
+    out
+}
+
+fn push_float(out: &mut String, measurement: &str, tags: &[(&str, &str)], value: f64, ts: i64) {
+    // Line protocol has no representation for NaN or infinity.
+    if value.is_finite() {
+        push_line(out, measurement, tags, &format!("{}", value), ts);
+    }
+}
+
+fn push_int(out: &mut String, measurement: &str, tags: &[(&str, &str)], value: i64, ts: i64) {
+    push_line(out, measurement, tags, &format!("{}i", value), ts);
+}
+
+fn push_line(out: &mut String, measurement: &str, tags: &[(&str, &str)], value: &str, ts: i64) {
+    out.push_str(&escape(measurement, false));
+    for (key, val) in tags {
+        let _ = write!(out, ",{}={}", escape(key, true), escape(val, true));
+    }
+    let _ = writeln!(out, " value={} {}", value, ts);
+}
+
+/// Escape commas and spaces (and `=` in tag keys/values) with a backslash.
+fn escape(s: &str, tag: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == ',' || c == ' ' || (tag && c == '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Writes line protocol to an InfluxDB v2 `/api/v2/write` endpoint.
+pub struct InfluxWriter {
+    client: reqwest::Client,
+    config: InfluxConfig,
+    token: Option<String>,
+}
+
+impl InfluxWriter {
+    pub fn from_config(cfg: &InfluxConfig) -> Self {
+        let token = match std::env::var(&cfg.token_env) {
+            Ok(token) => Some(token),
+            Err(_) => {
+                warn!(
+                    "{} not set — Influx writes will be unauthenticated",
+                    cfg.token_env
+                );
+                None
+            }
+        };
+        Self {
+            client: reqwest::Client::new(),
+            config: cfg.clone(),
+            token,
+        }
+    }
+
+    /// Send all points in one request, retrying 5xx and connection errors.
+    /// Never fails the run; problems are logged.
+    pub async fn write(&self, lines: &str) {
+        if lines.is_empty() {
+            return;
+        }
+        let mut delay = Duration::from_secs(1);
+        for attempt in 1..=self.config.max_attempts {
+            match self.try_write(lines).await {
+                Ok(()) => {
+                    info!("[influx] Wrote {} point(s)", lines.lines().count());
+                    return;
+                }
+                Err((retryable, e)) => {
+                    if !retryable || attempt == self.config.max_attempts {
+                        warn!("[influx] write failed: {:#}", e);
+                        return;
+                    }
+                    warn!(
+                        "[influx] write attempt {} failed, retrying in {}s: {:#}",
+                        attempt,
+                        delay.as_secs(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+
+    /// Err carries whether the failure is worth retrying.
+    async fn try_write(&self, lines: &str) -> Result<(), (bool, anyhow::Error)> {
+        let url = format!("{}/api/v2/write", self.config.url.trim_end_matches('/'));
+        let mut request = self
+            .client
+            .post(&url)
+            .query(&[
+                ("org", self.config.org.as_str()),
+                ("bucket", self.config.bucket.as_str()),
+                ("precision", "ns"),
+            ])
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .timeout(Duration::from_secs(30))
+            .body(lines.to_string());
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        let resp = request
+            .send()
+            .await
+            .context("Influx write request failed")
+            .map_err(|e| (true, e))?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = resp.text().await.unwrap_or_default();
+        Err((
+            status.is_server_error(),
+            anyhow::anyhow!("Influx returned HTTP {}: {}", status, body),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forex_aggregate::AggregatedForexRate;
+    use crate::oracle::{RunStats, SourceTiming};
+    use crate::output::{self, ReferenceCurrency, TableFormat};
+    use crate::types::AggregatedResult;
+    use chrono::{DateTime, Utc};
+    use serde_json::json;
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    fn quote(source: &str, price_usd: f64) -> serde_json::Value {
+        json!({
+            "name": "HOT", "chain": "ethereum", "contract": "0x6c6e",
+            "price_usd": price_usd, "market_cap": null, "volume_24h": null,
+            "liquidity": null, "price_change_24h": null,
+            "source": source, "timestamp": now(),
+        })
+    }
+
+    fn unit(
+        unit_index: u32,
+        name: &str,
+        avg: f64,
+        valid: bool,
+        per_source: Vec<serde_json::Value>,
+    ) -> AggregatedResult {
+        serde_json::from_value(json!({
+            "unit_index": unit_index, "name": name, "contract": "0x6c6e",
+            "avg_price_usd": avg, "volume_24h": null, "price_change_24h": null,
+            "sources": [], "rejected_sources": [], "valid": valid,
+            "reason": if valid { None } else { Some("insufficient sources") },
+            "strategy": "mean", "oldest_quote_age_secs": 0,
+            "per_source": per_source,
+        }))
+        .unwrap()
+    }
+
+    fn rate(symbol: &str, name: &str, foreign_per_usd: f64) -> AggregatedForexRate {
+        AggregatedForexRate {
+            symbol: symbol.to_string(),
+            name: name.to_string(),
+            foreign_per_usd,
+            cached_at: None,
+            per_source: Vec::new(),
+        }
+    }
+
+    fn timing(source: &str, subject: &str, elapsed_ms: i64) -> SourceTiming {
+        SourceTiming {
+            source: source.to_string(),
+            subject: subject.to_string(),
+            elapsed_ms,
+        }
+    }
+
+    /// Two sources agreeing on unit 3, one source for unit 7 (whose name
+    /// needs escaping and whose average is not a number), two forex rates.
+    fn fixture_run() -> PipelineOutput {
+        let mut btc = unit(
+            7,
+            "Wrapped BTC, bridged",
+            0.0,
+            false,
+            vec![quote("kraken", 61000.5)],
+        );
+        btc.avg_price_usd = f64::NAN;
+        let built = output::build_conversion_table(
+            &[],
+            &[],
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            None,
+            None,
+        );
+        PipelineOutput {
+            aggregated: vec![
+                unit(
+                    3,
+                    "HOT",
+                    0.00124,
+                    true,
+                    vec![quote("coingecko", 0.00123), quote("dexscreener", 0.00125)],
+                ),
+                btc,
+            ],
+            forex: vec![
+                rate("EUR", "Euro", 0.92),
+                rate("JPY", "Japanese Yen", 150.5),
+            ],
+            table: built.table,
+            omitted: Vec::new(),
+            reference: ReferenceCurrency::usd(),
+            stats: RunStats {
+                started_at: now(),
+                finished_at: now() + chrono::Duration::seconds(4),
+                units_valid: 1,
+                units_invalid: 1,
+                proxies_unresolved: 0,
+                units_unencodable: 0,
+                units_cached: 0,
+                forex_requested: 2,
+                forex_ok: 2,
+                forex_missing: 0,
+            },
+            source_errors: Vec::new(),
+            source_timings: vec![
+                timing("coingecko", "unit 3", 182),
+                timing("kraken", "unit 7", 95),
+                timing("frankfurter", "forex", 40),
+            ],
+        }
+    }
+
+    #[test]
+    fn fixture_run_renders_the_expected_line_protocol() {
+        let expected = "\
+oracle_price,unit=3,name=HOT,source=coingecko value=0.00123 1792152000000000000
+oracle_price,unit=3,name=HOT,source=dexscreener value=0.00125 1792152000000000000
+oracle_price,unit=3,name=HOT,source=aggregate value=0.00124 1792152000000000000
+oracle_valid,unit=3,name=HOT value=1i 1792152000000000000
+oracle_price,unit=7,name=Wrapped\\ BTC\\,\\ bridged,source=kraken value=61000.5 1792152000000000000
+oracle_valid,unit=7,name=Wrapped\\ BTC\\,\\ bridged value=0i 1792152000000000000
+oracle_forex,symbol=EUR,name=Euro value=0.92 1792152000000000000
+oracle_forex,symbol=JPY,name=Japanese\\ Yen value=150.5 1792152000000000000
+oracle_source_latency_ms,source=coingecko,subject=unit\\ 3 value=182i 1792152000000000000
+oracle_source_latency_ms,source=kraken,subject=unit\\ 7 value=95i 1792152000000000000
+oracle_source_latency_ms,source=frankfurter,subject=forex value=40i 1792152000000000000
+";
+        assert_eq!(line_protocol(&fixture_run()), expected);
+    }
+
+    #[test]
+    fn tags_escape_equals_signs_but_measurements_do_not() {
+        assert_eq!(escape("a=b, c", true), "a\\=b\\,\\ c");
+        assert_eq!(escape("a=b, c", false), "a=b\\,\\ c");
+    }
+}
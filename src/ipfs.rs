@@ -0,0 +1,269 @@
+use crate::config::IpfsConfig;
+use crate::types::ConversionTable;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Adds and pins submitted tables on an IPFS node so they can be verified
+/// without a Holochain node.
+pub struct IpfsPublisher {
+    client: reqwest::Client,
+    config: IpfsConfig,
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddResponse {
+    #[serde(rename = "Hash")]
+    hash: String,
+}
+
+impl IpfsPublisher {
+    pub fn from_config(cfg: &IpfsConfig) -> Self {
+        let token = cfg
+            .token_env
+            .as_ref()
+            .and_then(|var| match std::env::var(var) {
+                Ok(token) => Some(token),
+                Err(_) => {
+                    warn!("{} not set — IPFS requests will be unauthenticated", var);
+                    None
+                }
+            });
+        Self {
+            client: reqwest::Client::new(),
+            config: cfg.clone(),
+            token,
+        }
+    }
+
+    /// Add and pin the canonical JSON of `table`, returning its CID. The CID
+    /// is remembered for `link_previous`.
+    pub async fn publish(&self, table: &ConversionTable) -> Result<String> {
+        let body = canonical_json(table)?;
+        let form = reqwest::multipart::Form::new().part(
+            "file",
+            reqwest::multipart::Part::bytes(body)
+                .file_name("conversion_table.json")
+                .mime_str("application/json")?,
+        );
+        let url = format!(
+            "{}/api/v0/add?pin=true&cid-version=1",
+            self.config.api_url.trim_end_matches('/')
+        );
+
+        let mut request = self
+            .client
+            .post(&url)
+            .timeout(Duration::from_secs(60))
+            .multipart(form);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send().await.context("IPFS add request failed")?;
+        let status = resp.status();
+        let text = resp.text().await.context("reading IPFS add response")?;
+        if !status.is_success() {
+            anyhow::bail!("IPFS add returned HTTP {}: {}", status, text);
+        }
+        let cid = parse_add_response(&text)?;
+        info!("[ipfs] Pinned ConversionTable as {}", cid);
+
+        if let Err(e) = std::fs::write(&self.config.state_file, &cid) {
+            warn!(
+                "failed to record CID in {}: {}",
+                self.config.state_file.display(),
+                e
+            );
+        }
+        Ok(cid)
+    }
+
+    /// `additional_data` with `previous_table_cid` added when `link_previous`
+    /// is on and a previous CID is known. Keys already there (embedded run
+    /// metadata) are kept.
+    pub fn link_previous(&self, additional_data: Option<Vec<u8>>) -> Option<Vec<u8>> {
+        if !self.config.link_previous {
+            return additional_data;
+        }
+        let Some(cid) = std::fs::read_to_string(&self.config.state_file)
+            .ok()
+            .map(|cid| cid.trim().to_string())
+            .filter(|cid| !cid.is_empty())
+        else {
+            return additional_data;
+        };
+        let mut object = match additional_data.as_deref().map(serde_json::from_slice) {
+            None => serde_json::Map::new(),
+            Some(Ok(serde_json::Value::Object(object))) => object,
+            Some(_) => {
+                tracing::warn!("additional_data is not a JSON object; previous CID not linked");
+                return additional_data;
+            }
+        };
+        object.insert("previous_table_cid".to_string(), cid.into());
+        serde_json::to_vec(&object).ok()
+    }
+}
+
+/// Table JSON with object keys sorted, so the same table always hashes to
+/// the same CID regardless of `HashMap` iteration order.
+pub fn canonical_json(table: &ConversionTable) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(table).context("serializing ConversionTable")?;
+    serde_json::to_vec(&value).context("serializing ConversionTable")
+}
+
+/// Kubo streams one JSON object per added file; the table is the last one.
+fn parse_add_response(body: &str) -> Result<String> {
+    let line = body
+        .lines()
+        .rev()
+        .find(|l| !l.trim().is_empty())
+        .context("empty IPFS add response")?;
+    let parsed: AddResponse = serde_json::from_str(line)
+        .with_context(|| format!("unexpected IPFS add response: {}", line))?;
+    Ok(parsed.hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{build_conversion_table, ReferenceCurrency, TableFormat};
+    use std::path::PathBuf;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    const CID: &str = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+
+    fn state_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pricing-oracle-ipfs-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn publisher(api_url: &str, token_env: Option<&str>, state_file: PathBuf) -> IpfsPublisher {
+        IpfsPublisher::from_config(&IpfsConfig {
+            api_url: api_url.to_string(),
+            token_env: token_env.map(str::to_string),
+            link_previous: true,
+            state_file,
+        })
+    }
+
+    fn table() -> ConversionTable {
+        build_conversion_table(
+            &[],
+            &[],
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            None,
+            None,
+        )
+        .table
+    }
+
+    #[test]
+    fn add_response_cid_is_taken_from_the_last_object() {
+        // Trimmed from a kubo add of a directory-wrapped file.
+        let body = concat!(
+            "{\"Name\":\"conversion_table.json\",\"Hash\":\"bafkreiaaaa\",\"Size\":\"412\"}\n",
+            "{\"Name\":\"\",\"Hash\":\"bafybeibbbb\",\"Size\":\"470\"}\n",
+            "\n",
+        );
+        assert_eq!(parse_add_response(body).unwrap(), "bafybeibbbb");
+
+        assert_eq!(
+            parse_add_response("\n").unwrap_err().to_string(),
+            "empty IPFS add response"
+        );
+        let error = parse_add_response("{\"Message\":\"no file\"}").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("unexpected IPFS add response"));
+    }
+
+    #[tokio::test]
+    async fn publish_adds_and_pins_with_the_bearer_token() {
+        let server = MockServer::start().await;
+        let table = table();
+        let expected = String::from_utf8(canonical_json(&table).unwrap()).unwrap();
+        Mock::given(method("POST"))
+            .and(path("/api/v0/add"))
+            .and(query_param("pin", "true"))
+            .and(query_param("cid-version", "1"))
+            .and(header("authorization", "Bearer pinning-secret"))
+            .and(move |request: &Request| {
+                String::from_utf8_lossy(&request.body).contains(&expected)
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "{{\"Name\":\"conversion_table.json\",\"Hash\":\"{}\",\"Size\":\"412\"}}\n",
+                CID
+            )))
+            .expect(1)
+            .mount(&server)
+            .await;
+        std::env::set_var("PRICING_ORACLE_TEST_IPFS_TOKEN", "pinning-secret");
+        let state = state_file("publish");
+
+        let publisher = publisher(
+            &format!("{}/", server.uri()),
+            Some("PRICING_ORACLE_TEST_IPFS_TOKEN"),
+            state.clone(),
+        );
+        let cid = publisher.publish(&table).await.unwrap();
+
+        assert_eq!(cid, CID);
+        assert_eq!(std::fs::read_to_string(&state).unwrap(), CID);
+        let _ = std::fs::remove_file(&state);
+    }
+
+    #[tokio::test]
+    async fn rejected_add_is_an_error_with_the_node_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("invalid token"))
+            .mount(&server)
+            .await;
+        let state = state_file("rejected");
+
+        let error = publisher(&server.uri(), None, state.clone())
+            .publish(&table())
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "IPFS add returned HTTP 401 Unauthorized: invalid token"
+        );
+        assert!(!state.exists());
+        let requests = server.received_requests().await.unwrap();
+        assert!(!requests[0].headers.contains_key("authorization"));
+    }
+
+    #[test]
+    fn previous_cid_is_linked_next_to_existing_metadata() {
+        let state = state_file("link");
+        let publisher = publisher("http://127.0.0.1:5001", None, state.clone());
+        let metadata = br#"{"oracle_version":"0.1.0"}"#.to_vec();
+
+        // Nothing published yet.
+        assert_eq!(
+            publisher.link_previous(Some(metadata.clone())),
+            Some(metadata.clone())
+        );
+
+        std::fs::write(&state, format!("{}\n", CID)).unwrap();
+        let linked: serde_json::Value =
+            serde_json::from_slice(&publisher.link_previous(Some(metadata)).unwrap()).unwrap();
+        assert_eq!(
+            linked,
+            serde_json::json!({ "oracle_version": "0.1.0", "previous_table_cid": CID })
+        );
+        let _ = std::fs::remove_file(&state);
+    }
+}
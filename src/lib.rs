@@ -0,0 +1,67 @@
+//! Fetch token prices from multiple sources, aggregate them, and build the
+//! ConversionTable submitted to the Unyt DNA.
+//!
+//! The `pricing-oracle` binary is a thin CLI over this crate. Embedders
+//! usually need only [`config::Config::load`] and [`run_pipeline`]:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let cfg = pricing_oracle::config::Config::load("config.yaml".as_ref())?;
+//! let output = pricing_oracle::run_pipeline(cfg, Default::default()).await?;
+//! println!("{} units priced", output.aggregated.len());
+//! # Ok(())
+//! # }
+//! ```
+
+/// Contract address checks and normalization.
+pub mod address;
+/// Combining per-source prices into one price per unit.
+pub mod aggregate;
+/// Webhook alerts sent after a run.
+pub mod alerts;
+pub mod chains;
+/// Wall-clock abstraction so runs can be pinned to a fixed time.
+pub mod clock;
+/// `config.yaml` schema, loading, and validation.
+pub mod config;
+pub mod diff;
+/// Forex rate sources and their registry.
+pub mod forex;
+/// Combining per-source forex rates into one rate per currency.
+pub mod forex_aggregate;
+/// Last-known-good forex rates kept between runs.
+pub mod forex_cache;
+pub mod guard;
+pub mod history;
+/// HTTP client shared by sources, with retries and record/replay.
+pub mod http;
+/// InfluxDB line-protocol export.
+pub mod influx;
+/// Publishing submitted tables to IPFS.
+pub mod ipfs;
+/// Advisory lock that keeps runs from overlapping.
+pub mod lock;
+/// One fetch → aggregate → table run.
+pub mod oracle;
+/// Building, printing, and writing the ConversionTable.
+pub mod output;
+/// Run history sink for PostgreSQL.
+pub mod postgres;
+pub mod proxy;
+/// Last-known-good unit prices kept between runs.
+pub mod quote_cache;
+/// Per-source request pacing.
+pub mod rate_limit;
+/// Price sources and their registry.
+pub mod sources;
+/// Uploading run artifacts to S3-compatible storage.
+pub mod storage;
+/// Logging and trace export setup.
+pub mod telemetry;
+/// Data types shared across sources, aggregation, and output.
+pub mod types;
+pub mod validate;
+/// Holochain zome calls for reading and submitting tables.
+pub mod zome;
+
+pub use oracle::{run_pipeline, OracleSettings, PipelineOutput};
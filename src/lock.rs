@@ -0,0 +1,134 @@
+//! Advisory run lock (`--lock-file`): an exclusive OS file lock held for
+//! the whole run, so overlapping cron runs cannot both submit. The OS
+//! releases it when the process exits, however it exits, so a killed run
+//! never leaves a stale lock behind.
+
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a waiting run retries the lock.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// File name used next to the config when `--lock-file` is not given.
+pub const DEFAULT_FILE_NAME: &str = ".pricing-oracle.lock";
+
+/// Holds the lock until dropped, including while unwinding from a panic.
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Take the lock at `path`, creating the file if needed, retrying for up
+    /// to `wait`. `Ok(None)` when another process still holds it.
+    pub fn acquire(path: &Path, wait: Duration) -> Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("opening lock file {}", path.display()))?;
+        let deadline = Instant::now() + wait;
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Some(Self { _file: file })),
+                Err(TryLockError::WouldBlock) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Ok(None);
+                    }
+                    std::thread::sleep(left.min(POLL_INTERVAL));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("locking {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+/// [`DEFAULT_FILE_NAME`] in the config directory: `config` itself when it
+/// is a directory, otherwise the directory containing it.
+pub fn default_path(config: &Path) -> PathBuf {
+    let dir = if config.is_dir() {
+        config
+    } else {
+        config
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    };
+    dir.join(DEFAULT_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "pricing-oracle-lock-{}-{}.lock",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn second_run_is_locked_out_until_the_first_ends() {
+        let path = lock_path("exclusive");
+        let first = RunLock::acquire(&path, Duration::ZERO).unwrap();
+        assert!(first.is_some());
+
+        assert!(RunLock::acquire(&path, Duration::ZERO).unwrap().is_none());
+
+        drop(first);
+        assert!(RunLock::acquire(&path, Duration::ZERO).unwrap().is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn waiting_run_starts_once_the_first_releases() {
+        let path = lock_path("wait");
+        let first = RunLock::acquire(&path, Duration::ZERO).unwrap().unwrap();
+        let holder = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            drop(first);
+        });
+
+        let started = Instant::now();
+        let second = RunLock::acquire(&path, Duration::from_secs(10)).unwrap();
+
+        assert!(second.is_some());
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        holder.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn panicking_run_releases_the_lock() {
+        let path = lock_path("panic");
+        let held = path.clone();
+        let run = std::thread::spawn(move || {
+            let _lock = RunLock::acquire(&held, Duration::ZERO).unwrap().unwrap();
+            panic!("run failed");
+        });
+
+        assert!(run.join().is_err());
+        assert!(RunLock::acquire(&path, Duration::ZERO).unwrap().is_some());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn default_lock_sits_next_to_the_config() {
+        assert_eq!(
+            default_path(Path::new("/etc/oracle/config.yaml")),
+            Path::new("/etc/oracle").join(DEFAULT_FILE_NAME)
+        );
+        assert_eq!(
+            default_path(Path::new("config.yaml")),
+            Path::new(".").join(DEFAULT_FILE_NAME)
+        );
+    }
+}
@@ -1,36 +1,160 @@
-mod aggregate;
-mod config;
-mod forex;
-mod forex_aggregate;
-mod output;
-mod sources;
-mod types;
-mod zome;
-
 use anyhow::{Context, Result};
-use clap::Parser;
-use std::collections::HashMap;
+use clap::{Parser, Subcommand};
+use holo_hash::{ActionHash, ActionHashB64};
+use pricing_oracle::{
+    alerts, config, diff, forex_cache, guard, history, influx, ipfs, lock, oracle, output,
+    postgres, quote_cache, storage, telemetry, types, validate, zome,
+};
 use std::path::PathBuf;
-use tracing::info;
+use std::process::ExitCode;
+use tracing::{info, Instrument};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "pricing-oracle",
-    about = "Fetch token prices, validate, build ConversionTable, and optionally submit to Unyt DNA"
+    about = "Fetch token prices, validate, build ConversionTable, and optionally submit to Unyt DNA",
+    args_conflicts_with_subcommands = true
 )]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand, the `run` flags apply directly
+    #[command(flatten)]
+    run: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Fetch prices, build the ConversionTable, and optionally submit it (the default)
+    Run(Box<Args>),
+    /// Check a config file and list every problem found, not just the first
+    Validate {
+        /// Path to config YAML file
+        #[arg(short, long, default_value = "config.yaml")]
+        config: PathBuf,
+    },
+    /// Compare two ConversionTable JSON files entry by entry; exits non-zero
+    /// if any entry moved beyond --tolerance or exists on one side only
+    Diff {
+        /// Table JSON from the earlier run (e.g. an --output-file)
+        old: PathBuf,
+        /// Table JSON to compare against it
+        new: PathBuf,
+        /// Percent move allowed before the diff fails, e.g. `0.5%`
+        #[arg(long, value_name = "PERCENT", default_value = "0", value_parser = parse_percent)]
+        tolerance: f64,
+    },
+    /// Post sample alerts to every `alerts` destination; exits non-zero
+    /// if any delivery fails
+    NotifyTest {
+        /// Path to config YAML file
+        #[arg(short, long, default_value = "config.yaml")]
+        config: PathBuf,
+    },
+    /// Browse runs stored in the local history file
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HistoryCommand {
+    /// List recent runs, newest first
+    List {
+        #[command(flatten)]
+        store: HistoryStore,
+        /// Number of runs to show
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Print everything stored for one run as JSON
+    Show {
+        /// Run id as shown by `history list`
+        run_id: String,
+        #[command(flatten)]
+        store: HistoryStore,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct HistoryStore {
+    /// Path to config YAML file whose `history.path` is read
+    #[arg(short, long, default_value = "config.yaml")]
+    config: PathBuf,
+    /// History file to read instead of the config's `history.path`
+    #[arg(long, value_name = "PATH")]
+    history: Option<PathBuf>,
+}
+
+impl HistoryStore {
+    fn path(self) -> Result<PathBuf> {
+        if let Some(path) = self.history {
+            return Ok(path);
+        }
+        let cfg = config::Config::parse(&self.config)
+            .with_context(|| format!("loading config from {}", self.config.display()))?;
+        match cfg.history {
+            Some(history) => Ok(history.path),
+            None => anyhow::bail!(
+                "{} has no `history` section; pass --history PATH",
+                self.config.display()
+            ),
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
     /// Path to config YAML file
     #[arg(short, long, default_value = "config.yaml")]
     config: PathBuf,
 
-    /// Output format: "table" (default) or "json"
+    /// Output format: "table" (default), "json" or "csv"
     #[arg(short, long, default_value = "table")]
     output: String,
 
+    /// With --output csv, one row per unit and source with the raw price
+    #[arg(long)]
+    per_source: bool,
+
+    /// With --output json, print every unit and forex rate with its
+    /// per-source values instead of the ConversionTable
+    #[arg(long)]
+    detailed: bool,
+
+    /// With the table output, print each unit's per-source prices,
+    /// deviations, volumes and fetch times under its row
+    #[arg(long)]
+    verbose_table: bool,
+
+    /// Never color the table output (also off when stdout is not a
+    /// terminal or NO_COLOR is set)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Also write the aggregated forex rates as CSV to PATH
+    #[arg(long, value_name = "PATH")]
+    forex_csv: Option<PathBuf>,
+
     /// Only fetch for a specific unit index
     #[arg(short, long)]
     unit: Option<u32>,
 
+    /// Build the table without forex rates (no forex API calls)
+    #[arg(long)]
+    skip_forex: bool,
+
+    /// Embed run metadata (version, per-source prices, deviations, omissions)
+    /// in the table's additional_data
+    #[arg(long)]
+    embed_metadata: bool,
+
+    /// Number of units fetched at the same time
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+
     /// Submit the ConversionTable to the Unyt DNA via create_conversion_table zome call
     #[arg(long, conflicts_with = "dry_run")]
     submit: bool,
@@ -38,235 +162,853 @@ struct Args {
     /// Build and print the ConversionTable JSON without connecting to Holochain
     #[arg(long, conflicts_with = "submit")]
     dry_run: bool,
+
+    /// GlobalDefinition ActionHash (base64, `uhCkk...`) to put in the table
+    /// instead of the zeroed placeholder. With --submit the hash is still
+    /// fetched from the conductor, and the run fails unless the two match
+    #[arg(
+        long,
+        value_name = "ACTION_HASH",
+        env = "GLOBAL_DEFINITION_HASH",
+        value_parser = parse_action_hash
+    )]
+    global_definition: Option<ActionHash>,
+
+    /// Save every HTTP request/response (secrets redacted) to numbered files in DIR
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Serve HTTP responses from a --record directory instead of the network
+    #[arg(long, value_name = "DIR", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// Submit to this entry of the config's `targets`, or to every target
+    /// with `all`
+    #[arg(long, value_name = "NAME", requires = "submit")]
+    target: Option<String>,
+
+    /// After submitting, read the table back from the DHT and fail unless it
+    /// matches what was sent
+    #[arg(long, requires = "submit")]
+    verify: bool,
+
+    /// Submit even if the change guard finds large moves or missing units
+    #[arg(long, requires = "submit")]
+    force: bool,
+
+    /// Skip the submit when no unit price or forex rate moved beyond --change-tolerance
+    #[arg(long, requires = "submit")]
+    submit_if_changed: bool,
+
+    /// Percent move that counts as a change for --submit-if-changed, e.g. `0.1%`
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value = "0",
+        value_parser = parse_percent,
+        requires = "submit_if_changed"
+    )]
+    change_tolerance: f64,
+
+    /// Also write the ConversionTable JSON (only the JSON) to PATH; `-` writes
+    /// it to stdout in place of the usual output
+    #[arg(long, value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Don't submit when any unit or forex symbol would be left out of the
+    /// table; the run exits 2 instead
+    #[arg(long)]
+    strict: bool,
+
+    /// Print nothing to stdout except the run summary (or what --output-file -
+    /// writes), and only log warnings and errors unless RUST_LOG is set
+    #[arg(long)]
+    quiet: bool,
+
+    /// Log format on stderr: "pretty" (default) or "json" (one object per line)
+    #[arg(long, value_enum, default_value_t = telemetry::LogFormat::Pretty)]
+    log_format: telemetry::LogFormat,
+
+    /// Skip uploading run artifacts even if `storage` is configured
+    #[arg(long)]
+    no_upload: bool,
+
+    /// Lock held for the whole run so runs never overlap [default:
+    /// .pricing-oracle.lock in the config's directory]
+    #[arg(long, value_name = "PATH")]
+    lock_file: Option<PathBuf>,
+
+    /// When another run holds the lock, wait this long for it (e.g. `30s`,
+    /// `5m`) before exiting 4; by default exit 4 at once
+    #[arg(long, value_name = "DURATION", default_value = "0s", value_parser = parse_duration)]
+    lock_wait: std::time::Duration,
+
+    /// Also write the run as InfluxDB line protocol to FILE (no server needed)
+    #[arg(long, value_name = "FILE")]
+    influx_file: Option<PathBuf>,
+
+    /// Append the run to this history file (overrides `history.path`)
+    #[arg(long, value_name = "PATH")]
+    history: Option<PathBuf>,
+}
+
+/// How a run ended, as its process exit code. Configuration and startup
+/// errors are returned as `Err` and exit 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunStatus {
+    /// Every unit and forex symbol made it into the table (exit 0).
+    Complete,
+    /// The run finished, but something was left out of the table (exit 2).
+    Partial,
+    /// A submission was attempted and failed (exit 3).
+    SubmitFailed,
+    /// Another run held the lock; nothing was fetched (exit 4).
+    Locked,
+}
+
+impl RunStatus {
+    fn exit_code(self) -> ExitCode {
+        match self {
+            Self::Complete => ExitCode::SUCCESS,
+            Self::Partial => ExitCode::from(2),
+            Self::SubmitFailed => ExitCode::from(3),
+            Self::Locked => ExitCode::from(4),
+        }
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     dotenvy::dotenv().ok();
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
 
-    let args = Args::parse();
+    let cli = Cli::parse();
+    let args = match cli.command {
+        Some(Command::Validate { config }) => {
+            return validate::run(&config).map(|()| ExitCode::SUCCESS)
+        }
+        Some(Command::Diff {
+            old,
+            new,
+            tolerance,
+        }) => return diff::run(&old, &new, tolerance).map(|()| ExitCode::SUCCESS),
+        Some(Command::NotifyTest { config }) => {
+            return notify_test(&config).map(|()| ExitCode::SUCCESS)
+        }
+        Some(Command::History { command }) => {
+            return match command {
+                HistoryCommand::List { store, limit } => history::print_list(&store.path()?, limit),
+                HistoryCommand::Show { run_id, store } => {
+                    history::print_show(&store.path()?, &run_id)
+                }
+            }
+            .map(|()| ExitCode::SUCCESS)
+        }
+        Some(Command::Run(args)) => *args,
+        None => cli.run,
+    };
+    if args.per_source && args.output != "csv" {
+        anyhow::bail!("--per-source only applies to --output csv");
+    }
+    if args.detailed && args.output != "json" {
+        anyhow::bail!("--detailed only applies to --output json");
+    }
+    if args.verbose_table && args.output != "table" {
+        anyhow::bail!("--verbose-table only applies to --output table");
+    }
 
     let cfg = config::Config::load(&args.config)
         .with_context(|| format!("loading config from {}", args.config.display()))?;
 
+    // Set up (and later flush) trace export outside the runtime: the OTLP
+    // exporter uses a blocking HTTP client on its own thread.
+    let log_level = if args.quiet {
+        tracing::Level::WARN
+    } else {
+        tracing::Level::INFO
+    };
+    let telemetry = telemetry::init(args.log_format, log_level, cfg.otlp_endpoint.as_deref());
+    let lock_path = args
+        .lock_file
+        .clone()
+        .unwrap_or_else(|| lock::default_path(&args.config));
+    if !args.lock_wait.is_zero() {
+        info!(
+            "Waiting up to {:?} for lock {}",
+            args.lock_wait,
+            lock_path.display()
+        );
+    }
+    // Held until main returns, also while unwinding from a panic.
+    let Some(_lock) = lock::RunLock::acquire(&lock_path, args.lock_wait)? else {
+        tracing::error!("another run holds {}; not starting", lock_path.display());
+        drop(telemetry);
+        return Ok(RunStatus::Locked.exit_code());
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("starting tokio runtime")?;
+    let span = tracing::info_span!("oracle_run", submit = args.submit, dry_run = args.dry_run);
+    let result = runtime.block_on(run(args, cfg).instrument(span));
+    drop(runtime);
+    drop(telemetry);
+    result.map(RunStatus::exit_code)
+}
+
+async fn run(args: Args, cfg: config::Config) -> Result<RunStatus> {
     info!(
         "Loaded {} units and {} price reference(s) from config",
         cfg.units.len(),
         cfg.price_references.len()
     );
 
-    let coingecko_key = std::env::var("COINGECKO_API_KEY").ok();
-    let coinmarketcap_key = std::env::var("COINMARKETCAP_API_KEY").ok();
-    let twelve_data_key = std::env::var("TWELVE_DATA_API_KEY").ok();
-    let coinapi_key = std::env::var("COINAPI_API_KEY").ok();
-    let client = reqwest::Client::builder()
-        .user_agent("pricing-oracle/0.1")
-        .build()
-        .context("building HTTP client")?;
+    let uploader = match &cfg.storage {
+        Some(storage) if !args.no_upload => Some(storage::Uploader::from_config(storage)?),
+        _ => None,
+    };
 
-    let registry = sources::SourceRegistry::new(client, coingecko_key, coinmarketcap_key);
-    info!("Registered {} price source(s)", registry.source_count());
+    let replaying = args.replay.is_some();
+    let mut settings = oracle::OracleSettings {
+        unit_filter: args.unit,
+        concurrency: args.concurrency as usize,
+        skip_forex: args.skip_forex,
+        embed_metadata: args.embed_metadata,
+        global_definition: args.global_definition.clone(),
+        http_capture: match (args.record, args.replay) {
+            (Some(dir), _) => Some(oracle::HttpCapture::Record(dir)),
+            (_, Some(dir)) => Some(oracle::HttpCapture::Replay(dir)),
+            _ => None,
+        },
+    };
 
-    let mut reference_prices: HashMap<String, types::AggregatedResult> = HashMap::new();
-    for ref_entry in &cfg.price_references {
-        info!(
-            "Fetching price reference '{}' ({})",
-            ref_entry.id, ref_entry.name
+    // Targets that can't be reached are reported with the submissions
+    // rather than stopping the others. With none reachable the submit has
+    // failed (exit 3) before any prices are fetched.
+    let mut targets = Vec::new();
+    let mut unreachable = Vec::new();
+    let mut lone_error = None;
+    if args.submit {
+        let selected = selected_targets(&cfg, args.target.as_deref())?;
+        if selected.len() > 1 && args.global_definition.is_some() {
+            anyhow::bail!("--global-definition can only pin a single --target");
+        }
+        for (name, hc_config) in selected {
+            match connect_target(&hc_config, args.global_definition.as_ref()).await {
+                Ok((client, global_definition)) => targets.push(SubmitTarget {
+                    name,
+                    client,
+                    global_definition,
+                }),
+                Err(e) => match name {
+                    None => lone_error = Some(e),
+                    Some(name) => {
+                        tracing::warn!("target {}: {:#}", name, e);
+                        unreachable.push(TargetResult {
+                            name: Some(name),
+                            submitted: Err(e),
+                        });
+                    }
+                },
+            }
+        }
+        if targets.is_empty() {
+            let error =
+                lone_error.unwrap_or_else(|| targets_failed(unreachable.len(), unreachable));
+            eprintln!("Error: {:?}", error);
+            return Ok(RunStatus::SubmitFailed);
+        }
+        settings.global_definition = Some(targets[0].global_definition.clone());
+    }
+
+    let ipfs = cfg.ipfs.as_ref().map(ipfs::IpfsPublisher::from_config);
+
+    let oracle = oracle::Oracle::from_config(cfg, settings)?;
+    let mut outcome = oracle.run().await?;
+
+    let stats = &outcome.stats;
+    info!(
+        elapsed_ms = (stats.finished_at - stats.started_at).num_milliseconds(),
+        units_valid = stats.units_valid,
+        units_invalid = stats.units_invalid,
+        proxies_unresolved = stats.proxies_unresolved,
+        forex_ok = outcome.forex.len(),
+        forex_requested = stats.forex_requested,
+        source_errors = outcome.source_errors.len(),
+        global_definition = %outcome.table.global_definition,
+        "Run finished"
+    );
+    for e in &outcome.source_errors {
+        tracing::debug!(
+            source = %e.source,
+            subject = %e.subject,
+            error = %e.error,
+            "source error"
+        );
+    }
+
+    // On stderr whatever the output format, so wrappers see it even when
+    // stdout is parsed.
+    let omitted = outcome.omissions(oracle.forex_symbols());
+    if !omitted.is_empty() {
+        eprintln!("Omitted from ConversionTable ({}):", omitted.len());
+        for o in &omitted {
+            eprintln!("  {}: {}", o.entry, o.error);
+        }
+    }
+    let cached: Vec<_> = outcome
+        .aggregated
+        .iter()
+        .filter_map(|r| r.cached_at.map(|at| (r, at)))
+        .collect();
+    if !cached.is_empty() {
+        eprintln!("Cached prices in ConversionTable ({}):", cached.len());
+        for (r, at) in &cached {
+            eprintln!("  unit {} ({}): fetched {}", r.unit_index, r.name, at);
+        }
+    }
+    // Cached prices are in the table but count as degraded, like omissions.
+    let degraded = !omitted.is_empty() || !cached.is_empty();
+    let strict_refused = args.strict && args.submit && degraded;
+    if strict_refused {
+        tracing::error!(
+            "--strict: {} entr(ies) omitted and {} cached price(s); not submitting",
+            omitted.len(),
+            cached.len()
         );
-        let ref_unit = ref_entry.to_unit_config_for_fetch();
-        let fetch_results = registry.fetch_all(&ref_unit).await;
-        let mut successful: Vec<types::TokenData> = Vec::new();
-        for (source_name, result) in fetch_results {
-            match result {
-                Ok(data) => {
-                    info!("  [{}] price={:.8} USD", source_name, data.price_usd);
-                    successful.push(data);
+    }
+
+    // `--output-file -` owns stdout so the JSON can be piped.
+    let json_to_stdout = args.output_file.as_deref() == Some(std::path::Path::new("-"));
+    let print = !args.quiet && !json_to_stdout;
+    let mut receipt = None;
+    let mut submit_error = None;
+    // With --submit, only a submitted table goes to --output-file; one
+    // --strict kept back never is.
+    let mut write_output_file = !strict_refused;
+    if args.dry_run {
+        if print {
+            println!("--- Dry-run: ConversionTable that would be submitted ---");
+            output::print_json(&outcome.table)?;
+        }
+    } else if args.submit && !strict_refused {
+        if let Some(publisher) = &ipfs {
+            outcome.table.additional_data =
+                publisher.link_previous(outcome.table.additional_data.take());
+        }
+        if print {
+            println!("--- ConversionTable to submit ---");
+            output::print_json(&outcome.table)?;
+        }
+
+        let tolerance = args.submit_if_changed.then_some(args.change_tolerance);
+        let target_count = targets.len() + unreachable.len();
+        let mut results = unreachable;
+        // The first successful submission is the one IPFS, the receipt and
+        // --output-file record.
+        let mut first_submitted = None;
+        for target in &targets {
+            outcome.table.global_definition = target.global_definition.clone();
+            let mut submitted =
+                submit(&oracle, &target.client, &outcome, args.force, tolerance).await;
+            if let Ok(Some(action_hash)) = &submitted {
+                if print {
+                    match &target.name {
+                        Some(name) => {
+                            println!("Submitted ConversionTable to {}: {}", name, action_hash)
+                        }
+                        None => println!("Submitted ConversionTable: {}", action_hash),
+                    }
+                }
+                first_submitted
+                    .get_or_insert_with(|| (action_hash.clone(), target.global_definition.clone()));
+                if args.verify {
+                    if let Err(e) = zome::verify_submission(&target.client, &outcome.table).await {
+                        submitted = Err(e);
+                    }
+                }
+            }
+            results.push(TargetResult {
+                name: target.name.clone(),
+                submitted,
+            });
+        }
+
+        let mut ipfs_error = None;
+        // Only a table that was actually submitted goes to --output-file.
+        write_output_file = first_submitted.is_some();
+        if let Some((action_hash, global_definition)) = first_submitted {
+            outcome.table.global_definition = global_definition;
+            if let Some(path) = &oracle.config().last_table_file {
+                if let Err(e) = output::write_json_file(&outcome.table, path) {
+                    tracing::warn!("last_table_file not updated: {:#}", e);
+                }
+            }
+            // Publishing is best-effort: the submission already succeeded.
+            let ipfs_cid = match &ipfs {
+                Some(publisher) => match publisher.publish(&outcome.table).await {
+                    Ok(cid) => {
+                        if print {
+                            println!("Pinned on IPFS: {}", cid);
+                        }
+                        Some(cid)
+                    }
+                    Err(e) => {
+                        tracing::warn!("IPFS publish failed: {:#}", e);
+                        ipfs_error = Some(e);
+                        None
+                    }
+                },
+                None => None,
+            };
+            receipt = Some(storage::SubmissionReceipt {
+                action_hash: action_hash.to_string(),
+                submitted_at: oracle.clock().now(),
+                ipfs_cid,
+            });
+        }
+        submit_error = if results.iter().any(|r| r.name.is_some()) {
+            report_targets(&results, print);
+            let failed: Vec<_> = results
+                .into_iter()
+                .filter(|r| r.submitted.is_err())
+                .collect();
+            (!failed.is_empty()).then(|| targets_failed(target_count, failed))
+        } else {
+            results.pop().and_then(|r| r.submitted.err())
+        };
+        let post_run = alerts::PostRun {
+            action_hash: receipt.as_ref().map(|r| r.action_hash.as_str()),
+            submit: submit_error.as_ref(),
+            ipfs: ipfs_error.as_ref(),
+        };
+        send_alerts(&oracle, &outcome, &post_run).await;
+    } else {
+        send_alerts(&oracle, &outcome, &alerts::PostRun::default()).await;
+
+        if print {
+            match args.output.as_str() {
+                "json" if args.detailed => {
+                    output::print_detailed_json(&output::DetailedReport::new(
+                        &outcome.aggregated,
+                        &outcome.forex,
+                    ))?;
                 }
-                Err(e) => {
-                    tracing::warn!("  [{}] failed: {}", source_name, e);
+                "json" => {
+                    output::print_json(&outcome.table)?;
+                }
+                "csv" if args.per_source => {
+                    print!("{}", output::per_source_csv(&outcome.aggregated));
+                }
+                "csv" => {
+                    print!("{}", output::aggregated_csv(&outcome.aggregated));
+                }
+                _ => {
+                    output::print_table(
+                        &outcome.aggregated,
+                        &outcome.reference,
+                        &output::TableFormat::from_config(oracle.config()),
+                        &output::PrintOptions {
+                            verbose: args.verbose_table.then(|| oracle.config()),
+                            color: output::color_enabled(args.no_color),
+                        },
+                    );
                 }
             }
         }
-        let agg = aggregate::aggregate(0, successful);
-        reference_prices.insert(ref_entry.id.clone(), agg);
     }
 
-    let real_units: Vec<_> = match args.unit {
-        Some(idx) => cfg
-            .real_units()
-            .into_iter()
-            .filter(|u| u.unit_index == idx)
-            .collect(),
-        None => cfg.real_units(),
-    };
+    if let Some(path) = &args.forex_csv {
+        std::fs::write(path, output::forex_csv(&outcome.forex))
+            .with_context(|| format!("writing {}", path.display()))?;
+    }
 
-    let mut aggregated: Vec<types::AggregatedResult> = Vec::new();
+    if let Some(path) = args.output_file.as_deref().filter(|_| write_output_file) {
+        if json_to_stdout {
+            output::print_json(&outcome.table)?;
+        } else {
+            output::write_json_file(&outcome.table, path)?;
+        }
+    }
 
-    for unit in &real_units {
-        info!(
-            "Fetching prices for unit {} ({})",
-            unit.unit_index, unit.name
-        );
-        let fetch_results = registry.fetch_all(unit).await;
-
-        let mut successful: Vec<types::TokenData> = Vec::new();
-        for (source_name, result) in fetch_results {
-            match result {
-                Ok(data) => {
-                    info!("  [{}] price={:.8} USD", source_name, data.price_usd);
-                    successful.push(data);
-                }
-                Err(e) => {
-                    tracing::warn!("  [{}] failed: {}", source_name, e);
+    // Upload even after a failed submit: the table and report explain the failure.
+    if let Some(uploader) = &uploader {
+        match uploader.upload_run(&outcome, receipt.as_ref()).await {
+            Ok(urls) => {
+                for url in urls.iter().filter(|_| print) {
+                    println!("Uploaded {}", url);
                 }
             }
+            Err(e) if submit_error.is_some() => {
+                tracing::warn!("artifact upload failed: {:#}", e)
+            }
+            Err(e) => return Err(e.context("uploading run artifacts")),
         }
+    }
 
-        let agg = aggregate::aggregate(unit.unit_index, successful);
-        aggregated.push(agg);
+    // Runs last so history writes can never hold up pricing or submission.
+    if let Some(url) = &oracle.config().postgres_url {
+        let sink =
+            postgres::PostgresSink::new(url.clone(), oracle.config().postgres_spill_file.clone());
+        let action_hash = receipt.as_ref().map(|r| r.action_hash.clone());
+        sink.record(postgres::RunRecord::from_outcome(&outcome, action_hash))
+            .await;
     }
 
-    let proxy_units: Vec<_> = match args.unit {
-        Some(idx) => cfg
-            .proxy_units()
-            .into_iter()
-            .filter(|u| u.unit_index == idx)
-            .collect(),
-        None => cfg.proxy_units(),
-    };
+    // A replay's prices are recorded ones; caching them would pass old
+    // quotes off as fresh.
+    if let Some(path) = oracle.config().cache_file.as_ref().filter(|_| !replaying) {
+        if let Err(e) = quote_cache::save(path, &outcome.aggregated, outcome.stats.started_at) {
+            tracing::warn!("quote cache write failed: {:#}", e);
+        }
+    }
+    if let Some(path) = oracle
+        .config()
+        .forex_cache_file
+        .as_ref()
+        .filter(|_| !replaying)
+    {
+        if let Err(e) = forex_cache::save(path, &outcome.forex, outcome.stats.started_at) {
+            tracing::warn!("forex cache write failed: {:#}", e);
+        }
+    }
 
-    for proxy_unit in &proxy_units {
-        let proxy_cfg = proxy_unit.price_proxy.as_ref().unwrap();
-        let source = cfg
-            .resolve_proxy_source(proxy_unit.unit_index, proxy_cfg)
-            .context("resolving price_proxy")?;
-
-        let source_agg = match &source {
-            config::ProxySource::Unit(use_unit) => aggregated
-                .iter()
-                .find(|a| a.unit_index == *use_unit)
-                .cloned(),
-            config::ProxySource::Reference(id) => reference_prices.get(id).cloned(),
-        };
+    let history_path = args
+        .history
+        .as_ref()
+        .or(oracle.config().history.as_ref().map(|h| &h.path));
+    if let Some(path) = history_path {
+        let keep_days = oracle
+            .config()
+            .history
+            .as_ref()
+            .map_or_else(config::default_history_keep_days, |h| h.keep_days);
+        let action_hash = receipt.as_ref().map(|r| r.action_hash.as_str());
+        match history::append(path, keep_days, &outcome, action_hash, oracle.clock().now()) {
+            Ok(run_id) => info!("Recorded run {} in {}", run_id, path.display()),
+            Err(e) => tracing::warn!("history write failed: {:#}", e),
+        }
+    }
 
-        if let Some(source_agg) = source_agg {
-            let from = match &source {
-                config::ProxySource::Unit(u) => format!("unit {}", u),
-                config::ProxySource::Reference(id) => format!("reference '{}'", id),
-            };
-            info!(
-                "Proxying unit {} ({}) from {} — price={:.8}",
-                proxy_unit.unit_index, proxy_unit.name, from, source_agg.avg_price_usd
-            );
-            let mut proxied = source_agg;
-            proxied.unit_index = proxy_unit.unit_index;
-            proxied.name = proxy_unit.name.clone();
-            proxied.contract = proxy_unit.contract.clone();
-            aggregated.push(proxied);
-        } else {
-            let (kind, val) = match &source {
-                config::ProxySource::Unit(u) => ("unit", format!("{}", u)),
-                config::ProxySource::Reference(id) => ("reference", id.clone()),
-            };
-            tracing::warn!(
-                "unit {} ({}) proxy {} {} not found or not fetched",
-                proxy_unit.unit_index,
-                proxy_unit.name,
-                kind,
-                val,
-            );
+    if args.influx_file.is_some() || oracle.config().influx.is_some() {
+        let lines = influx::line_protocol(&outcome);
+        if let Some(path) = &args.influx_file {
+            if let Err(e) = std::fs::write(path, &lines) {
+                tracing::warn!("failed to write {}: {}", path.display(), e);
+            }
+        }
+        if let Some(cfg) = &oracle.config().influx {
+            influx::InfluxWriter::from_config(cfg).write(&lines).await;
         }
     }
 
-    aggregated.sort_by_key(|a| a.unit_index);
+    let status = match submit_error {
+        Some(e) => {
+            eprintln!("Error: {:?}", e);
+            RunStatus::SubmitFailed
+        }
+        None if degraded => RunStatus::Partial,
+        None => RunStatus::Complete,
+    };
 
-    let batch_size = cfg.forex.max_symbols_per_run;
-    let delay_secs = cfg.forex.delay_between_batches_secs;
-    let forex_registry = forex::ForexSourceRegistry::new(
-        reqwest::Client::builder()
-            .user_agent("pricing-oracle/0.1")
-            .build()
-            .context("building forex HTTP client")?,
-        twelve_data_key,
-        coinapi_key,
-        cfg.forex.use_twelve_data,
-        cfg.forex.use_coinapi,
-    );
+    let summary =
+        oracle::RunTotals::new(&outcome, receipt.as_ref().map(|r| r.action_hash.as_str()));
     info!(
-        "Registered {} forex source(s); fetching in batches of {} ({} total symbols)",
-        forex_registry.source_count(),
-        batch_size,
-        cfg.forex.symbols.len()
+        units_ok = summary.units_ok,
+        units_invalid = summary.units_invalid,
+        units_missing = summary.units_missing,
+        units_cached = summary.units_cached,
+        forex_ok = summary.forex_ok,
+        forex_missing = summary.forex_missing,
+        submitted = summary.submitted,
+        global_definition = %summary.global_definition,
+        action = summary.action.as_deref(),
+        "Run summary"
     );
+    // Stays off stdout when stdout carries JSON or CSV for a pipe.
+    let machine_stdout = json_to_stdout
+        || (!args.quiet && (args.dry_run || (!args.submit && args.output != "table")));
+    if machine_stdout {
+        eprintln!("{}", summary);
+    } else {
+        println!("{}", summary);
+    }
 
-    let mut aggregated_forex: Vec<forex_aggregate::AggregatedForexRate> = Vec::new();
-    let chunks: Vec<Vec<String>> = cfg
-        .forex
-        .symbols
-        .chunks(batch_size)
-        .map(|c| c.to_vec())
+    Ok(status)
+}
+
+/// `notify-test`: send sample alerts to every `alerts` destination.
+fn notify_test(config_path: &std::path::Path) -> Result<()> {
+    let cfg = config::Config::load(config_path)
+        .with_context(|| format!("loading config from {}", config_path.display()))?;
+    let Some(alerts) = &cfg.alerts else {
+        anyhow::bail!("{} has no `alerts` section", config_path.display());
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("starting tokio runtime")?;
+    let results = runtime.block_on(alerts::send_test(alerts));
+    let mut failed = 0;
+    for (i, result) in results.iter().enumerate() {
+        match result {
+            Ok(()) => println!("destination {}: ok", i + 1),
+            Err(e) => {
+                println!("destination {}: FAILED ({:#})", i + 1, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} destination(s) failed", failed, results.len());
+    }
+    Ok(())
+}
+
+/// A conductor the table is submitted to.
+struct SubmitTarget {
+    /// Entry in the config's `targets`; `None` for the `holochain` section.
+    name: Option<String>,
+    client: zome::HamZomeClient,
+    global_definition: ActionHash,
+}
+
+struct TargetResult {
+    name: Option<String>,
+    /// The ActionHash, or `None` when `--submit-if-changed` skipped it.
+    submitted: Result<Option<ActionHash>>,
+}
+
+/// Holochain settings for `--submit`: the `holochain` section when the
+/// config has no `targets`, otherwise the targets `--target` names.
+fn selected_targets(
+    cfg: &config::Config,
+    target: Option<&str>,
+) -> Result<Vec<(Option<String>, zome::HolochainConfig)>> {
+    if cfg.targets.is_empty() {
+        if target.is_some() {
+            anyhow::bail!("--target needs a `targets` list in the config");
+        }
+        let hc_config = zome::HolochainConfig::from_config(cfg.holochain.as_ref())
+            .context("loading Holochain config for --submit")?;
+        return Ok(vec![(None, hc_config)]);
+    }
+    let Some(target) = target else {
+        anyhow::bail!("the config has `targets`; pass --target <NAME> or --target all");
+    };
+    let selected: Vec<_> = cfg
+        .targets
+        .iter()
+        .filter(|t| target == "all" || t.name == target)
+        .map(|t| Ok((Some(t.name.clone()), zome::HolochainConfig::for_target(t)?)))
+        .collect::<Result<_>>()?;
+    if selected.is_empty() {
+        let names: Vec<&str> = cfg.targets.iter().map(|t| t.name.as_str()).collect();
+        anyhow::bail!(
+            "no target named '{}' (targets: {}, or all)",
+            target,
+            names.join(", ")
+        );
+    }
+    Ok(selected)
+}
+
+/// Connect and fetch the target's active GlobalDefinition, which must
+/// match `pinned` when `--global-definition` is set.
+async fn connect_target(
+    hc_config: &zome::HolochainConfig,
+    pinned: Option<&ActionHash>,
+) -> Result<(zome::HamZomeClient, ActionHash)> {
+    let client = zome::HamZomeClient::connect(hc_config).await?;
+    let global_def = zome::fetch_pinned_global_definition(&client, pinned).await?;
+    Ok((client, global_def))
+}
+
+/// One line per target, so a partial failure is obvious at a glance.
+fn report_targets(results: &[TargetResult], print: bool) {
+    if print {
+        println!("--- Submission targets ---");
+    }
+    for r in results {
+        let name = r.name.as_deref().unwrap_or("-");
+        let status = match &r.submitted {
+            Ok(Some(action_hash)) => format!("submitted {}", action_hash),
+            Ok(None) => "unchanged, not submitted".to_string(),
+            Err(e) => format!("FAILED: {:#}", e),
+        };
+        match &r.submitted {
+            Err(_) => tracing::error!("target {}: {}", name, status),
+            Ok(_) => info!("target {}: {}", name, status),
+        }
+        if print {
+            println!("{:<16} {}", name, status);
+        }
+    }
+}
+
+fn targets_failed(total: usize, failed: Vec<TargetResult>) -> anyhow::Error {
+    let details: Vec<String> = failed
+        .into_iter()
+        .filter_map(|r| Some(format!("{}: {:#}", r.name?, r.submitted.err()?)))
         .collect();
-    let total_batches = chunks.len();
+    anyhow::anyhow!(
+        "{} of {} target(s) failed; {}",
+        details.len(),
+        total,
+        details.join("; ")
+    )
+}
 
-    for (i, chunk) in chunks.into_iter().enumerate() {
-        if i > 0 && delay_secs > 0 {
+/// Run the change guard, then submit. With `change_tolerance` set
+/// (`--submit-if-changed`), returns `None` without submitting when nothing
+/// moved beyond it since the previous table.
+async fn submit(
+    oracle: &oracle::Oracle,
+    client: &dyn zome::ZomeClient,
+    outcome: &oracle::PipelineOutput,
+    force: bool,
+    change_tolerance: Option<f64>,
+) -> Result<Option<holo_hash::ActionHash>> {
+    check_omitted(oracle, outcome, force)?;
+    let previous = check_change_guard(oracle, client, outcome, force).await?;
+    if let (Some(tolerance), Some(previous)) = (change_tolerance, &previous) {
+        let changes = diff::changed_entries(previous, &outcome.table, tolerance)?;
+        if changes.is_empty() {
             info!(
-                "Waiting {}s before next forex batch (rate limit)",
-                delay_secs
+                "[submit] ConversionTable unchanged within {}%, skipping",
+                tolerance
+            );
+            return Ok(None);
+        }
+        let fmt = |v: Option<f64>| v.map_or_else(|| "—".to_string(), |p| format!("{:.8}", p));
+        for c in &changes {
+            info!(
+                "[submit] {} changed: {} -> {}",
+                c.entry,
+                fmt(c.previous),
+                fmt(c.current)
             );
-            tokio::time::sleep(std::time::Duration::from_secs(delay_secs)).await;
         }
-        info!(
-            "Forex batch {}/{}: {}",
-            i + 1,
-            total_batches,
-            chunk.join(", ")
-        );
-        let forex_results = forex_registry.fetch_all(&chunk).await;
-        let batch_rates = forex_aggregate::aggregate_forex_rates(&chunk, forex_results);
-        aggregated_forex.extend(batch_rates);
     }
+    zome::submit_conversion_table(client, outcome.table.clone())
+        .await
+        .map(Some)
+}
 
-    if args.dry_run {
-        let table = output::build_conversion_table(&aggregated, &aggregated_forex, None)?;
-        println!("--- Dry-run: ConversionTable that would be submitted ---");
-        output::print_json(&table)?;
-        return Ok(());
+/// Compare against the last published table before submitting and return
+/// it. A failed lookup (with no `last_table_file` to fall back on) blocks
+/// the submit too, unless `--force`.
+async fn check_change_guard(
+    oracle: &oracle::Oracle,
+    client: &dyn zome::ZomeClient,
+    outcome: &oracle::PipelineOutput,
+    force: bool,
+) -> Result<Option<types::ConversionTable>> {
+    let snapshot = oracle.config().last_table_file.as_deref();
+    let previous = match zome::fetch_previous_table(client, snapshot).await {
+        Ok(previous) => previous,
+        Err(e) if force => {
+            tracing::warn!("[guard] skipped, previous table unavailable: {:#}", e);
+            return Ok(None);
+        }
+        Err(e) => return Err(e.context("change guard: fetching previous ConversionTable")),
+    };
+    if let Some(previous) = &previous {
+        guard::check(
+            previous,
+            &outcome.table,
+            oracle.config().max_change_percent,
+            force,
+        )?;
     }
+    Ok(previous)
+}
 
-    if args.submit {
-        let hc_config =
-            zome::HolochainConfig::from_env().context("loading Holochain config for --submit")?;
+/// A base64 ActionHash as printed by `hc` (`uhCkk...`).
+fn parse_action_hash(s: &str) -> Result<ActionHash, String> {
+    ActionHashB64::from_b64_str(s.trim())
+        .map(ActionHash::from)
+        .map_err(|e| format!("'{}' is not a base64 ActionHash: {}", s, e))
+}
 
-        let global_def = zome::fetch_global_definition(&hc_config)
-            .await
-            .context("fetching current GlobalDefinition")?;
+/// `0.1%` or `0.1` → 0.1 (percent).
+fn parse_percent(s: &str) -> Result<f64, String> {
+    let value: f64 = s
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("'{}' is not a percentage", s))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("'{}' must be a non-negative percentage", s));
+    }
+    Ok(value)
+}
 
-        let table =
-            output::build_conversion_table(&aggregated, &aggregated_forex, Some(global_def))?;
-        println!("--- ConversionTable to submit ---");
-        output::print_json(&table)?;
+/// `500ms`, `30s`, `5m` or `1h`; a bare number is seconds.
+fn parse_duration(s: &str) -> Result<std::time::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("'{}' is not a duration like 30s or 5m", s))?;
+    let secs = match unit {
+        "ms" => return Ok(std::time::Duration::from_millis(value)),
+        "" | "s" => value,
+        "m" => value.saturating_mul(60),
+        "h" => value.saturating_mul(3600),
+        _ => return Err(format!("'{}' has an unknown unit; use ms, s, m or h", s)),
+    };
+    Ok(std::time::Duration::from_secs(secs))
+}
 
-        let action_hash = zome::submit_conversion_table(&hc_config, table).await?;
-        println!("Submitted ConversionTable: {}", action_hash);
+/// Refuse to submit a table missing more than `max_omitted_fraction` of
+/// the valid units, unless `force` is set: that points at a systemic
+/// encoding problem rather than one bad price.
+fn check_omitted(
+    oracle: &oracle::Oracle,
+    outcome: &oracle::PipelineOutput,
+    force: bool,
+) -> Result<()> {
+    let omitted_units = outcome.stats.units_unencodable;
+    if omitted_units == 0 {
         return Ok(());
     }
-
-    match args.output.as_str() {
-        "json" => {
-            let table = output::build_conversion_table(&aggregated, &aggregated_forex, None)?;
-            output::print_json(&table)?;
-        }
-        _ => {
-            output::print_table(&aggregated);
-        }
+    let fraction = omitted_units as f64 / outcome.stats.units_valid.max(1) as f64;
+    let max = oracle.config().max_omitted_fraction;
+    if fraction <= max {
+        return Ok(());
     }
+    if force {
+        tracing::warn!(
+            "[submit] {} of {} valid unit(s) omitted (max {:.0}%); submitting anyway (--force)",
+            omitted_units,
+            outcome.stats.units_valid,
+            max * 100.0
+        );
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{} of {} valid unit(s) could not be encoded and were omitted (max {:.0}%); \
+         refusing to submit without --force",
+        omitted_units,
+        outcome.stats.units_valid,
+        max * 100.0
+    )
+}
 
-    Ok(())
+async fn send_alerts(
+    oracle: &oracle::Oracle,
+    outcome: &oracle::PipelineOutput,
+    post_run: &alerts::PostRun<'_>,
+) {
+    if let Some(cfg) = &oracle.config().alerts {
+        alerts::send_alerts(
+            cfg,
+            outcome,
+            oracle.forex_symbols(),
+            post_run,
+            oracle.clock(),
+        )
+        .await;
+    }
 }
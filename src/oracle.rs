@@ -0,0 +1,1245 @@
+use crate::aggregate;
+use crate::clock::{Clock, ManualClock, SystemClock};
+use crate::config::{self, Config, UnitConfig};
+use crate::forex::ForexSourceRegistry;
+use crate::forex_aggregate::{self, AggregatedForexRate};
+use crate::forex_cache;
+use crate::http::HttpClient;
+use crate::output;
+use crate::proxy;
+use crate::quote_cache;
+use crate::sources::{self, SourceRegistry};
+use crate::types::{AggregatedResult, ConversionTable, SourceResult, TokenData};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use holo_hash::ActionHash;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, info_span, warn, Instrument};
+
+/// Price reference id whose aggregated price converts USDT-quoted sources to USD.
+const USDT_REFERENCE: &str = "USDT";
+
+/// Per-run knobs that are not part of config.yaml.
+#[derive(Debug, Clone, Default)]
+pub struct OracleSettings {
+    /// Only process this unit index (references and forex are still fetched).
+    pub unit_filter: Option<u32>,
+    /// GlobalDefinition to stamp into the table; zeroed placeholder if `None`.
+    pub global_definition: Option<ActionHash>,
+    /// Record HTTP traffic to, or replay it from, a directory.
+    pub http_capture: Option<HttpCapture>,
+    /// Units fetched at the same time; `0` is treated as `1`.
+    pub concurrency: usize,
+    /// Build the table without forex rates and make no forex API calls.
+    pub skip_forex: bool,
+    /// Embed `RunMetadata` in the table even if the config doesn't ask for it.
+    pub embed_metadata: bool,
+}
+
+/// Where a run's HTTP traffic goes besides the network.
+#[derive(Debug, Clone)]
+pub enum HttpCapture {
+    /// Fetch normally and save every response under this directory.
+    Record(PathBuf),
+    /// Serve responses saved by [`HttpCapture::Record`] instead of fetching.
+    Replay(PathBuf),
+}
+
+/// One failed fetch, kept so callers can report it without scraping logs.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceError {
+    /// Source name as registered, e.g. `coingecko`.
+    pub source: String,
+    /// What was being fetched: `unit 3`, `reference 'HOT'`, or `forex`.
+    pub subject: String,
+    /// The error with its context chain.
+    pub error: String,
+}
+
+/// Counts and timestamps for one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunStats {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub units_valid: usize,
+    pub units_invalid: usize,
+    pub proxies_unresolved: usize,
+    /// Valid units left out of the table because their price could not be
+    /// encoded.
+    pub units_unencodable: usize,
+    /// Units priced from `cache_file` because no source answered.
+    pub units_cached: usize,
+    pub forex_requested: usize,
+    /// Forex symbols that made it into the table.
+    pub forex_ok: usize,
+    /// Requested forex symbols not in the table: no valid rate, or a rate
+    /// that could not be encoded.
+    pub forex_missing: usize,
+}
+
+/// What the end-of-run `summary` line reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunTotals {
+    /// Units in the table.
+    pub units_ok: usize,
+    pub units_invalid: usize,
+    /// Valid units that could not be encoded, plus unresolved proxies.
+    pub units_missing: usize,
+    /// Units in the table at a cached price.
+    pub units_cached: usize,
+    pub forex_ok: usize,
+    pub forex_missing: usize,
+    pub submitted: bool,
+    /// GlobalDefinition ActionHash the table was built against.
+    pub global_definition: String,
+    /// ActionHash of the submitted table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
+impl RunTotals {
+    pub fn new(outcome: &PipelineOutput, action_hash: Option<&str>) -> Self {
+        let stats = &outcome.stats;
+        Self {
+            units_ok: stats.units_valid - stats.units_unencodable,
+            units_invalid: stats.units_invalid,
+            units_missing: stats.units_unencodable + stats.proxies_unresolved,
+            units_cached: stats.units_cached,
+            forex_ok: stats.forex_ok,
+            forex_missing: stats.forex_missing,
+            submitted: action_hash.is_some(),
+            global_definition: outcome.table.global_definition.to_string(),
+            action: action_hash.map(str::to_string),
+        }
+    }
+}
+
+/// `summary units_ok=12 units_invalid=1 ... submitted=true global_definition=uhCkk... action=uhCkk...`
+impl std::fmt::Display for RunTotals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "summary units_ok={} units_invalid={} units_missing={} units_cached={} forex_ok={} forex_missing={} submitted={} global_definition={}",
+            self.units_ok,
+            self.units_invalid,
+            self.units_missing,
+            self.units_cached,
+            self.forex_ok,
+            self.forex_missing,
+            self.submitted,
+            self.global_definition
+        )?;
+        if let Some(action) = &self.action {
+            write!(f, " action={}", action)?;
+        }
+        Ok(())
+    }
+}
+
+/// How long one source took for one fetch, successful or not.
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceTiming {
+    /// Source name as registered.
+    pub source: String,
+    /// What was being fetched, as in [`SourceError::subject`].
+    pub subject: String,
+    /// Wall time of the fetch, retries included.
+    pub elapsed_ms: i64,
+}
+
+/// Per-fetch bookkeeping collected while a run progresses.
+#[derive(Default)]
+struct FetchLog {
+    errors: Vec<SourceError>,
+    timings: Vec<SourceTiming>,
+}
+
+impl FetchLog {
+    fn record<T>(&mut self, subject: &str, fetch: &SourceResult<T>) {
+        self.timings.push(SourceTiming {
+            source: fetch.source.clone(),
+            subject: subject.to_string(),
+            elapsed_ms: fetch.elapsed_ms,
+        });
+        if let Err(e) = &fetch.result {
+            self.errors.push(SourceError {
+                source: fetch.source.clone(),
+                subject: subject.to_string(),
+                error: format!("{:#}", e),
+            });
+        }
+    }
+}
+
+/// Everything one run computes, minus printing and submission.
+#[derive(Debug, Clone)]
+pub struct PipelineOutput {
+    /// One entry per unit processed, valid or not, in config order.
+    pub aggregated: Vec<AggregatedResult>,
+    /// Forex rates that passed validation.
+    pub forex: Vec<AggregatedForexRate>,
+    /// The table a submit would publish.
+    pub table: ConversionTable,
+    /// Valid units and forex rates left out of `table` because their value
+    /// could not be encoded.
+    pub omitted: Vec<output::OmittedEntry>,
+    /// Currency `table` is priced in.
+    pub reference: output::ReferenceCurrency,
+    pub stats: RunStats,
+    /// Every failed fetch, including ones other sources made up for.
+    pub source_errors: Vec<SourceError>,
+    /// Every fetch attempted, successful or not.
+    pub source_timings: Vec<SourceTiming>,
+}
+
+impl PipelineOutput {
+    /// Everything the run was configured to publish that is not in `table`,
+    /// with the reason: invalid units, unencodable entries, requested forex
+    /// symbols without a valid rate and unresolved proxies.
+    pub fn omissions(&self, forex_symbols: &[String]) -> Vec<output::OmittedEntry> {
+        let invalid = self
+            .aggregated
+            .iter()
+            .filter(|r| !r.valid)
+            .map(|r| output::OmittedEntry {
+                entry: format!("unit {} ({})", r.unit_index, r.name),
+                error: r.reason.clone().unwrap_or_else(|| "invalid".to_string()),
+            });
+        let forex = forex_symbols
+            .iter()
+            .filter(|s| !self.forex.iter().any(|f| f.symbol == **s))
+            .map(|s| output::OmittedEntry {
+                entry: format!("forex {}", s),
+                error: "no valid rate".to_string(),
+            });
+        let proxies = (self.stats.proxies_unresolved > 0).then(|| output::OmittedEntry {
+            entry: format!("{} proxy unit(s)", self.stats.proxies_unresolved),
+            error: "source not found or not fetched".to_string(),
+        });
+        invalid
+            .chain(self.omitted.iter().cloned())
+            .chain(forex)
+            .chain(proxies)
+            .collect()
+    }
+}
+
+/// Fetch prices and forex rates for every configured unit and build the
+/// ConversionTable, without printing or submitting anything.
+///
+/// Shorthand for [`Oracle::from_config`] followed by [`Oracle::run`].
+pub async fn run_pipeline(cfg: Config, options: OracleSettings) -> Result<PipelineOutput> {
+    Oracle::from_config(cfg, options)?.run().await
+}
+
+/// A configured set of sources ready to run; see [`run_pipeline`].
+pub struct Oracle {
+    config: Config,
+    registry: SourceRegistry,
+    forex_registry: ForexSourceRegistry,
+    settings: OracleSettings,
+    clock: Arc<dyn Clock>,
+}
+
+impl Oracle {
+    /// Assemble an oracle from prebuilt registries, e.g. with mocked sources.
+    pub fn new(
+        config: Config,
+        registry: SourceRegistry,
+        forex_registry: ForexSourceRegistry,
+        settings: OracleSettings,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            config,
+            registry,
+            forex_registry,
+            settings,
+            clock,
+        }
+    }
+
+    /// Build HTTP clients and env/config-driven registries on the system
+    /// clock, or pinned to the recording time under `--replay`.
+    pub fn from_config(config: Config, settings: OracleSettings) -> Result<Self> {
+        let mut clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let http = &config.http;
+        let mut builder = reqwest::Client::builder()
+            .user_agent("pricing-oracle/0.1")
+            .timeout(Duration::from_secs(http.timeout_seconds))
+            .connect_timeout(Duration::from_secs(http.connect_timeout_seconds));
+        if let Some(proxy) = &http.proxy_url {
+            // Drop reqwest's error: it may echo credentials in the URL.
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|_| anyhow::anyhow!("invalid http.proxy_url"))?;
+            builder = builder.proxy(proxy);
+        }
+        let inner = builder.build().context("building HTTP client")?;
+        let client = match &settings.http_capture {
+            None => HttpClient::new(inner),
+            Some(HttpCapture::Record(dir)) => {
+                info!("Recording HTTP traffic to {}", dir.display());
+                HttpClient::recording(inner, dir, clock.clone())?
+            }
+            Some(HttpCapture::Replay(dir)) => {
+                info!("Replaying HTTP traffic from {}", dir.display());
+                HttpClient::replaying(inner, dir)?
+            }
+        }
+        .with_retries(config.http_retries);
+        // Replays age data against the recording time so output is reproducible.
+        if let Some(recorded_at) = client.recorded_at() {
+            info!("Replay clock pinned to {}", recorded_at.to_rfc3339());
+            clock = Arc::new(ManualClock::new(recorded_at));
+        }
+
+        let registry = SourceRegistry::from_config(client.clone(), &config, clock.clone());
+        info!("Registered {} price source(s)", registry.source_count());
+        let forex_registry = ForexSourceRegistry::from_config(client, &config, clock.clone());
+
+        Ok(Self::new(config, registry, forex_registry, settings, clock))
+    }
+
+    /// The config this oracle was built from.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Forex symbols this run asks for: none with `--skip-forex`.
+    pub fn forex_symbols(&self) -> &[String] {
+        if self.settings.skip_forex {
+            &[]
+        } else {
+            &self.config.forex.symbols
+        }
+    }
+
+    /// Clock used for run timestamps.
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Run references → units → proxies → forex → table once.
+    ///
+    /// Nothing is persisted or submitted, so dropping the returned future
+    /// (e.g. on Ctrl-C) simply abandons in-flight HTTP requests; a later
+    /// `run()` starts from scratch.
+    pub async fn run(&self) -> Result<PipelineOutput> {
+        let started_at = self.clock.now();
+        let cfg = &self.config;
+        let mut log = FetchLog::default();
+
+        let mut reference_prices: HashMap<String, AggregatedResult> = HashMap::new();
+        async {
+            for ref_entry in &cfg.price_references {
+                let agg = async {
+                    info!(
+                        "Fetching price reference '{}' ({})",
+                        ref_entry.id, ref_entry.name
+                    );
+                    let ref_unit = ref_entry.to_unit_config_for_fetch();
+                    self.fetch_and_aggregate(&ref_unit, None, &mut log).await
+                }
+                .instrument(info_span!("reference", reference = %ref_entry.id))
+                .await;
+                reference_prices.insert(ref_entry.id.clone(), agg);
+            }
+        }
+        .instrument(info_span!("references"))
+        .await;
+
+        let usdt_usd = reference_prices
+            .get(USDT_REFERENCE)
+            .filter(|agg| agg.valid)
+            .map(|agg| agg.avg_price_usd);
+        if let Some(rate) = usdt_usd {
+            info!("USDT-quoted sources converted at {:.6} USD", rate);
+        }
+
+        let real_units: Vec<_> = match self.settings.unit_filter {
+            Some(idx) => cfg
+                .real_units()
+                .into_iter()
+                .filter(|u| u.unit_index == idx)
+                .collect(),
+            None => cfg.real_units(),
+        };
+
+        info!("Fetching prices for {} unit(s)", real_units.len());
+        let fetched = self
+            .registry
+            .fetch_all_units(&real_units, self.settings.concurrency)
+            .instrument(info_span!("units"))
+            .await;
+        let mut aggregated: Vec<AggregatedResult> = Vec::with_capacity(fetched.len());
+        for (unit, fetch_results) in real_units.iter().zip(fetched) {
+            let agg = info_span!("unit", unit_index = unit.unit_index).in_scope(|| {
+                info!("Prices for unit {} ({})", unit.unit_index, unit.name);
+                self.aggregate_fetched(unit, fetch_results, usdt_usd, &mut log)
+            });
+            aggregated.push(agg);
+        }
+        if let Some(path) = cfg
+            .cache_file
+            .as_ref()
+            .filter(|_| cfg.max_cache_age_seconds > 0)
+        {
+            self.fill_units_from_cache(path, &mut aggregated);
+        }
+
+        let proxies_unresolved = self.resolve_proxies(&reference_prices, &mut aggregated)?;
+        aggregated.sort_by_key(|a| a.unit_index);
+
+        let forex = if self.settings.skip_forex {
+            info!("Skipping forex (--skip-forex)");
+            Vec::new()
+        } else {
+            self.fetch_forex(&mut log)
+                .instrument(info_span!("forex"))
+                .await
+        };
+
+        let reference = output::ReferenceCurrency::resolve(&cfg.reference_currency, &forex)?;
+        let built = info_span!("build_table").in_scope(|| {
+            output::build_conversion_table(
+                &aggregated,
+                &forex,
+                &reference,
+                &output::TableFormat::from_config(cfg),
+                self.settings.global_definition.clone(),
+                (self.settings.embed_metadata || cfg.embed_metadata).then_some(started_at),
+            )
+        });
+
+        let units_valid = aggregated.iter().filter(|a| a.valid).count();
+        let omitted_units = built
+            .omitted
+            .iter()
+            .filter(|o| o.entry.starts_with("unit "))
+            .count();
+        let forex_requested = self.forex_symbols().len();
+        let forex_ok = forex.len() - (built.omitted.len() - omitted_units);
+        let stats = RunStats {
+            started_at,
+            finished_at: self.clock.now(),
+            units_valid,
+            units_invalid: aggregated.len() - units_valid,
+            proxies_unresolved,
+            units_unencodable: omitted_units,
+            units_cached: aggregated.iter().filter(|a| a.cached_at.is_some()).count(),
+            forex_requested,
+            forex_ok,
+            forex_missing: forex_requested.saturating_sub(forex_ok),
+        };
+
+        Ok(PipelineOutput {
+            aggregated,
+            forex,
+            table: built.table,
+            omitted: built.omitted,
+            reference,
+            stats,
+            source_errors: log.errors,
+            source_timings: log.timings,
+        })
+    }
+
+    async fn fetch_and_aggregate(
+        &self,
+        unit: &UnitConfig,
+        usdt_usd: Option<f64>,
+        log: &mut FetchLog,
+    ) -> AggregatedResult {
+        let fetch_results = self.registry.fetch_all(unit).await;
+        self.aggregate_fetched(unit, fetch_results, usdt_usd, log)
+    }
+
+    fn aggregate_fetched(
+        &self,
+        unit: &UnitConfig,
+        fetch_results: Vec<SourceResult<TokenData>>,
+        usdt_usd: Option<f64>,
+        log: &mut FetchLog,
+    ) -> AggregatedResult {
+        let key = unit.key();
+        let subject = key.to_string();
+        let mut successful: Vec<TokenData> = Vec::new();
+        for fetch in fetch_results {
+            if sources::is_not_configured(&fetch.result) {
+                continue;
+            }
+            log.record(&subject, &fetch);
+            match fetch.result {
+                Ok(mut data) => {
+                    if let Some(rate) =
+                        usdt_usd.filter(|_| self.registry.quote_currency(&fetch.source) == "USDT")
+                    {
+                        data.price_usd *= rate;
+                        data.volume_24h = data.volume_24h.map(|v| v * rate);
+                    }
+                    info!(
+                        unit_index = key.index(),
+                        reference = key.reference(),
+                        source = %fetch.source,
+                        price_usd = data.price_usd,
+                        "source price"
+                    );
+                    successful.push(data);
+                }
+                Err(e) => warn!(
+                    unit_index = key.index(),
+                    reference = key.reference(),
+                    source = %fetch.source,
+                    error = %format_args!("{:#}", e),
+                    "source fetch failed"
+                ),
+            }
+        }
+        let mut result = aggregate::aggregate(
+            &key,
+            successful,
+            self.config.aggregate_settings_for(unit),
+            self.clock.now(),
+        );
+        // Without a quote the result can't name the unit; omissions would
+        // read `unit 2 ()`.
+        if result.name.is_empty() {
+            result.name = unit.name.clone();
+            result.contract = unit.contract.clone();
+        }
+        result
+    }
+
+    /// Copy each proxy unit's price from its source unit or reference,
+    /// scaled by the proxy's `multiplier` when set. Proxies of proxies are
+    /// resolved after the proxy they copy from.
+    /// Returns how many proxies could not be resolved.
+    fn resolve_proxies(
+        &self,
+        reference_prices: &HashMap<String, AggregatedResult>,
+        aggregated: &mut Vec<AggregatedResult>,
+    ) -> Result<usize> {
+        let cfg = &self.config;
+        let proxy_units: Vec<_> = match self.settings.unit_filter {
+            Some(idx) => cfg
+                .proxy_units()
+                .into_iter()
+                .filter(|u| u.unit_index == idx)
+                .collect(),
+            None => cfg.proxy_units(),
+        };
+        let proxy_units = proxy::resolution_order(cfg, proxy_units)?;
+
+        let mut unresolved = 0;
+        for proxy_unit in &proxy_units {
+            let proxy_cfg = proxy_unit.price_proxy.as_ref().unwrap();
+            let source = cfg
+                .resolve_proxy_source(proxy_unit.unit_index, proxy_cfg)
+                .context("resolving price_proxy")?;
+
+            let source_agg = match &source {
+                config::ProxySource::Unit(use_unit) => aggregated
+                    .iter()
+                    .find(|a| a.unit_index == *use_unit)
+                    .cloned(),
+                config::ProxySource::Reference(id) => reference_prices.get(id).cloned(),
+            };
+
+            if let Some(source_agg) = source_agg {
+                let from = match &source {
+                    config::ProxySource::Unit(u) => format!("unit {}", u),
+                    config::ProxySource::Reference(id) => format!("reference '{}'", id),
+                };
+                let mut proxied = source_agg;
+                if let Some(m) = proxy_cfg.multiplier {
+                    proxied.avg_price_usd *= m;
+                    proxied.volume_24h = proxied.volume_24h.map(|v| v * m);
+                }
+                let hops = proxy::chain(cfg, proxy_unit)?;
+                if let Some(label) = proxy::label(cfg, proxy_unit, &hops) {
+                    proxied.sources = vec![label];
+                }
+                info!(
+                    "Proxying unit {} ({}) from {} — price={:.8}",
+                    proxy_unit.unit_index, proxy_unit.name, from, proxied.avg_price_usd
+                );
+                proxied.unit_index = proxy_unit.unit_index;
+                proxied.reference_id = None;
+                proxied.name = proxy_unit.name.clone();
+                proxied.contract = proxy_unit.contract.clone();
+                aggregated.push(proxied);
+            } else {
+                let (kind, val) = match &source {
+                    config::ProxySource::Unit(u) => ("unit", format!("{}", u)),
+                    config::ProxySource::Reference(id) => ("reference", id.clone()),
+                };
+                warn!(
+                    "unit {} ({}) proxy {} {} not found or not fetched",
+                    proxy_unit.unit_index, proxy_unit.name, kind, val,
+                );
+                unresolved += 1;
+            }
+        }
+        Ok(unresolved)
+    }
+
+    async fn fetch_forex(&self, log: &mut FetchLog) -> Vec<AggregatedForexRate> {
+        let forex_cfg = &self.config.forex;
+        let batch_size = forex_cfg.max_symbols_per_run;
+        let delay_secs = forex_cfg.delay_between_batches_secs;
+        info!(
+            "Registered {} forex source(s); fetching in batches of {} ({} total symbols)",
+            self.forex_registry.source_count(),
+            batch_size,
+            forex_cfg.symbols.len()
+        );
+
+        let mut aggregated_forex: Vec<AggregatedForexRate> = Vec::new();
+        let chunks: Vec<Vec<String>> = forex_cfg
+            .symbols
+            .chunks(batch_size)
+            .map(|c| c.to_vec())
+            .collect();
+        let total_batches = chunks.len();
+
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            if i > 0 && delay_secs > 0 {
+                info!(
+                    "Waiting {}s before next forex batch (rate limit)",
+                    delay_secs
+                );
+                tokio::time::sleep(std::time::Duration::from_secs(delay_secs)).await;
+            }
+            info!(
+                "Forex batch {}/{}: {}",
+                i + 1,
+                total_batches,
+                chunk.join(", ")
+            );
+            let forex_results = self
+                .forex_registry
+                .fetch_all(&chunk)
+                .await
+                .into_iter()
+                .map(|fetch| {
+                    log.record("forex", &fetch);
+                    (fetch.source, fetch.result)
+                })
+                .collect();
+            let batch_rates = forex_aggregate::aggregate_forex_rates(
+                &chunk,
+                forex_results,
+                self.config.forex_aggregate_settings(),
+                &self.config.forex.bounds,
+                &self.config.forex.names,
+            );
+            aggregated_forex.extend(batch_rates);
+        }
+        if let Some(path) = &self.config.forex_cache_file {
+            self.fill_from_cache(path, &mut aggregated_forex);
+        }
+        aggregated_forex
+    }
+
+    /// Replace each unit no source answered for with its cached price, if it
+    /// is younger than `max_cache_age_seconds`. The result lists `cache` as
+    /// its only source and carries `cached_at`.
+    fn fill_units_from_cache(&self, path: &Path, aggregated: &mut [AggregatedResult]) {
+        let cache = match quote_cache::load(path) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("{:#}; cached unit prices not used", e);
+                return;
+            }
+        };
+        let max_age = chrono::Duration::seconds(
+            i64::try_from(self.config.max_cache_age_seconds).unwrap_or(i64::MAX),
+        );
+        let now = self.clock.now();
+        for agg in aggregated
+            .iter_mut()
+            .filter(|a| !a.valid && a.per_source.is_empty())
+        {
+            let Some(cached) = cache.get(&agg.unit_index) else {
+                continue;
+            };
+            if !cached.is_fresh(now, max_age) {
+                warn!(
+                    unit_index = agg.unit_index,
+                    fetched_at = %cached.fetched_at,
+                    "cached price is stale — not used, omitted from ConversionTable"
+                );
+                continue;
+            }
+            warn!(
+                unit_index = agg.unit_index,
+                price_usd = cached.result.avg_price_usd,
+                fetched_at = %cached.fetched_at,
+                provenance = "cached",
+                "no source returned a price this run; using cached price"
+            );
+            let name = std::mem::take(&mut agg.name);
+            *agg = AggregatedResult {
+                unit_index: agg.unit_index,
+                name: if name.is_empty() {
+                    cached.result.name.clone()
+                } else {
+                    name
+                },
+                sources: vec!["cache".to_string()],
+                rejected_sources: Vec::new(),
+                valid: true,
+                reason: None,
+                weights: Vec::new(),
+                oldest_quote_age_secs: Some(
+                    (now - cached.fetched_at).num_seconds()
+                        + cached.result.oldest_quote_age_secs.unwrap_or(0),
+                ),
+                cached_at: Some(cached.fetched_at),
+                per_source: Vec::new(),
+                ..cached.result.clone()
+            };
+        }
+    }
+
+    /// Add the cached rate for each symbol that got none this run, if it is
+    /// younger than `forex_max_cache_age_seconds`. Symbols stay in config order.
+    fn fill_from_cache(&self, path: &Path, rates: &mut Vec<AggregatedForexRate>) {
+        let cache = match forex_cache::load(path) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("{:#}; cached forex rates not used", e);
+                return;
+            }
+        };
+        let max_age = chrono::Duration::seconds(
+            i64::try_from(self.config.forex_max_cache_age_seconds).unwrap_or(i64::MAX),
+        );
+        let now = self.clock.now();
+        let mut fresh: HashMap<String, AggregatedForexRate> =
+            rates.drain(..).map(|r| (r.symbol.clone(), r)).collect();
+        for symbol in &self.config.forex.symbols {
+            if let Some(rate) = fresh.remove(symbol) {
+                rates.push(rate);
+                continue;
+            }
+            let Some(cached) = cache.get(symbol) else {
+                continue;
+            };
+            if !cached.is_fresh(now, max_age) {
+                warn!(
+                    symbol = %symbol,
+                    fetched_at = %cached.fetched_at,
+                    "cached forex rate is stale — not used, omitted from ConversionTable"
+                );
+                continue;
+            }
+            warn!(
+                symbol = %symbol,
+                rate = cached.foreign_per_usd,
+                fetched_at = %cached.fetched_at,
+                provenance = "cached",
+                "no valid forex rate this run; using cached rate"
+            );
+            rates.push(AggregatedForexRate {
+                symbol: symbol.clone(),
+                name: forex_aggregate::display_name(symbol, &self.config.forex.names),
+                foreign_per_usd: cached.foreign_per_usd,
+                cached_at: Some(cached.fetched_at),
+                per_source: Vec::new(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forex::mock::MockForexSource;
+    use crate::sources::mock::MockSource;
+
+    const CONFIG: &str = r#"
+price_references:
+  - id: HOT
+    name: HOT
+    chain: ethereum
+    contract: "0x6c6ee5e31d828de241282b9606c8e98ea48526e2"
+units:
+  - unit_index: 1
+    name: HOT
+    chain: ethereum
+    contract: "0x6c6ee5e31d828de241282b9606c8e98ea48526e2"
+  - unit_index: 2
+    name: WETH
+    chain: ethereum
+    contract: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+  - unit_index: 3
+    name: HF
+    chain: ethereum
+    contract: "0xeac8eeee9f84f3e3f592e9d8604100ea1b788749"
+    price_proxy:
+      use_reference: HOT
+forex:
+  symbols: [EUR, JPY]
+min_sources: 1
+"#;
+
+    fn oracle(
+        sources: Vec<MockSource>,
+        forex_sources: Vec<MockForexSource>,
+        settings: OracleSettings,
+    ) -> Oracle {
+        let config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        oracle_with(
+            config,
+            sources,
+            forex_sources,
+            settings,
+            Arc::new(SystemClock),
+        )
+    }
+
+    fn oracle_with(
+        config: Config,
+        sources: Vec<MockSource>,
+        forex_sources: Vec<MockForexSource>,
+        settings: OracleSettings,
+        clock: Arc<dyn Clock>,
+    ) -> Oracle {
+        let sources = sources
+            .into_iter()
+            .map(|s| Box::new(s) as Box<dyn sources::PriceSource>)
+            .collect();
+        let forex_sources = forex_sources
+            .into_iter()
+            .map(|s| Box::new(s) as Box<dyn crate::forex::ForexSource>)
+            .collect();
+        Oracle::new(
+            config,
+            SourceRegistry::with_sources(sources),
+            ForexSourceRegistry::with_sources(forex_sources),
+            settings,
+            clock,
+        )
+    }
+
+    fn forex() -> MockForexSource {
+        MockForexSource::new(
+            "fx",
+            HashMap::from([("EUR".to_string(), 0.92), ("JPY".to_string(), 150.0)]),
+        )
+    }
+
+    fn unit(output: &PipelineOutput, unit_index: u32) -> &AggregatedResult {
+        output
+            .aggregated
+            .iter()
+            .find(|a| a.unit_index == unit_index)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn run_builds_the_table_from_scripted_sources() {
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+        let beta = MockSource::new("beta")
+            .with_price(1, 0.00201)
+            .with_error(2, "HTTP 503");
+
+        let output = oracle(vec![alpha, beta], vec![forex()], Default::default())
+            .run()
+            .await
+            .unwrap();
+
+        let hot = unit(&output, 1);
+        assert!(hot.valid, "{:?}", hot.reason);
+        assert_eq!(hot.sources, ["alpha", "beta"]);
+        assert!((0.002..=0.00201).contains(&hot.avg_price_usd));
+        let weth = unit(&output, 2);
+        assert!(weth.valid, "{:?}", weth.reason);
+        assert_eq!(weth.sources, ["alpha"]);
+        assert_eq!(weth.avg_price_usd, 2500.0);
+        let hf = unit(&output, 3);
+        assert!(hf.valid, "{:?}", hf.reason);
+        assert_eq!(hf.avg_price_usd, 0.002);
+
+        let mut keys: Vec<_> = output.table.data.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["1", "2", "3"]);
+        let symbols: Vec<_> = output.forex.iter().map(|r| r.symbol.as_str()).collect();
+        assert_eq!(symbols, ["EUR", "JPY"]);
+        assert_eq!(output.table.forex_rates.len(), 2);
+
+        assert_eq!(output.source_errors.len(), 1);
+        let error = &output.source_errors[0];
+        assert_eq!(
+            (error.source.as_str(), error.subject.as_str()),
+            ("beta", "unit 2")
+        );
+        assert!(error.error.contains("HTTP 503"), "{}", error.error);
+        assert_eq!(output.stats.units_valid, 3);
+        assert_eq!(output.stats.forex_ok, 2);
+        assert_eq!(output.stats.forex_missing, 0);
+    }
+
+    #[tokio::test]
+    async fn unit_no_source_answers_for_is_left_out() {
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002);
+
+        let oracle = oracle(vec![alpha], vec![forex()], Default::default());
+        let output = oracle.run().await.unwrap();
+
+        assert!(!unit(&output, 2).valid);
+        assert!(!output.table.data.contains_key("2"));
+        assert_eq!(output.stats.units_invalid, 1);
+        let omitted: Vec<_> = output
+            .omissions(oracle.forex_symbols())
+            .into_iter()
+            .map(|o| o.entry)
+            .collect();
+        assert_eq!(omitted, ["unit 2 (WETH)"]);
+    }
+
+    #[tokio::test]
+    async fn failing_forex_source_leaves_the_rates_out() {
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+        let fx = MockForexSource::failing("fx", "quota exceeded");
+
+        let output = oracle(vec![alpha], vec![fx], Default::default())
+            .run()
+            .await
+            .unwrap();
+
+        assert!(output.forex.is_empty());
+        assert!(output.table.forex_rates.is_empty());
+        assert_eq!(output.stats.forex_missing, 2);
+        assert_eq!(output.source_errors.len(), 1);
+        assert_eq!(output.source_errors[0].subject, "forex");
+        assert_eq!(output.table.data.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn skip_forex_and_unit_filter_narrow_the_run() {
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+        let fx = MockForexSource::failing("fx", "should not be called");
+        let settings = OracleSettings {
+            unit_filter: Some(2),
+            skip_forex: true,
+            ..Default::default()
+        };
+
+        let output = oracle(vec![alpha], vec![fx], settings).run().await.unwrap();
+
+        let indexes: Vec<_> = output.aggregated.iter().map(|a| a.unit_index).collect();
+        assert_eq!(indexes, [2]);
+        assert!(output.forex.is_empty());
+        assert!(output.source_errors.is_empty());
+        assert_eq!(output.stats.forex_requested, 0);
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pricing-oracle-oracle-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// A run at [`now`] where the forex source only has EUR, with JPY
+    /// cached `age` earlier.
+    async fn run_with_cached_jpy(name: &str, age: chrono::Duration) -> PipelineOutput {
+        let path = temp_path(name);
+        let jpy = AggregatedForexRate {
+            symbol: "JPY".to_string(),
+            name: "Japanese Yen".to_string(),
+            foreign_per_usd: 150.0,
+            cached_at: None,
+            per_source: Vec::new(),
+        };
+        forex_cache::save(&path, &[jpy], now() - age).unwrap();
+        let mut config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        config.forex_cache_file = Some(path.clone());
+        config.forex_max_cache_age_seconds = 3600;
+        let fx = MockForexSource::new("fx", HashMap::from([("EUR".to_string(), 0.92)]));
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+
+        let output = oracle_with(
+            config,
+            vec![alpha],
+            vec![fx],
+            Default::default(),
+            Arc::new(ManualClock::new(now())),
+        )
+        .run()
+        .await
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+        output
+    }
+
+    #[tokio::test]
+    async fn missing_forex_rate_is_taken_from_a_fresh_cache() {
+        let output = run_with_cached_jpy("forex-hit", chrono::Duration::minutes(30)).await;
+
+        let symbols: Vec<_> = output.forex.iter().map(|r| r.symbol.as_str()).collect();
+        assert_eq!(symbols, ["EUR", "JPY"]);
+        let jpy = &output.forex[1];
+        assert_eq!(jpy.foreign_per_usd, 150.0);
+        assert_eq!(jpy.cached_at, Some(now() - chrono::Duration::minutes(30)));
+        assert_eq!(output.table.forex_rates.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_cached_forex_rate_is_left_out() {
+        let output = run_with_cached_jpy("forex-stale", chrono::Duration::minutes(61)).await;
+
+        let symbols: Vec<_> = output.forex.iter().map(|r| r.symbol.as_str()).collect();
+        assert_eq!(symbols, ["EUR"]);
+        assert_eq!(output.stats.forex_missing, 1);
+    }
+
+    #[tokio::test]
+    async fn proxied_price_and_volume_are_scaled_by_the_multiplier() {
+        let mut config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        config.units[2].price_proxy.as_mut().unwrap().multiplier = Some(1000.0);
+        config.units.push(
+            serde_yaml::from_str(
+                "unit_index: 4\nname: mWETH\nchain: ethereum\ncontract: \"\"\nprice_proxy:\n  use_unit: 2\n  multiplier: 0.001",
+            )
+            .unwrap(),
+        );
+        let weth = TokenData {
+            name: "WETH".to_string(),
+            chain: "ethereum".to_string(),
+            contract: "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2".to_string(),
+            price_usd: 2500.0,
+            market_cap: None,
+            volume_24h: Some(2_000_000.0),
+            liquidity: None,
+            price_change_24h: None,
+            source: "alpha".to_string(),
+            timestamp: Utc::now(),
+            reported_symbol: None,
+            reported_name: None,
+        };
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_token_data(2, weth);
+
+        let output = oracle_with(
+            config,
+            vec![alpha],
+            vec![forex()],
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .run()
+        .await
+        .unwrap();
+
+        let from_reference = unit(&output, 3);
+        assert!((from_reference.avg_price_usd - 2.0).abs() < 1e-12);
+        assert_eq!(from_reference.sources, ["proxy(x1000)"]);
+        let from_unit = unit(&output, 4);
+        assert_eq!(from_unit.avg_price_usd, 2.5);
+        assert_eq!(from_unit.volume_24h, Some(2000.0));
+        assert_eq!(from_unit.sources, ["proxy(x0.001)"]);
+        assert_eq!(output.stats.proxies_unresolved, 0);
+    }
+
+    #[tokio::test]
+    async fn eur_reference_currency_reprices_units_and_forex() {
+        let mut config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        config.reference_currency = "EUR".to_string();
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+
+        let output = oracle_with(
+            config,
+            vec![alpha],
+            vec![forex()],
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .run()
+        .await
+        .unwrap();
+
+        let table = &output.table;
+        assert_eq!(table.reference_unit.symbol, "€");
+        assert_eq!(table.reference_unit.name, "Euro");
+        let price = |key: &str| crate::guard::zfuel_to_f64(&table.data[key].current_price).unwrap();
+        assert!((price("2") - 2300.0).abs() < 1e-9);
+        assert!((price("1") - 0.00184).abs() < 1e-15);
+        let rates: Vec<(&str, f64)> = table
+            .forex_rates
+            .iter()
+            .map(|r| {
+                (
+                    r.symbol.as_str(),
+                    crate::guard::zfuel_to_f64(&r.rate).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(rates[0], ("EUR", 1.0));
+        assert_eq!(rates[1].0, "JPY");
+        assert!((rates[1].1 - 150.0 / 0.92).abs() < 1e-9);
+        // Unit prices stay in USD everywhere but the table.
+        assert_eq!(unit(&output, 2).avg_price_usd, 2500.0);
+    }
+
+    #[tokio::test]
+    async fn missing_reference_currency_rate_fails_the_run() {
+        let mut config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        config.reference_currency = "EUR".to_string();
+        let jpy_only = MockForexSource::new("fx", HashMap::from([("JPY".to_string(), 150.0)]));
+
+        let error = oracle_with(
+            config,
+            vec![MockSource::new("alpha").with_price(1, 0.002)],
+            vec![jpy_only],
+            Default::default(),
+            Arc::new(SystemClock),
+        )
+        .run()
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "reference_currency EUR: no aggregated forex rate this run, cannot build the table"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_stats_count_one_invalid_unit() {
+        let alpha = MockSource::new("alpha")
+            .with_price(0, 0.002)
+            .with_price(1, 0.002)
+            .with_price(2, 2500.0);
+        let beta = MockSource::new("beta").with_price(2, 2600.0);
+        let eur_only = MockForexSource::new("fx", HashMap::from([("EUR".to_string(), 0.92)]));
+
+        let output = oracle(vec![alpha, beta], vec![eur_only], Default::default())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(unit(&output, 2).reason.as_deref(), Some("sources disagree"));
+        let stats = &output.stats;
+        assert_eq!(
+            (
+                stats.units_valid,
+                stats.units_invalid,
+                stats.proxies_unresolved,
+                stats.units_unencodable,
+                stats.units_cached,
+            ),
+            (2, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            (stats.forex_requested, stats.forex_ok, stats.forex_missing),
+            (2, 1, 1)
+        );
+        let totals = RunTotals::new(&output, None);
+        assert!(
+            totals.to_string().starts_with(
+                "summary units_ok=2 units_invalid=1 units_missing=0 units_cached=0 forex_ok=1 forex_missing=1 submitted=false global_definition="
+            ),
+            "{}",
+            totals
+        );
+        assert!(totals.action.is_none());
+    }
+
+    /// A run that prices WETH and saves the quote cache as `main` does,
+    /// then a run `age` later in which no source answers for WETH.
+    async fn run_after_weth_outage(name: &str, age: chrono::Duration) -> PipelineOutput {
+        let path = temp_path(name);
+        let mut config: Config = serde_yaml::from_str(CONFIG).unwrap();
+        config.cache_file = Some(path.clone());
+        config.max_cache_age_seconds = 3600;
+        let clock = Arc::new(ManualClock::new(now()));
+        let alpha = || {
+            MockSource::new("alpha")
+                .with_price(0, 0.002)
+                .with_price(1, 0.002)
+        };
+
+        let first = oracle_with(
+            config.clone(),
+            vec![alpha().with_price(2, 2500.0)],
+            vec![forex()],
+            Default::default(),
+            clock.clone(),
+        )
+        .run()
+        .await
+        .unwrap();
+        quote_cache::save(&path, &first.aggregated, first.stats.started_at).unwrap();
+
+        clock.advance(age);
+        let output = oracle_with(
+            config,
+            vec![alpha().with_error(2, "HTTP 503")],
+            vec![forex()],
+            Default::default(),
+            clock,
+        )
+        .run()
+        .await
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+        output
+    }
+
+    #[tokio::test]
+    async fn unit_no_source_answers_for_uses_a_fresh_cached_quote() {
+        let output = run_after_weth_outage("fresh-quote", chrono::Duration::minutes(30)).await;
+
+        let weth = unit(&output, 2);
+        assert!(weth.valid);
+        assert_eq!(weth.avg_price_usd, 2500.0);
+        assert_eq!(weth.sources, ["cache"]);
+        assert_eq!(weth.cached_at, Some(now()));
+        assert!(output.table.data.contains_key("2"));
+        assert_eq!(output.stats.units_cached, 1);
+    }
+
+    #[tokio::test]
+    async fn stale_cached_quote_is_not_used() {
+        let output = run_after_weth_outage("stale-quote", chrono::Duration::minutes(61)).await;
+
+        let weth = unit(&output, 2);
+        assert!(!weth.valid);
+        assert!(weth.cached_at.is_none());
+        assert!(!output.table.data.contains_key("2"));
+        assert_eq!(output.stats.units_cached, 0);
+    }
+}
@@ -1,19 +1,213 @@
-use crate::forex_aggregate::AggregatedForexRate;
-use crate::types::{AggregatedResult, ConversionData, ConversionTable, ForexRate, ReferenceUnit};
+use crate::aggregate::AggregationStrategy;
+use crate::config::Config;
+use crate::forex_aggregate::{self, AggregatedForexRate};
+use crate::types::{
+    AggregatedResult, CachedForexMetadata, ConversionData, ConversionTable, ForexRate,
+    OmittedMetadata, ReferenceUnit, RunMetadata, SourcePrice, UnitMetadata,
+};
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use holo_hash::ActionHash;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::path::Path;
 use std::str::FromStr;
 use zfuel::fuel::ZFuel;
 
+/// Default for `price_decimals`.
+pub const DEFAULT_PRICE_DECIMALS: u32 = 18;
+
+/// Default for `volume_decimals`.
+pub const DEFAULT_VOLUME_DECIMALS: u32 = 2;
+
+/// Default for `change_decimals`.
+pub const DEFAULT_CHANGE_DECIMALS: u32 = 4;
+
+/// Upper bound for `price_decimals`, `volume_decimals` and `change_decimals`.
+pub const MAX_DECIMALS: u32 = 30;
+
+/// Below this magnitude `format_amount` counts significant figures rather
+/// than decimal places, so small values don't collapse to `0.00`.
+const SIGNIFICANT_FIGURES_BELOW: f64 = 0.01;
+
+/// Significant digits kept when writing an f64 as a decimal; more would
+/// expose binary noise (`0.1` as `0.1000000000000000055`).
+const SIGNIFICANT_DIGITS: i32 = 15;
+
+/// Per-source prices kept per unit in `RunMetadata`.
+const METADATA_MAX_SOURCES: usize = 8;
+
+/// Characters kept from each omission reason in `RunMetadata`.
+const METADATA_MAX_REASON_CHARS: usize = 200;
+
+/// Encoded `RunMetadata` larger than this drops its per-source prices.
+const METADATA_MAX_BYTES: usize = 16 * 1024;
+
+/// How numbers are written into the table and the printed summary.
+#[derive(Debug, Clone, Copy)]
+pub struct TableFormat {
+    /// Most fractional digits in a price or forex rate.
+    pub price_decimals: u32,
+    /// Decimal places for volumes.
+    pub volume_decimals: u32,
+    /// Decimal places for 24h change percentages.
+    pub change_decimals: u32,
+}
+
+impl TableFormat {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            price_decimals: cfg.price_decimals,
+            volume_decimals: cfg.volume_decimals,
+            change_decimals: cfg.change_decimals,
+        }
+    }
+
+    pub fn volume(&self, value: f64) -> String {
+        format_amount(value, self.volume_decimals)
+    }
+
+    pub fn change(&self, value: f64) -> String {
+        format_amount(value, self.change_decimals)
+    }
+}
+
+impl Default for TableFormat {
+    fn default() -> Self {
+        Self {
+            price_decimals: DEFAULT_PRICE_DECIMALS,
+            volume_decimals: DEFAULT_VOLUME_DECIMALS,
+            change_decimals: DEFAULT_CHANGE_DECIMALS,
+        }
+    }
+}
+
+/// `value` with `decimals` decimal places, e.g. `1234.50` for 2. Non-zero
+/// values below 0.01 get `decimals` significant figures instead (at least
+/// one), so `0.00345` with 2 is `0.0035` rather than `0.00`.
+pub fn format_amount(value: f64, decimals: u32) -> String {
+    let places = if value != 0.0 && value.abs() < SIGNIFICANT_FIGURES_BELOW {
+        let magnitude = value.abs().log10().floor() as i32;
+        (decimals.max(1) as i32 - 1 - magnitude).clamp(0, MAX_DECIMALS as i32) as usize
+    } else {
+        decimals as usize
+    };
+    format!("{:.*}", places, value)
+}
+
+/// `value` as a plain decimal for `ZFuel::from_str`: never scientific
+/// notation, at most `max_decimals` fractional digits and 15 significant
+/// digits, trailing zeros trimmed. Fails on NaN and infinities.
+pub fn decimal_string(value: f64, max_decimals: u32) -> Result<String> {
+    if !value.is_finite() {
+        anyhow::bail!("{} is not a finite number", value);
+    }
+    let magnitude = if value == 0.0 {
+        0
+    } else {
+        value.abs().log10().floor() as i32
+    };
+    let decimals = (SIGNIFICANT_DIGITS - 1 - magnitude).clamp(0, max_decimals as i32) as usize;
+    let mut text = format!("{:.*}", decimals, value);
+    if text.contains('.') {
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.').len();
+        text.truncate(trimmed);
+    }
+    // Rounding a tiny negative to zero digits leaves "-0".
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    Ok(text)
+}
+
+fn to_zfuel(value: f64, max_decimals: u32) -> Result<ZFuel> {
+    let text = decimal_string(value, max_decimals)?;
+    ZFuel::from_str(&text).map_err(|e| anyhow::anyhow!("ZFuel parse error for '{}': {:?}", text, e))
+}
+
+/// A unit price or forex rate left out of the table, e.g. because it could
+/// not be encoded as ZFuel.
+#[derive(Debug, Clone, Serialize)]
+pub struct OmittedEntry {
+    /// `unit 3`, `unit 3 (ETH)` or `forex EUR`.
+    pub entry: String,
+    pub error: String,
+}
+
+/// The table plus whatever had to be left out of it.
+#[derive(Debug, Clone)]
+pub struct BuiltTable {
+    pub table: ConversionTable,
+    pub omitted: Vec<OmittedEntry>,
+}
+
+/// Currency the ConversionTable is denominated in.
+#[derive(Debug, Clone)]
+pub struct ReferenceCurrency {
+    /// ISO code, e.g. `EUR`.
+    pub code: String,
+    pub sign: String,
+    pub name: String,
+    /// Units of this currency per 1 USD.
+    pub per_usd: f64,
+}
+
+impl ReferenceCurrency {
+    pub fn usd() -> Self {
+        Self {
+            code: "USD".to_string(),
+            sign: "$".to_string(),
+            name: "US Dollar".to_string(),
+            per_usd: 1.0,
+        }
+    }
+
+    /// `code` at this run's aggregated forex rate. Fails when the run has no
+    /// rate for it: pricing the table in USD instead would be silently wrong.
+    pub fn resolve(code: &str, forex_rates: &[AggregatedForexRate]) -> Result<Self> {
+        if code == "USD" {
+            return Ok(Self::usd());
+        }
+        let rate = forex_rates
+            .iter()
+            .find(|r| r.symbol == code)
+            .with_context(|| {
+                format!(
+                    "reference_currency {}: no aggregated forex rate this run, cannot build the table",
+                    code
+                )
+            })?;
+        Ok(Self {
+            code: code.to_string(),
+            sign: forex_aggregate::currency_sign(code).to_string(),
+            name: rate.name.clone(),
+            per_usd: rate.foreign_per_usd,
+        })
+    }
+}
+
+/// `embed_metadata_at` is the run's start time when a `RunMetadata` should
+/// go into `additional_data`.
 pub fn build_conversion_table(
     results: &[AggregatedResult],
     forex_rates: &[AggregatedForexRate],
+    reference: &ReferenceCurrency,
+    format: &TableFormat,
     global_definition: Option<ActionHash>,
-) -> Result<ConversionTable> {
+    embed_metadata_at: Option<DateTime<Utc>>,
+) -> BuiltTable {
+    let mut omitted = Vec::new();
+    let mut omit = |entry: String, error: anyhow::Error| {
+        tracing::warn!("{} omitted from ConversionTable: {:#}", entry, error);
+        omitted.push(OmittedEntry {
+            entry,
+            error: format!("{:#}", error),
+        });
+    };
+
     let reference_unit = ReferenceUnit {
-        symbol: "$".to_string(),
-        name: "US Dollar".to_string(),
+        symbol: reference.sign.clone(),
+        name: reference.name.clone(),
     };
 
     let mut data: HashMap<String, ConversionData> = HashMap::new();
@@ -27,18 +221,23 @@ pub fn build_conversion_table(
             continue;
         }
 
-        let price_str = format!("{}", r.avg_price_usd);
-        let current_price = ZFuel::from_str(&price_str)
-            .map_err(|e| anyhow::anyhow!("ZFuel parse error for '{}': {:?}", price_str, e))?;
+        let current_price =
+            match to_zfuel(r.avg_price_usd * reference.per_usd, format.price_decimals) {
+                Ok(price) => price,
+                Err(e) => {
+                    omit(format!("unit {}", r.unit_index), e);
+                    continue;
+                }
+            };
 
         let volume = r
             .volume_24h
-            .map(|v| format!("{:.2}", v))
+            .map(|v| format.volume(v * reference.per_usd))
             .unwrap_or_default();
 
         let net_change = r
             .price_change_24h
-            .map(|c| format!("{:.4}", c))
+            .map(|c| format.change(c))
             .unwrap_or_default();
 
         let conversion = ConversionData {
@@ -46,7 +245,8 @@ pub fn build_conversion_table(
             volume,
             net_change,
             sources: r.sources.clone(),
-            contract: Some(r.contract.clone()),
+            // Native coins have no contract.
+            contract: (!r.contract.is_empty()).then(|| r.contract.clone()),
         };
 
         data.insert(r.unit_index.to_string(), conversion);
@@ -57,48 +257,574 @@ pub fn build_conversion_table(
 
     let mut output_forex_rates = Vec::new();
     for rate in forex_rates {
-        let rate_str = format!("{}", rate.foreign_per_usd);
-        let rate_zfuel = ZFuel::from_str(&rate_str)
-            .map_err(|e| anyhow::anyhow!("ZFuel parse error for forex '{}': {:?}", rate_str, e))?;
+        // Foreign units per 1 unit of the reference currency.
+        let rate_zfuel = match to_zfuel(
+            rate.foreign_per_usd / reference.per_usd,
+            format.price_decimals,
+        ) {
+            Ok(rate) => rate,
+            Err(e) => {
+                omit(format!("forex {}", rate.symbol), e);
+                continue;
+            }
+        };
         output_forex_rates.push(ForexRate {
             symbol: rate.symbol.clone(),
             name: rate.name.clone(),
             rate: rate_zfuel,
         });
     }
+    // Stable output order, whatever order the forex providers answered in.
+    output_forex_rates.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    let additional_data = embed_metadata_at.and_then(|run_at| {
+        let metadata = run_metadata(results, forex_rates, &data, &omitted, run_at);
+        encode_metadata(metadata)
+            .inspect_err(|e| tracing::warn!("run metadata not embedded: {:#}", e))
+            .ok()
+    });
 
-    Ok(ConversionTable {
-        reference_unit,
-        data,
-        forex_rates: output_forex_rates,
-        additional_data: None,
-        global_definition,
-    })
+    BuiltTable {
+        table: ConversionTable {
+            reference_unit,
+            data,
+            forex_rates: output_forex_rates,
+            additional_data,
+            global_definition,
+        },
+        omitted,
+    }
+}
+
+/// Metadata for the units that made it into `data`, plus every unit or
+/// forex rate that did not.
+fn run_metadata(
+    results: &[AggregatedResult],
+    forex_rates: &[AggregatedForexRate],
+    data: &HashMap<String, ConversionData>,
+    omitted: &[OmittedEntry],
+    run_at: DateTime<Utc>,
+) -> RunMetadata {
+    let reason = |text: &str| text.chars().take(METADATA_MAX_REASON_CHARS).collect();
+    let units = results
+        .iter()
+        .filter(|r| data.contains_key(&r.unit_index.to_string()))
+        .map(|r| UnitMetadata {
+            unit_index: r.unit_index,
+            sources: r
+                .per_source
+                .iter()
+                .take(METADATA_MAX_SOURCES)
+                .map(|d| SourcePrice {
+                    source: d.source.clone(),
+                    price_usd: d.price_usd,
+                })
+                .collect(),
+            max_deviation: (r.avg_price_usd > 0.0)
+                .then(|| {
+                    r.per_source
+                        .iter()
+                        .map(|d| (d.price_usd - r.avg_price_usd).abs() / r.avg_price_usd)
+                        .reduce(f64::max)
+                })
+                .flatten(),
+        })
+        .collect();
+    let invalid = results
+        .iter()
+        .filter(|r| !r.valid)
+        .map(|r| OmittedMetadata {
+            entry: format!("unit {}", r.unit_index),
+            reason: reason(r.reason.as_deref().unwrap_or("invalid")),
+        });
+    let unencodable = omitted.iter().map(|o| OmittedMetadata {
+        entry: o.entry.clone(),
+        reason: reason(&o.error),
+    });
+    RunMetadata {
+        oracle_version: env!("CARGO_PKG_VERSION").to_string(),
+        run_at,
+        units,
+        omitted: invalid.chain(unencodable).collect(),
+        cached_forex: forex_rates
+            .iter()
+            .filter_map(|r| {
+                r.cached_at.map(|fetched_at| CachedForexMetadata {
+                    symbol: r.symbol.clone(),
+                    fetched_at,
+                })
+            })
+            .collect(),
+        truncated: false,
+    }
 }
 
-pub fn print_table(results: &[AggregatedResult]) {
-    println!(
-        "\n{:<8} {:<12} {:<16} {:<14} {:<14} {:<8} {}",
-        "Index", "Name", "Price (USD)", "Volume 24h", "Change 24h%", "Valid", "Sources"
+/// JSON bytes for `additional_data`, dropping per-source prices if the
+/// result would exceed `METADATA_MAX_BYTES`.
+pub fn encode_metadata(mut metadata: RunMetadata) -> Result<Vec<u8>> {
+    let bytes = serde_json::to_vec(&metadata).context("encoding run metadata")?;
+    if bytes.len() <= METADATA_MAX_BYTES {
+        return Ok(bytes);
+    }
+    tracing::warn!(
+        "run metadata is {} bytes; dropping per-source prices to stay under {}",
+        bytes.len(),
+        METADATA_MAX_BYTES
     );
-    println!("{}", "-".repeat(90));
-    for r in results {
-        let vol = r
-            .volume_24h
-            .map(|v| format!("{:.2}", v))
+    for unit in &mut metadata.units {
+        unit.sources.clear();
+    }
+    metadata.truncated = true;
+    serde_json::to_vec(&metadata).context("encoding run metadata")
+}
+
+/// The `RunMetadata` in a table's `additional_data`. Other keys in the same
+/// object, such as `previous_table_cid`, are ignored.
+pub fn decode_metadata(additional_data: &[u8]) -> Result<RunMetadata> {
+    serde_json::from_slice(additional_data).context("decoding run metadata")
+}
+
+/// How `print_table` lays out the summary besides number formatting.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions<'a> {
+    /// Add per-source lines under each unit; the config supplies each
+    /// unit's deviation threshold.
+    pub verbose: Option<&'a Config>,
+    /// Wrap rows in ANSI colors (see [`color_enabled`]).
+    pub color: bool,
+}
+
+/// Colors only when stdout is a terminal, `NO_COLOR` is unset or empty, and
+/// `--no-color` was not given.
+pub fn color_enabled(no_color_flag: bool) -> bool {
+    use std::io::IsTerminal;
+    !no_color_flag
+        && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && std::io::stdout().is_terminal()
+}
+
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Widest a `Name` or `Sources` cell may get before it is ellipsized.
+const MAX_NAME_WIDTH: usize = 24;
+const MAX_SOURCES_WIDTH: usize = 48;
+
+struct Column {
+    header: String,
+    right_aligned: bool,
+    max_width: Option<usize>,
+}
+
+/// Prices are shown in the table's reference currency.
+pub fn print_table(
+    results: &[AggregatedResult],
+    reference: &ReferenceCurrency,
+    format: &TableFormat,
+    options: &PrintOptions,
+) {
+    print!("{}", render_table(results, reference, format, options));
+}
+
+/// The summary table as text. Column widths fit the data, numeric columns
+/// are right-aligned, and long names or source lists end in `…`.
+pub fn render_table(
+    results: &[AggregatedResult],
+    reference: &ReferenceCurrency,
+    format: &TableFormat,
+    options: &PrintOptions,
+) -> String {
+    let column = |header: &str, right_aligned: bool, max_width: Option<usize>| Column {
+        header: header.to_string(),
+        right_aligned,
+        max_width,
+    };
+    let columns = [
+        column("Index", true, None),
+        column("Name", false, Some(MAX_NAME_WIDTH)),
+        column(&format!("Price ({})", reference.code), true, None),
+        column("Volume 24h", true, None),
+        column("Change 24h%", true, None),
+        column("Valid", false, None),
+        column("Aggregation", false, None),
+        column("Age", true, None),
+        column("Sources", false, Some(MAX_SOURCES_WIDTH)),
+    ];
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| unit_cells(r, reference, format))
+        .collect();
+
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let widest = rows
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([c.header.chars().count()])
+                .max()
+                .unwrap_or(0);
+            c.max_width.map_or(widest, |max| widest.min(max))
+        })
+        .collect();
+    let line = |cells: &[String]| -> String {
+        let padded: Vec<String> = columns
+            .iter()
+            .zip(cells)
+            .zip(&widths)
+            .map(|((c, cell), width)| {
+                let cell = ellipsize(cell, *width);
+                if c.right_aligned {
+                    format!("{:>width$}", cell, width = width)
+                } else {
+                    format!("{:<width$}", cell, width = width)
+                }
+            })
+            .collect();
+        padded.join("  ").trim_end().to_string()
+    };
+
+    let headers: Vec<String> = columns.iter().map(|c| c.header.clone()).collect();
+    let mut out = format!("\n{}\n", line(&headers));
+    let total = widths.iter().sum::<usize>() + 2 * (widths.len() - 1);
+    out.push_str(&"-".repeat(total));
+    out.push('\n');
+    for (r, cells) in results.iter().zip(&rows) {
+        let color = if !r.valid {
+            RED
+        } else if r.sources.len() < 2 {
+            YELLOW
+        } else {
+            GREEN
+        };
+        out.push_str(&paint(&line(cells), color, options.color));
+        out.push('\n');
+        if let Some(cfg) = options.verbose {
+            let threshold = deviation_threshold(cfg, r.unit_index);
+            out.push_str(&source_rows(r, reference, format, threshold, options.color));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+fn unit_cells(
+    r: &AggregatedResult,
+    reference: &ReferenceCurrency,
+    format: &TableFormat,
+) -> Vec<String> {
+    let vol = r
+        .volume_24h
+        .map(|v| format.volume(v * reference.per_usd))
+        .unwrap_or_else(|| "—".to_string());
+    let change = r
+        .price_change_24h
+        .map(|c| {
+            let sign = if c >= 0.0 { "+" } else { "" };
+            format!("{}{}%", sign, format.change(c))
+        })
+        .unwrap_or_else(|| "—".to_string());
+    let age = r
+        .oldest_quote_age_secs
+        .map(|a| format!("{}s", a))
+        .unwrap_or_else(|| "—".to_string());
+    let mut sources = r.sources.join(", ");
+    if !r.rejected_sources.is_empty() {
+        sources.push_str(&format!(" (rejected: {})", r.rejected_sources.join(", ")));
+    }
+    vec![
+        r.unit_index.to_string(),
+        r.name.clone(),
+        format!("{:.8}", r.avg_price_usd * reference.per_usd),
+        vol,
+        change,
+        if r.valid { "yes" } else { "NO" }.to_string(),
+        r.strategy.as_str().to_string(),
+        age,
+        sources,
+    ]
+}
+
+/// `text` cut to `width` characters, ending in `…` when shortened.
+fn ellipsize(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+fn paint(text: &str, color: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+fn deviation_threshold(cfg: &Config, unit_index: u32) -> f64 {
+    cfg.units
+        .iter()
+        .find(|u| u.unit_index == unit_index)
+        .map_or(cfg.deviation_threshold, |u| {
+            cfg.aggregate_settings_for(u).deviation_threshold
+        })
+}
+
+/// `--verbose-table` lines under a unit row. A source further from the
+/// unit's price than `threshold` is marked with `!` (and red with color).
+fn source_rows(
+    r: &AggregatedResult,
+    reference: &ReferenceCurrency,
+    format: &TableFormat,
+    threshold: f64,
+    color: bool,
+) -> String {
+    let mut out = String::new();
+    for d in &r.per_source {
+        let deviation = (r.avg_price_usd > 0.0)
+            .then(|| (d.price_usd - r.avg_price_usd).abs() / r.avg_price_usd);
+        let deviation_str = deviation
+            .map(|dev| format!("{:.2}%", dev * 100.0))
             .unwrap_or_else(|| "—".to_string());
-        let change = r
-            .price_change_24h
-            .map(|c| format!("{:+.4}%", c))
+        let vol = d
+            .volume_24h
+            .map(|v| format.volume(v * reference.per_usd))
             .unwrap_or_else(|| "—".to_string());
-        let valid_str = if r.valid { "yes" } else { "NO" };
-        let sources = r.sources.join(", ");
-        println!(
-            "{:<8} {:<12} {:<16.8} {:<14} {:<14} {:<8} {}",
-            r.unit_index, r.name, r.avg_price_usd, vol, change, valid_str, sources
+        let status = if r.rejected_sources.contains(&d.source) {
+            " (rejected)"
+        } else if !r.sources.contains(&d.source) {
+            " (dropped)"
+        } else {
+            ""
+        };
+        let identity = match (&d.reported_symbol, &d.reported_name) {
+            (Some(symbol), Some(name)) => format!("  {} ({})", symbol, name),
+            (Some(symbol), None) => format!("  {}", symbol),
+            (None, Some(name)) => format!("  ({})", name),
+            (None, None) => String::new(),
+        };
+        let flagged = deviation.is_some_and(|dev| dev > threshold);
+        let row = format!(
+            "  {} {:<18} {:>16.8} {:>9} {:>14} {}{}{}",
+            if flagged { "!" } else { " " },
+            d.source,
+            d.price_usd * reference.per_usd,
+            deviation_str,
+            vol,
+            d.timestamp.format("%Y-%m-%d %H:%M:%SZ"),
+            identity,
+            status
         );
+        out.push_str(&paint(&row, RED, color && flagged));
+        out.push('\n');
+    }
+    if let Some(reason) = r.reason.as_deref().filter(|_| !r.valid) {
+        out.push_str(&format!("    reason: {}\n", reason));
+    }
+    out
+}
+
+/// One row per unit: the aggregated price and the sources behind it.
+pub fn aggregated_csv(results: &[AggregatedResult]) -> String {
+    let mut out = String::from(
+        "unit_index,name,contract,avg_price_usd,volume_24h,price_change_24h,valid,sources\n",
+    );
+    for r in results {
+        let row = [
+            r.unit_index.to_string(),
+            r.name.clone(),
+            r.contract.clone(),
+            r.avg_price_usd.to_string(),
+            optional(r.volume_24h),
+            optional(r.price_change_24h),
+            r.valid.to_string(),
+            r.sources.join(";"),
+        ];
+        push_csv_row(&mut out, &row);
+    }
+    out
+}
+
+/// One row per unit and source with the raw price that source returned.
+pub fn per_source_csv(results: &[AggregatedResult]) -> String {
+    let mut out = String::from(
+        "unit_index,name,contract,source,price_usd,volume_24h,timestamp,reported_symbol,reported_name\n",
+    );
+    for r in results {
+        for data in &r.per_source {
+            let row = [
+                r.unit_index.to_string(),
+                r.name.clone(),
+                r.contract.clone(),
+                data.source.clone(),
+                data.price_usd.to_string(),
+                optional(data.volume_24h),
+                data.timestamp.to_rfc3339(),
+                data.reported_symbol.clone().unwrap_or_default(),
+                data.reported_name.clone().unwrap_or_default(),
+            ];
+            push_csv_row(&mut out, &row);
+        }
+    }
+    out
+}
+
+pub fn forex_csv(rates: &[AggregatedForexRate]) -> String {
+    let mut out = String::from("symbol,name,foreign_per_usd\n");
+    for rate in rates {
+        let row = [
+            rate.symbol.clone(),
+            rate.name.clone(),
+            rate.foreign_per_usd.to_string(),
+        ];
+        push_csv_row(&mut out, &row);
     }
-    println!();
+    out
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// RFC 4180: fields with a comma, quote or line break are quoted, with
+/// inner quotes doubled.
+fn push_csv_row(out: &mut String, fields: &[String]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+}
+
+/// `--output json --detailed`: every unit and forex rate with the per-source
+/// values behind it. The ConversionTable JSON is unaffected.
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedReport {
+    pub units: Vec<DetailedUnit>,
+    pub forex: Vec<DetailedForexRate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedUnit {
+    pub unit_index: u32,
+    pub name: String,
+    pub contract: String,
+    pub avg_price_usd: f64,
+    pub volume_24h: Option<f64>,
+    pub price_change_24h: Option<f64>,
+    pub valid: bool,
+    pub reason: Option<String>,
+    pub strategy: AggregationStrategy,
+    pub sources: Vec<DetailedSource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedSource {
+    pub source: String,
+    pub price_usd: f64,
+    pub volume_24h: Option<f64>,
+    pub liquidity: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+    /// Fraction this price is from `avg_price_usd`; `None` for an invalid unit.
+    pub deviation: Option<f64>,
+    /// `used`, `rejected` (outlier) or `dropped` (stale or out of bounds).
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedForexRate {
+    pub symbol: String,
+    pub name: String,
+    pub foreign_per_usd: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
+    pub sources: Vec<DetailedForexSource>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DetailedForexSource {
+    pub source: String,
+    pub foreign_per_usd: f64,
+    /// Fraction this rate is from the aggregated rate.
+    pub deviation: f64,
+}
+
+impl DetailedReport {
+    pub fn new(results: &[AggregatedResult], forex_rates: &[AggregatedForexRate]) -> Self {
+        let units = results
+            .iter()
+            .map(|r| DetailedUnit {
+                unit_index: r.unit_index,
+                name: r.name.clone(),
+                contract: r.contract.clone(),
+                avg_price_usd: r.avg_price_usd,
+                volume_24h: r.volume_24h,
+                price_change_24h: r.price_change_24h,
+                valid: r.valid,
+                reason: r.reason.clone(),
+                strategy: r.strategy,
+                sources: r
+                    .per_source
+                    .iter()
+                    .map(|d| DetailedSource {
+                        source: d.source.clone(),
+                        price_usd: d.price_usd,
+                        volume_24h: d.volume_24h,
+                        liquidity: d.liquidity,
+                        timestamp: d.timestamp,
+                        deviation: (r.avg_price_usd > 0.0)
+                            .then(|| (d.price_usd - r.avg_price_usd).abs() / r.avg_price_usd),
+                        status: if r.sources.contains(&d.source) {
+                            "used"
+                        } else if r.rejected_sources.contains(&d.source) {
+                            "rejected"
+                        } else {
+                            "dropped"
+                        },
+                    })
+                    .collect(),
+            })
+            .collect();
+        let forex = forex_rates
+            .iter()
+            .map(|f| DetailedForexRate {
+                symbol: f.symbol.clone(),
+                name: f.name.clone(),
+                foreign_per_usd: f.foreign_per_usd,
+                cached_at: f.cached_at,
+                sources: f
+                    .per_source
+                    .iter()
+                    .map(|s| DetailedForexSource {
+                        source: s.source.clone(),
+                        foreign_per_usd: s.foreign_per_usd,
+                        deviation: (s.foreign_per_usd - f.foreign_per_usd).abs()
+                            / f.foreign_per_usd,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self { units, forex }
+    }
+}
+
+pub fn print_detailed_json(report: &DetailedReport) -> Result<()> {
+    let json = serde_json::to_string_pretty(report).context("serializing detailed report")?;
+    println!("{}", json);
+    Ok(())
 }
 
 pub fn print_json(table: &ConversionTable) -> Result<()> {
@@ -106,3 +832,531 @@ pub fn print_json(table: &ConversionTable) -> Result<()> {
     println!("{}", json);
     Ok(())
 }
+
+/// Write the table JSON to `path` through a temp file and rename, so readers
+/// never see a half-written file.
+pub fn write_json_file(table: &ConversionTable, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(table).context("serializing ConversionTable")?;
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} is not a file path", path.display()))?;
+    let tmp = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    std::fs::write(&tmp, json + "\n").with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("renaming {} to {}", tmp.display(), path.display()))
+}
+
+/// A table JSON file as written by [`write_json_file`] or `--output-file`.
+pub fn read_json_file(path: &Path) -> Result<ConversionTable> {
+    let json =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a ConversionTable JSON file", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TokenData;
+    use chrono::TimeZone;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+
+    fn token(source: &str, price: f64) -> TokenData {
+        TokenData {
+            name: "WETH".to_string(),
+            chain: "ethereum".to_string(),
+            contract: WETH.to_string(),
+            price_usd: price,
+            market_cap: None,
+            volume_24h: Some(1000.5),
+            liquidity: None,
+            price_change_24h: None,
+            source: source.to_string(),
+            timestamp: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+            reported_symbol: None,
+            reported_name: None,
+        }
+    }
+
+    fn result(unit_index: u32, name: &str, price: f64, sources: &[&str]) -> AggregatedResult {
+        AggregatedResult {
+            unit_index,
+            reference_id: None,
+            name: name.to_string(),
+            contract: WETH.to_string(),
+            avg_price_usd: price,
+            volume_24h: Some(1234.5),
+            price_change_24h: Some(-1.25),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            rejected_sources: Vec::new(),
+            valid: true,
+            reason: None,
+            strategy: AggregationStrategy::default(),
+            weights: Vec::new(),
+            oldest_quote_age_secs: Some(12),
+            cached_at: None,
+            per_source: sources.iter().map(|s| token(s, price)).collect(),
+        }
+    }
+
+    /// Splits RFC 4180 text into rows of fields, undoing `push_csv_row`.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => row.push(std::mem::take(&mut field)),
+                (false, '\n') => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        assert!(field.is_empty() && row.is_empty(), "unterminated row");
+        rows
+    }
+
+    #[test]
+    fn aggregated_csv_round_trips() {
+        let mut tricky = result(2, "Wrapped \"Ether\", bridged", 3150.25, &["a", "b"]);
+        tricky.valid = false;
+        tricky.volume_24h = None;
+        let results = [result(1, "HOT", 0.00123, &["coingecko"]), tricky];
+
+        let rows = parse_csv(&aggregated_csv(&results));
+
+        assert_eq!(
+            rows[0],
+            [
+                "unit_index",
+                "name",
+                "contract",
+                "avg_price_usd",
+                "volume_24h",
+                "price_change_24h",
+                "valid",
+                "sources"
+            ]
+        );
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            [
+                "1",
+                "HOT",
+                WETH,
+                "0.00123",
+                "1234.5",
+                "-1.25",
+                "true",
+                "coingecko"
+            ]
+        );
+        assert_eq!(
+            rows[2],
+            [
+                "2",
+                "Wrapped \"Ether\", bridged",
+                WETH,
+                "3150.25",
+                "",
+                "-1.25",
+                "false",
+                "a;b"
+            ]
+        );
+        assert_eq!(rows[2][3].parse::<f64>().unwrap(), 3150.25);
+    }
+
+    #[test]
+    fn per_source_csv_has_a_row_per_source() {
+        let mut r = result(3, "Line\nbreak", 2.5, &["coingecko", "dexscreener"]);
+        r.per_source[1].price_usd = 2.75;
+        r.per_source[1].reported_symbol = Some("WETH".to_string());
+        r.per_source[1].reported_name = Some("Wrapped Ether, \"WETH\"".to_string());
+
+        let rows = parse_csv(&per_source_csv(&[r]));
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][3..5], ["source", "price_usd"]);
+        assert_eq!(
+            rows[1],
+            [
+                "3",
+                "Line\nbreak",
+                WETH,
+                "coingecko",
+                "2.5",
+                "1000.5",
+                "2026-01-02T03:04:05+00:00",
+                "",
+                ""
+            ]
+        );
+        assert_eq!(rows[2][3..5], ["dexscreener", "2.75"]);
+        assert_eq!(rows[2][7..], ["WETH", "Wrapped Ether, \"WETH\""]);
+    }
+
+    #[test]
+    fn forex_csv_round_trips() {
+        let rates = [AggregatedForexRate {
+            symbol: "JPY".to_string(),
+            name: "Yen, Japanese".to_string(),
+            foreign_per_usd: 150.1,
+            cached_at: None,
+            per_source: Vec::new(),
+        }];
+
+        let rows = parse_csv(&forex_csv(&rates));
+
+        assert_eq!(
+            rows,
+            [
+                ["symbol", "name", "foreign_per_usd"],
+                ["JPY", "Yen, Japanese", "150.1"]
+            ]
+        );
+    }
+
+    fn forex_rate(symbol: &str, foreign_per_usd: f64) -> AggregatedForexRate {
+        AggregatedForexRate {
+            symbol: symbol.to_string(),
+            name: symbol.to_string(),
+            foreign_per_usd,
+            cached_at: None,
+            per_source: Vec::new(),
+        }
+    }
+
+    fn build(results: &[AggregatedResult], forex_rates: &[AggregatedForexRate]) -> BuiltTable {
+        build_conversion_table(
+            results,
+            forex_rates,
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            None,
+            None,
+        )
+    }
+
+    /// Keys of the `data` object in pretty-printed table JSON, in order.
+    fn data_keys(json: &str) -> Vec<String> {
+        let data = &json[json.find("\"data\": {").unwrap()..json.find("\"forex_rates\"").unwrap()];
+        data.lines()
+            .filter_map(|line| line.strip_prefix("    \"")?.split_once("\": {"))
+            .map(|(key, _)| key.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn table_json_orders_units_numerically_and_forex_by_symbol() {
+        let results: Vec<_> = [10, 2, 1, 33, 3]
+            .into_iter()
+            .map(|i| result(i, "WETH", 1.5, &["a"]))
+            .collect();
+        let forex = [
+            forex_rate("JPY", 150.0),
+            forex_rate("EUR", 0.9),
+            forex_rate("CHF", 0.8),
+        ];
+
+        let json = serde_json::to_string_pretty(&build(&results, &forex).table).unwrap();
+
+        assert_eq!(data_keys(&json), ["1", "2", "3", "10", "33"]);
+        let symbols: Vec<_> = ["\"CHF\"", "\"EUR\"", "\"JPY\""]
+            .iter()
+            .map(|s| json.find(&format!("\"symbol\": {}", s)).unwrap())
+            .collect();
+        assert!(symbols.is_sorted(), "{}", json);
+
+        // The same table built from entries in another order encodes the same.
+        let mut reversed = results.clone();
+        reversed.reverse();
+        let mut forex = forex.to_vec();
+        forex.reverse();
+        let again = serde_json::to_string_pretty(&build(&reversed, &forex).table).unwrap();
+        assert_eq!(json, again);
+
+        // Only the order changed: the map reads back with the same entries.
+        let decoded: ConversionTable = serde_json::from_str(&json).unwrap();
+        let mut keys: Vec<_> = decoded.data.keys().cloned().collect();
+        keys.sort_by_key(|k| k.parse::<u32>().unwrap());
+        assert_eq!(keys, ["1", "2", "3", "10", "33"]);
+        assert_eq!(decoded.data["10"].sources, ["a"]);
+    }
+
+    #[test]
+    fn decimal_string_writes_plain_decimals() {
+        let cases = [
+            (1e-9, "0.000000001"),
+            (8.3e-9, "0.0000000083"),
+            (1234567.891, "1234567.891"),
+            (0.1, "0.1"),
+            (0.1 + 0.2, "0.3"),
+            (150.0, "150"),
+            (0.0, "0"),
+            (-2.5, "-2.5"),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(decimal_string(value, 18).unwrap(), expected, "{:e}", value);
+        }
+        assert_eq!(decimal_string(1e-9, 6).unwrap(), "0");
+        assert_eq!(decimal_string(-1e-9, 6).unwrap(), "0");
+        assert_eq!(decimal_string(0.123456789, 4).unwrap(), "0.1235");
+        assert!(decimal_string(f64::NAN, 18).is_err());
+        assert!(decimal_string(f64::INFINITY, 18).is_err());
+    }
+
+    #[test]
+    fn table_prices_are_the_zfuel_of_the_decimal_string() {
+        let results = [
+            result(1, "TINY", 1e-9, &["a"]),
+            result(2, "BIG", 1234567.891, &["a"]),
+            result(3, "TENTH", 0.1, &["a"]),
+        ];
+        let forex = [forex_rate("JPY", 0.1)];
+
+        let built = build(&results, &forex);
+
+        assert!(built.omitted.is_empty(), "{:?}", built.omitted);
+        for (key, expected) in [("1", "0.000000001"), ("2", "1234567.891"), ("3", "0.1")] {
+            assert_eq!(
+                built.table.data[key].current_price,
+                ZFuel::from_str(expected).unwrap(),
+                "unit {}",
+                key
+            );
+        }
+        assert_eq!(
+            built.table.forex_rates[0].rate,
+            ZFuel::from_str("0.1").unwrap()
+        );
+    }
+
+    #[test]
+    fn unencodable_entries_are_omitted_and_the_rest_still_build() {
+        let results = [
+            result(1, "HOT", 0.00123, &["a"]),
+            result(2, "NAN", f64::NAN, &["a"]),
+            result(3, "INF", f64::INFINITY, &["a"]),
+            result(4, "WETH", 3150.25, &["a"]),
+        ];
+        let forex = [forex_rate("EUR", 0.92), forex_rate("XXX", f64::NAN)];
+
+        let built = build(&results, &forex);
+
+        let mut keys: Vec<_> = built.table.data.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["1", "4"]);
+        let symbols: Vec<_> = built.table.forex_rates.iter().map(|r| &r.symbol).collect();
+        assert_eq!(symbols, ["EUR"]);
+        let omitted: Vec<_> = built.omitted.iter().map(|o| o.entry.as_str()).collect();
+        assert_eq!(omitted, ["unit 2", "unit 3", "forex XXX"]);
+        assert!(
+            built.omitted[0].error.contains("not a finite number"),
+            "{}",
+            built.omitted[0].error
+        );
+    }
+
+    #[test]
+    fn format_amount_across_magnitudes() {
+        let cases = [
+            (123456789.0, 0, "123456789"),
+            (1234.5, 2, "1234.50"),
+            (0.5, 2, "0.50"),
+            (0.01, 2, "0.01"),
+            (0.00346, 2, "0.0035"),
+            (0.00003, 4, "0.00003000"),
+            (-0.00003, 4, "-0.00003000"),
+            (0.000123, 0, "0.0001"),
+            (0.0, 2, "0.00"),
+            (-1.23456, 4, "-1.2346"),
+        ];
+        for (value, decimals, expected) in cases {
+            assert_eq!(
+                format_amount(value, decimals),
+                expected,
+                "{:e} with {}",
+                value,
+                decimals
+            );
+        }
+        // Significant figures never ask for more than MAX_DECIMALS places.
+        assert_eq!(format_amount(1e-40, 2), format!("0.{}", "0".repeat(30)));
+
+        let format = TableFormat::default();
+        assert_eq!(format.volume(0.004), "0.0040");
+        assert_eq!(format.volume(150000.0), "150000.00");
+        assert_eq!(format.change(0.00003), "0.00003000");
+        assert_eq!(format.change(-2.5), "-2.5000");
+    }
+
+    #[test]
+    fn plain_table_snapshot() {
+        let mut long = result(
+            12,
+            "Wrapped Staked Ether From Another Chain",
+            0.00000345,
+            &["geckoterminal"],
+        );
+        long.volume_24h = None;
+        long.price_change_24h = Some(0.5);
+        long.oldest_quote_age_secs = None;
+        let mut invalid = result(3, "HF", 1.0, &["coingecko", "dexscreener"]);
+        invalid.valid = false;
+        invalid.reason = Some("sources disagree".to_string());
+        invalid.rejected_sources = vec!["coinpaprika".to_string(), "coinmarketcap".to_string()];
+        let results = [
+            result(1, "HOT", 0.00123, &["coingecko", "dexscreener"]),
+            long,
+            invalid,
+        ];
+
+        let text = render_table(
+            &results,
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            &PrintOptions::default(),
+        );
+
+        let expected = concat!(
+            "\n",
+            "Index  Name                      Price (USD)  Volume 24h  Change 24h%  Valid  Aggregation  Age  Sources\n",
+            "------------------------------------------------------------------------------------------------------------------------------------------------\n",
+            "    1  HOT                        0.00123000     1234.50     -1.2500%  yes    mean         12s  coingecko, dexscreener\n",
+            "   12  Wrapped Staked Ether Fr…   0.00000345           —     +0.5000%  yes    mean           —  geckoterminal\n",
+            "    3  HF                         1.00000000     1234.50     -1.2500%  NO     mean         12s  coingecko, dexscreener (rejected: coinpaprika, …\n",
+            "\n",
+        );
+        assert_eq!(text, expected);
+        assert!(!text.contains('\x1b'));
+
+        let colored = render_table(
+            &results,
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            &PrintOptions {
+                color: true,
+                ..Default::default()
+            },
+        );
+        let rows: Vec<_> = colored.lines().skip(3).take(3).collect();
+        assert!(rows[0].starts_with(GREEN) && rows[0].ends_with(RESET));
+        assert!(rows[1].starts_with(YELLOW));
+        assert!(rows[2].starts_with(RED));
+    }
+
+    #[test]
+    fn embedded_metadata_decodes_to_what_was_encoded() {
+        let run_at = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let mut hot = result(1, "HOT", 0.002, &["coingecko", "kraken"]);
+        hot.per_source[1].price_usd = 0.0021;
+        let invalid = AggregatedResult {
+            valid: false,
+            reason: Some("sources disagree".to_string()),
+            ..result(2, "WETH", 2500.0, &["coingecko"])
+        };
+        let eur = AggregatedForexRate {
+            cached_at: Some(run_at - chrono::Duration::hours(1)),
+            ..forex_rate("EUR", 0.92)
+        };
+
+        let built = build_conversion_table(
+            &[hot, invalid],
+            &[eur],
+            &ReferenceCurrency::usd(),
+            &TableFormat::default(),
+            None,
+            Some(run_at),
+        );
+        let bytes = built.table.additional_data.unwrap();
+        let metadata = decode_metadata(&bytes).unwrap();
+
+        assert_eq!(
+            metadata,
+            RunMetadata {
+                oracle_version: env!("CARGO_PKG_VERSION").to_string(),
+                run_at,
+                units: vec![UnitMetadata {
+                    unit_index: 1,
+                    sources: vec![
+                        SourcePrice {
+                            source: "coingecko".to_string(),
+                            price_usd: 0.002,
+                        },
+                        SourcePrice {
+                            source: "kraken".to_string(),
+                            price_usd: 0.0021,
+                        },
+                    ],
+                    max_deviation: Some((0.0021 - 0.002) / 0.002),
+                }],
+                omitted: vec![OmittedMetadata {
+                    entry: "unit 2".to_string(),
+                    reason: "sources disagree".to_string(),
+                }],
+                cached_forex: vec![CachedForexMetadata {
+                    symbol: "EUR".to_string(),
+                    fetched_at: run_at - chrono::Duration::hours(1),
+                }],
+                truncated: false,
+            }
+        );
+        assert_eq!(encode_metadata(metadata).unwrap(), bytes);
+    }
+
+    #[test]
+    fn oversized_metadata_drops_per_source_prices_and_says_so() {
+        let source = |i: usize| SourcePrice {
+            source: format!("custom-source-{}", i),
+            price_usd: 0.00123456789,
+        };
+        let metadata = RunMetadata {
+            oracle_version: env!("CARGO_PKG_VERSION").to_string(),
+            run_at: Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap(),
+            units: (1..=100)
+                .map(|unit_index| UnitMetadata {
+                    unit_index,
+                    sources: (0..METADATA_MAX_SOURCES).map(source).collect(),
+                    max_deviation: Some(0.001),
+                })
+                .collect(),
+            omitted: Vec::new(),
+            cached_forex: Vec::new(),
+            truncated: false,
+        };
+        assert!(serde_json::to_vec(&metadata).unwrap().len() > METADATA_MAX_BYTES);
+
+        let bytes = encode_metadata(metadata.clone()).unwrap();
+
+        assert!(bytes.len() <= METADATA_MAX_BYTES);
+        let decoded = decode_metadata(&bytes).unwrap();
+        assert!(decoded.truncated);
+        assert_eq!(decoded.units.len(), 100);
+        assert!(decoded.units.iter().all(|u| u.sources.is_empty()));
+        assert_eq!(decoded.units[0].max_deviation, Some(0.001));
+    }
+
+    #[test]
+    fn decoding_ignores_other_keys_in_additional_data() {
+        let bytes = br#"{"oracle_version":"0.1.0","run_at":"2026-10-16T12:00:00Z","units":[],"previous_table_cid":"bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"}"#;
+
+        let metadata = decode_metadata(bytes).unwrap();
+
+        assert!(metadata.units.is_empty() && metadata.omitted.is_empty());
+        assert!(!metadata.truncated);
+    }
+}
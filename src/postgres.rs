@@ -0,0 +1,489 @@
+use crate::oracle::PipelineOutput;
+use crate::types::AggregatedResult;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_postgres::NoTls;
+use tracing::{info, warn};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Applied on every connection; each statement is idempotent.
+const MIGRATIONS: &str = "
+CREATE TABLE IF NOT EXISTS oracle_runs (
+    id                 BIGSERIAL PRIMARY KEY,
+    started_at         TIMESTAMPTZ NOT NULL,
+    finished_at        TIMESTAMPTZ NOT NULL,
+    units_valid        INTEGER NOT NULL,
+    units_invalid      INTEGER NOT NULL,
+    proxies_unresolved INTEGER NOT NULL,
+    forex_requested    INTEGER NOT NULL,
+    source_errors      INTEGER NOT NULL,
+    action_hash        TEXT
+);
+CREATE TABLE IF NOT EXISTS oracle_unit_prices (
+    run_id     BIGINT NOT NULL REFERENCES oracle_runs(id) ON DELETE CASCADE,
+    unit_index INTEGER NOT NULL,
+    name       TEXT NOT NULL,
+    source     TEXT NOT NULL,
+    price      DOUBLE PRECISION NOT NULL,
+    valid      BOOLEAN NOT NULL,
+    reason     TEXT,
+    spread     DOUBLE PRECISION
+);
+CREATE TABLE IF NOT EXISTS oracle_forex_rates (
+    run_id          BIGINT NOT NULL REFERENCES oracle_runs(id) ON DELETE CASCADE,
+    symbol          TEXT NOT NULL,
+    name            TEXT NOT NULL,
+    foreign_per_usd DOUBLE PRECISION NOT NULL
+);
+CREATE INDEX IF NOT EXISTS oracle_unit_prices_run_idx ON oracle_unit_prices (run_id);
+CREATE INDEX IF NOT EXISTS oracle_forex_rates_run_idx ON oracle_forex_rates (run_id);
+";
+
+/// One run, flattened to rows. Also the line format of the spill file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub units_valid: i32,
+    pub units_invalid: i32,
+    pub proxies_unresolved: i32,
+    pub forex_requested: i32,
+    pub source_errors: i32,
+    pub action_hash: Option<String>,
+    pub unit_prices: Vec<UnitPriceRow>,
+    pub forex_rates: Vec<ForexRateRow>,
+}
+
+/// One row per source quote, plus an `aggregate` row carrying the unit's average.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitPriceRow {
+    pub unit_index: i32,
+    pub name: String,
+    pub source: String,
+    pub price: f64,
+    pub valid: bool,
+    pub reason: Option<String>,
+    /// (max - min) / average across sources; `None` with fewer than two.
+    pub spread: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForexRateRow {
+    pub symbol: String,
+    pub name: String,
+    pub foreign_per_usd: f64,
+}
+
+impl RunRecord {
+    pub fn from_outcome(outcome: &PipelineOutput, action_hash: Option<String>) -> Self {
+        let stats = &outcome.stats;
+        let mut unit_prices = Vec::new();
+        for r in &outcome.aggregated {
+            let spread = spread(r);
+            let reason = r.reason.clone();
+            let row = |source: &str, price: f64, reason: Option<String>| UnitPriceRow {
+                unit_index: r.unit_index as i32,
+                name: r.name.clone(),
+                source: source.to_string(),
+                price,
+                valid: r.valid,
+                reason,
+                spread,
+            };
+            for d in &r.per_source {
+                let reason = if r.rejected_sources.contains(&d.source) {
+                    Some("rejected as outlier".to_string())
+                } else {
+                    reason.clone()
+                };
+                unit_prices.push(row(&d.source, d.price_usd, reason));
+            }
+            unit_prices.push(row("aggregate", r.avg_price_usd, reason));
+        }
+
+        Self {
+            started_at: stats.started_at,
+            finished_at: stats.finished_at,
+            units_valid: stats.units_valid as i32,
+            units_invalid: stats.units_invalid as i32,
+            proxies_unresolved: stats.proxies_unresolved as i32,
+            forex_requested: stats.forex_requested as i32,
+            source_errors: outcome.source_errors.len() as i32,
+            action_hash,
+            unit_prices,
+            forex_rates: outcome
+                .forex
+                .iter()
+                .map(|f| ForexRateRow {
+                    symbol: f.symbol.clone(),
+                    name: f.name.clone(),
+                    foreign_per_usd: f.foreign_per_usd,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn spread(r: &AggregatedResult) -> Option<f64> {
+    if r.per_source.len() < 2 || r.avg_price_usd <= 0.0 {
+        return None;
+    }
+    let prices = r.per_source.iter().map(|d| d.price_usd);
+    let max = prices.clone().fold(f64::MIN, f64::max);
+    let min = prices.fold(f64::MAX, f64::min);
+    Some((max - min) / r.avg_price_usd)
+}
+
+/// Writes run records to the database, so the spill logic can run
+/// against something other than a live Postgres.
+#[async_trait]
+pub trait RunExecutor: Send + Sync {
+    /// Insert `records` in order, each in its own transaction. Returns how
+    /// many (from the front) were committed, with the error that stopped it.
+    async fn insert_all(&self, records: &[RunRecord]) -> Result<usize, (usize, anyhow::Error)>;
+}
+
+/// Inserts run history into Postgres, spilling to a local file when the
+/// database is unreachable. Never returns an error to the caller.
+pub struct PostgresSink {
+    executor: Box<dyn RunExecutor>,
+    spill_file: PathBuf,
+}
+
+impl PostgresSink {
+    pub fn new(url: String, spill_file: PathBuf) -> Self {
+        Self::with_executor(PgExecutor { url }, spill_file)
+    }
+
+    pub fn with_executor(executor: impl RunExecutor + 'static, spill_file: PathBuf) -> Self {
+        Self {
+            executor: Box::new(executor),
+            spill_file,
+        }
+    }
+
+    /// Insert earlier spilled runs and then `record`, each in its own
+    /// transaction. Whatever is not inserted ends up in the spill file.
+    pub async fn record(&self, record: RunRecord) {
+        let mut pending = match read_spill(&self.spill_file) {
+            Ok(spilled) => spilled,
+            Err(e) => {
+                warn!("[postgres] ignoring unreadable spill file: {:#}", e);
+                Vec::new()
+            }
+        };
+        let spilled = pending.len();
+        pending.push(record);
+
+        let inserted = match self.executor.insert_all(&pending).await {
+            Ok(n) => n,
+            Err((n, e)) => {
+                warn!("[postgres] insert failed, spilling: {:#}", e);
+                n
+            }
+        };
+        if inserted > 0 {
+            info!(
+                "[postgres] Inserted {} run(s) ({} from spill file)",
+                inserted,
+                inserted.min(spilled)
+            );
+        }
+
+        if let Err(e) = write_spill(&self.spill_file, &pending[inserted..]) {
+            warn!("[postgres] failed to write spill file: {:#}", e);
+        }
+    }
+}
+
+/// `RunExecutor` that connects to `url` for each batch and runs the
+/// migrations first.
+struct PgExecutor {
+    url: String,
+}
+
+#[async_trait]
+impl RunExecutor for PgExecutor {
+    async fn insert_all(&self, records: &[RunRecord]) -> Result<usize, (usize, anyhow::Error)> {
+        let connect = tokio_postgres::connect(&self.url, NoTls);
+        let (mut client, connection) = tokio::time::timeout(CONNECT_TIMEOUT, connect)
+            .await
+            .map_err(|_| (0, anyhow::anyhow!("connection timed out")))?
+            .map_err(|e| (0, anyhow::Error::new(e).context("connecting to Postgres")))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                warn!("[postgres] connection error: {}", e);
+            }
+        });
+
+        client
+            .batch_execute(MIGRATIONS)
+            .await
+            .map_err(|e| (0, anyhow::Error::new(e).context("running migrations")))?;
+
+        for (i, record) in records.iter().enumerate() {
+            insert_run(&mut client, record).await.map_err(|e| (i, e))?;
+        }
+        Ok(records.len())
+    }
+}
+
+async fn insert_run(client: &mut tokio_postgres::Client, r: &RunRecord) -> Result<()> {
+    let tx = client.transaction().await?;
+    let row = tx
+        .query_one(
+            "INSERT INTO oracle_runs (started_at, finished_at, units_valid, units_invalid, \
+             proxies_unresolved, forex_requested, source_errors, action_hash) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+            &[
+                &r.started_at,
+                &r.finished_at,
+                &r.units_valid,
+                &r.units_invalid,
+                &r.proxies_unresolved,
+                &r.forex_requested,
+                &r.source_errors,
+                &r.action_hash,
+            ],
+        )
+        .await
+        .context("inserting oracle_runs")?;
+    let run_id: i64 = row.get(0);
+
+    let unit_stmt = tx
+        .prepare(
+            "INSERT INTO oracle_unit_prices (run_id, unit_index, name, source, price, valid, \
+             reason, spread) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .await?;
+    for u in &r.unit_prices {
+        tx.execute(
+            &unit_stmt,
+            &[
+                &run_id,
+                &u.unit_index,
+                &u.name,
+                &u.source,
+                &u.price,
+                &u.valid,
+                &u.reason,
+                &u.spread,
+            ],
+        )
+        .await
+        .context("inserting oracle_unit_prices")?;
+    }
+
+    let forex_stmt = tx
+        .prepare(
+            "INSERT INTO oracle_forex_rates (run_id, symbol, name, foreign_per_usd) \
+             VALUES ($1, $2, $3, $4)",
+        )
+        .await?;
+    for f in &r.forex_rates {
+        tx.execute(
+            &forex_stmt,
+            &[&run_id, &f.symbol, &f.name, &f.foreign_per_usd],
+        )
+        .await
+        .context("inserting oracle_forex_rates")?;
+    }
+
+    tx.commit().await.context("committing run")?;
+    Ok(())
+}
+
+fn read_spill(path: &Path) -> Result<Vec<RunRecord>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+    };
+    contents
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| serde_json::from_str(l).context("parsing spilled run"))
+        .collect()
+}
+
+/// Replace the spill file with `records`, removing it when there are none.
+fn write_spill(path: &Path, records: &[RunRecord]) -> Result<()> {
+    if records.is_empty() {
+        return match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("removing {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    let mut file =
+        std::fs::File::create(path).with_context(|| format!("writing {}", path.display()))?;
+    for r in records {
+        serde_json::to_writer(&mut file, r)?;
+        file.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// How the scripted database answers one `insert_all` call.
+    enum Answer {
+        Down,
+        FailAt(usize),
+        Up,
+    }
+
+    /// Answers calls from a script and keeps the start times of every
+    /// committed run, in commit order.
+    #[derive(Default)]
+    struct Scripted {
+        answers: Mutex<VecDeque<Answer>>,
+        committed: Mutex<Vec<DateTime<Utc>>>,
+    }
+
+    #[async_trait]
+    impl RunExecutor for Arc<Scripted> {
+        async fn insert_all(&self, records: &[RunRecord]) -> Result<usize, (usize, anyhow::Error)> {
+            let answer = self
+                .answers
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or(Answer::Up);
+            let committed = match answer {
+                Answer::Down => 0,
+                Answer::FailAt(i) => i,
+                Answer::Up => records.len(),
+            };
+            self.committed
+                .lock()
+                .unwrap()
+                .extend(records[..committed].iter().map(|r| r.started_at));
+            match answer {
+                Answer::Up => Ok(committed),
+                _ => Err((committed, anyhow::anyhow!("connection refused"))),
+            }
+        }
+    }
+
+    fn sink(name: &str, answers: Vec<Answer>) -> (PostgresSink, Arc<Scripted>, PathBuf) {
+        let spill_file = std::env::temp_dir().join(format!(
+            "pricing-oracle-spill-{}-{}.jsonl",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&spill_file);
+        let db = Arc::new(Scripted {
+            answers: Mutex::new(answers.into()),
+            ..Default::default()
+        });
+        let sink = PostgresSink::with_executor(db.clone(), spill_file.clone());
+        (sink, db, spill_file)
+    }
+
+    fn started(minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, minute, 0).unwrap()
+    }
+
+    fn record(minute: u32) -> RunRecord {
+        RunRecord {
+            started_at: started(minute),
+            finished_at: started(minute) + chrono::Duration::seconds(20),
+            units_valid: 1,
+            units_invalid: 0,
+            proxies_unresolved: 0,
+            forex_requested: 1,
+            source_errors: 0,
+            action_hash: None,
+            unit_prices: vec![UnitPriceRow {
+                unit_index: 1,
+                name: "WETH".to_string(),
+                source: "aggregate".to_string(),
+                price: 2500.0,
+                valid: true,
+                reason: None,
+                spread: None,
+            }],
+            forex_rates: vec![ForexRateRow {
+                symbol: "EUR".to_string(),
+                name: "Euro".to_string(),
+                foreign_per_usd: 0.92,
+            }],
+        }
+    }
+
+    fn spilled(path: &Path) -> Vec<DateTime<Utc>> {
+        read_spill(path)
+            .unwrap()
+            .iter()
+            .map(|r| r.started_at)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn runs_spill_while_down_and_flush_in_order_on_reconnect() {
+        let (sink, db, spill_file) = sink("flush", vec![Answer::Down, Answer::Down]);
+
+        sink.record(record(0)).await;
+        assert_eq!(spilled(&spill_file), vec![started(0)]);
+        sink.record(record(5)).await;
+        assert_eq!(spilled(&spill_file), vec![started(0), started(5)]);
+        assert!(db.committed.lock().unwrap().is_empty());
+
+        sink.record(record(10)).await;
+        assert_eq!(
+            *db.committed.lock().unwrap(),
+            vec![started(0), started(5), started(10)]
+        );
+        assert!(!spill_file.exists());
+    }
+
+    #[tokio::test]
+    async fn failure_mid_flush_keeps_only_the_uncommitted_runs() {
+        let (sink, db, spill_file) = sink("partial", vec![Answer::Down, Answer::FailAt(1)]);
+
+        sink.record(record(0)).await;
+        sink.record(record(5)).await;
+
+        assert_eq!(*db.committed.lock().unwrap(), vec![started(0)]);
+        assert_eq!(spilled(&spill_file), vec![started(5)]);
+        let _ = std::fs::remove_file(&spill_file);
+    }
+
+    #[tokio::test]
+    async fn spilled_run_round_trips_every_row() {
+        let (sink, _db, spill_file) = sink("rows", vec![Answer::Down]);
+
+        sink.record(record(0)).await;
+
+        let spilled = read_spill(&spill_file).unwrap();
+        assert_eq!(
+            serde_json::to_value(&spilled).unwrap(),
+            serde_json::to_value(vec![record(0)]).unwrap()
+        );
+        let _ = std::fs::remove_file(&spill_file);
+    }
+
+    #[tokio::test]
+    async fn unreadable_spill_file_does_not_stop_the_insert() {
+        let (sink, db, spill_file) = sink("unreadable", vec![]);
+        std::fs::write(&spill_file, "not json\n").unwrap();
+
+        sink.record(record(0)).await;
+
+        assert_eq!(*db.committed.lock().unwrap(), vec![started(0)]);
+        assert!(!spill_file.exists());
+    }
+}
@@ -0,0 +1,242 @@
+//! Multi-hop `price_proxy` resolution: a proxy may copy from another proxy,
+//! so proxies are resolved in dependency order and cycles are rejected.
+
+use crate::config::{Config, ProxySource, UnitConfig};
+use anyhow::Result;
+
+/// Follow `price_proxy` links from `unit` to the first unit that is fetched
+/// or to a price reference. Returns every hop in order; a chain that loops
+/// back on itself is an error naming the cycle.
+pub fn chain(config: &Config, unit: &UnitConfig) -> Result<Vec<ProxySource>> {
+    let mut visited = vec![unit];
+    let mut hops = Vec::new();
+    let mut current = unit;
+    while let Some(proxy) = &current.price_proxy {
+        let source = config.resolve_proxy_source(current.unit_index, proxy)?;
+        hops.push(source.clone());
+        let ProxySource::Unit(idx) = source else {
+            break;
+        };
+        let Some(next) = config.units.iter().find(|u| u.unit_index == idx) else {
+            break;
+        };
+        if let Some(pos) = visited.iter().position(|u| u.unit_index == idx) {
+            let names: Vec<String> = visited[pos..]
+                .iter()
+                .chain([&next])
+                .map(|u| format!("'{}'", u.name))
+                .collect();
+            anyhow::bail!("price_proxy cycle: {}", names.join(" -> "));
+        }
+        visited.push(next);
+        current = next;
+    }
+    Ok(hops)
+}
+
+/// `proxies` ordered so each one comes after any proxy it copies from.
+/// Proxies at the same depth keep their config order.
+pub fn resolution_order<'a>(
+    config: &Config,
+    proxies: Vec<&'a UnitConfig>,
+) -> Result<Vec<&'a UnitConfig>> {
+    let mut ordered = proxies
+        .into_iter()
+        .map(|u| Ok((chain(config, u)?.len(), u)))
+        .collect::<Result<Vec<_>>>()?;
+    ordered.sort_by_key(|(depth, _)| *depth);
+    Ok(ordered.into_iter().map(|(_, u)| u).collect())
+}
+
+/// Provenance for a proxied unit's `sources`, e.g. `proxy<-3<-1` for a
+/// two-hop chain or `proxy(x0.001)` for a scaled single hop. `None` for a
+/// plain single hop, which keeps the sources of the unit it copies.
+pub fn label(config: &Config, unit: &UnitConfig, hops: &[ProxySource]) -> Option<String> {
+    // Every hop but the last lands on another proxy, whose multiplier was
+    // already applied to the price this unit copies.
+    let proxies_in_chain =
+        std::iter::once(unit).chain(hops.iter().take(hops.len().saturating_sub(1)).filter_map(
+            |hop| match hop {
+                ProxySource::Unit(idx) => config.units.iter().find(|u| u.unit_index == *idx),
+                ProxySource::Reference(_) => None,
+            },
+        ));
+    let multiplier = proxies_in_chain
+        .filter_map(|u| u.price_proxy.as_ref().and_then(|p| p.multiplier))
+        .reduce(|a, b| a * b);
+
+    if hops.len() <= 1 && multiplier.is_none() {
+        return None;
+    }
+    let mut label = String::from("proxy");
+    if hops.len() > 1 {
+        for hop in hops {
+            match hop {
+                ProxySource::Unit(idx) => label.push_str(&format!("<-{}", idx)),
+                ProxySource::Reference(id) => label.push_str(&format!("<-{}", id)),
+            }
+        }
+    }
+    if let Some(m) = multiplier {
+        label.push_str(&format!("(x{})", m));
+    }
+    Some(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PriceProxy;
+
+    const CONFIG: &str = r#"
+price_references:
+  - id: WBTC
+    name: WBTC
+    chain: ethereum
+    contract: "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"
+units:
+  - unit_index: 1
+    name: BTC
+    chain: ethereum
+    contract: "0x2260fac5e5542a773aa44fbcfedf7c193bc2c599"
+  - unit_index: 2
+    name: mBTC
+    chain: ethereum
+    contract: ""
+    price_proxy:
+      use_unit: 1
+      multiplier: 0.001
+  - unit_index: 3
+    name: mWBTC
+    chain: ethereum
+    contract: ""
+    price_proxy:
+      use_reference: WBTC
+      multiplier: 0.001
+  - unit_index: 4
+    name: BTC2
+    chain: ethereum
+    contract: ""
+    price_proxy:
+      use_unit: 1
+  - unit_index: 5
+    name: uBTC
+    chain: ethereum
+    contract: ""
+    price_proxy:
+      use_unit: 2
+      multiplier: 0.5
+  - unit_index: 6
+    name: BTC3
+    chain: ethereum
+    contract: ""
+    price_proxy:
+      use_unit: 4
+"#;
+
+    fn config() -> Config {
+        serde_yaml::from_str(CONFIG).unwrap()
+    }
+
+    fn label_of(config: &Config, unit_index: u32) -> Option<String> {
+        let unit = config
+            .units
+            .iter()
+            .find(|u| u.unit_index == unit_index)
+            .unwrap();
+        label(config, unit, &chain(config, unit).unwrap())
+    }
+
+    #[test]
+    fn multiplier_shows_in_the_label_for_units_and_references() {
+        let config = config();
+
+        assert_eq!(label_of(&config, 2).as_deref(), Some("proxy(x0.001)"));
+        assert_eq!(label_of(&config, 3).as_deref(), Some("proxy(x0.001)"));
+        // A plain single hop keeps the sources it copies.
+        assert_eq!(label_of(&config, 4), None);
+    }
+
+    #[test]
+    fn multiplier_must_be_positive_and_finite() {
+        for bad in ["0", "-0.001", ".nan", ".inf"] {
+            let yaml = CONFIG.replacen("multiplier: 0.001", &format!("multiplier: {}", bad), 1);
+            let config: Config = serde_yaml::from_str(&yaml).unwrap();
+            let error = config.validate().unwrap_err().to_string();
+            assert!(error.contains("must be a positive number"), "{}", error);
+        }
+        config().validate().unwrap();
+    }
+
+    #[test]
+    fn two_hop_chain_records_every_hop() {
+        let config = config();
+        let hops = |unit_index: u32| -> Vec<String> {
+            let unit = config
+                .units
+                .iter()
+                .find(|u| u.unit_index == unit_index)
+                .unwrap();
+            chain(&config, unit)
+                .unwrap()
+                .iter()
+                .map(|hop| match hop {
+                    ProxySource::Unit(idx) => idx.to_string(),
+                    ProxySource::Reference(id) => id.clone(),
+                })
+                .collect()
+        };
+
+        assert_eq!(hops(6), ["4", "1"]);
+        assert_eq!(hops(3), ["WBTC"]);
+        assert_eq!(label_of(&config, 6).as_deref(), Some("proxy<-4<-1"));
+        // Both multipliers along the chain apply.
+        assert_eq!(
+            label_of(&config, 5).as_deref(),
+            Some("proxy<-2<-1(x0.0005)")
+        );
+    }
+
+    #[test]
+    fn proxies_resolve_after_the_proxies_they_copy() {
+        let config = config();
+        // Config order reversed, so dependencies come last.
+        let proxies = config
+            .units
+            .iter()
+            .rev()
+            .filter(|u| u.price_proxy.is_some())
+            .collect();
+
+        let order: Vec<u32> = resolution_order(&config, proxies)
+            .unwrap()
+            .iter()
+            .map(|u| u.unit_index)
+            .collect();
+        assert_eq!(order, [4, 3, 2, 6, 5]);
+    }
+
+    #[test]
+    fn cycle_is_rejected_at_validation_naming_the_cycle() {
+        let mut config = config();
+        for (unit_index, use_unit) in [(1, 6), (4, 1)] {
+            let unit = config
+                .units
+                .iter_mut()
+                .find(|u| u.unit_index == unit_index)
+                .unwrap();
+            unit.price_proxy = Some(PriceProxy {
+                use_unit: Some(use_unit),
+                use_reference: None,
+                multiplier: None,
+            });
+        }
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(
+            error.ends_with("price_proxy cycle: 'BTC' -> 'BTC3' -> 'BTC2' -> 'BTC'"),
+            "{}",
+            error
+        );
+    }
+}
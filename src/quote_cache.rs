@@ -0,0 +1,187 @@
+//! Last-known-good unit prices (`cache_file`): each unit's most recent
+//! valid [`AggregatedResult`] and when it was fetched, used when a later
+//! run gets no quote at all for it.
+
+use crate::types::AggregatedResult;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Bumped whenever the file layout changes; other versions are ignored.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedQuote {
+    pub fetched_at: DateTime<Utc>,
+    pub result: AggregatedResult,
+}
+
+impl CachedQuote {
+    /// Whether the quote is at most `max_age` old at `now`.
+    pub fn is_fresh(&self, now: DateTime<Utc>, max_age: chrono::Duration) -> bool {
+        now - self.fetched_at <= max_age
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Keyed by unit index.
+    units: BTreeMap<u32, CachedQuote>,
+}
+
+/// Quotes by unit index; an absent file is an empty cache.
+pub fn load(path: &Path) -> Result<BTreeMap<u32, CachedQuote>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e).with_context(|| format!("reading quote cache {}", path.display())),
+    };
+    let file: CacheFile = serde_json::from_str(&contents)
+        .with_context(|| format!("parsing quote cache {}", path.display()))?;
+    if file.version != FORMAT_VERSION {
+        anyhow::bail!(
+            "quote cache {} has version {}, expected {}",
+            path.display(),
+            file.version,
+            FORMAT_VERSION
+        );
+    }
+    Ok(file.units)
+}
+
+/// Store every valid, freshly fetched unit in `results` at `fetched_at`,
+/// keeping entries for other units. Units that came from the cache are not
+/// rewritten, so they keep their original fetch time and still expire.
+pub fn save(path: &Path, results: &[AggregatedResult], fetched_at: DateTime<Utc>) -> Result<()> {
+    let mut units = load(path).unwrap_or_else(|e| {
+        tracing::warn!("{:#}; starting a new quote cache", e);
+        BTreeMap::new()
+    });
+    for result in results.iter().filter(|r| r.valid && r.cached_at.is_none()) {
+        units.insert(
+            result.unit_index,
+            CachedQuote {
+                fetched_at,
+                result: result.clone(),
+            },
+        );
+    }
+    let file = CacheFile {
+        version: FORMAT_VERSION,
+        units,
+    };
+    let contents = serde_json::to_string_pretty(&file).context("serializing quote cache")?;
+    // Replace via rename so a crash mid-write can't truncate the cache.
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents).with_context(|| format!("writing {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replacing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregate::AggregationStrategy;
+    use crate::clock::{Clock, ManualClock};
+    use std::path::PathBuf;
+
+    fn cache_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pricing-oracle-quote-cache-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn result(unit_index: u32, price: f64, cached_at: Option<DateTime<Utc>>) -> AggregatedResult {
+        AggregatedResult {
+            unit_index,
+            reference_id: None,
+            name: format!("unit {}", unit_index),
+            contract: String::new(),
+            avg_price_usd: price,
+            volume_24h: None,
+            price_change_24h: None,
+            sources: vec!["kraken".to_string()],
+            rejected_sources: Vec::new(),
+            valid: true,
+            reason: None,
+            strategy: AggregationStrategy::default(),
+            weights: Vec::new(),
+            oldest_quote_age_secs: Some(0),
+            cached_at,
+            per_source: Vec::new(),
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        "2026-10-16T12:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn valid_fresh_units_are_saved_under_the_current_version() {
+        let path = cache_path("round-trip");
+        let invalid = AggregatedResult {
+            valid: false,
+            ..result(2, 3.0, None)
+        };
+
+        save(&path, &[result(1, 2500.0, None), invalid], start()).unwrap();
+
+        let file: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(file["version"], FORMAT_VERSION);
+        let cache = load(&path).unwrap();
+        assert_eq!(cache.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(cache[&1].result.avg_price_usd, 2500.0);
+        assert_eq!(cache[&1].fetched_at, start());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn units_served_from_the_cache_keep_their_fetch_time() {
+        let path = cache_path("keep");
+        let clock = ManualClock::new(start());
+        save(&path, &[result(1, 2500.0, None)], clock.now()).unwrap();
+
+        clock.advance(chrono::Duration::hours(1));
+        let run = [result(1, 2500.0, Some(start())), result(2, 0.5, None)];
+        save(&path, &run, clock.now()).unwrap();
+
+        let cache = load(&path).unwrap();
+        assert_eq!(cache[&1].fetched_at, start());
+        assert_eq!(cache[&2].fetched_at, clock.now());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn cached_quote_is_a_hit_until_max_age_then_stale() {
+        let path = cache_path("age");
+        let clock = ManualClock::new(start());
+        save(&path, &[result(1, 2500.0, None)], clock.now()).unwrap();
+        let max_age = chrono::Duration::seconds(3600);
+
+        clock.advance(max_age);
+        let cache = load(&path).unwrap();
+        assert!(cache[&1].is_fresh(clock.now(), max_age));
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(!cache[&1].is_fresh(clock.now(), max_age));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let path = cache_path("version");
+        std::fs::write(&path, r#"{"version": 2, "units": {}}"#).unwrap();
+
+        let error = load(&path).unwrap_err().to_string();
+        assert!(error.ends_with("has version 2, expected 1"), "{}", error);
+        let _ = std::fs::remove_file(&path);
+    }
+}
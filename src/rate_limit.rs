@@ -0,0 +1,84 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Token bucket shared by every call to one rate-limited API in a run.
+/// Waiters queue on the mutex, so calls go out in arrival order.
+pub struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    state: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `calls_per_minute` calls, with bursts of up to that many.
+    pub fn per_minute(calls_per_minute: u32) -> Self {
+        let capacity = f64::from(calls_per_minute.max(1));
+        Self {
+            capacity,
+            per_second: capacity / 60.0,
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until a call may be made and take its token.
+    pub async fn acquire(&self) {
+        let mut bucket = self.state.lock().await;
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_second).min(self.capacity);
+        bucket.refilled_at = now;
+        if bucket.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second);
+            tracing::debug!("Rate limit reached; waiting {:.1}s", wait.as_secs_f64());
+            tokio::time::sleep(wait).await;
+            bucket.tokens = 1.0;
+            bucket.refilled_at = Instant::now();
+        }
+        bucket.tokens -= 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn bucket_allows_a_burst_then_paces_calls() {
+        let limiter = RateLimiter::per_minute(2);
+        let started = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        // Two calls a minute: the third waits for one token, 30s.
+        limiter.acquire().await;
+        assert_eq!(started.elapsed(), Duration::from_secs(30));
+        limiter.acquire().await;
+        assert_eq!(started.elapsed(), Duration::from_secs(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_time_refills_the_bucket_up_to_capacity() {
+        let limiter = RateLimiter::per_minute(2);
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let resumed = Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert_eq!(resumed.elapsed(), Duration::ZERO);
+        limiter.acquire().await;
+        assert_eq!(resumed.elapsed(), Duration::from_secs(30));
+    }
+}
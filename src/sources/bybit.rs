@@ -0,0 +1,183 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Production API root; `source_endpoints` or `BYBIT_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.bybit.com";
+
+/// Bybit public spot ticker; only units with a `bybit_symbol` (e.g. `WBTCUSDT`).
+/// Prices come back in USDT and are converted to USD by the oracle.
+pub struct Bybit {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Bybit {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl PriceSource for Bybit {
+    fn name(&self) -> &str {
+        "bybit"
+    }
+
+    fn quote_currency(&self) -> &str {
+        "USDT"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let symbol = unit.source_id("bybit").ok_or_else(|| {
+            NotConfigured(format!("bybit_symbol not configured for '{}'", unit.name))
+        })?;
+
+        let request = self
+            .client
+            .get(&format!("{}/v5/market/tickers", self.base_url))
+            .query(&[("category", "spot"), ("symbol", symbol)]);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Bybit request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("Bybit HTTP {}: {}", status, body);
+        }
+
+        let body: serde_json::Value = resp.json().context("Bybit parse failed")?;
+        if body["retCode"].as_i64() != Some(0) {
+            anyhow::bail!(
+                "Bybit error for {}: {} {}",
+                symbol,
+                body["retCode"],
+                body["retMsg"].as_str().unwrap_or_default()
+            );
+        }
+        // An unknown symbol yields an empty list rather than an error code.
+        let ticker = body["result"]["list"]
+            .get(0)
+            .with_context(|| format!("Bybit: no ticker for symbol {}", symbol))?;
+
+        let price_usd = parse_f64(ticker, "lastPrice").context("Bybit: missing lastPrice")?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: parse_f64(ticker, "turnover24h"),
+            liquidity: None,
+            price_change_24h: parse_f64(ticker, "price24hPcnt").map(|f| f * 100.0),
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+fn parse_f64(obj: &serde_json::Value, key: &str) -> Option<f64> {
+    obj[key].as_str().and_then(|s| s.parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(
+            "unit_index: 1\nname: WBTC\nchain: ethereum\ncontract: '0x2260fac5e5542a773aa44fbcfedf7c193bc2c599'\nbybit_symbol: WBTCUSDT",
+        )
+        .unwrap()
+    }
+
+    async fn fetch(body: serde_json::Value) -> Result<TokenData> {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/tickers"))
+            .and(query_param("category", "spot"))
+            .and(query_param("symbol", "WBTCUSDT"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+        Bybit::new(HttpClient::new(reqwest::Client::new()), server.uri())
+            .fetch(&unit(), &SystemClock)
+            .await
+    }
+
+    #[tokio::test]
+    async fn parses_the_spot_ticker() {
+        // Trimmed from a live /v5/market/tickers?category=spot response.
+        let data = fetch(serde_json::json!({
+            "retCode": 0,
+            "retMsg": "OK",
+            "result": {
+                "category": "spot",
+                "list": [{
+                    "symbol": "WBTCUSDT",
+                    "bid1Price": "64990.5",
+                    "ask1Price": "65010.5",
+                    "lastPrice": "65000.00",
+                    "prevPrice24h": "63700.00",
+                    "price24hPcnt": "0.0204",
+                    "highPrice24h": "65500.00",
+                    "lowPrice24h": "63500.00",
+                    "turnover24h": "1300000.50",
+                    "volume24h": "20.0"
+                }]
+            },
+            "time": 1791201600000_u64
+        }))
+        .await
+        .unwrap();
+
+        assert_eq!(data.price_usd, 65000.0);
+        assert_eq!(data.volume_24h, Some(1_300_000.5));
+        assert!((data.price_change_24h.unwrap() - 2.04).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn empty_list_is_an_error() {
+        // What Bybit sends for a symbol it does not list.
+        let err = fetch(serde_json::json!({
+            "retCode": 0,
+            "retMsg": "OK",
+            "result": { "category": "", "list": [] },
+            "time": 1791201600000_u64
+        }))
+        .await
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "Bybit: no ticker for symbol WBTCUSDT");
+    }
+
+    #[tokio::test]
+    async fn non_zero_ret_code_is_an_error() {
+        let err = fetch(serde_json::json!({
+            "retCode": 10001,
+            "retMsg": "Not supported symbols",
+            "result": {},
+            "time": 1791201600000_u64
+        }))
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Bybit error for WBTCUSDT: 10001 Not supported symbols"
+        );
+    }
+}
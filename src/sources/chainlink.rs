@@ -0,0 +1,103 @@
+use super::eth_rpc;
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// `latestRoundData()` selector.
+const LATEST_ROUND_DATA: &str = "0xfeaf968c";
+const DEFAULT_FEED_DECIMALS: u8 = 8;
+
+/// Chainlink aggregator read via `eth_call`; only units with `chainlink_feed`.
+pub struct Chainlink {
+    client: HttpClient,
+    rpc_url: String,
+    max_age_secs: u64,
+}
+
+impl Chainlink {
+    pub fn new(client: HttpClient, rpc_url: String, max_age_secs: u64) -> Self {
+        Self {
+            client,
+            rpc_url,
+            max_age_secs,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for Chainlink {
+    fn name(&self) -> &str {
+        "chainlink"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let feed = unit.source_id("chainlink").ok_or_else(|| {
+            NotConfigured(format!("chainlink_feed not configured for '{}'", unit.name))
+        })?;
+        let decimals = unit.chainlink_decimals.unwrap_or(DEFAULT_FEED_DECIMALS);
+
+        let result = eth_rpc::eth_call(&self.client, &self.rpc_url, feed, LATEST_ROUND_DATA)
+            .await
+            .with_context(|| format!("Chainlink: latestRoundData on {}", feed))?;
+        let round = decode_latest_round(&result)
+            .with_context(|| format!("Chainlink: bad latestRoundData from {}", feed))?;
+
+        let age = clock.now().timestamp() - round.updated_at as i64;
+        if age > self.max_age_secs as i64 {
+            anyhow::bail!(
+                "Chainlink feed {} is stale: updated {}s ago (max {}s)",
+                feed,
+                age,
+                self.max_age_secs
+            );
+        }
+
+        let price_usd = round.answer as f64 / 10f64.powi(decimals as i32);
+        let timestamp = DateTime::<Utc>::from_timestamp(round.updated_at as i64, 0)
+            .unwrap_or_else(|| clock.now());
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: None,
+            price_change_24h: None,
+            source: self.name().to_string(),
+            timestamp,
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+struct LatestRound {
+    answer: u128,
+    updated_at: u64,
+}
+
+/// Decode the ABI-encoded `(uint80 roundId, int256 answer, uint256 startedAt,
+/// uint256 updatedAt, uint80 answeredInRound)` tuple.
+fn decode_latest_round(hex: &str) -> Result<LatestRound> {
+    let answer = eth_rpc::word(hex, 1)?;
+    if answer.starts_with(|c: char| c >= '8') {
+        anyhow::bail!("negative answer");
+    }
+    let answer = eth_rpc::parse_word(answer).context("answer")?;
+    if answer == 0 {
+        anyhow::bail!("zero answer");
+    }
+    let updated_at = eth_rpc::parse_word(eth_rpc::word(hex, 3)?).context("updatedAt")?;
+
+    Ok(LatestRound {
+        answer,
+        updated_at: u64::try_from(updated_at).context("updatedAt out of range")?,
+    })
+}
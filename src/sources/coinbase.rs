@@ -0,0 +1,193 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Production API root; `source_endpoints` or `COINBASE_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.exchange.coinbase.com";
+
+/// Coinbase Exchange public ticker + 24h stats; only units with
+/// `coinbase_product` are priced.
+pub struct Coinbase {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Coinbase {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+
+    async fn get_json(&self, url: &str) -> Result<serde_json::Value> {
+        // Coinbase rejects requests without a User-Agent; the shared client sets one.
+        let request = self.client.get(url).header("Accept", "application/json");
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Coinbase request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("Coinbase HTTP {}: {}", status, body);
+        }
+        resp.json().context("Coinbase parse failed")
+    }
+}
+
+#[async_trait]
+impl PriceSource for Coinbase {
+    fn name(&self) -> &str {
+        "coinbase"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let product = unit.source_id("coinbase").ok_or_else(|| {
+            NotConfigured(format!(
+                "coinbase_product not configured for '{}'",
+                unit.name
+            ))
+        })?;
+
+        let ticker = self
+            .get_json(&format!("{}/products/{}/ticker", self.base_url, product))
+            .await?;
+        let stats = self
+            .get_json(&format!("{}/products/{}/stats", self.base_url, product))
+            .await?;
+
+        let price_usd =
+            parse_string_f64(&ticker, "price").context("Coinbase: missing ticker price")?;
+        // `stats.volume` is in the base currency; quote it in USD like the other sources.
+        let volume_24h = parse_string_f64(&stats, "volume").map(|base| base * price_usd);
+        let price_change_24h = parse_string_f64(&stats, "open")
+            .filter(|open| *open > 0.0)
+            .map(|open| (price_usd - open) / open * 100.0);
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h,
+            liquidity: None,
+            price_change_24h,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+fn parse_string_f64(obj: &serde_json::Value, key: &str) -> Option<f64> {
+    obj.get(key)
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(
+            "unit_index: 1\nname: ETH\nchain: ethereum\nnative: true\ncoinbase_product: ETH-USD",
+        )
+        .unwrap()
+    }
+
+    fn source(server: &MockServer) -> Coinbase {
+        Coinbase::new(HttpClient::new(reqwest::Client::new()), server.uri())
+    }
+
+    async fn serve(ticker: ResponseTemplate, stats: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/products/ETH-USD/ticker"))
+            .respond_with(ticker)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/products/ETH-USD/stats"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(stats))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Trimmed from a live /products/ETH-USD/ticker response.
+    fn ticker() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "ask": "2500.02",
+            "bid": "2499.98",
+            "volume": "152340.12345678",
+            "trade_id": 512345678,
+            "price": "2500.00",
+            "size": "0.01",
+            "time": "2026-10-16T11:59:58.123456Z",
+            "rfq_volume": "12.5"
+        }))
+    }
+
+    #[tokio::test]
+    async fn combines_ticker_price_and_24h_stats() {
+        let server = serve(
+            ticker(),
+            serde_json::json!({
+                "open": "2000.00",
+                "high": "2520.00",
+                "low": "1990.00",
+                "last": "2500.00",
+                "volume": "4000.00000000",
+                "volume_30day": "3000000.00000000"
+            }),
+        )
+        .await;
+
+        let data = source(&server).fetch(&unit(), &SystemClock).await.unwrap();
+
+        assert_eq!(data.price_usd, 2500.0);
+        // stats.volume is in ETH; it is reported in USD.
+        assert_eq!(data.volume_24h, Some(10_000_000.0));
+        assert_eq!(data.price_change_24h, Some(25.0));
+        assert_eq!(data.source, "coinbase");
+    }
+
+    #[tokio::test]
+    async fn stats_without_open_or_volume_leave_them_out() {
+        let server = serve(ticker(), serde_json::json!({ "last": "2500.00" })).await;
+
+        let data = source(&server).fetch(&unit(), &SystemClock).await.unwrap();
+
+        assert_eq!(data.price_usd, 2500.0);
+        assert_eq!(data.volume_24h, None);
+        assert_eq!(data.price_change_24h, None);
+    }
+
+    #[tokio::test]
+    async fn unknown_product_is_an_error() {
+        let server = serve(
+            ResponseTemplate::new(404).set_body_json(serde_json::json!({ "message": "NotFound" })),
+            serde_json::json!({}),
+        )
+        .await;
+
+        let err = source(&server)
+            .fetch(&unit(), &SystemClock)
+            .await
+            .unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.starts_with("Coinbase HTTP 404"), "{}", message);
+        assert!(message.contains("NotFound"), "{}", message);
+    }
+}
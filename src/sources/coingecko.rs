@@ -1,69 +1,167 @@
 use super::PriceSource;
+use crate::chains::{self, ChainRegistry};
+use crate::clock::Clock;
 use crate::config::UnitConfig;
+use crate::http::{HttpClient, HttpResponse};
+use crate::rate_limit::RateLimiter;
 use crate::types::TokenData;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Calls per minute allowed by the demo plan; override with `COINGECKO_RATE_LIMIT`.
+pub const DEFAULT_RATE_LIMIT: u32 = 30;
+
+/// Further attempts after CoinGecko answers 429 or 5xx.
+const MAX_RETRIES: u32 = 2;
+
+/// Contract addresses per `simple/token_price` request.
+const MAX_CONTRACTS_PER_REQUEST: usize = 100;
+
+/// Wait after a 429 without a usable `Retry-After`, before the cap.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Wait after a 5xx without a `Retry-After`.
+const SERVER_ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Production API root; `source_endpoints` or `COINGECKO_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.coingecko.com";
 
 pub struct CoinGecko {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
     api_key: String,
+    limiter: RateLimiter,
+    chains: Arc<ChainRegistry>,
+    /// Longest wait before one retry, so every retry fits in the
+    /// registry's per-fetch timeout.
+    max_wait: Duration,
 }
 
 impl CoinGecko {
-    pub fn new(client: reqwest::Client, api_key: String) -> Self {
-        Self { client, api_key }
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        api_key: String,
+        calls_per_minute: u32,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            limiter: RateLimiter::per_minute(calls_per_minute),
+            chains,
+            max_wait: Self::max_wait_within(super::DEFAULT_SOURCE_TIMEOUT),
+        }
     }
 
-    fn platform_id(chain: &str) -> &str {
-        match chain {
-            "ethereum" => "ethereum",
-            "sepolia" => "ethereum",
-            _ => chain,
+    /// Cap waits between retries so all of them fit in `timeout`, the
+    /// registry's limit for one fetch.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.max_wait = Self::max_wait_within(timeout);
+        self
+    }
+
+    fn max_wait_within(timeout: Duration) -> Duration {
+        timeout / (MAX_RETRIES + 1)
+    }
+
+    /// Send through the shared rate limiter; on 429 or 5xx, sleep for
+    /// `Retry-After` (or a default) capped at `max_wait`, and try again a
+    /// bounded number of times. The client itself must not retry, or its
+    /// retries would bypass the limiter.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        let mut retries = 0;
+        loop {
+            let attempt = request
+                .try_clone()
+                .context("CoinGecko request cannot be retried")?;
+            self.limiter.acquire().await;
+            let resp = self.client.send(attempt).await?;
+            let status = resp.status();
+            let default_wait = if status == StatusCode::TOO_MANY_REQUESTS {
+                DEFAULT_RETRY_AFTER
+            } else if status.is_server_error() {
+                SERVER_ERROR_BACKOFF
+            } else {
+                return Ok(resp);
+            };
+            if retries == MAX_RETRIES {
+                return Ok(resp);
+            }
+            retries += 1;
+            let wait = resp
+                .retry_after()
+                .unwrap_or(default_wait)
+                .min(self.max_wait);
+            tracing::warn!(
+                "CoinGecko HTTP {}; retrying in {:.1}s ({}/{})",
+                status.as_u16(),
+                wait.as_secs_f64(),
+                retries,
+                MAX_RETRIES
+            );
+            tokio::time::sleep(wait).await;
         }
     }
-}
 
-#[async_trait]
-impl PriceSource for CoinGecko {
-    fn name(&self) -> &str {
-        "coingecko"
+    /// One `simple/token_price` call for `contracts` on `platform`; the body
+    /// is keyed by lowercase contract address.
+    async fn fetch_prices(&self, platform: &str, contracts: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/api/v3/simple/token_price/{}", self.base_url, platform);
+        self.fetch_quotes(&url, ("contract_addresses", contracts))
+            .await
     }
 
-    async fn fetch(&self, unit: &UnitConfig) -> Result<TokenData> {
-        let platform = Self::platform_id(&unit.chain);
-        let url = format!(
-            "https://api.coingecko.com/api/v3/simple/token_price/{}",
-            platform
-        );
+    /// One `simple/price` call for native coins by CoinGecko id; the body is
+    /// keyed by id.
+    async fn fetch_native_prices(&self, ids: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/api/v3/simple/price", self.base_url);
+        self.fetch_quotes(&url, ("ids", ids)).await
+    }
 
-        let resp = self
+    async fn fetch_quotes(&self, url: &str, lookup: (&str, &str)) -> Result<serde_json::Value> {
+        let request = self
             .client
-            .get(&url)
+            .get(url)
             .query(&[
-                ("contract_addresses", unit.contract.as_str()),
+                lookup,
                 ("vs_currencies", "usd"),
                 ("include_market_cap", "true"),
                 ("include_24hr_vol", "true"),
                 ("include_24hr_change", "true"),
+                ("include_last_updated_at", "true"),
             ])
-            .header("x-cg-demo-api-key", &self.api_key)
-            .send()
+            .header("x-cg-demo-api-key", &self.api_key);
+        let resp = self
+            .send(request)
             .await
             .context("CoinGecko request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
-            let body = resp.text().await.unwrap_or_default();
+            let body = resp.text();
             anyhow::bail!("CoinGecko HTTP {}: {}", status, body);
         }
 
-        let body: serde_json::Value = resp.json().await.context("CoinGecko parse failed")?;
+        resp.json().context("CoinGecko parse failed")
+    }
 
-        let addr_lower = unit.contract.to_lowercase();
+    fn parse_token(
+        &self,
+        unit: &UnitConfig,
+        body: &serde_json::Value,
+        clock: &dyn Clock,
+    ) -> Result<TokenData> {
+        let key = Self::quote_key(unit);
         let token_data = body
-            .get(&addr_lower)
-            .with_context(|| format!("CoinGecko: no data for contract {}", addr_lower))?;
+            .get(&key)
+            .with_context(|| format!("CoinGecko: no data for {}", key))?;
 
         let price_usd = token_data["usd"]
             .as_f64()
@@ -72,6 +170,10 @@ impl PriceSource for CoinGecko {
         let market_cap = token_data["usd_market_cap"].as_f64();
         let volume_24h = token_data["usd_24h_vol"].as_f64();
         let price_change_24h = token_data["usd_24h_change"].as_f64();
+        let timestamp = token_data["last_updated_at"]
+            .as_i64()
+            .and_then(|t| DateTime::<Utc>::from_timestamp(t, 0))
+            .unwrap_or_else(|| clock.now());
 
         Ok(TokenData {
             name: unit.name.clone(),
@@ -82,8 +184,201 @@ impl PriceSource for CoinGecko {
             volume_24h,
             liquidity: None,
             price_change_24h,
-            source: self.name().to_string(),
-            timestamp: Utc::now(),
+            source: "coingecko".to_string(),
+            timestamp,
+            reported_symbol: None,
+            reported_name: None,
         })
     }
+
+    /// Response key for `unit`: its coin id if native, else its contract
+    /// address, already lowercased at config load.
+    fn quote_key(unit: &UnitConfig) -> String {
+        if unit.native {
+            chains::native_coin(&unit.chain)
+                .map(|n| n.coingecko_id.to_string())
+                .unwrap_or_default()
+        } else {
+            unit.contract.clone()
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinGecko {
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let body = if unit.native {
+            self.fetch_native_prices(&Self::quote_key(unit)).await?
+        } else {
+            let platform = self.chains.id_for(&unit.chain, "coingecko")?;
+            self.fetch_prices(&platform, &unit.contract).await?
+        };
+        self.parse_token(unit, &body, clock)
+    }
+
+    /// One request for all native coins, and one per platform for up to 100
+    /// contracts; an entry missing from a response fails only its own unit.
+    async fn fetch_many(
+        &self,
+        units: &[&UnitConfig],
+        clock: &dyn Clock,
+    ) -> Vec<(u32, Result<TokenData>)> {
+        let (natives, tokens): (Vec<&UnitConfig>, Vec<&UnitConfig>) =
+            units.iter().copied().partition(|u| u.native);
+        let mut results = Vec::with_capacity(units.len());
+        let mut by_platform: BTreeMap<String, Vec<&UnitConfig>> = BTreeMap::new();
+        for unit in tokens {
+            match self.chains.id_for(&unit.chain, "coingecko") {
+                Ok(platform) => by_platform.entry(platform).or_default().push(unit),
+                Err(e) => results.push((unit.unit_index, Err(e))),
+            }
+        }
+
+        let mut spread = |chunk: &[&UnitConfig], body: Result<serde_json::Value>| match body {
+            Ok(body) => results.extend(
+                chunk
+                    .iter()
+                    .map(|u| (u.unit_index, self.parse_token(u, &body, clock))),
+            ),
+            Err(e) => results.extend(
+                chunk
+                    .iter()
+                    .map(|u| (u.unit_index, Err(anyhow::anyhow!("{:#}", e)))),
+            ),
+        };
+
+        if !natives.is_empty() {
+            let mut ids: Vec<String> = natives.iter().map(|u| Self::quote_key(u)).collect();
+            ids.sort();
+            ids.dedup();
+            spread(&natives, self.fetch_native_prices(&ids.join(",")).await);
+        }
+        for (platform, platform_units) in by_platform {
+            for chunk in platform_units.chunks(MAX_CONTRACTS_PER_REQUEST) {
+                let contracts = chunk
+                    .iter()
+                    .map(|u| u.contract.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                spread(chunk, self.fetch_prices(&platform, &contracts).await);
+            }
+        }
+        results
+    }
+
+    fn batches(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::SourceRegistry;
+    use std::collections::HashMap;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: HOT\nchain: ethereum\ncontract: '{}'",
+            HOT
+        ))
+        .unwrap()
+    }
+
+    /// Answer `first` `times` times, then a price for HOT.
+    async fn server(first: ResponseTemplate, times: u64) -> MockServer {
+        let server = MockServer::start().await;
+        let token_price = "/api/v3/simple/token_price/ethereum";
+        Mock::given(method("GET"))
+            .and(path(token_price))
+            .respond_with(first)
+            .up_to_n_times(times)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(token_price))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({ HOT: { "usd": 0.002 } })),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// CoinGecko as `SourceRegistry::from_config` wires it, with a 600ms
+    /// fetch timeout so each retry waits at most 200ms.
+    fn registry(server: &MockServer) -> SourceRegistry {
+        SourceRegistry::builder(HttpClient::new(reqwest::Client::new()))
+            .with_timeout(Duration::from_millis(600))
+            .with_endpoints(HashMap::from([("coingecko".to_string(), server.uri())]))
+            .with_coingecko("demo-key".to_string(), 600)
+            .build()
+    }
+
+    async fn fetch(server: &MockServer) -> Result<TokenData> {
+        let mut results = registry(server).fetch_all(&unit()).await;
+        results.remove(0).result
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_is_capped_to_fit_the_fetch_timeout() {
+        let limited = ResponseTemplate::new(429).insert_header("Retry-After", "120");
+        let server = server(limited, 2).await;
+
+        let data = fetch(&server).await.unwrap();
+
+        assert_eq!(data.price_usd, 0.002);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_retry_without_client_retries() {
+        let server = server(ResponseTemplate::new(429), 10).await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("CoinGecko HTTP 429"),
+            "{:#}",
+            err
+        );
+        // One attempt plus MAX_RETRIES, none multiplied by client retries.
+        assert_eq!(
+            server.received_requests().await.unwrap().len(),
+            1 + MAX_RETRIES as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_through_the_limiter() {
+        let server = server(ResponseTemplate::new(503), 1).await;
+
+        let data = fetch(&server).await.unwrap();
+
+        assert_eq!(data.price_usd, 0.002);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let server = server(ResponseTemplate::new(401), 1).await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("CoinGecko HTTP 401"),
+            "{:#}",
+            err
+        );
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
 }
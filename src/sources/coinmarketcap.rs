@@ -1,61 +1,75 @@
 use super::PriceSource;
+use crate::address;
+use crate::chains::{self, ChainRegistry, NativeCoin};
+use crate::clock::Clock;
 use crate::config::UnitConfig;
+use crate::http::HttpClient;
 use crate::types::TokenData;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `COINMARKETCAP_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://pro-api.coinmarketcap.com";
 
 pub struct CoinMarketCap {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
     api_key: String,
+    chains: Arc<ChainRegistry>,
 }
 
 impl CoinMarketCap {
-    pub fn new(client: reqwest::Client, api_key: String) -> Self {
-        Self { client, api_key }
-    }
-
-    fn platform_slug(chain: &str) -> &str {
-        match chain {
-            "ethereum" => "ethereum",
-            "sepolia" => "ethereum",
-            _ => chain,
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        api_key: String,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            chains,
         }
     }
-}
 
-#[async_trait]
-impl PriceSource for CoinMarketCap {
-    fn name(&self) -> &str {
-        "coinmarketcap"
-    }
-
-    async fn fetch(&self, unit: &UnitConfig) -> Result<TokenData> {
-        let url = "https://pro-api.coinmarketcap.com/v2/cryptocurrency/quotes/latest";
-        let resp = self
+    /// One `quotes/latest` call, e.g. for `("address", "0x..,0x..")` or
+    /// `("slug", "ethereum")`.
+    async fn fetch_quotes(&self, lookup: (&str, &str)) -> Result<Value> {
+        let url = format!("{}/v2/cryptocurrency/quotes/latest", self.base_url);
+        let request = self
             .client
-            .get(url)
-            .query(&[
-                ("address", unit.contract.as_str()),
-                ("skip_invalid", "true"),
-            ])
+            .get(&url)
+            .query(&[lookup, ("skip_invalid", "true")])
             .header("Accept", "application/json")
-            .header("X-CMC_PRO_API_KEY", &self.api_key)
-            .send()
+            .header("X-CMC_PRO_API_KEY", &self.api_key);
+        let resp = self
+            .client
+            .send(request)
             .await
             .context("CoinMarketCap request failed")?;
 
         let status = resp.status();
         if !status.is_success() {
-            let body = resp.text().await.unwrap_or_default();
+            let body = resp.text();
             anyhow::bail!("CoinMarketCap HTTP {}: {}", status, body);
         }
 
-        let body: Value = resp.json().await.context("CoinMarketCap parse failed")?;
-        let expected_platform = Self::platform_slug(&unit.chain);
-        let token_data = extract_best_token(&body["data"], &unit.contract, expected_platform)
-            .context("CoinMarketCap: no matching token for contract")?;
+        resp.json().context("CoinMarketCap parse failed")
+    }
+
+    fn parse_token(
+        &self,
+        unit: &UnitConfig,
+        token_data: &Value,
+        clock: &dyn Clock,
+    ) -> Result<TokenData> {
+        if token_data.get("is_active").and_then(Value::as_i64) == Some(0) {
+            anyhow::bail!("CoinMarketCap: token {} is inactive", unit.contract);
+        }
 
         let usd_quote = token_data
             .get("quote")
@@ -69,9 +83,13 @@ impl PriceSource for CoinMarketCap {
 
         let market_cap = usd_quote.get("market_cap").and_then(Value::as_f64);
         let volume_24h = usd_quote.get("volume_24h").and_then(Value::as_f64);
-        let price_change_24h = usd_quote
-            .get("percent_change_24h")
-            .and_then(Value::as_f64);
+        let price_change_24h = usd_quote.get("percent_change_24h").and_then(Value::as_f64);
+        let timestamp = usd_quote
+            .get("last_updated")
+            .and_then(Value::as_str)
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or_else(|| clock.now());
 
         Ok(TokenData {
             name: unit.name.clone(),
@@ -82,43 +100,232 @@ impl PriceSource for CoinMarketCap {
             volume_24h,
             liquidity: None,
             price_change_24h,
-            source: self.name().to_string(),
-            timestamp: Utc::now(),
+            source: "coinmarketcap".to_string(),
+            timestamp,
+            reported_symbol: token_data["symbol"].as_str().map(str::to_string),
+            reported_name: token_data["name"].as_str().map(str::to_string),
         })
     }
 }
 
-fn extract_best_token<'a>(
-    data: &'a Value,
-    contract: &str,
-    expected_platform: &str,
-) -> Option<&'a Value> {
-    let contract = contract.to_ascii_lowercase();
-    let mut fallback: Option<&Value> = None;
+#[async_trait]
+impl PriceSource for CoinMarketCap {
+    fn name(&self) -> &str {
+        "coinmarketcap"
+    }
 
-    for token in flatten_token_entries(data) {
-        if fallback.is_none() {
-            fallback = Some(token);
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        if let Some(coin) = native_coin(unit) {
+            let body = self.fetch_quotes(("slug", coin.cmc_slug)).await?;
+            let token_data = find_native(&body["data"], &coin)?;
+            return self.parse_token(unit, token_data, clock);
         }
+        let expected_platform = self.chains.id_for(&unit.chain, "coinmarketcap")?;
+        let body = self.fetch_quotes(("address", &unit.contract)).await?;
+        let token_data = extract_best_token(
+            &body["data"],
+            &unit.contract,
+            &expected_platform,
+            unit.allow_loose_match,
+        )?;
+        self.parse_token(unit, token_data, clock)
+    }
 
-        let matches_contract = token_contract_address(token)
-            .map(|addr| addr.eq_ignore_ascii_case(&contract))
-            .unwrap_or(false);
+    /// All contracts in one request and all native coins in another; each
+    /// unit is matched back by contract address and platform (or slug), and
+    /// one with no entry fails on its own. Units with `allow_loose_match`
+    /// are fetched individually.
+    async fn fetch_many(
+        &self,
+        units: &[&UnitConfig],
+        clock: &dyn Clock,
+    ) -> Vec<(u32, Result<TokenData>)> {
+        let (natives, tokens): (Vec<&UnitConfig>, Vec<&UnitConfig>) =
+            units.iter().copied().partition(|u| u.native);
+        let mut results = Vec::with_capacity(units.len());
 
-        if !matches_contract {
-            continue;
+        if !natives.is_empty() {
+            let mut slugs: Vec<&str> = natives
+                .iter()
+                .filter_map(|u| native_coin(u).map(|n| n.cmc_slug))
+                .collect();
+            slugs.sort();
+            slugs.dedup();
+            let body = self.fetch_quotes(("slug", &slugs.join(","))).await;
+            results.extend(natives.iter().map(|unit| {
+                let result = match (&body, native_coin(unit)) {
+                    (Ok(body), Some(coin)) => find_native(&body["data"], &coin)
+                        .and_then(|token| self.parse_token(unit, token, clock)),
+                    (Ok(_), None) => Err(anyhow::anyhow!(
+                        "CoinMarketCap: no native coin for chain {}",
+                        unit.chain
+                    )),
+                    (Err(e), _) => Err(anyhow::anyhow!("{:#}", e)),
+                };
+                (unit.unit_index, result)
+            }));
         }
+        // A loose match is only safe in a response for the unit's own
+        // contract, so those units are fetched one by one.
+        let (loose, tokens): (Vec<&UnitConfig>, Vec<&UnitConfig>) =
+            tokens.into_iter().partition(|u| u.allow_loose_match);
+        for unit in loose {
+            results.push((unit.unit_index, self.fetch(unit, clock).await));
+        }
+        let tokens: Vec<(&UnitConfig, String)> = tokens
+            .into_iter()
+            .filter_map(
+                |unit| match self.chains.id_for(&unit.chain, "coinmarketcap") {
+                    Ok(platform) => Some((unit, platform)),
+                    Err(e) => {
+                        results.push((unit.unit_index, Err(e)));
+                        None
+                    }
+                },
+            )
+            .collect();
+        if tokens.is_empty() {
+            return results;
+        }
+
+        let addresses = tokens
+            .iter()
+            .map(|(u, _)| u.contract.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        let body = match self.fetch_quotes(("address", &addresses)).await {
+            Ok(body) => body,
+            Err(e) => {
+                results.extend(
+                    tokens
+                        .iter()
+                        .map(|(u, _)| (u.unit_index, Err(anyhow::anyhow!("{:#}", e)))),
+                );
+                return results;
+            }
+        };
+        results.extend(tokens.iter().map(|(unit, expected_platform)| {
+            let result = find_token(&body["data"], &unit.contract, expected_platform)
+                .and_then(|token| self.parse_token(unit, token, clock));
+            (unit.unit_index, result)
+        }));
+        results
+    }
+
+    fn batches(&self) -> bool {
+        true
+    }
+}
+
+fn native_coin(unit: &UnitConfig) -> Option<NativeCoin> {
+    unit.native
+        .then(|| chains::native_coin(&unit.chain))
+        .flatten()
+}
+
+/// The entry whose `slug` is the native coin's.
+fn find_native<'a>(data: &'a Value, coin: &NativeCoin) -> Result<&'a Value> {
+    flatten_token_entries(data)
+        .into_iter()
+        .find(|token| {
+            token
+                .get("slug")
+                .and_then(Value::as_str)
+                .is_some_and(|slug| slug.eq_ignore_ascii_case(coin.cmc_slug))
+        })
+        .with_context(|| format!("CoinMarketCap: no data for native {}", coin.symbol))
+}
+
+/// The entry for exactly this contract on this platform; unlike
+/// `extract_best_token` there is no loose match, since a batched response
+/// holds other units' tokens.
+fn find_token<'a>(data: &'a Value, contract: &str, expected_platform: &str) -> Result<&'a Value> {
+    let tokens = flatten_token_entries(data);
+    tokens
+        .iter()
+        .copied()
+        .find(|token| {
+            token_contract_address(token).is_some_and(|addr| addr == contract)
+                && token_platform_slug(token)
+                    .is_none_or(|slug| slug.eq_ignore_ascii_case(expected_platform))
+        })
+        .ok_or_else(|| no_match(contract, expected_platform, &tokens))
+}
 
-        let platform_ok = token_platform_slug(token)
-            .map(|slug| slug.eq_ignore_ascii_case(expected_platform))
-            .unwrap_or(true);
+/// The entry for `contract` on `expected_platform`. An entry on that
+/// platform whose contract address the response leaves out is only used
+/// with `allow_loose`, and then logged; any other miss is an error listing
+/// what CoinMarketCap returned.
+fn extract_best_token<'a>(
+    data: &'a Value,
+    contract: &str,
+    expected_platform: &str,
+    allow_loose: bool,
+) -> Result<&'a Value> {
+    let tokens = flatten_token_entries(data);
+    let mut loose: Option<&Value> = None;
+    for token in tokens.iter().copied() {
+        let platform = token_platform_slug(token);
+        match token_contract_address(token) {
+            Some(addr)
+                if addr == contract
+                    && platform.is_none_or(|slug| slug.eq_ignore_ascii_case(expected_platform)) =>
+            {
+                return Ok(token);
+            }
+            None if loose.is_none()
+                && platform.is_some_and(|slug| slug.eq_ignore_ascii_case(expected_platform)) =>
+            {
+                loose = Some(token);
+            }
+            _ => {}
+        }
+    }
 
-        if platform_ok {
-            return Some(token);
+    match loose {
+        Some(token) if allow_loose => {
+            tracing::warn!(
+                "CoinMarketCap: no entry lists contract {}; loosely matched {} (allow_loose_match)",
+                contract,
+                describe_token(token)
+            );
+            Ok(token)
         }
+        Some(token) => anyhow::bail!(
+            "CoinMarketCap: no token matching contract {} on platform {}; {} has no contract address in the response, set allow_loose_match: true to accept it",
+            contract,
+            expected_platform,
+            describe_token(token)
+        ),
+        None => Err(no_match(contract, expected_platform, &tokens)),
     }
+}
+
+fn no_match(contract: &str, expected_platform: &str, tokens: &[&Value]) -> anyhow::Error {
+    anyhow::anyhow!(
+        "CoinMarketCap: no token matching contract {} on platform {}; candidates were [{}]",
+        contract,
+        expected_platform,
+        tokens
+            .iter()
+            .map(|t| describe_token(t))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
 
-    fallback
+/// `SYMBOL (Name) 0x... on slug`, for logs and errors.
+fn describe_token(token: &Value) -> String {
+    let field = |key| token.get(key).and_then(Value::as_str).unwrap_or("?");
+    format!(
+        "{} ({}) {} on {}",
+        field("symbol"),
+        field("name"),
+        token_contract_address(token)
+            .as_deref()
+            .unwrap_or("no contract"),
+        token_platform_slug(token).unwrap_or("?")
+    )
 }
 
 fn flatten_token_entries(data: &Value) -> Vec<&Value> {
@@ -136,6 +343,8 @@ fn flatten_token_entries(data: &Value) -> Vec<&Value> {
     }
 }
 
+/// The entry's contract in the same normalized form as a unit's, so the
+/// two compare directly.
 fn token_contract_address(token: &Value) -> Option<String> {
     token
         .get("contract_address")
@@ -146,7 +355,7 @@ fn token_contract_address(token: &Value) -> Option<String> {
                 .and_then(|p| p.get("token_address").or_else(|| p.get("contract_address")))
                 .and_then(Value::as_str)
         })
-        .map(|s| s.to_ascii_lowercase())
+        .map(address::normalize)
 }
 
 fn token_platform_slug(token: &Value) -> Option<&str> {
@@ -155,3 +364,128 @@ fn token_platform_slug(token: &Value) -> Option<&str> {
         .and_then(|p| p.get("slug"))
         .and_then(Value::as_str)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const TYPO: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e3";
+
+    fn entry(symbol: &str, slug: &str, token_address: Option<&str>) -> Value {
+        let mut platform = json!({ "slug": slug });
+        if let Some(address) = token_address {
+            platform["token_address"] = json!(address);
+        }
+        json!({
+            "symbol": symbol,
+            "name": format!("{} token", symbol),
+            "platform": platform,
+            "quote": { "USD": { "price": 1.0 } },
+        })
+    }
+
+    #[test]
+    fn entry_with_the_contract_on_the_platform_is_picked() {
+        let data = json!({
+            "1": [entry("FAKE", "bnb", Some(HOT)), entry("HOT", "ethereum", Some(HOT))],
+            "2": [entry("WETH", "ethereum", Some(WETH))],
+        });
+
+        let token = extract_best_token(&data, HOT, "ethereum", false).unwrap();
+
+        assert_eq!(token["symbol"], "HOT");
+    }
+
+    #[test]
+    fn typo_in_the_contract_lists_the_candidates() {
+        let data = json!({ "1": [entry("HOT", "ethereum", Some("0x6C6EE5E31D828DE241282B9606C8E98EA48526E2"))] });
+
+        for allow_loose in [false, true] {
+            let error = extract_best_token(&data, TYPO, "ethereum", allow_loose)
+                .unwrap_err()
+                .to_string();
+
+            assert_eq!(
+                error,
+                format!(
+                    "CoinMarketCap: no token matching contract {} on platform ethereum; candidates were [HOT (HOT token) {} on ethereum]",
+                    TYPO, HOT
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn missing_contract_field_needs_allow_loose_match() {
+        let data = json!({ "1": [
+            entry("OTHER", "bnb", None),
+            entry("HOT", "ethereum", None),
+        ] });
+
+        let error = extract_best_token(&data, HOT, "ethereum", false)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("HOT (HOT token) no contract on ethereum has no contract address"),
+            "{}",
+            error
+        );
+        assert!(error.contains("allow_loose_match: true"), "{}", error);
+
+        let token = extract_best_token(&data, HOT, "ethereum", true).unwrap();
+        assert_eq!(token["symbol"], "HOT");
+    }
+
+    #[test]
+    fn missing_contract_field_on_another_platform_is_no_match() {
+        let data = json!({ "1": [entry("HOT", "bnb", None)] });
+
+        let error = extract_best_token(&data, HOT, "ethereum", true)
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.contains("candidates were [HOT (HOT token) no contract on bnb]"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn batched_response_missing_one_token_fails_only_that_unit() {
+        const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+        // Asked for HOT, WETH and USDC; CoinMarketCap left USDC out.
+        let data = json!({
+            "1": [entry("HOT", "ethereum", Some(HOT))],
+            "2": [entry("WETH", "ethereum", Some(WETH))],
+        });
+
+        assert_eq!(find_token(&data, HOT, "ethereum").unwrap()["symbol"], "HOT");
+        assert_eq!(
+            find_token(&data, WETH, "ethereum").unwrap()["symbol"],
+            "WETH"
+        );
+        let error = find_token(&data, USDC, "ethereum").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!(
+                "CoinMarketCap: no token matching contract {} on platform ethereum; candidates were [HOT (HOT token) {} on ethereum, WETH (WETH token) {} on ethereum]",
+                USDC, HOT, WETH
+            )
+        );
+    }
+
+    #[test]
+    fn batched_lookup_never_takes_an_entry_without_a_contract() {
+        // In a batch this entry could belong to any of the requested units.
+        let data = json!({
+            "1": [entry("HOT", "ethereum", None)],
+            "2": [entry("WETH", "ethereum", Some(WETH))],
+        });
+
+        assert!(find_token(&data, HOT, "ethereum").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Production API root; `source_endpoints` or `COINPAPRIKA_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.coinpaprika.com";
+
+/// CoinPaprika free-tier ticker; keyed on `coinpaprika_id` (no contract lookup).
+pub struct CoinPaprika {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl CoinPaprika {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl PriceSource for CoinPaprika {
+    fn name(&self) -> &str {
+        "coinpaprika"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let coin_id = unit.source_id("coinpaprika").ok_or_else(|| {
+            NotConfigured(format!("coinpaprika_id not configured for '{}'", unit.name))
+        })?;
+        let url = format!("{}/v1/tickers/{}", self.base_url, coin_id);
+
+        let request = self.client.get(&url).header("Accept", "application/json");
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("CoinPaprika request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("CoinPaprika HTTP {}: {}", status, body);
+        }
+
+        let body: serde_json::Value = resp.json().context("CoinPaprika parse failed")?;
+        let quote = &body["quotes"]["USD"];
+        let price_usd = quote["price"]
+            .as_f64()
+            .context("CoinPaprika: missing quotes.USD.price")?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: quote["market_cap"].as_f64(),
+            volume_24h: quote["volume_24h"].as_f64(),
+            liquidity: None,
+            price_change_24h: quote["percent_change_24h"].as_f64(),
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: body["symbol"].as_str().map(str::to_string),
+            reported_name: body["name"].as_str().map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(
+            "unit_index: 1\nname: HOT\nchain: ethereum\ncontract: '0x6c6ee5e31d828de241282b9606c8e98ea48526e2'\ncoinpaprika_id: hot-holo",
+        )
+        .unwrap()
+    }
+
+    async fn serve(response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/tickers/hot-holo"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn fetch(server: &MockServer) -> Result<TokenData> {
+        CoinPaprika::new(HttpClient::new(reqwest::Client::new()), server.uri())
+            .fetch(&unit(), &SystemClock)
+            .await
+    }
+
+    #[tokio::test]
+    async fn parses_the_usd_quote_and_reported_identity() {
+        // Trimmed from a live /v1/tickers/hot-holo response.
+        let server = serve(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "hot-holo",
+            "name": "Holo",
+            "symbol": "HOT",
+            "rank": 150,
+            "circulating_supply": 173_000_000_000_u64,
+            "last_updated": "2026-10-16T11:55:00Z",
+            "quotes": {
+                "USD": {
+                    "price": 0.00123,
+                    "volume_24h": 5_000_000.5,
+                    "volume_24h_change_24h": -3.1,
+                    "market_cap": 212_790_000,
+                    "percent_change_1h": 0.1,
+                    "percent_change_24h": -2.5,
+                    "ath_price": 0.0311
+                }
+            }
+        })))
+        .await;
+
+        let data = fetch(&server).await.unwrap();
+
+        assert_eq!(data.price_usd, 0.00123);
+        assert_eq!(data.volume_24h, Some(5_000_000.5));
+        assert_eq!(data.market_cap, Some(212_790_000.0));
+        assert_eq!(data.price_change_24h, Some(-2.5));
+        assert_eq!(data.reported_symbol.as_deref(), Some("HOT"));
+        assert_eq!(data.reported_name.as_deref(), Some("Holo"));
+    }
+
+    #[tokio::test]
+    async fn ticker_without_a_usd_price_is_an_error() {
+        let server = serve(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "hot-holo",
+            "symbol": "HOT",
+            "quotes": {}
+        })))
+        .await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "CoinPaprika: missing quotes.USD.price");
+    }
+
+    #[tokio::test]
+    async fn unknown_id_is_an_error() {
+        let server = serve(
+            ResponseTemplate::new(404)
+                .set_body_json(serde_json::json!({ "error": "id not found" })),
+        )
+        .await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert!(err.to_string().contains("id not found"), "{:#}", err);
+    }
+}
@@ -0,0 +1,364 @@
+use super::eth_rpc;
+use super::{NotConfigured, PriceSource};
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Curve API registries searched, in order, for the configured pool.
+const REGISTRIES: &[&str] = &[
+    "main",
+    "crypto",
+    "factory",
+    "factory-crypto",
+    "factory-stable-ng",
+    "factory-twocrypto",
+    "factory-tricrypto",
+];
+
+/// `get_dy(int128,int128,uint256)` (stableswap pools).
+const GET_DY_INT128: &str = "0x5e0d443f";
+/// `get_dy(uint256,uint256,uint256)` (crypto pools).
+const GET_DY_UINT256: &str = "0x556d6e9f";
+
+/// Production API root; `source_endpoints` or `CURVE_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.curve.finance";
+
+/// Curve pool price for a pool-native token; keyed on `curve_pool` + `curve_index`.
+///
+/// The pool is looked up through the Curve API for its coins and TVL. With an
+/// Ethereum RPC URL, mainnet pools are priced from an on-chain `get_dy` quote
+/// into the pool's other coin; otherwise the API's `usdPrice` is used.
+pub struct Curve {
+    client: HttpClient,
+    base_url: String,
+    rpc_url: Option<String>,
+    chains: Arc<ChainRegistry>,
+}
+
+impl Curve {
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        rpc_url: Option<String>,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            rpc_url,
+            chains,
+        }
+    }
+
+    async fn find_pool(&self, chain: &str, address: &str) -> Result<CurvePool> {
+        for registry in REGISTRIES {
+            let url = format!("{}/api/getPools/{}/{}", self.base_url, chain, registry);
+            let request = self.client.get(&url).header("Accept", "application/json");
+            let resp = self
+                .client
+                .send(request)
+                .await
+                .context("Curve request failed")?;
+
+            // Not every chain has every registry.
+            if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                continue;
+            }
+            let status = resp.status();
+            if !status.is_success() {
+                let body = resp.text();
+                anyhow::bail!("Curve HTTP {}: {}", status, body);
+            }
+
+            let body: PoolsResponse = resp.json().context("Curve parse failed")?;
+            if let Some(pool) = body
+                .data
+                .pool_data
+                .into_iter()
+                .find(|p| p.address.eq_ignore_ascii_case(address))
+            {
+                return Ok(pool);
+            }
+        }
+        anyhow::bail!("Curve: pool {} not found on {}", address, chain)
+    }
+
+    /// On-chain quote for one whole token `i` in units of token `j`.
+    async fn quote_dy(
+        &self,
+        rpc_url: &str,
+        pool: &str,
+        i: usize,
+        j: usize,
+        dec_i: u8,
+        dec_j: u8,
+    ) -> Result<f64> {
+        let dx = 10u128
+            .checked_pow(dec_i as u32)
+            .context("coin decimals out of range")?;
+        let args = format!(
+            "{}{}{}",
+            eth_rpc::encode_word(i as u128),
+            eth_rpc::encode_word(j as u128),
+            eth_rpc::encode_word(dx)
+        );
+
+        let result = match eth_rpc::eth_call(
+            &self.client,
+            rpc_url,
+            pool,
+            &format!("{}{}", GET_DY_INT128, args),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => eth_rpc::eth_call(
+                &self.client,
+                rpc_url,
+                pool,
+                &format!("{}{}", GET_DY_UINT256, args),
+            )
+            .await
+            .context("get_dy failed")?,
+        };
+        let dy = eth_rpc::parse_word(eth_rpc::word(&result, 0)?).context("get_dy result")?;
+        Ok(dy_to_rate(dy, dec_j))
+    }
+}
+
+/// `dy` returned for one whole input token, scaled down by the output decimals.
+fn dy_to_rate(dy: u128, out_decimals: u8) -> f64 {
+    dy as f64 / 10f64.powi(out_decimals as i32)
+}
+
+#[derive(Deserialize)]
+struct PoolsResponse {
+    data: PoolsData,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolsData {
+    pool_data: Vec<CurvePool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurvePool {
+    address: String,
+    coins: Vec<CurveCoin>,
+    usd_total: Option<f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurveCoin {
+    #[serde(deserialize_with = "de_decimals")]
+    decimals: u8,
+    usd_price: Option<f64>,
+    #[serde(default)]
+    symbol: Option<String>,
+}
+
+/// The API sends `decimals` as a string on some registries.
+fn de_decimals<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u8, D::Error> {
+    match serde_json::Value::deserialize(d)? {
+        serde_json::Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+        v => v
+            .as_u64()
+            .and_then(|n| u8::try_from(n).ok())
+            .ok_or_else(|| serde::de::Error::custom("invalid decimals")),
+    }
+}
+
+#[async_trait]
+impl PriceSource for Curve {
+    fn name(&self) -> &str {
+        "curve"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let pool_address = unit.source_id("curve").ok_or_else(|| {
+            NotConfigured(format!("curve_pool not configured for '{}'", unit.name))
+        })?;
+        let index = unit.curve_index.unwrap_or(0) as usize;
+        let chain = self.chains.id_for(&unit.chain, "curve")?;
+
+        let pool = self.find_pool(&chain, pool_address).await?;
+        let coin = pool
+            .coins
+            .get(index)
+            .with_context(|| format!("Curve: pool {} has no coin {}", pool_address, index))?;
+
+        // Price through the first other coin the API can value in USD.
+        let counterpart = pool
+            .coins
+            .iter()
+            .enumerate()
+            .find(|(j, c)| *j != index && c.usd_price.is_some());
+
+        let price_usd = match (&self.rpc_url, counterpart) {
+            (Some(rpc_url), Some((j, other))) if chain == "ethereum" => {
+                let rate = self
+                    .quote_dy(
+                        rpc_url,
+                        pool_address,
+                        index,
+                        j,
+                        coin.decimals,
+                        other.decimals,
+                    )
+                    .await
+                    .with_context(|| format!("Curve: get_dy on {}", pool_address))?;
+                rate * other.usd_price.unwrap_or_default()
+            }
+            _ => coin
+                .usd_price
+                .with_context(|| format!("Curve: no usdPrice for coin {}", index))?,
+        };
+        if price_usd <= 0.0 {
+            anyhow::bail!(
+                "Curve: non-positive price for coin {} in {}",
+                index,
+                pool_address
+            );
+        }
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: pool.usd_total,
+            price_change_24h: None,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: coin.symbol.clone(),
+            reported_name: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    /// Curve's DAI/USDC/USDT 3pool on mainnet.
+    const THREE_POOL: &str = "0xbebc44782c7db0a1a60cb6fe97d0b483032ff1c7";
+
+    #[test]
+    fn dy_is_scaled_by_the_output_decimals() {
+        assert_eq!(dy_to_rate(999_800, 6), 0.9998);
+        assert_eq!(dy_to_rate(1_000_200_000_000_000_000, 18), 1.0002);
+        assert_eq!(dy_to_rate(0, 6), 0.0);
+    }
+
+    fn dai() -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: DAI\nchain: ethereum\ncontract: '0x6b175474e89094c44da98b954eedeac495271d0f'\ncurve_pool: '{}'\ncurve_index: 0",
+            THREE_POOL
+        ))
+        .unwrap()
+    }
+
+    /// The Curve API listing the 3pool in the `main` registry; decimals
+    /// come as strings there.
+    async fn serve_api() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/getPools/ethereum/main"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": true,
+                "data": {
+                    "poolData": [{
+                        "id": "0",
+                        "address": "0xbEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7",
+                        "name": "Curve.fi DAI/USDC/USDT",
+                        "coins": [
+                            { "address": "0x6B175474E89094C44Da98b954EedeAC495271d0F",
+                              "decimals": "18", "symbol": "DAI", "usdPrice": 0.9995 },
+                            { "address": "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48",
+                              "decimals": "6", "symbol": "USDC", "usdPrice": 1.0 },
+                            { "address": "0xdAC17F958D2ee523a2206206994597C13D831ec7",
+                              "decimals": "6", "symbol": "USDT", "usdPrice": 1.0 }
+                        ],
+                        "usdTotal": 180_000_000.0
+                    }]
+                }
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn source(api: &MockServer, rpc_url: Option<String>) -> Curve {
+        Curve::new(
+            HttpClient::new(reqwest::Client::new()),
+            api.uri(),
+            rpc_url,
+            Arc::new(ChainRegistry::builtin()),
+        )
+    }
+
+    #[tokio::test]
+    async fn prices_through_get_dy_into_the_next_coin() {
+        let api = serve_api().await;
+        // get_dy(0, 1, 1e18): one DAI in, 0.9998 USDC (6 decimals) out.
+        let calldata = format!(
+            "{}{}{}{}",
+            GET_DY_INT128,
+            eth_rpc::encode_word(0),
+            eth_rpc::encode_word(1),
+            eth_rpc::encode_word(1_000_000_000_000_000_000)
+        );
+        let rpc = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(move |request: &Request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                body["params"][0]["to"] == THREE_POOL && body["params"][0]["data"] == calldata
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": format!("0x{}", eth_rpc::encode_word(999_800))
+            })))
+            .mount(&rpc)
+            .await;
+
+        let data = source(&api, Some(rpc.uri()))
+            .fetch(&dai(), &SystemClock)
+            .await
+            .unwrap();
+
+        assert!(
+            (data.price_usd - 0.9998).abs() < 1e-12,
+            "{}",
+            data.price_usd
+        );
+        assert_eq!(data.liquidity, Some(180_000_000.0));
+        assert_eq!(data.reported_symbol.as_deref(), Some("DAI"));
+    }
+
+    #[tokio::test]
+    async fn without_an_rpc_url_the_api_price_is_used() {
+        let api = serve_api().await;
+
+        let data = source(&api, None)
+            .fetch(&dai(), &SystemClock)
+            .await
+            .unwrap();
+
+        assert_eq!(data.price_usd, 0.9995);
+    }
+}
@@ -0,0 +1,217 @@
+use super::PriceSource;
+use crate::clock::Clock;
+use crate::config::{CustomSourceConfig, UnitConfig};
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+
+/// Price source defined entirely in config: a URL template and JSON pointers
+/// into the response body.
+pub struct CustomRestSource {
+    client: HttpClient,
+    config: CustomSourceConfig,
+    /// Header values with `${VAR}` references already resolved.
+    headers: Vec<(String, String)>,
+}
+
+impl CustomRestSource {
+    /// Resolves header env vars up front so a missing key disables the source
+    /// at startup instead of failing every fetch.
+    pub fn new(client: HttpClient, config: CustomSourceConfig) -> Result<Self> {
+        let mut headers = Vec::with_capacity(config.headers.len());
+        for (name, value) in &config.headers {
+            let resolved = interpolate_env(value)
+                .with_context(|| format!("custom source '{}' header '{}'", config.name, name))?;
+            headers.push((name.clone(), resolved));
+        }
+        Ok(Self {
+            client,
+            config,
+            headers,
+        })
+    }
+
+    fn url_for(&self, unit: &UnitConfig) -> String {
+        self.config
+            .url
+            .replace("{contract}", unit.lookup_contract())
+            .replace("{chain}", &unit.chain)
+    }
+}
+
+#[async_trait]
+impl PriceSource for CustomRestSource {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let url = self.url_for(unit);
+        let mut req = self.client.get(&url).header("Accept", "application/json");
+        for (name, value) in &self.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+
+        let resp = self
+            .client
+            .send(req)
+            .await
+            .with_context(|| format!("{} request failed", self.config.name))?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("{} HTTP {}: {}", self.config.name, status, body);
+        }
+
+        let body: Value = resp
+            .json()
+            .with_context(|| format!("{} parse failed", self.config.name))?;
+
+        let scale = self.config.scale.unwrap_or(1.0);
+        let price_usd = extract_f64(&body, &self.config.price_pointer).with_context(|| {
+            format!(
+                "{}: no numeric price at '{}'",
+                self.config.name, self.config.price_pointer
+            )
+        })? * scale;
+
+        let volume_24h = self
+            .config
+            .volume_pointer
+            .as_deref()
+            .and_then(|p| extract_f64(&body, p));
+        let market_cap = self
+            .config
+            .market_cap_pointer
+            .as_deref()
+            .and_then(|p| extract_f64(&body, p));
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap,
+            volume_24h,
+            liquidity: None,
+            price_change_24h: None,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+/// Numbers are taken as-is; strings are parsed (many APIs quote decimals).
+fn extract_f64(body: &Value, pointer: &str) -> Option<f64> {
+    match body.pointer(pointer)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Replace `${VAR}` with the value of env var `VAR`; error if it is unset.
+fn interpolate_env(value: &str) -> Result<String> {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .with_context(|| format!("unterminated '${{' in '{}'", value))?;
+        let var = &after[..end];
+        let resolved = std::env::var(var).with_context(|| format!("env var {} not set", var))?;
+        out.push_str(&resolved);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use std::collections::HashMap;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(
+            "unit_index: 1\nname: HOT\nchain: ethereum\ncontract: '0x6c6ee5e31d828de241282b9606c8e98ea48526e2'",
+        )
+        .unwrap()
+    }
+
+    fn source(server: &MockServer, scale: Option<f64>) -> CustomRestSource {
+        let config = CustomSourceConfig {
+            name: "custom".to_string(),
+            url: format!("{}/price/{{chain}}/{{contract}}", server.uri()),
+            headers: HashMap::new(),
+            price_pointer: "/data/price".to_string(),
+            volume_pointer: Some("/data/volume".to_string()),
+            market_cap_pointer: Some("/data/mcap".to_string()),
+            scale,
+        };
+        CustomRestSource::new(HttpClient::new(reqwest::Client::new()), config).unwrap()
+    }
+
+    async fn serve(body: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/price/ethereum/0x6c6ee5e31d828de241282b9606c8e98ea48526e2",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn extracts_numbers_and_quoted_numbers() {
+        let server = serve(serde_json::json!({
+            "data": { "price": "0.00123", "volume": 5000, "mcap": "not a number" }
+        }))
+        .await;
+        let data = source(&server, None)
+            .fetch(&unit(), &SystemClock)
+            .await
+            .unwrap();
+        assert_eq!(data.price_usd, 0.00123);
+        assert_eq!(data.volume_24h, Some(5000.0));
+        assert_eq!(data.market_cap, None);
+        assert_eq!(data.source, "custom");
+    }
+
+    #[tokio::test]
+    async fn applies_scale_to_the_price() {
+        let server = serve(serde_json::json!({ "data": { "price": 1_230_000 } })).await;
+        let data = source(&server, Some(1e-6))
+            .fetch(&unit(), &SystemClock)
+            .await
+            .unwrap();
+        assert!((data.price_usd - 1.23).abs() < 1e-12);
+        assert_eq!(data.volume_24h, None);
+    }
+
+    #[tokio::test]
+    async fn missing_price_pointer_is_an_error() {
+        let server = serve(serde_json::json!({ "data": { "volume": 5000 } })).await;
+        let err = source(&server, None)
+            .fetch(&unit(), &SystemClock)
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("no numeric price at '/data/price'"),
+            "{:#}",
+            err
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use super::PriceSource;
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `DEXSCREENER_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.dexscreener.com";
+
+/// DexScreener token lookup; useful for new pools GeckoTerminal has not indexed.
+pub struct DexScreener {
+    client: HttpClient,
+    base_url: String,
+    chains: Arc<ChainRegistry>,
+}
+
+impl DexScreener {
+    pub fn new(client: HttpClient, base_url: String, chains: Arc<ChainRegistry>) -> Self {
+        Self {
+            client,
+            base_url,
+            chains,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for DexScreener {
+    fn name(&self) -> &str {
+        "dexscreener"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let chain = self.chains.id_for(&unit.chain, "dexscreener")?;
+        let url = format!(
+            "{}/latest/dex/tokens/{}",
+            self.base_url,
+            unit.lookup_contract()
+        );
+
+        let request = self.client.get(&url).header("Accept", "application/json");
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("DexScreener request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("DexScreener HTTP {}: {}", status, body);
+        }
+
+        let body: serde_json::Value = resp.json().context("DexScreener parse failed")?;
+        let pairs = body["pairs"].as_array().cloned().unwrap_or_default();
+        if pairs.is_empty() {
+            anyhow::bail!("DexScreener: no pairs for {}", unit.lookup_contract());
+        }
+
+        // `priceUsd` prices the pair's base token, so only pairs where our
+        // token is the base are usable.
+        let best = pairs
+            .iter()
+            .filter(|p| p["chainId"].as_str() == Some(chain.as_str()))
+            .filter(|p| {
+                p["baseToken"]["address"]
+                    .as_str()
+                    .is_some_and(|a| a.eq_ignore_ascii_case(unit.lookup_contract()))
+            })
+            .max_by(|a, b| liquidity_usd(a).total_cmp(&liquidity_usd(b)))
+            .with_context(|| format!("DexScreener: no pairs on chain {}", chain))?;
+
+        let price_usd = best["priceUsd"]
+            .as_str()
+            .and_then(|s| s.parse::<f64>().ok())
+            .context("DexScreener: missing priceUsd")?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: best["marketCap"].as_f64(),
+            volume_24h: best["volume"]["h24"].as_f64(),
+            liquidity: best["liquidity"]["usd"].as_f64(),
+            price_change_24h: best["priceChange"]["h24"].as_f64(),
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: best["baseToken"]["symbol"].as_str().map(str::to_string),
+            reported_name: best["baseToken"]["name"].as_str().map(str::to_string),
+        })
+    }
+}
+
+fn liquidity_usd(pair: &serde_json::Value) -> f64 {
+    pair["liquidity"]["usd"].as_f64().unwrap_or(0.0)
+}
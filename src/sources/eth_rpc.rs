@@ -0,0 +1,52 @@
+//! Minimal JSON-RPC `eth_call` plumbing shared by the on-chain sources.
+
+use crate::http::HttpClient;
+use anyhow::{Context, Result};
+
+/// `eth_call` against the latest block; returns the raw hex result.
+pub async fn eth_call(client: &HttpClient, rpc_url: &str, to: &str, data: &str) -> Result<String> {
+    let payload = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_call",
+        "params": [{ "to": to, "data": data }, "latest"],
+    });
+    let request = client.post(rpc_url).json(&payload);
+    let resp = client.send(request).await.context("eth_call failed")?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text();
+        anyhow::bail!("RPC HTTP {}: {}", status, body);
+    }
+
+    let body: serde_json::Value = resp.json().context("RPC parse failed")?;
+    if let Some(err) = body.get("error") {
+        anyhow::bail!("RPC error calling {}: {}", to, err);
+    }
+    body["result"]
+        .as_str()
+        .map(str::to_string)
+        .context("missing eth_call result")
+}
+
+/// The `index`-th 32-byte word of an ABI-encoded result.
+pub fn word(hex: &str, index: usize) -> Result<&str> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    hex.get(index * 64..(index + 1) * 64)
+        .with_context(|| format!("result has no word {} ({} hex chars)", index, hex.len()))
+}
+
+/// An unsigned 256-bit word that must fit in 128 bits.
+pub fn parse_word(word: &str) -> Result<u128> {
+    let (high, low) = word.split_at(32);
+    if high.chars().any(|c| c != '0') {
+        anyhow::bail!("value exceeds 128 bits");
+    }
+    u128::from_str_radix(low, 16).context("invalid hex")
+}
+
+/// Encode `value` as one ABI word.
+pub fn encode_word(value: u128) -> String {
+    format!("{:064x}", value)
+}
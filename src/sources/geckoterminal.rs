@@ -1,25 +1,125 @@
 use super::PriceSource;
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
 use crate::config::UnitConfig;
+use crate::http::HttpClient;
 use crate::types::TokenData;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use chrono::Utc;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `GECKOTERMINAL_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.geckoterminal.com";
 
 pub struct GeckoTerminal {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
+    chains: Arc<ChainRegistry>,
 }
 
 impl GeckoTerminal {
-    pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+    pub fn new(client: HttpClient, base_url: String, chains: Arc<ChainRegistry>) -> Self {
+        Self {
+            client,
+            base_url,
+            chains,
+        }
     }
 
-    fn network_id(chain: &str) -> &str {
-        match chain {
-            "ethereum" => "eth",
-            "sepolia" => "eth",
-            _ => chain,
+    async fn get(&self, url: &str) -> Result<serde_json::Value> {
+        let request = self.client.get(url).header("Accept", "application/json");
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("GeckoTerminal request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("GeckoTerminal HTTP {}: {}", status, body);
         }
+        resp.json().context("GeckoTerminal parse failed")
+    }
+
+    /// Price from one pool: the USD price of whichever side `contract` is.
+    async fn fetch_pool(
+        &self,
+        unit: &UnitConfig,
+        network: &str,
+        pool: &str,
+        clock: &dyn Clock,
+    ) -> Result<TokenData> {
+        let url = format!(
+            "{}/api/v2/networks/{}/pools/{}",
+            self.base_url, network, pool
+        );
+        let body = self.get(&url).await?;
+        let attrs = &body["data"]["attributes"];
+        let contract = unit.lookup_contract();
+
+        let side = pool_side(&body["data"]["relationships"], contract).with_context(|| {
+            format!(
+                "GeckoTerminal pool {}: {} is neither of its tokens",
+                pool, contract
+            )
+        })?;
+        let price_key = match side {
+            PoolSide::Base => "base_token_price_usd",
+            PoolSide::Quote => "quote_token_price_usd",
+        };
+        let price_usd = parse_string_f64(attrs, price_key)
+            .with_context(|| format!("GeckoTerminal: missing {}", price_key))?;
+
+        // The pool's change is the base token's; it says nothing about the quote side.
+        let price_change_24h = match side {
+            PoolSide::Base => attrs["price_change_percentage"]
+                .get("h24")
+                .and_then(|v| v.as_str())
+                .and_then(|s| s.parse::<f64>().ok()),
+            PoolSide::Quote => None,
+        };
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: parse_h24_volume(attrs),
+            liquidity: parse_optional_string_f64(attrs, "reserve_in_usd"),
+            price_change_24h,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PoolSide {
+    Base,
+    Quote,
+}
+
+/// Which of the pool's tokens is `contract`. Token ids look like
+/// `eth_0xc02a...`: network, underscore, address.
+fn pool_side(relationships: &serde_json::Value, contract: &str) -> Option<PoolSide> {
+    let matches = |token: &str| {
+        relationships[token]["data"]["id"]
+            .as_str()
+            .and_then(|id| id.rsplit_once('_'))
+            .is_some_and(|(_, address)| address.eq_ignore_ascii_case(contract))
+    };
+    if contract.is_empty() {
+        None
+    } else if matches("base_token") {
+        Some(PoolSide::Base)
+    } else if matches("quote_token") {
+        Some(PoolSide::Quote)
+    } else {
+        None
     }
 }
 
@@ -29,37 +129,26 @@ impl PriceSource for GeckoTerminal {
         "geckoterminal"
     }
 
-    async fn fetch(&self, unit: &UnitConfig) -> Result<TokenData> {
-        let network = Self::network_id(&unit.chain);
+    /// The token's blended price, or with `geckoterminal_pool` the price in
+    /// that one pool.
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let network = self.chains.id_for(&unit.chain, "geckoterminal")?;
+        if let Some(pool) = unit.source_id("geckoterminal") {
+            return self.fetch_pool(unit, &network, pool, clock).await;
+        }
         let url = format!(
-            "https://api.geckoterminal.com/api/v2/networks/{}/tokens/{}",
-            network, unit.contract
+            "{}/api/v2/networks/{}/tokens/{}",
+            self.base_url,
+            network,
+            unit.lookup_contract()
         );
-
-        let resp = self
-            .client
-            .get(&url)
-            .header("Accept", "application/json")
-            .send()
-            .await
-            .context("GeckoTerminal request failed")?;
-
-        let status = resp.status();
-        if !status.is_success() {
-            let body = resp.text().await.unwrap_or_default();
-            anyhow::bail!("GeckoTerminal HTTP {}: {}", status, body);
-        }
-
-        let body: serde_json::Value = resp.json().await.context("GeckoTerminal parse failed")?;
+        let body = self.get(&url).await?;
         let attrs = &body["data"]["attributes"];
 
         let price_usd =
             parse_string_f64(attrs, "price_usd").context("GeckoTerminal: missing price_usd")?;
 
-        let volume_24h = attrs["volume_usd"]
-            .get("h24")
-            .and_then(|v| v.as_str())
-            .and_then(|s| s.parse::<f64>().ok());
+        let volume_24h = parse_h24_volume(attrs);
         let liquidity = parse_optional_string_f64(attrs, "total_reserve_in_usd");
         let market_cap = parse_optional_string_f64(attrs, "market_cap_usd");
 
@@ -73,11 +162,20 @@ impl PriceSource for GeckoTerminal {
             liquidity,
             price_change_24h: None,
             source: self.name().to_string(),
-            timestamp: Utc::now(),
+            timestamp: clock.now(),
+            reported_symbol: attrs["symbol"].as_str().map(str::to_string),
+            reported_name: attrs["name"].as_str().map(str::to_string),
         })
     }
 }
 
+fn parse_h24_volume(attrs: &serde_json::Value) -> Option<f64> {
+    attrs["volume_usd"]
+        .get("h24")
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.parse::<f64>().ok())
+}
+
 fn parse_string_f64(obj: &serde_json::Value, key: &str) -> Option<f64> {
     obj.get(key)
         .and_then(|v| v.as_str())
@@ -93,3 +191,104 @@ fn parse_optional_string_f64(obj: &serde_json::Value, key: &str) -> Option<f64>
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const POOL: &str = "0x8e0b1a7e1a9e6d8e2f3c0a4b5c6d7e8f9a0b1c2d";
+
+    fn unit(name: &str, contract: &str) -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: {}\nchain: ethereum\ncontract: '{}'\ngeckoterminal_pool: '{}'",
+            name, contract, POOL
+        ))
+        .unwrap()
+    }
+
+    /// A HOT/WETH pool: HOT is the base token, WETH the quote token.
+    async fn serve() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/v2/networks/eth/pools/{}", POOL)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "data": {
+                    "id": format!("eth_{}", POOL),
+                    "type": "pool",
+                    "attributes": {
+                        "name": "HOT / WETH",
+                        "address": POOL,
+                        "base_token_price_usd": "0.00123",
+                        "quote_token_price_usd": "2500.5",
+                        "base_token_price_quote_token": "0.000000492",
+                        "price_change_percentage": { "h1": "0.1", "h24": "-2.5" },
+                        "volume_usd": { "h1": "1000.0", "h24": "250000.75" },
+                        "reserve_in_usd": "1523456.78"
+                    },
+                    "relationships": {
+                        "base_token": { "data": { "id": format!("eth_{}", HOT), "type": "token" } },
+                        "quote_token": { "data": { "id": "eth_0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2", "type": "token" } },
+                        "dex": { "data": { "id": "uniswap_v2", "type": "dex" } }
+                    }
+                }
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn fetch(server: &MockServer, unit: &UnitConfig) -> Result<TokenData> {
+        GeckoTerminal::new(
+            HttpClient::new(reqwest::Client::new()),
+            server.uri(),
+            Arc::new(ChainRegistry::builtin()),
+        )
+        .fetch(unit, &SystemClock)
+        .await
+    }
+
+    #[tokio::test]
+    async fn base_token_takes_the_base_price_and_change() {
+        let server = serve().await;
+
+        let data = fetch(&server, &unit("HOT", HOT)).await.unwrap();
+
+        assert_eq!(data.price_usd, 0.00123);
+        assert_eq!(data.price_change_24h, Some(-2.5));
+        assert_eq!(data.volume_24h, Some(250_000.75));
+        assert_eq!(data.liquidity, Some(1_523_456.78));
+    }
+
+    #[tokio::test]
+    async fn quote_token_takes_the_quote_price_without_change() {
+        let server = serve().await;
+
+        // The id's checksummed address still matches.
+        let data = fetch(&server, &unit("WETH", WETH)).await.unwrap();
+
+        assert_eq!(data.price_usd, 2500.5);
+        assert_eq!(data.price_change_24h, None);
+        assert_eq!(data.liquidity, Some(1_523_456.78));
+    }
+
+    #[tokio::test]
+    async fn token_outside_the_pool_is_an_error() {
+        let server = serve().await;
+        let usdc = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+        let err = fetch(&server, &unit("USDC", usdc)).await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "GeckoTerminal pool {}: {} is neither of its tokens",
+                POOL, usdc
+            )
+        );
+    }
+}
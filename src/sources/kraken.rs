@@ -0,0 +1,189 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Production API root; `source_endpoints` or `KRAKEN_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.kraken.com";
+
+/// Kraken public Ticker; pair-based, so only units with `kraken_pair` are priced.
+pub struct Kraken {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Kraken {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl PriceSource for Kraken {
+    fn name(&self) -> &str {
+        "kraken"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let pair = unit.source_id("kraken").ok_or_else(|| {
+            NotConfigured(format!("kraken_pair not configured for '{}'", unit.name))
+        })?;
+
+        let request = self
+            .client
+            .get(&format!("{}/0/public/Ticker", self.base_url))
+            .query(&[("pair", pair)]);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Kraken request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("Kraken HTTP {}: {}", status, body);
+        }
+
+        let body: serde_json::Value = resp.json().context("Kraken parse failed")?;
+        if let Some(errors) = body["error"].as_array().filter(|e| !e.is_empty()) {
+            anyhow::bail!("Kraken error for pair {}: {:?}", pair, errors);
+        }
+
+        // The result is keyed by Kraken's canonical pair name (e.g. XXBTZUSD
+        // for XBTUSD), so take the single entry rather than looking up `pair`.
+        let ticker = body["result"]
+            .as_object()
+            .and_then(|r| r.values().next())
+            .with_context(|| format!("Kraken: no ticker for pair {}", pair))?;
+
+        // c = [last price, lot volume]; v = [today, last 24h]; o = today's open.
+        let price_usd =
+            parse_index_f64(ticker, "c", 0).context("Kraken: missing last trade price")?;
+        let volume_24h = parse_index_f64(ticker, "v", 1).map(|base| base * price_usd);
+        let price_change_24h = ticker["o"]
+            .as_str()
+            .and_then(|s| s.parse::<f64>().ok())
+            .filter(|open| *open > 0.0)
+            .map(|open| (price_usd - open) / open * 100.0);
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h,
+            liquidity: None,
+            price_change_24h,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+fn parse_index_f64(obj: &serde_json::Value, key: &str, index: usize) -> Option<f64> {
+    obj.get(key)
+        .and_then(|v| v.get(index))
+        .and_then(|v| v.as_str())
+        .and_then(|s| s.parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit(kraken_pair: Option<&str>) -> UnitConfig {
+        let mut yaml = "unit_index: 1\nname: ETH\nchain: ethereum\nnative: true".to_string();
+        if let Some(pair) = kraken_pair {
+            yaml.push_str(&format!("\nkraken_pair: {}", pair));
+        }
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    async fn serve(body: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/0/public/Ticker"))
+            .and(query_param("pair", "ETHUSD"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn source(server: &MockServer) -> Kraken {
+        Kraken::new(HttpClient::new(reqwest::Client::new()), server.uri())
+    }
+
+    #[tokio::test]
+    async fn parses_the_ticker_under_its_canonical_pair_name() {
+        // Trimmed from a live /0/public/Ticker?pair=ETHUSD response.
+        let server = serve(serde_json::json!({
+            "error": [],
+            "result": {
+                "XETHZUSD": {
+                    "a": ["2500.01000", "1", "1.000"],
+                    "b": ["2500.00000", "3", "3.000"],
+                    "c": ["2500.00000", "0.05000000"],
+                    "v": ["1200.50000000", "4000.00000000"],
+                    "p": ["2490.12345", "2480.54321"],
+                    "t": [9000, 31000],
+                    "l": ["2450.00000", "2440.00000"],
+                    "h": ["2510.00000", "2520.00000"],
+                    "o": "2000.00000"
+                }
+            }
+        }))
+        .await;
+
+        let data = source(&server)
+            .fetch(&unit(Some("ETHUSD")), &SystemClock)
+            .await
+            .unwrap();
+
+        assert_eq!(data.price_usd, 2500.0);
+        // v[1] is the 24h volume in ETH; it is reported in USD.
+        assert_eq!(data.volume_24h, Some(10_000_000.0));
+        assert_eq!(data.price_change_24h, Some(25.0));
+        assert_eq!(data.source, "kraken");
+    }
+
+    #[tokio::test]
+    async fn error_envelope_is_an_error() {
+        let server = serve(serde_json::json!({
+            "error": ["EQuery:Unknown asset pair"],
+            "result": {}
+        }))
+        .await;
+
+        let err = source(&server)
+            .fetch(&unit(Some("ETHUSD")), &SystemClock)
+            .await
+            .unwrap_err();
+
+        assert!(
+            err.to_string().contains("EQuery:Unknown asset pair"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn units_without_a_pair_are_not_configured() {
+        let server = MockServer::start().await;
+        let err = source(&server)
+            .fetch(&unit(None), &SystemClock)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<NotConfigured>().is_some(), "{:#}", err);
+    }
+}
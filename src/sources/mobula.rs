@@ -0,0 +1,96 @@
+use super::PriceSource;
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `MOBULA_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.mobula.io";
+
+/// Mobula market data, looked up by contract and blockchain.
+pub struct Mobula {
+    client: HttpClient,
+    base_url: String,
+    api_key: String,
+    chains: Arc<ChainRegistry>,
+}
+
+impl Mobula {
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        api_key: String,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            chains,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for Mobula {
+    fn name(&self) -> &str {
+        "mobula"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let blockchain = self.chains.id_for(&unit.chain, "mobula")?;
+        let url = format!("{}/api/1/market/data", self.base_url);
+        let request = self
+            .client
+            .get(&url)
+            .query(&[
+                ("asset", unit.lookup_contract()),
+                ("blockchain", blockchain.as_str()),
+            ])
+            .header("Accept", "application/json")
+            .header("Authorization", &self.api_key);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Mobula request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let envelope = resp.json::<Value>().ok();
+            match envelope.as_ref().and_then(|v| v["error"].as_str()) {
+                Some(message) => anyhow::bail!("Mobula HTTP {}: {}", status, message),
+                None => anyhow::bail!("Mobula HTTP {}: {}", status, resp.text()),
+            }
+        }
+
+        let body: Value = resp.json().context("Mobula parse failed")?;
+        if let Some(message) = body["error"].as_str() {
+            anyhow::bail!("Mobula error: {}", message);
+        }
+        let data = &body["data"];
+        let price_usd = data["price"]
+            .as_f64()
+            .context("Mobula: missing data.price")?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: data["market_cap"].as_f64(),
+            volume_24h: data["volume"].as_f64(),
+            liquidity: data["liquidity"].as_f64(),
+            price_change_24h: data["price_change_24h"].as_f64(),
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: data["symbol"].as_str().map(str::to_string),
+            reported_name: data["name"].as_str().map(str::to_string),
+        })
+    }
+}
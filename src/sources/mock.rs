@@ -0,0 +1,108 @@
+//! Scripted price source for exercising the pipeline without network access.
+//! Built for tests and with the `testing` feature.
+
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::types::TokenData;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::Duration;
+
+enum Scripted {
+    Price(f64),
+    Data(Box<TokenData>),
+    Error(String),
+}
+
+/// Returns a scripted price, `TokenData`, or error per `unit_index`. Units
+/// with nothing scripted are skipped like an unconfigured source.
+pub struct MockSource {
+    name: String,
+    quote_currency: String,
+    responses: HashMap<u32, Scripted>,
+    delay: Duration,
+}
+
+impl MockSource {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            quote_currency: "USD".to_string(),
+            responses: HashMap::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Quote prices in `currency` (e.g. `USDT`) instead of USD.
+    pub fn quoted_in(mut self, currency: &str) -> Self {
+        self.quote_currency = currency.to_string();
+        self
+    }
+
+    /// Price `unit_index` at `price_usd`, stamped with the fetch clock.
+    pub fn with_price(mut self, unit_index: u32, price_usd: f64) -> Self {
+        self.responses
+            .insert(unit_index, Scripted::Price(price_usd));
+        self
+    }
+
+    /// Return `data` as-is for `unit_index`.
+    pub fn with_token_data(mut self, unit_index: u32, data: TokenData) -> Self {
+        self.responses
+            .insert(unit_index, Scripted::Data(Box::new(data)));
+        self
+    }
+
+    /// Sleep this long before answering each fetch, like a slow API.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Fail every fetch of `unit_index` with `message`.
+    pub fn with_error(mut self, unit_index: u32, message: &str) -> Self {
+        self.responses
+            .insert(unit_index, Scripted::Error(message.to_string()));
+        self
+    }
+}
+
+#[async_trait]
+impl PriceSource for MockSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn quote_currency(&self) -> &str {
+        &self.quote_currency
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        tokio::time::sleep(self.delay).await;
+        match self.responses.get(&unit.unit_index) {
+            Some(Scripted::Price(price_usd)) => Ok(TokenData {
+                name: unit.name.clone(),
+                chain: unit.chain.clone(),
+                contract: unit.contract.clone(),
+                price_usd: *price_usd,
+                market_cap: None,
+                volume_24h: None,
+                liquidity: None,
+                price_change_24h: None,
+                source: self.name.clone(),
+                timestamp: clock.now(),
+                reported_symbol: None,
+                reported_name: None,
+            }),
+            Some(Scripted::Data(data)) => Ok(data.as_ref().clone()),
+            Some(Scripted::Error(message)) => anyhow::bail!("{}", message),
+            None => Err(NotConfigured(format!(
+                "{} has no scripted response for '{}'",
+                self.name, unit.name
+            ))
+            .into()),
+        }
+    }
+}
@@ -1,57 +1,769 @@
+pub mod bybit;
+pub mod chainlink;
+pub mod coinbase;
 pub mod coingecko;
 pub mod coinmarketcap;
+pub mod coinpaprika;
+pub mod curve;
+pub mod custom_rest;
+pub mod dexscreener;
+mod eth_rpc;
 pub mod geckoterminal;
+pub mod kraken;
+pub mod mobula;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+pub mod moralis;
+pub mod okx;
+pub mod oneinch;
+pub mod pyth;
 
-use crate::config::UnitConfig;
-use crate::types::TokenData;
+use crate::chains::ChainRegistry;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, CustomSourceConfig, UnitConfig};
+use crate::http::HttpClient;
+use crate::types::{SourceResult, TokenData};
 use anyhow::Result;
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::Instrument;
 
 #[async_trait]
 pub trait PriceSource: Send + Sync {
     fn name(&self) -> &str;
-    async fn fetch(&self, unit: &UnitConfig) -> Result<TokenData>;
+    /// `clock` supplies the fetch timestamp when the API payload has none.
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData>;
+    /// Currency `fetch` prices are quoted in. `USDT` quotes are converted
+    /// through the `USDT` price reference when one is configured.
+    fn quote_currency(&self) -> &str {
+        "USD"
+    }
+    /// Fetch several units, returning one result per unit keyed by
+    /// `unit_index`. The default calls `fetch` for each unit in turn.
+    async fn fetch_many(
+        &self,
+        units: &[&UnitConfig],
+        clock: &dyn Clock,
+    ) -> Vec<(u32, Result<TokenData>)> {
+        let mut results = Vec::with_capacity(units.len());
+        for unit in units {
+            results.push((unit.unit_index, self.fetch(unit, clock).await));
+        }
+        results
+    }
+    /// True if `fetch_many` covers many units in fewer requests than
+    /// calling `fetch` per unit; `fetch_all_units` then uses it.
+    fn batches(&self) -> bool {
+        false
+    }
+}
+
+pub const DEFAULT_SOURCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Names of the compiled-in sources; custom sources may not reuse them.
+pub const BUILTIN_SOURCE_NAMES: &[&str] = &[
+    "geckoterminal",
+    "coingecko",
+    "coinmarketcap",
+    "kraken",
+    "coinbase",
+    "dexscreener",
+    "coinpaprika",
+    "chainlink",
+    "pyth",
+    "mobula",
+    "moralis",
+    "okx",
+    "curve",
+    "1inch",
+    "bybit",
+];
+
+/// Error for a source that has nothing to query for a unit (e.g. no trading
+/// pair configured). The run skips it instead of counting a failure.
+#[derive(Debug)]
+pub struct NotConfigured(pub String);
+
+impl std::fmt::Display for NotConfigured {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NotConfigured {}
+
+/// True if `result` failed only because the source does not apply to the unit.
+pub fn is_not_configured<T>(result: &Result<T>) -> bool {
+    matches!(result, Err(e) if e.downcast_ref::<NotConfigured>().is_some())
 }
 
 pub struct SourceRegistry {
     sources: Vec<Box<dyn PriceSource>>,
+    clock: Arc<dyn Clock>,
+    /// Per-source limit for one `fetch`.
+    timeout: Duration,
 }
 
 impl SourceRegistry {
-    pub fn new(
-        client: reqwest::Client,
-        coingecko_api_key: Option<String>,
-        coinmarketcap_api_key: Option<String>,
-    ) -> Self {
-        let mut sources: Vec<Box<dyn PriceSource>> =
-            vec![Box::new(geckoterminal::GeckoTerminal::new(client.clone()))];
+    pub fn builder(client: HttpClient) -> SourceRegistryBuilder {
+        SourceRegistryBuilder {
+            client,
+            endpoints: HashMap::new(),
+            request_timeouts: HashMap::new(),
+            chains: Arc::new(ChainRegistry::builtin()),
+            registry: SourceRegistry {
+                sources: Vec::new(),
+                clock: Arc::new(SystemClock),
+                timeout: DEFAULT_SOURCE_TIMEOUT,
+            },
+        }
+    }
+
+    /// Config/env-driven registry used by `main`: keyless sources always, key-gated
+    /// sources when their env var is set, plus every `custom_sources` entry.
+    pub fn from_config(client: HttpClient, cfg: &Config, clock: Arc<dyn Clock>) -> Self {
+        let mut builder = Self::builder(client)
+            .with_clock(clock)
+            .with_timeout(Duration::from_secs(cfg.source_timeout_secs))
+            .with_endpoints(cfg.source_endpoints.clone())
+            .with_request_timeouts(cfg.http.source_timeouts.clone())
+            .with_chains(Arc::new(cfg.chain_registry()))
+            .with_geckoterminal()
+            .with_dexscreener()
+            .with_kraken()
+            .with_coinbase()
+            .with_okx()
+            .with_bybit();
 
-        if let Some(key) = coingecko_api_key {
-            sources.push(Box::new(coingecko::CoinGecko::new(client.clone(), key)));
-        } else {
-            tracing::warn!("COINGECKO_API_KEY not set; CoinGecko source disabled");
+        match std::env::var("COINGECKO_API_KEY") {
+            Ok(key) => {
+                let rate_limit = match std::env::var("COINGECKO_RATE_LIMIT") {
+                    Ok(v) => match v.parse::<u32>() {
+                        Ok(n) if n > 0 => n,
+                        _ => {
+                            tracing::warn!(
+                                "Invalid COINGECKO_RATE_LIMIT {:?}; using {} calls/minute",
+                                v,
+                                coingecko::DEFAULT_RATE_LIMIT
+                            );
+                            coingecko::DEFAULT_RATE_LIMIT
+                        }
+                    },
+                    Err(_) => coingecko::DEFAULT_RATE_LIMIT,
+                };
+                builder = builder.with_coingecko(key, rate_limit)
+            }
+            Err(_) => tracing::warn!("COINGECKO_API_KEY not set; CoinGecko source disabled"),
         }
 
-        if let Some(key) = coinmarketcap_api_key {
-            sources.push(Box::new(coinmarketcap::CoinMarketCap::new(client, key)));
-        } else {
-            tracing::warn!("COINMARKETCAP_API_KEY not set; CoinMarketCap source disabled");
+        match std::env::var("COINMARKETCAP_API_KEY") {
+            Ok(key) => builder = builder.with_coinmarketcap(key),
+            Err(_) => {
+                tracing::warn!("COINMARKETCAP_API_KEY not set; CoinMarketCap source disabled")
+            }
         }
 
-        Self { sources }
+        match std::env::var("MOBULA_API_KEY") {
+            Ok(key) => builder = builder.with_mobula(key),
+            Err(_) => tracing::warn!("MOBULA_API_KEY not set; Mobula source disabled"),
+        }
+
+        match std::env::var("MORALIS_API_KEY") {
+            Ok(key) => builder = builder.with_moralis(key),
+            Err(_) => tracing::warn!("MORALIS_API_KEY not set; Moralis source disabled"),
+        }
+
+        match std::env::var("ONEINCH_API_KEY") {
+            Ok(key) => builder = builder.with_oneinch(key),
+            Err(_) => tracing::warn!("ONEINCH_API_KEY not set; 1inch source disabled"),
+        }
+
+        let eth_rpc_url = std::env::var("ETH_RPC_URL").ok();
+        match &eth_rpc_url {
+            Some(url) => builder = builder.with_chainlink(url.clone(), cfg.chainlink_max_age_secs),
+            None => tracing::warn!("ETH_RPC_URL not set; Chainlink source disabled"),
+        }
+
+        let wants = |source: &str| {
+            cfg.units.iter().any(|u| u.source_id(source).is_some())
+                || cfg
+                    .price_references
+                    .iter()
+                    .any(|r| r.to_unit_config_for_fetch().source_id(source).is_some())
+        };
+        if wants("coinpaprika") {
+            builder = builder.with_coinpaprika();
+        }
+
+        if wants("curve") {
+            builder = builder.with_curve(eth_rpc_url);
+        }
+
+        if wants("pyth") {
+            builder = builder.with_pyth(cfg.pyth_max_age_secs, cfg.pyth_max_conf_ratio);
+        }
+
+        builder.with_custom_sources(&cfg.custom_sources).build()
     }
 
-    pub async fn fetch_all(&self, unit: &UnitConfig) -> Vec<(String, Result<TokenData>)> {
-        let mut results = Vec::new();
-        for source in &self.sources {
-            let name = source.name().to_string();
-            let result = source.fetch(unit).await;
-            results.push((name, result));
+    /// Registry over exactly `sources`, on the system clock with the default
+    /// timeout. Use [`SourceRegistry::builder`] to change either.
+    pub fn with_sources(sources: Vec<Box<dyn PriceSource>>) -> Self {
+        Self {
+            sources,
+            clock: Arc::new(SystemClock),
+            timeout: DEFAULT_SOURCE_TIMEOUT,
         }
-        results
+    }
+
+    /// Add a source after the built-in ones; it is queried like any other.
+    pub fn register(&mut self, source: Box<dyn PriceSource>) {
+        self.sources.push(source);
+    }
+
+    /// Quote currency of the named source (`USD` for unknown names).
+    pub fn quote_currency(&self, source: &str) -> &str {
+        self.sources
+            .iter()
+            .find(|s| s.name() == source)
+            .map_or("USD", |s| s.quote_currency())
+    }
+
+    /// Query every source (or only those named in `unit.sources`)
+    /// concurrently; results keep registration order. A source slower than
+    /// the registry timeout yields an `Err`.
+    pub async fn fetch_all(&self, unit: &UnitConfig) -> Vec<SourceResult<TokenData>> {
+        let fetches = self
+            .sources
+            .iter()
+            .filter(|source| selects(unit, source.as_ref()))
+            .map(|source| self.fetch_one(source.as_ref(), unit));
+        futures::future::join_all(fetches).await
+    }
+
+    /// `fetch_all` for every unit at once, returned in `units` order. Sources
+    /// that batch get one `fetch_many` call for all their units; the rest are
+    /// queried per unit with up to `concurrency` units in flight.
+    pub async fn fetch_all_units(
+        &self,
+        units: &[&UnitConfig],
+        concurrency: usize,
+    ) -> Vec<Vec<SourceResult<TokenData>>> {
+        let batched = self
+            .sources
+            .iter()
+            .enumerate()
+            .filter(|(_, source)| source.batches())
+            .map(|(i, source)| async move {
+                let selected: Vec<&UnitConfig> = units
+                    .iter()
+                    .copied()
+                    .filter(|unit| selects(unit, source.as_ref()))
+                    .collect();
+                (i, self.fetch_batch(source.as_ref(), &selected).await)
+            });
+        let per_unit = stream::iter(units.iter().enumerate())
+            .map(|(pos, unit)| async move {
+                let fetches =
+                    self.sources
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, source)| !source.batches() && selects(unit, source.as_ref()))
+                        .map(|(i, source)| async move {
+                            (i, self.fetch_one(source.as_ref(), unit).await)
+                        });
+                (pos, futures::future::join_all(fetches).await)
+            })
+            .buffer_unordered(concurrency.max(1))
+            .collect::<Vec<_>>();
+        let (batched, per_unit) = tokio::join!(futures::future::join_all(batched), per_unit);
+
+        let mut by_unit: Vec<Vec<(usize, SourceResult<TokenData>)>> =
+            units.iter().map(|_| Vec::new()).collect();
+        for (pos, fetched) in per_unit {
+            by_unit[pos].extend(fetched);
+        }
+        for (i, fetched) in batched {
+            for (unit_index, fetch) in fetched {
+                if let Some(pos) = units.iter().position(|u| u.unit_index == unit_index) {
+                    by_unit[pos].push((i, fetch));
+                }
+            }
+        }
+        by_unit
+            .into_iter()
+            .map(|mut fetched| {
+                fetched.sort_by_key(|(i, _)| *i);
+                fetched.into_iter().map(|(_, fetch)| fetch).collect()
+            })
+            .collect()
+    }
+
+    async fn fetch_one(
+        &self,
+        source: &dyn PriceSource,
+        unit: &UnitConfig,
+    ) -> SourceResult<TokenData> {
+        let key = unit.key();
+        let span = tracing::info_span!(
+            "source_fetch",
+            source = %source.name(),
+            unit_index = key.index(),
+            reference = key.reference(),
+            price = tracing::field::Empty,
+        );
+        async move {
+            let started = self.clock.now();
+            let fetch = source.fetch(unit, self.clock.as_ref());
+            let result = match tokio::time::timeout(self.timeout, fetch).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow::anyhow!(
+                    "timed out after {}s",
+                    self.timeout.as_secs()
+                )),
+            };
+            if let Ok(data) = &result {
+                tracing::Span::current().record("price", data.price_usd);
+            }
+            SourceResult {
+                source: source.name().to_string(),
+                result,
+                elapsed_ms: (self.clock.now() - started).num_milliseconds(),
+            }
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// One `fetch_many` call under the registry timeout. Every unit gets a
+    /// result; units the source left out come back as errors.
+    async fn fetch_batch(
+        &self,
+        source: &dyn PriceSource,
+        units: &[&UnitConfig],
+    ) -> Vec<(u32, SourceResult<TokenData>)> {
+        if units.is_empty() {
+            return Vec::new();
+        }
+        let span =
+            tracing::info_span!("source_fetch", source = %source.name(), units = units.len());
+        let started = self.clock.now();
+        let fetch = source.fetch_many(units, self.clock.as_ref());
+        let mut results = tokio::time::timeout(self.timeout, fetch)
+            .instrument(span)
+            .await
+            .ok();
+        let elapsed_ms = (self.clock.now() - started).num_milliseconds();
+        units
+            .iter()
+            .map(|unit| {
+                let result = match &mut results {
+                    None => Err(anyhow::anyhow!(
+                        "timed out after {}s",
+                        self.timeout.as_secs()
+                    )),
+                    Some(results) => {
+                        match results.iter().position(|(i, _)| *i == unit.unit_index) {
+                            Some(pos) => results.swap_remove(pos).1,
+                            None => Err(anyhow::anyhow!("no result for unit {}", unit.unit_index)),
+                        }
+                    }
+                };
+                let fetch = SourceResult {
+                    source: source.name().to_string(),
+                    result,
+                    elapsed_ms,
+                };
+                (unit.unit_index, fetch)
+            })
+            .collect()
     }
 
     pub fn source_count(&self) -> usize {
         self.sources.len()
     }
 }
+
+/// Base URL for `source`: the `env` variable if set, else its
+/// `source_endpoints` entry, else the production `default`.
+pub fn endpoint(
+    endpoints: &HashMap<String, String>,
+    source: &str,
+    env: &str,
+    default: &str,
+) -> String {
+    let url = std::env::var(env)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .or_else(|| endpoints.get(source).cloned())
+        .unwrap_or_else(|| default.to_string());
+    if url != default {
+        tracing::info!("{} base URL overridden: {}", source, url);
+    }
+    url.trim_end_matches('/').to_string()
+}
+
+/// `client` tagged with `source`, with its request timeout override applied
+/// if it has one.
+pub fn client_for(
+    client: &HttpClient,
+    timeouts: &HashMap<String, u64>,
+    source: &str,
+) -> HttpClient {
+    let client = client.clone().for_source(source);
+    match timeouts.get(source) {
+        Some(secs) => client.with_timeout(Duration::from_secs(*secs)),
+        None => client,
+    }
+}
+
+/// True unless the unit's `sources` list leaves this source out.
+fn selects(unit: &UnitConfig, source: &dyn PriceSource) -> bool {
+    unit.sources
+        .as_ref()
+        .is_none_or(|names| names.iter().any(|n| n == source.name()))
+}
+
+pub struct SourceRegistryBuilder {
+    client: HttpClient,
+    /// Base URL overrides by source name (`source_endpoints`).
+    endpoints: HashMap<String, String>,
+    /// Request timeout overrides in seconds by source name.
+    request_timeouts: HashMap<String, u64>,
+    /// Chain ids for sources added after `with_chains`.
+    chains: Arc<ChainRegistry>,
+    registry: SourceRegistry,
+}
+
+impl SourceRegistryBuilder {
+    pub fn with(mut self, source: Box<dyn PriceSource>) -> Self {
+        self.registry.register(source);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.registry.clock = clock;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.registry.timeout = timeout;
+        self
+    }
+
+    /// Base URLs for sources added after this call, keyed by source name.
+    pub fn with_endpoints(mut self, endpoints: HashMap<String, String>) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// HTTP request timeouts in seconds for sources added after this call,
+    /// keyed by source name; others keep the client's timeout.
+    pub fn with_request_timeouts(mut self, timeouts: HashMap<String, u64>) -> Self {
+        self.request_timeouts = timeouts;
+        self
+    }
+
+    /// Chain table for sources added after this call; the built-in one
+    /// otherwise.
+    pub fn with_chains(mut self, chains: Arc<ChainRegistry>) -> Self {
+        self.chains = chains;
+        self
+    }
+
+    pub fn with_geckoterminal(self) -> Self {
+        let source = geckoterminal::GeckoTerminal::new(
+            self.client_for("geckoterminal"),
+            self.base_url(
+                "geckoterminal",
+                "GECKOTERMINAL_BASE_URL",
+                geckoterminal::DEFAULT_BASE_URL,
+            ),
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_dexscreener(self) -> Self {
+        let source = dexscreener::DexScreener::new(
+            self.client_for("dexscreener"),
+            self.base_url(
+                "dexscreener",
+                "DEXSCREENER_BASE_URL",
+                dexscreener::DEFAULT_BASE_URL,
+            ),
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_kraken(self) -> Self {
+        let source = kraken::Kraken::new(
+            self.client_for("kraken"),
+            self.base_url("kraken", "KRAKEN_BASE_URL", kraken::DEFAULT_BASE_URL),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_mobula(self, api_key: String) -> Self {
+        let source = mobula::Mobula::new(
+            self.client_for("mobula"),
+            self.base_url("mobula", "MOBULA_BASE_URL", mobula::DEFAULT_BASE_URL),
+            api_key,
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_moralis(self, api_key: String) -> Self {
+        let source = moralis::Moralis::new(
+            self.client_for("moralis"),
+            self.base_url("moralis", "MORALIS_BASE_URL", moralis::DEFAULT_BASE_URL),
+            api_key,
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    /// Curve pools; `rpc_url` enables on-chain `get_dy` pricing for mainnet
+    /// pools. Its path is kept out of logs and recordings.
+    pub fn with_curve(self, rpc_url: Option<String>) -> Self {
+        let client = match &rpc_url {
+            Some(url) => self.client_for("curve").with_secret_url(url),
+            None => self.client_for("curve"),
+        };
+        let source = curve::Curve::new(
+            client,
+            self.base_url("curve", "CURVE_BASE_URL", curve::DEFAULT_BASE_URL),
+            rpc_url,
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_oneinch(self, api_key: String) -> Self {
+        let source = oneinch::OneInch::new(
+            self.client_for("1inch"),
+            self.base_url("1inch", "ONEINCH_BASE_URL", oneinch::DEFAULT_BASE_URL),
+            api_key,
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_pyth(self, max_age_secs: u64, max_conf_ratio: f64) -> Self {
+        let source = pyth::Pyth::new(
+            self.client_for("pyth"),
+            self.base_url("pyth", "PYTH_BASE_URL", pyth::DEFAULT_BASE_URL),
+            max_age_secs,
+            max_conf_ratio,
+        );
+        self.with(Box::new(source))
+    }
+
+    /// Chainlink feeds read via `eth_call` to `rpc_url`, whose path is kept
+    /// out of logs and recordings.
+    pub fn with_chainlink(self, rpc_url: String, max_age_secs: u64) -> Self {
+        let client = self.client_for("chainlink").with_secret_url(&rpc_url);
+        let source = chainlink::Chainlink::new(client, rpc_url, max_age_secs);
+        self.with(Box::new(source))
+    }
+
+    pub fn with_okx(self) -> Self {
+        let source = okx::Okx::new(
+            self.client_for("okx"),
+            self.base_url("okx", "OKX_BASE_URL", okx::DEFAULT_BASE_URL),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_bybit(self) -> Self {
+        let source = bybit::Bybit::new(
+            self.client_for("bybit"),
+            self.base_url("bybit", "BYBIT_BASE_URL", bybit::DEFAULT_BASE_URL),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_coinbase(self) -> Self {
+        let source = coinbase::Coinbase::new(
+            self.client_for("coinbase"),
+            self.base_url("coinbase", "COINBASE_BASE_URL", coinbase::DEFAULT_BASE_URL),
+        );
+        self.with(Box::new(source))
+    }
+
+    /// `calls_per_minute` is enforced across every CoinGecko call this run.
+    /// CoinGecko retries 429s and 5xx itself, through its rate limiter, so
+    /// its client does not retry.
+    pub fn with_coingecko(self, api_key: String, calls_per_minute: u32) -> Self {
+        let source = coingecko::CoinGecko::new(
+            self.client_for("coingecko").with_retries(0),
+            self.base_url(
+                "coingecko",
+                "COINGECKO_BASE_URL",
+                coingecko::DEFAULT_BASE_URL,
+            ),
+            api_key,
+            calls_per_minute,
+            self.chains.clone(),
+        )
+        .with_timeout(self.registry.timeout);
+        self.with(Box::new(source))
+    }
+
+    pub fn with_coinpaprika(self) -> Self {
+        let source = coinpaprika::CoinPaprika::new(
+            self.client_for("coinpaprika"),
+            self.base_url(
+                "coinpaprika",
+                "COINPAPRIKA_BASE_URL",
+                coinpaprika::DEFAULT_BASE_URL,
+            ),
+        );
+        self.with(Box::new(source))
+    }
+
+    pub fn with_coinmarketcap(self, api_key: String) -> Self {
+        let source = coinmarketcap::CoinMarketCap::new(
+            self.client_for("coinmarketcap"),
+            self.base_url(
+                "coinmarketcap",
+                "COINMARKETCAP_BASE_URL",
+                coinmarketcap::DEFAULT_BASE_URL,
+            ),
+            api_key,
+            self.chains.clone(),
+        );
+        self.with(Box::new(source))
+    }
+
+    /// Custom sources whose header env vars are unset are skipped with a warning.
+    pub fn with_custom_sources(mut self, custom_sources: &[CustomSourceConfig]) -> Self {
+        for custom in custom_sources {
+            match custom_rest::CustomRestSource::new(self.client_for(&custom.name), custom.clone())
+            {
+                Ok(source) => self.registry.register(Box::new(source)),
+                Err(e) => tracing::warn!("{:#}; custom source disabled", e),
+            }
+        }
+        self
+    }
+
+    fn client_for(&self, source: &str) -> HttpClient {
+        client_for(&self.client, &self.request_timeouts, source)
+    }
+
+    fn base_url(&self, source: &str, env: &str, default: &str) -> String {
+        endpoint(&self.endpoints, source, env, default)
+    }
+
+    pub fn build(self) -> SourceRegistry {
+        self.registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockSource;
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(
+            "unit_index: 1\nname: HOT\nchain: ethereum\ncontract: '0x6c6ee5e31d828de241282b9606c8e98ea48526e2'",
+        )
+        .unwrap()
+    }
+
+    fn registry(sources: Vec<MockSource>) -> SourceRegistry {
+        let mut builder = SourceRegistry::builder(HttpClient::new(reqwest::Client::new()))
+            .with_timeout(Duration::from_secs(2));
+        for source in sources {
+            builder = builder.with(Box::new(source));
+        }
+        builder.build()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_source_times_out_without_holding_up_the_others() {
+        let registry = registry(vec![
+            MockSource::new("slow")
+                .with_price(1, 0.002)
+                .with_delay(Duration::from_secs(60)),
+            MockSource::new("fast").with_price(1, 0.0021),
+            MockSource::new("slow2")
+                .with_price(1, 0.002)
+                .with_delay(Duration::from_secs(60)),
+        ]);
+
+        let started = tokio::time::Instant::now();
+        let results = registry.fetch_all(&unit()).await;
+
+        // Both slow sources ran side by side and were cut off together.
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        let names: Vec<_> = results.iter().map(|r| r.source.as_str()).collect();
+        assert_eq!(names, ["slow", "fast", "slow2"]);
+        for slow in [&results[0], &results[2]] {
+            let err = slow.result.as_ref().unwrap_err();
+            assert_eq!(err.to_string(), "timed out after 2s");
+        }
+        assert_eq!(results[1].result.as_ref().unwrap().price_usd, 0.0021);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn source_inside_the_timeout_answers() {
+        let registry = registry(vec![MockSource::new("steady")
+            .with_price(1, 0.002)
+            .with_delay(Duration::from_secs(1))]);
+
+        let results = registry.fetch_all_units(&[&unit()], 1).await;
+
+        assert_eq!(results[0][0].result.as_ref().unwrap().price_usd, 0.002);
+    }
+
+    #[tokio::test]
+    async fn request_timeout_override_turns_a_slow_server_into_a_source_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "error": [], "result": {} }))
+                    .set_delay(Duration::from_secs(10)),
+            )
+            .mount(&server)
+            .await;
+        let registry =
+            SourceRegistry::builder(HttpClient::new(reqwest::Client::new()).with_retries(0))
+                .with_timeout(Duration::from_secs(5))
+                .with_endpoints(HashMap::from([("kraken".to_string(), server.uri())]))
+                .with_request_timeouts(HashMap::from([("kraken".to_string(), 1)]))
+                .with_kraken()
+                .with(Box::new(MockSource::new("fast").with_price(1, 0.0021)))
+                .build();
+        let unit: UnitConfig = serde_yaml::from_str(
+            "unit_index: 1\nname: ETH\nchain: ethereum\nnative: true\nkraken_pair: ETHUSD",
+        )
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let results = registry.fetch_all(&unit).await;
+
+        // Cut off by kraken's own 1s request timeout, not the registry's 5s.
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let err = results[0].result.as_ref().unwrap_err();
+        assert!(
+            err.chain().any(|e| e
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_timeout())),
+            "{:#}",
+            err
+        );
+        assert_eq!(results[1].result.as_ref().unwrap().price_usd, 0.0021);
+    }
+}
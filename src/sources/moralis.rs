@@ -0,0 +1,203 @@
+use super::PriceSource;
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `MORALIS_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://deep-index.moralis.io";
+
+/// Moralis ERC-20 price, looked up by contract and chain.
+pub struct Moralis {
+    client: HttpClient,
+    base_url: String,
+    api_key: String,
+    chains: Arc<ChainRegistry>,
+}
+
+impl Moralis {
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        api_key: String,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            chains,
+        }
+    }
+}
+
+/// Moralis returns some numeric fields as strings.
+fn number(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+#[async_trait]
+impl PriceSource for Moralis {
+    fn name(&self) -> &str {
+        "moralis"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let chain = self.chains.id_for(&unit.chain, "moralis")?;
+        let url = format!(
+            "{}/api/v2.2/erc20/{}/price",
+            self.base_url,
+            unit.lookup_contract()
+        );
+        let request = self
+            .client
+            .get(&url)
+            .query(&[("chain", chain.as_str())])
+            .header("Accept", "application/json")
+            .header("X-API-Key", &self.api_key);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Moralis request failed")?;
+
+        let status = resp.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            // e.g. "No liquidity pools found for ..."; keep it verbatim for the aggregate log.
+            anyhow::bail!("Moralis: {}", resp.text());
+        }
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("Moralis HTTP {}: {}", status, body);
+        }
+
+        let body: Value = resp.json().context("Moralis parse failed")?;
+        let price_usd = number(&body["usdPrice"]).context("Moralis: missing usdPrice")?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: number(&body["pairTotalLiquidityUsd"]),
+            price_change_24h: number(&body["24hrPercentChange"]),
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: body["tokenSymbol"].as_str().map(str::to_string),
+            reported_name: body["tokenName"].as_str().map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SystemClock;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: HOT\nchain: ethereum\ncontract: '{}'",
+            HOT
+        ))
+        .unwrap()
+    }
+
+    async fn serve(response: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/v2.2/erc20/{}/price", HOT)))
+            .and(query_param("chain", "eth"))
+            .and(header("X-API-Key", "test-key"))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn fetch(server: &MockServer) -> Result<TokenData> {
+        Moralis::new(
+            HttpClient::new(reqwest::Client::new()),
+            server.uri(),
+            "test-key".to_string(),
+            Arc::new(ChainRegistry::builtin()),
+        )
+        .fetch(&unit(), &SystemClock)
+        .await
+    }
+
+    #[tokio::test]
+    async fn parses_numbers_sent_as_strings() {
+        // Trimmed from a live /api/v2.2/erc20/{address}/price response.
+        let server = serve(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "tokenName": "HoloToken",
+            "tokenSymbol": "HOT",
+            "tokenDecimals": "18",
+            "nativePrice": {
+                "value": "492000000000000",
+                "decimals": 18,
+                "name": "Ether",
+                "symbol": "ETH"
+            },
+            "usdPrice": 0.00123,
+            "usdPriceFormatted": "0.00123",
+            "exchangeName": "Uniswap v2",
+            "pairAddress": "0x1111111111111111111111111111111111111111",
+            "pairTotalLiquidityUsd": "1523456.78",
+            "24hrPercentChange": "-2.5",
+            "possibleSpam": false,
+            "verifiedContract": true
+        })))
+        .await;
+
+        let data = fetch(&server).await.unwrap();
+
+        assert_eq!(data.price_usd, 0.00123);
+        assert_eq!(data.liquidity, Some(1_523_456.78));
+        assert_eq!(data.price_change_24h, Some(-2.5));
+        assert_eq!(data.volume_24h, None);
+        assert_eq!(data.reported_symbol.as_deref(), Some("HOT"));
+        assert_eq!(data.reported_name.as_deref(), Some("HoloToken"));
+    }
+
+    #[tokio::test]
+    async fn not_found_keeps_the_message() {
+        let server = serve(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+            "message": "No liquidity pools found with enough liquidity to calculate the price"
+        })))
+        .await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert!(
+            err.to_string()
+                .starts_with("Moralis: {\"message\":\"No liquidity pools found"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_usd_price_is_an_error() {
+        let server = serve(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!({ "tokenSymbol": "HOT" })),
+        )
+        .await;
+
+        let err = fetch(&server).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "Moralis: missing usdPrice");
+    }
+}
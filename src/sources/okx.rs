@@ -0,0 +1,93 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+
+/// Production API root; `source_endpoints` or `OKX_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://www.okx.com";
+
+/// OKX public spot ticker; only units with an `okx_inst_id` (e.g. `HOT-USDT`).
+/// Prices come back in USDT and are converted to USD by the oracle.
+pub struct Okx {
+    client: HttpClient,
+    base_url: String,
+}
+
+impl Okx {
+    pub fn new(client: HttpClient, base_url: String) -> Self {
+        Self { client, base_url }
+    }
+}
+
+#[async_trait]
+impl PriceSource for Okx {
+    fn name(&self) -> &str {
+        "okx"
+    }
+
+    fn quote_currency(&self) -> &str {
+        "USDT"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let inst_id = unit.source_id("okx").ok_or_else(|| {
+            NotConfigured(format!("okx_inst_id not configured for '{}'", unit.name))
+        })?;
+
+        let request = self
+            .client
+            .get(&format!("{}/api/v5/market/ticker", self.base_url))
+            .query(&[("instId", inst_id)]);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("OKX request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("OKX HTTP {}: {}", status, body);
+        }
+
+        let body: serde_json::Value = resp.json().context("OKX parse failed")?;
+        if body["code"].as_str() != Some("0") {
+            anyhow::bail!(
+                "OKX error for {}: {} {}",
+                inst_id,
+                body["code"],
+                body["msg"].as_str().unwrap_or_default()
+            );
+        }
+        let ticker = body["data"]
+            .get(0)
+            .with_context(|| format!("OKX: no ticker for {}", inst_id))?;
+
+        let price_usd = parse_f64(ticker, "last").context("OKX: missing last price")?;
+        let price_change_24h = parse_f64(ticker, "open24h")
+            .filter(|open| *open > 0.0)
+            .map(|open| (price_usd - open) / open * 100.0);
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: parse_f64(ticker, "volCcy24h"),
+            liquidity: None,
+            price_change_24h,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+fn parse_f64(obj: &serde_json::Value, key: &str) -> Option<f64> {
+    obj[key].as_str().and_then(|s| s.parse::<f64>().ok())
+}
@@ -0,0 +1,110 @@
+use super::PriceSource;
+use crate::chains::ChainRegistry;
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Production API root; `source_endpoints` or `ONEINCH_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://api.1inch.dev";
+
+/// 1inch Spot Price API; price only, looked up by EVM chain id and contract.
+pub struct OneInch {
+    client: HttpClient,
+    base_url: String,
+    api_key: String,
+    chains: Arc<ChainRegistry>,
+}
+
+impl OneInch {
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        api_key: String,
+        chains: Arc<ChainRegistry>,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            chains,
+        }
+    }
+}
+
+#[async_trait]
+impl PriceSource for OneInch {
+    fn name(&self) -> &str {
+        "1inch"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let chain_id = self.chains.id_for(&unit.chain, "1inch")?;
+        let url = format!(
+            "{}/price/v1.1/{}/{}",
+            self.base_url,
+            chain_id,
+            unit.lookup_contract()
+        );
+        let request = self
+            .client
+            .get(&url)
+            .query(&[("currency", "USD")])
+            .header("Accept", "application/json")
+            .bearer_auth(&self.api_key);
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("1inch request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("1inch HTTP {}: {}", status, body);
+        }
+
+        // Keyed by the (lowercased) token address.
+        let body: Value = resp.json().context("1inch parse failed")?;
+        let raw = body
+            .as_object()
+            .and_then(|m| {
+                m.iter()
+                    .find(|(addr, _)| addr.eq_ignore_ascii_case(unit.lookup_contract()))
+            })
+            .and_then(|(_, v)| v.as_str())
+            .with_context(|| format!("1inch: no price for {}", unit.lookup_contract()))?;
+        let price_usd = scale_price(raw, unit.decimals)
+            .with_context(|| format!("1inch: bad price '{}' for {}", raw, unit.name))?;
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: None,
+            price_change_24h: None,
+            source: self.name().to_string(),
+            timestamp: clock.now(),
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+/// Decimal strings are taken as-is; integer strings are scaled down by the
+/// token's `decimals`, which must then be configured.
+fn scale_price(raw: &str, decimals: Option<u8>) -> Result<f64> {
+    let value: f64 = raw.parse().context("not a number")?;
+    if raw.contains(['.', 'e', 'E']) {
+        return Ok(value);
+    }
+    let decimals = decimals.context("integer price needs the unit's `decimals` to scale")?;
+    Ok(value / 10f64.powi(decimals as i32))
+}
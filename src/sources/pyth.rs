@@ -0,0 +1,275 @@
+use super::{NotConfigured, PriceSource};
+use crate::clock::Clock;
+use crate::config::UnitConfig;
+use crate::http::HttpClient;
+use crate::types::TokenData;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+/// Production API root; `source_endpoints` or `PYTH_BASE_URL` can point elsewhere.
+pub const DEFAULT_BASE_URL: &str = "https://hermes.pyth.network";
+
+/// Pyth Network price via the public Hermes REST API; keyed on `pyth_price_id`.
+pub struct Pyth {
+    client: HttpClient,
+    base_url: String,
+    max_age_secs: u64,
+    max_conf_ratio: f64,
+}
+
+impl Pyth {
+    pub fn new(
+        client: HttpClient,
+        base_url: String,
+        max_age_secs: u64,
+        max_conf_ratio: f64,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            max_age_secs,
+            max_conf_ratio,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HermesResponse {
+    parsed: Vec<ParsedUpdate>,
+}
+
+#[derive(Deserialize)]
+struct ParsedUpdate {
+    price: PythPrice,
+}
+
+/// Hermes encodes `price` and `conf` as decimal strings scaled by `10^expo`.
+#[derive(Deserialize)]
+struct PythPrice {
+    price: String,
+    conf: String,
+    expo: i32,
+    publish_time: i64,
+}
+
+impl PythPrice {
+    /// `(price, conf)` with the exponent applied.
+    fn scaled(&self) -> Result<(f64, f64)> {
+        let price: i64 = self.price.parse().context("invalid price")?;
+        let conf: u64 = self.conf.parse().context("invalid conf")?;
+        let scale = 10f64.powi(self.expo);
+        Ok((price as f64 * scale, conf as f64 * scale))
+    }
+}
+
+#[async_trait]
+impl PriceSource for Pyth {
+    fn name(&self) -> &str {
+        "pyth"
+    }
+
+    async fn fetch(&self, unit: &UnitConfig, clock: &dyn Clock) -> Result<TokenData> {
+        let price_id = unit.source_id("pyth").ok_or_else(|| {
+            NotConfigured(format!("pyth_price_id not configured for '{}'", unit.name))
+        })?;
+        let url = format!(
+            "{}/v2/updates/price/latest?ids[]={}&parsed=true",
+            self.base_url, price_id
+        );
+
+        let request = self.client.get(&url).header("Accept", "application/json");
+        let resp = self
+            .client
+            .send(request)
+            .await
+            .context("Pyth request failed")?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text();
+            anyhow::bail!("Pyth HTTP {}: {}", status, body);
+        }
+
+        let body: HermesResponse = resp.json().context("Pyth parse failed")?;
+        let quote = body
+            .parsed
+            .into_iter()
+            .next()
+            .map(|u| u.price)
+            .with_context(|| format!("Pyth: no update for price id {}", price_id))?;
+        let (price_usd, conf) = quote
+            .scaled()
+            .with_context(|| format!("Pyth: bad quote for {}", price_id))?;
+        if price_usd <= 0.0 {
+            anyhow::bail!("Pyth: non-positive price {} for {}", price_usd, price_id);
+        }
+
+        let ratio = conf / price_usd;
+        if ratio > self.max_conf_ratio {
+            anyhow::bail!(
+                "Pyth: confidence interval too wide for {} (conf/price {:.4} > {})",
+                price_id,
+                ratio,
+                self.max_conf_ratio
+            );
+        }
+
+        let age = clock.now().timestamp() - quote.publish_time;
+        if age > self.max_age_secs as i64 {
+            anyhow::bail!(
+                "Pyth: price for {} is stale: published {}s ago (max {}s)",
+                price_id,
+                age,
+                self.max_age_secs
+            );
+        }
+
+        let timestamp =
+            DateTime::<Utc>::from_timestamp(quote.publish_time, 0).unwrap_or_else(|| clock.now());
+
+        Ok(TokenData {
+            name: unit.name.clone(),
+            chain: unit.chain.clone(),
+            contract: unit.contract.clone(),
+            price_usd,
+            market_cap: None,
+            volume_24h: None,
+            liquidity: None,
+            price_change_24h: None,
+            source: self.name().to_string(),
+            timestamp,
+            reported_symbol: None,
+            reported_name: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ETH_USD: &str = "ff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace";
+    const PUBLISH_TIME: i64 = 1_791_201_600;
+
+    fn quote(price: &str, conf: &str, expo: i32) -> PythPrice {
+        PythPrice {
+            price: price.to_string(),
+            conf: conf.to_string(),
+            expo,
+            publish_time: PUBLISH_TIME,
+        }
+    }
+
+    #[test]
+    fn negative_exponent_scales_down() {
+        let (price, conf) = quote("250012345678", "150000000", -8).scaled().unwrap();
+        assert!((price - 2500.12345678).abs() < 1e-9, "{}", price);
+        assert!((conf - 1.5).abs() < 1e-12, "{}", conf);
+    }
+
+    #[test]
+    fn positive_exponent_scales_up() {
+        let (price, conf) = quote("25", "3", 2).scaled().unwrap();
+        assert_eq!(price, 2500.0);
+        assert_eq!(conf, 300.0);
+    }
+
+    #[test]
+    fn malformed_price_is_an_error() {
+        assert!(quote("2500.5", "1", -8).scaled().is_err());
+    }
+
+    fn unit() -> UnitConfig {
+        serde_yaml::from_str(&format!(
+            "unit_index: 1\nname: ETH\nchain: ethereum\nnative: true\npyth_price_id: '{}'",
+            ETH_USD
+        ))
+        .unwrap()
+    }
+
+    /// Hermes answering with one parsed update for ETH/USD.
+    async fn serve(price: &str, conf: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v2/updates/price/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "binary": { "encoding": "hex", "data": ["504e4155"] },
+                "parsed": [{
+                    "id": ETH_USD,
+                    "price": {
+                        "price": price,
+                        "conf": conf,
+                        "expo": -8,
+                        "publish_time": PUBLISH_TIME
+                    },
+                    "ema_price": {
+                        "price": "249900000000",
+                        "conf": "160000000",
+                        "expo": -8,
+                        "publish_time": PUBLISH_TIME
+                    },
+                    "metadata": { "slot": 123, "proof_available_time": PUBLISH_TIME + 1 }
+                }]
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn fetch(server: &MockServer, age_secs: i64) -> Result<TokenData> {
+        let clock =
+            ManualClock::new(DateTime::<Utc>::from_timestamp(PUBLISH_TIME + age_secs, 0).unwrap());
+        Pyth::new(
+            HttpClient::new(reqwest::Client::new()),
+            server.uri(),
+            60,
+            0.01,
+        )
+        .fetch(&unit(), &clock)
+        .await
+    }
+
+    #[tokio::test]
+    async fn parses_the_latest_update() {
+        let server = serve("250000000000", "150000000").await;
+
+        let data = fetch(&server, 5).await.unwrap();
+
+        assert_eq!(data.price_usd, 2500.0);
+        assert_eq!(data.timestamp.timestamp(), PUBLISH_TIME);
+        assert_eq!(data.source, "pyth");
+    }
+
+    #[tokio::test]
+    async fn wide_confidence_interval_is_rejected() {
+        // conf/price = 30 / 2500 = 0.012, over the 0.01 limit.
+        let server = serve("250000000000", "3000000000").await;
+
+        let err = fetch(&server, 5).await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("confidence interval too wide for"),
+            "{:#}",
+            err
+        );
+        assert!(err.to_string().contains("0.0120 > 0.01"), "{:#}", err);
+    }
+
+    #[tokio::test]
+    async fn stale_update_is_rejected() {
+        let server = serve("250000000000", "150000000").await;
+
+        let err = fetch(&server, 61).await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("published 61s ago (max 60s)"),
+            "{:#}",
+            err
+        );
+    }
+}
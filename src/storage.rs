@@ -0,0 +1,135 @@
+use crate::config::StorageConfig;
+use crate::oracle::PipelineOutput;
+use anyhow::{Context, Result};
+use aws_sdk_s3::config::retry::RetryConfig;
+use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::info;
+
+/// Proof of a successful `--submit`, uploaded next to the table it covers.
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmissionReceipt {
+    pub action_hash: String,
+    pub submitted_at: DateTime<Utc>,
+    /// CID of the same table pinned on IPFS, if publishing succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipfs_cid: Option<String>,
+}
+
+/// Pushes each run's artifacts to an S3-compatible bucket.
+pub struct Uploader {
+    client: aws_sdk_s3::Client,
+    config: StorageConfig,
+}
+
+impl Uploader {
+    pub fn from_config(cfg: &StorageConfig) -> Result<Self> {
+        let access_key = std::env::var(&cfg.access_key_env).with_context(|| {
+            format!("storage: {} not set (use --no-upload)", cfg.access_key_env)
+        })?;
+        let secret_key = std::env::var(&cfg.secret_key_env).with_context(|| {
+            format!("storage: {} not set (use --no-upload)", cfg.secret_key_env)
+        })?;
+
+        let mut builder = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new(cfg.region.clone()))
+            .credentials_provider(Credentials::new(
+                access_key,
+                secret_key,
+                None,
+                None,
+                "pricing-oracle",
+            ))
+            .force_path_style(cfg.path_style)
+            .retry_config(RetryConfig::standard().with_max_attempts(cfg.max_attempts));
+        if let Some(endpoint) = &cfg.endpoint {
+            builder = builder.endpoint_url(endpoint);
+        }
+
+        Ok(Self {
+            client: aws_sdk_s3::Client::from_conf(builder.build()),
+            config: cfg.clone(),
+        })
+    }
+
+    /// Upload the table, the run report and (after `--submit`) the receipt
+    /// under `<prefix><started_at>-<table hash>/`. Returns the object URLs.
+    pub async fn upload_run(
+        &self,
+        outcome: &PipelineOutput,
+        receipt: Option<&SubmissionReceipt>,
+    ) -> Result<Vec<String>> {
+        let table = serde_json::to_vec_pretty(&outcome.table).context("serializing table")?;
+        let short_hash: String = format!("{:x}", Sha256::digest(&table))
+            .chars()
+            .take(8)
+            .collect();
+        let run_dir = format!(
+            "{}{}-{}/",
+            self.config.prefix,
+            outcome.stats.started_at.format("%Y%m%dT%H%M%SZ"),
+            short_hash
+        );
+
+        let report = RunReport {
+            stats: &outcome.stats,
+            units: &outcome.aggregated,
+            forex: &outcome.forex,
+            source_errors: &outcome.source_errors,
+            ipfs_cid: receipt.and_then(|r| r.ipfs_cid.as_deref()),
+        };
+        let report = serde_json::to_vec_pretty(&report).context("serializing run report")?;
+        let mut objects = vec![("conversion_table.json", table), ("report.json", report)];
+        if let Some(receipt) = receipt {
+            let receipt = serde_json::to_vec_pretty(receipt).context("serializing receipt")?;
+            objects.push(("receipt.json", receipt));
+        }
+
+        let mut urls = Vec::with_capacity(objects.len());
+        for (name, body) in objects {
+            let key = format!("{}{}", run_dir, name);
+            self.client
+                .put_object()
+                .bucket(&self.config.bucket)
+                .key(&key)
+                .content_type("application/json")
+                .body(ByteStream::from(body))
+                .send()
+                .await
+                .with_context(|| format!("uploading s3://{}/{}", self.config.bucket, key))?;
+            info!("[storage] Uploaded s3://{}/{}", self.config.bucket, key);
+            urls.push(self.object_url(&key));
+        }
+        Ok(urls)
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        match &self.config.endpoint {
+            Some(endpoint) => format!(
+                "{}/{}/{}",
+                endpoint.trim_end_matches('/'),
+                self.config.bucket,
+                key
+            ),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{}",
+                self.config.bucket, self.config.region, key
+            ),
+        }
+    }
+}
+
+/// Everything about a run except the table itself, for after-the-fact review.
+#[derive(Serialize)]
+struct RunReport<'a> {
+    stats: &'a crate::oracle::RunStats,
+    units: &'a [crate::types::AggregatedResult],
+    forex: &'a [crate::forex_aggregate::AggregatedForexRate],
+    source_errors: &'a [crate::oracle::SourceError],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipfs_cid: Option<&'a str>,
+}
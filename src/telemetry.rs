@@ -0,0 +1,238 @@
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
+
+/// Flushes and shuts down trace export when dropped. Keep it alive until the
+/// run is over, and drop it outside the tokio runtime.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("OpenTelemetry shutdown failed: {}", e);
+            }
+        }
+    }
+}
+
+/// How stderr log lines are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Pretty,
+    /// One JSON object per line, event fields flattened to the top level.
+    Json,
+}
+
+/// Install the global tracing subscriber: stderr logs filtered by `RUST_LOG`
+/// (`level` when unset), plus OTLP span export when built with `otel` and an
+/// endpoint is set. Exporter setup failures only disable export; they never
+/// fail the run.
+pub fn init(
+    format: LogFormat,
+    level: tracing::Level,
+    otlp_endpoint: Option<&str>,
+) -> TelemetryGuard {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(level.as_str().to_lowercase()));
+    let fmt = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let fmt = match format {
+        LogFormat::Pretty => fmt.boxed(),
+        LogFormat::Json => fmt.json().flatten_event(true).boxed(),
+    };
+    let registry = tracing_subscriber::registry().with(filter).with(fmt);
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let (provider, error) = match otlp_endpoint.map(otel::provider) {
+            Some(Ok(provider)) => (Some(provider), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, None),
+        };
+        let layer = provider
+            .as_ref()
+            .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer("pricing-oracle")));
+        registry.with(layer).init();
+
+        if let Some(e) = error {
+            tracing::warn!("OpenTelemetry export disabled: {:#}", e);
+        } else if let Some(endpoint) = otlp_endpoint {
+            tracing::info!("Exporting traces to {}", endpoint);
+        }
+        TelemetryGuard { provider }
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.init();
+        if otlp_endpoint.is_some() {
+            tracing::warn!(
+                "otlp_endpoint is set but this build lacks the `otel` feature; traces are not exported"
+            );
+        }
+        TelemetryGuard {}
+    }
+}
+
+/// Add W3C `traceparent`/`tracestate` headers for `span` to an outgoing
+/// request so gateway logs can be correlated with our traces.
+#[cfg(feature = "otel")]
+pub fn inject_trace_context(span: &tracing::Span, headers: &mut reqwest::header::HeaderMap) {
+    use reqwest::header::{HeaderName, HeaderValue};
+    use std::collections::HashMap;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let cx = span.context();
+    let mut carrier: HashMap<String, String> = HashMap::new();
+    opentelemetry::global::get_text_map_propagator(|p| p.inject_context(&cx, &mut carrier));
+    for (name, value) in carrier {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use anyhow::{Context, Result};
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    pub fn provider(endpoint: &str) -> Result<SdkTracerProvider> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .context("building OTLP span exporter")?;
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name("pricing-oracle")
+                    .build(),
+            )
+            .build())
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use crate::config::Config;
+    use crate::oracle::{run_pipeline, OracleSettings};
+    use crate::sources::BUILTIN_SOURCE_NAMES;
+    use opentelemetry::trace::{SpanId, TracerProvider as _};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+    use serde_json::json;
+    use tracing::Instrument;
+    use tracing_subscriber::prelude::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+
+    fn attribute(span: &SpanData, key: &str) -> Option<String> {
+        span.attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.to_string())
+    }
+
+    /// One run pricing HOT from a mocked GeckoTerminal, traced into an
+    /// in-memory exporter the same way `init` wires the OTLP one.
+    #[tokio::test]
+    async fn run_is_exported_as_one_trace_with_phase_spans() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/api/v2/networks/eth/tokens/{}", HOT)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "data": { "attributes": { "price_usd": "0.00123", "symbol": "HOT" } }
+            })))
+            .mount(&server)
+            .await;
+        let endpoints: String = BUILTIN_SOURCE_NAMES
+            .iter()
+            .filter(|name| **name != "chainlink")
+            .map(|name| format!("  \"{}\": \"{}\"\n", name, server.uri()))
+            .collect();
+        let config: Config = serde_yaml::from_str(&format!(
+            "units:\n  - unit_index: 1\n    name: HOT\n    chain: ethereum\n    contract: \"{}\"\nmin_sources: 1\nhttp_retries: 0\nsource_endpoints:\n{}",
+            HOT, endpoints
+        ))
+        .unwrap();
+        let settings = OracleSettings {
+            skip_forex: true,
+            ..Default::default()
+        };
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("pricing-oracle")));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let output = run_pipeline(config, settings)
+            .instrument(tracing::info_span!("oracle_run"))
+            .await
+            .unwrap();
+        assert_eq!(output.stats.units_valid, 1);
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+
+        let named =
+            |name: &str| -> Vec<&SpanData> { spans.iter().filter(|s| s.name == name).collect() };
+        let root = named("oracle_run");
+        assert_eq!(root.len(), 1);
+        let root = root[0];
+        assert_eq!(root.parent_span_id, SpanId::INVALID);
+        let trace_id = root.span_context.trace_id();
+        for name in [
+            "units",
+            "unit",
+            "source_fetch",
+            "http_request",
+            "build_table",
+        ] {
+            assert!(!named(name).is_empty(), "no {} span", name);
+        }
+        assert!(spans.iter().all(|s| s.span_context.trace_id() == trace_id));
+        assert_eq!(
+            named("units")[0].parent_span_id,
+            root.span_context.span_id()
+        );
+        assert_eq!(
+            attribute(named("unit")[0], "unit_index"),
+            Some("1".to_string())
+        );
+
+        let answered = named("http_request")
+            .into_iter()
+            .find(|s| attribute(s, "http.status") == Some("200".to_string()))
+            .expect("no http_request span with http.status 200");
+        let requests = server.received_requests().await.unwrap();
+        let gecko = requests
+            .iter()
+            .find(|r| r.url.path().ends_with(HOT))
+            .unwrap();
+        let traceparent = gecko.headers["traceparent"].to_str().unwrap();
+        assert_eq!(
+            traceparent,
+            format!("00-{}-{}-01", trace_id, answered.span_context.span_id())
+        );
+    }
+}
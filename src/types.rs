@@ -1,7 +1,9 @@
+use crate::aggregate::{AggregationStrategy, WeightBasis};
 use chrono::{DateTime, Utc};
 use holo_hash::{ActionHash, ActionHashB64};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
+use std::fmt;
 use zfuel::fuel::ZFuel;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,32 +18,123 @@ pub struct TokenData {
     pub price_change_24h: Option<f64>,
     pub source: String,
     pub timestamp: DateTime<Utc>,
+    /// Symbol the source's API gave for the token, if it gives one;
+    /// compared across sources and against `expected_symbol`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_symbol: Option<String>,
+    /// Name the source's API gave for the token; `name` is the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reported_name: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AggregatedResult {
+    /// `0` for a price reference; see `reference_id`.
     pub unit_index: u32,
+    /// The `id` of the price reference this price is for; unset for units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_id: Option<String>,
     pub name: String,
     pub contract: String,
     pub avg_price_usd: f64,
     pub volume_24h: Option<f64>,
     pub price_change_24h: Option<f64>,
     pub sources: Vec<String>,
+    /// Sources dropped as outliers before the cross-check.
+    pub rejected_sources: Vec<String>,
     pub valid: bool,
+    /// Why the unit is invalid, e.g. `sources disagree` or `stale data`.
+    pub reason: Option<String>,
+    /// How `avg_price_usd` was derived from `per_source`.
+    pub strategy: AggregationStrategy,
+    /// Per-source weights when `strategy` is `weighted`; empty otherwise.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weights: Vec<SourceWeight>,
+    /// Age in seconds of the oldest quote that went into the price.
+    pub oldest_quote_age_secs: Option<i64>,
+    /// Set when no source answered this run and the price came from
+    /// `cache_file`: when that price was fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_at: Option<DateTime<Utc>>,
     pub per_source: Vec<TokenData>,
 }
 
+/// What a fetch or aggregation is for: a table unit, or a price reference
+/// that is only fetched to be proxied.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnitKey {
+    Index(u32),
+    Reference(String),
+}
+
+impl UnitKey {
+    /// The `unit_index` log field; absent for references.
+    pub fn index(&self) -> Option<u32> {
+        match self {
+            UnitKey::Index(index) => Some(*index),
+            UnitKey::Reference(_) => None,
+        }
+    }
+
+    /// The `reference` log field; absent for units.
+    pub fn reference(&self) -> Option<&str> {
+        match self {
+            UnitKey::Index(_) => None,
+            UnitKey::Reference(id) => Some(id),
+        }
+    }
+}
+
+/// `unit 3` or `reference 'HOT'`, as in fetch timings and proxy logs.
+impl fmt::Display for UnitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitKey::Index(index) => write!(f, "unit {}", index),
+            UnitKey::Reference(id) => write!(f, "reference '{}'", id),
+        }
+    }
+}
+
+/// A surviving source's share of a weighted average (weights sum to 1).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceWeight {
+    pub source: String,
+    pub weight: f64,
+    pub basis: WeightBasis,
+}
+
+/// One source's answer to one fetch, with how long it took.
+#[derive(Debug)]
+pub struct SourceResult<T> {
+    pub source: String,
+    pub result: anyhow::Result<T>,
+    pub elapsed_ms: i64,
+}
+
 /// Mirrors rave_engine ConversionTable (not yet in published crate).
 /// Will be replaced by rave_engine import when a new version is published.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConversionTable {
     pub reference_unit: ReferenceUnit,
+    #[serde(serialize_with = "serialize_by_unit_index")]
     pub data: HashMap<String, ConversionData>,
     pub forex_rates: Vec<ForexRate>,
     pub additional_data: Option<Vec<u8>>,
     pub global_definition: ActionHash,
 }
 
+/// Writes `data` as a map ordered by numeric unit index (`2` before `10`),
+/// so JSON output is stable between runs. The encoded map is the same one a
+/// `HashMap` would produce, only in a fixed order.
+fn serialize_by_unit_index<S: Serializer>(
+    data: &HashMap<String, ConversionData>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut entries: Vec<_> = data.iter().collect();
+    entries.sort_by_key(|(key, _)| (key.parse::<u32>().unwrap_or(u32::MAX), key.as_str()));
+    serializer.collect_map(entries)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReferenceUnit {
     pub symbol: String,
@@ -62,6 +155,54 @@ pub struct ForexRate {
     pub name: String,
     pub rate: ZFuel,
 }
+/// Provenance for one run, JSON-encoded into `ConversionTable::additional_data`
+/// when metadata embedding is on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    /// `CARGO_PKG_VERSION` of the oracle that built the table.
+    pub oracle_version: String,
+    pub run_at: DateTime<Utc>,
+    pub units: Vec<UnitMetadata>,
+    /// Units and forex symbols left out of the table.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub omitted: Vec<OmittedMetadata>,
+    /// Forex rates taken from `forex_cache_file` because no source gave one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cached_forex: Vec<CachedForexMetadata>,
+    /// Set when per-source prices were dropped to stay within the size limit.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnitMetadata {
+    pub unit_index: u32,
+    /// Raw USD price from each source that went into the unit's price.
+    pub sources: Vec<SourcePrice>,
+    /// Largest fraction any source was from the aggregated price.
+    pub max_deviation: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourcePrice {
+    pub source: String,
+    pub price_usd: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OmittedMetadata {
+    /// `unit 3` or `forex EUR`.
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedForexMetadata {
+    pub symbol: String,
+    /// When the cached rate was originally fetched.
+    pub fetched_at: DateTime<Utc>,
+}
+
 /// Minimal mirror of rave_engine's GlobalDefinitionExt.
 /// Only the `id` field is needed; remaining fields are ignored during
 /// MessagePack deserialization (named-map format).
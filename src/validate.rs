@@ -0,0 +1,163 @@
+//! `validate` subcommand: report every problem in a config file instead of
+//! stopping at the first one like a run does.
+
+use crate::address;
+use crate::config::Config;
+use crate::forex_aggregate;
+use anyhow::Result;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config is wrong; `validate` exits non-zero.
+    Error,
+    /// Probably a mistake, but runs will proceed.
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Load `path`, print every issue found, and fail if any is an error.
+pub fn run(path: &Path) -> Result<()> {
+    let cfg = Config::parse(path)?;
+    let issues = check(&cfg);
+    for issue in &issues {
+        match issue.severity {
+            Severity::Error => println!("error: {}", issue.message),
+            Severity::Warning => println!("warning: {}", issue.message),
+        }
+    }
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    if errors > 0 {
+        anyhow::bail!(
+            "{}: {} error(s), {} warning(s)",
+            path.display(),
+            errors,
+            warnings
+        );
+    }
+    println!("{}: OK ({} warning(s))", path.display(), warnings);
+    Ok(())
+}
+
+/// `Config::validate` (its first failure) plus checks a run does not make.
+pub fn check(cfg: &Config) -> Vec<Issue> {
+    let mut issues = Issues::default();
+    if let Err(e) = cfg.validate() {
+        issues.error(format!("{:#}", e));
+    }
+
+    let entries = cfg
+        .units
+        .iter()
+        .map(|u| {
+            (
+                u.name.as_str(),
+                u.chain.as_str(),
+                u.contract.as_str(),
+                u.native,
+                &u.sources,
+            )
+        })
+        .chain(cfg.price_references.iter().map(|r| {
+            (
+                r.name.as_str(),
+                r.chain.as_str(),
+                r.contract.as_str(),
+                r.native,
+                &r.sources,
+            )
+        }));
+    let chains = cfg.chain_registry();
+    let mut valid_sources: Vec<&str> = crate::sources::BUILTIN_SOURCE_NAMES.to_vec();
+    valid_sources.extend(cfg.custom_sources.iter().map(|c| c.name.as_str()));
+    for (name, chain, contract, native, sources) in entries {
+        match chains.resolve(chain) {
+            None => issues.warning(format!(
+                "'{}': chain '{}' is not mapped by any source; contract lookups will likely fail",
+                name, chain
+            )),
+            // Natives have no contract of their own to check.
+            Some(_) if native => {}
+            Some(_) if contract.is_empty() => {
+                issues.warning(format!("'{}': no contract on {}", name, chain))
+            }
+            Some(info) => {
+                // Same wording as `Config::validate`.
+                if let Err(e) = address::check(info, contract) {
+                    issues.error(format!("'{}' on {}: {:#}", name, chain, e));
+                }
+            }
+        }
+        // Same wording as `Config::validate`, so its copy is not repeated.
+        for unknown in sources
+            .iter()
+            .flatten()
+            .filter(|s| !valid_sources.contains(&s.as_str()))
+        {
+            issues.error(format!(
+                "'{}': unknown source '{}' in sources (valid: {})",
+                name,
+                unknown,
+                valid_sources.join(", ")
+            ));
+        }
+    }
+
+    let source_ids = cfg
+        .units
+        .iter()
+        .map(|u| (u.name.as_str(), &u.source_ids))
+        .chain(
+            cfg.price_references
+                .iter()
+                .map(|r| (r.name.as_str(), &r.source_ids)),
+        );
+    for (name, ids) in source_ids {
+        for source in ids.keys().filter(|s| !valid_sources.contains(&s.as_str())) {
+            issues.warning(format!(
+                "'{}': source_ids has '{}', which is not a known source; it is ignored",
+                name, source
+            ));
+        }
+    }
+
+    for symbol in &cfg.forex.symbols {
+        if !cfg.forex.names.contains_key(symbol) && !forex_aggregate::is_known_currency(symbol) {
+            issues.warning(format!(
+                "forex symbol '{}' is not a known currency and has no forex.names entry; it will be named 'Unknown Currency'",
+                symbol
+            ));
+        }
+    }
+
+    issues.0
+}
+
+/// Issue list that drops exact repeats.
+#[derive(Default)]
+struct Issues(Vec<Issue>);
+
+impl Issues {
+    fn error(&mut self, message: String) {
+        self.push(Severity::Error, message);
+    }
+
+    fn warning(&mut self, message: String) {
+        self.push(Severity::Warning, message);
+    }
+
+    fn push(&mut self, severity: Severity, message: String) {
+        if !self.0.iter().any(|i| i.message == message) {
+            self.0.push(Issue { severity, message });
+        }
+    }
+}
@@ -1,107 +1,1102 @@
+mod app;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+
+use crate::config::{HolochainSection, TargetConfig};
 use crate::types::{ConversionTable, GlobalDefinitionExt};
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use ham::{Ham, HamConfig};
 use holo_hash::ActionHash;
-use tracing::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, info_span, warn, Instrument};
+
+/// Reads of the published table before `verify_submission` gives up; a new
+/// entry may take a moment to become gettable.
+const VERIFY_ATTEMPTS: u32 = 5;
+
+/// First wait between verification reads; doubles each time.
+const VERIFY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Error text marking a conductor hiccup worth reconnecting for, as opposed
+/// to a zome error that would fail the same way again.
+const TRANSIENT_MARKERS: &[&str] = &[
+    "websocket",
+    "connection",
+    "closed",
+    "broken pipe",
+    "reset by peer",
+    "timed out",
+    "timeout",
+    "busy",
+];
+
+/// How the oracle reaches the conductor.
+#[derive(Clone)]
+pub enum HolochainConnection {
+    /// Admin and app websockets through HAM, which issues its own app
+    /// token and signing credentials.
+    Admin { admin_port: u16 },
+    /// The app websocket only, with a pre-issued app token and signing
+    /// credentials read from `signing_key_path`.
+    AppOnly {
+        token: Vec<u8>,
+        signing_key_path: PathBuf,
+    },
+}
 
+impl std::fmt::Debug for HolochainConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Admin { admin_port } => f
+                .debug_struct("Admin")
+                .field("admin_port", admin_port)
+                .finish(),
+            Self::AppOnly {
+                signing_key_path, ..
+            } => f
+                .debug_struct("AppOnly")
+                .field("token", &"<redacted>")
+                .field("signing_key_path", signing_key_path)
+                .finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct HolochainConfig {
-    pub admin_port: u16,
+    pub connection: HolochainConnection,
     pub app_port: u16,
     pub app_id: String,
     pub role_name: String,
-    /// Per-request timeout applied to the Holochain app websocket. Bounds
-    /// how long a hung conductor call can block this cron invocation.
-    pub request_timeout_secs: u64,
+    /// Bounds each attempt to open the conductor websockets.
+    pub connect_timeout: Duration,
+    /// Bounds each zome call, so a hung conductor can't block this cron
+    /// invocation. A call that times out counts as transient.
+    pub call_timeout: Duration,
+    /// Tries per zome call (and for connecting) on transient errors.
+    pub retry_attempts: u32,
+    /// First wait before reconnecting; doubles on each retry.
+    pub retry_backoff: Duration,
 }
 
 impl HolochainConfig {
-    pub fn from_env() -> Result<Self> {
-        let admin_port: u16 = std::env::var("HOLOCHAIN_ADMIN_PORT")
-            .unwrap_or_else(|_| "30000".to_string())
-            .parse()
-            .context("Invalid HOLOCHAIN_ADMIN_PORT")?;
+    /// Settings from the `holochain:` config section, with `HOLOCHAIN_*` env
+    /// vars taking precedence over it. An app token plus signing credentials
+    /// select an app-only connection; otherwise an admin port is required.
+    pub fn from_config(section: Option<&HolochainSection>) -> Result<Self> {
+        Self::load(section, "")
+    }
 
-        let app_port: u16 = std::env::var("HOLOCHAIN_APP_PORT")
-            .unwrap_or_else(|_| "30001".to_string())
-            .parse()
-            .context("Invalid HOLOCHAIN_APP_PORT")?;
+    /// Settings for one of the config's `targets`; its env overrides carry
+    /// the target's `env_prefix`.
+    pub fn for_target(target: &TargetConfig) -> Result<Self> {
+        Self::load(Some(&target.holochain), &target.env_prefix)
+            .with_context(|| format!("target '{}'", target.name))
+    }
 
-        let app_id =
-            std::env::var("HOLOCHAIN_APP_ID").unwrap_or_else(|_| "bridging-app".to_string());
+    fn load(section: Option<&HolochainSection>, env_prefix: &str) -> Result<Self> {
+        let file = section.cloned().unwrap_or_default();
+        let var = |name: &str| format!("{}{}", env_prefix, name);
 
-        let role_name =
-            std::env::var("HOLOCHAIN_ROLE_NAME").unwrap_or_else(|_| "alliance".to_string());
+        let token = std::env::var(var("HOLOCHAIN_APP_TOKEN")).ok();
+        let signing_key_path =
+            optional_setting(&var("HOLOCHAIN_SIGNING_KEY_PATH"), file.signing_key_path)?;
+        let admin_port = optional_setting(&var("HOLOCHAIN_ADMIN_PORT"), file.admin_port)?;
+        let connection = match (token, signing_key_path, admin_port) {
+            (Some(token), Some(signing_key_path), _) => HolochainConnection::AppOnly {
+                token: app::decode_hex(&token)
+                    .with_context(|| format!("Invalid {}", var("HOLOCHAIN_APP_TOKEN")))?,
+                signing_key_path,
+            },
+            (Some(_), None, _) | (None, Some(_), _) => anyhow::bail!(
+                "an app-only Holochain connection needs both {} and {}",
+                var("HOLOCHAIN_APP_TOKEN"),
+                var("HOLOCHAIN_SIGNING_KEY_PATH")
+            ),
+            (None, None, Some(admin_port)) => HolochainConnection::Admin { admin_port },
+            (None, None, None) => anyhow::bail!(
+                "no Holochain connection configured: set {} for an admin connection, or {} \
+                 and {} for an app-only one",
+                var("HOLOCHAIN_ADMIN_PORT"),
+                var("HOLOCHAIN_APP_TOKEN"),
+                var("HOLOCHAIN_SIGNING_KEY_PATH")
+            ),
+        };
 
-        let request_timeout_secs: u64 = std::env::var("HAM_REQUEST_TIMEOUT_SECS")
-            .unwrap_or_else(|_| "120".to_string())
-            .parse()
-            .context("Invalid HAM_REQUEST_TIMEOUT_SECS")?;
+        let app_port = setting(&var("HOLOCHAIN_APP_PORT"), file.app_port, 30001)?;
+        let app_id = setting(
+            &var("HOLOCHAIN_APP_ID"),
+            file.app_id,
+            "bridging-app".to_string(),
+        )?;
+        let role_name = setting(
+            &var("HOLOCHAIN_ROLE_NAME"),
+            file.role_name,
+            "alliance".to_string(),
+        )?;
+
+        let connect_timeout_ms = setting(
+            &var("HOLOCHAIN_CONNECT_TIMEOUT_MS"),
+            file.connect_timeout_ms,
+            10_000,
+        )?;
+        // HAM_REQUEST_TIMEOUT_SECS predates HOLOCHAIN_CALL_TIMEOUT_MS and is
+        // still honoured when the newer variable isn't set.
+        let call_timeout_ms = match std::env::var(var("HAM_REQUEST_TIMEOUT_SECS")) {
+            Ok(secs) if std::env::var_os(var("HOLOCHAIN_CALL_TIMEOUT_MS")).is_none() => secs
+                .parse::<u64>()
+                .with_context(|| format!("Invalid {}", var("HAM_REQUEST_TIMEOUT_SECS")))?
+                .saturating_mul(1000),
+            _ => setting(
+                &var("HOLOCHAIN_CALL_TIMEOUT_MS"),
+                file.call_timeout_ms,
+                120_000,
+            )?,
+        };
+        if connect_timeout_ms == 0 || call_timeout_ms == 0 {
+            anyhow::bail!("Holochain connect and call timeouts must be greater than 0");
+        }
+
+        let retry_attempts = setting(&var("HOLOCHAIN_RETRY_ATTEMPTS"), file.retry_attempts, 3)?;
+        if retry_attempts == 0 {
+            anyhow::bail!("{} must be at least 1", var("HOLOCHAIN_RETRY_ATTEMPTS"));
+        }
+        let retry_backoff_ms = setting(
+            &var("HOLOCHAIN_RETRY_BACKOFF_MS"),
+            file.retry_backoff_ms,
+            1000,
+        )?;
 
         Ok(Self {
-            admin_port,
+            connection,
             app_port,
             app_id,
             role_name,
-            request_timeout_secs,
+            connect_timeout: Duration::from_millis(connect_timeout_ms),
+            call_timeout: Duration::from_millis(call_timeout_ms),
+            retry_attempts,
+            retry_backoff: Duration::from_millis(retry_backoff_ms),
         })
     }
 
-    fn ham_config(&self) -> HamConfig {
-        HamConfig::new(self.admin_port, self.app_port, self.app_id.clone())
-            .with_request_timeout_secs(self.request_timeout_secs)
+    fn ham_config(&self, admin_port: u16) -> HamConfig {
+        // HAM's own request timeout is whole seconds; round up so the
+        // millisecond timeout around each call is the one that fires.
+        HamConfig::new(admin_port, self.app_port, self.app_id.clone())
+            .with_request_timeout_secs(self.call_timeout.as_secs_f64().ceil() as u64)
+    }
+
+    /// One connection attempt, bounded by `connect_timeout`.
+    async fn open(&self) -> Result<Connection> {
+        let connect = async {
+            match &self.connection {
+                HolochainConnection::Admin { admin_port } => {
+                    Ham::connect(self.ham_config(*admin_port))
+                        .await
+                        .map(Connection::Ham)
+                }
+                HolochainConnection::AppOnly {
+                    token,
+                    signing_key_path,
+                } => app::AppConnection::connect(
+                    self.app_port,
+                    &self.app_id,
+                    &self.role_name,
+                    token,
+                    signing_key_path,
+                )
+                .await
+                .map(Connection::App),
+            }
+        };
+        tokio::time::timeout(self.connect_timeout, connect)
+            .await
+            .map_err(|_| anyhow::anyhow!("connecting timed out after {:?}", self.connect_timeout))?
     }
 }
 
-pub async fn fetch_global_definition(hc: &HolochainConfig) -> Result<ActionHash> {
-    info!(
-        "[gd] Connecting to Holochain (admin:{}, app:{}, app_id:{})",
-        hc.admin_port, hc.app_port, hc.app_id
-    );
+/// `var` if set, else the config-file value, else `default`.
+fn setting<T>(var: &str, file: Option<T>, default: T) -> Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(optional_setting(var, file)?.unwrap_or(default))
+}
 
-    let ham = Ham::connect(hc.ham_config())
-        .await
-        .context("Failed to connect to Holochain")?;
+/// `var` if set, else the config-file value.
+fn optional_setting<T>(var: &str, file: Option<T>) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(var) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .with_context(|| format!("Invalid {}", var)),
+        Err(_) => Ok(file),
+    }
+}
 
-    info!("[gd] Calling transactor/get_current_global_definition");
-    let gd: GlobalDefinitionExt = ham
-        .call_zome(
-            &hc.role_name,
-            "transactor",
-            "get_current_global_definition",
-            (),
-        )
-        .await
-        .context("get_current_global_definition zome call failed")?;
+/// An open conductor connection of either kind.
+enum Connection {
+    Ham(Ham),
+    App(app::AppConnection),
+    /// A scripted conductor, so tests see the exact calls made.
+    #[cfg(test)]
+    Scripted(std::sync::Arc<tests::Conductor>),
+}
+
+/// The transactor zome calls the oracle makes, so the submit path can run
+/// against something other than a live conductor.
+#[async_trait]
+pub trait ZomeClient: Send + Sync {
+    async fn get_global_definition(&self) -> Result<GlobalDefinitionExt>;
+    async fn create_table(&self, table: ConversionTable) -> Result<ActionHash>;
+    /// The most recently published table, if any.
+    async fn get_latest_table(&self) -> Result<Option<ConversionTable>>;
+}
+
+/// True if `error` looks like a dropped connection, timeout or busy
+/// conductor rather than a zome-level failure.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    let text = format!("{:#}", error).to_lowercase();
+    TRANSIENT_MARKERS.iter().any(|m| text.contains(m))
+}
+
+/// `ZomeClient` backed by a HAM or app-only connection to a running
+/// conductor. Transient failures reconnect and retry up to
+/// `retry_attempts` times.
+pub struct HamZomeClient {
+    conn: RwLock<Connection>,
+    hc: HolochainConfig,
+}
+
+impl HamZomeClient {
+    pub async fn connect(hc: &HolochainConfig) -> Result<Self> {
+        match &hc.connection {
+            HolochainConnection::Admin { admin_port } => info!(
+                "Connecting to Holochain (admin:{}, app:{}, app_id:{})",
+                admin_port, hc.app_port, hc.app_id
+            ),
+            HolochainConnection::AppOnly { .. } => info!(
+                "Connecting to Holochain app interface (app:{}, app_id:{})",
+                hc.app_port, hc.app_id
+            ),
+        }
+        let mut delay = hc.retry_backoff;
+        let mut attempt = 1;
+        let conn = loop {
+            match hc.open().await {
+                Ok(conn) => break conn,
+                Err(e) if attempt < hc.retry_attempts && is_transient(&e) => {
+                    warn!(
+                        "Holochain connect failed (attempt {}/{}), retrying in {:?}: {:#}",
+                        attempt, hc.retry_attempts, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context("Failed to connect to Holochain")),
+            }
+        };
+        Ok(Self {
+            conn: RwLock::new(conn),
+            hc: hc.clone(),
+        })
+    }
+
+    /// Replace the connection after a transient failure. A failed reconnect
+    /// is only logged: the next call fails and counts as another attempt.
+    async fn reconnect(&self) {
+        #[cfg(test)]
+        if let Connection::Scripted(conductor) = &*self.conn.read().await {
+            conductor.reconnected();
+            return;
+        }
+        match self.hc.open().await {
+            Ok(conn) => {
+                *self.conn.write().await = conn;
+                info!("Reconnected to Holochain");
+            }
+            Err(e) => warn!("Holochain reconnect failed: {:#}", e),
+        }
+    }
+
+    async fn call_once<I, O>(&self, fn_name: &'static str, payload: I) -> Result<O>
+    where
+        I: Serialize + Send,
+        O: DeserializeOwned,
+    {
+        let conn = self.conn.read().await;
+        let role = &self.hc.role_name;
+        let call = async {
+            match &*conn {
+                Connection::Ham(ham) => ham.call_zome(role, "transactor", fn_name, payload).await,
+                Connection::App(app) => app.call_zome(role, "transactor", fn_name, payload).await,
+                #[cfg(test)]
+                Connection::Scripted(conductor) => {
+                    conductor.call_zome(role, "transactor", fn_name, payload)
+                }
+            }
+        }
+        .instrument(info_span!("zome_call", fn_name = fn_name));
+        match tokio::time::timeout(self.hc.call_timeout, call).await {
+            Ok(result) => result.with_context(|| format!("{} zome call failed", fn_name)),
+            Err(_) => anyhow::bail!(
+                "{} zome call timed out after {:?}",
+                fn_name,
+                self.hc.call_timeout
+            ),
+        }
+    }
 
+    /// A read-only call, retried after reconnecting on transient errors.
+    async fn call<I, O>(&self, fn_name: &'static str, payload: I) -> Result<O>
+    where
+        I: Serialize + Clone + Send,
+        O: DeserializeOwned,
+    {
+        let mut delay = self.hc.retry_backoff;
+        let mut attempt = 1;
+        loop {
+            match self.call_once(fn_name, payload.clone()).await {
+                Err(e) if attempt < self.hc.retry_attempts && is_transient(&e) => {
+                    warn!(
+                        "{} failed (attempt {}/{}), reconnecting in {:?}: {:#}",
+                        fn_name, attempt, self.hc.retry_attempts, delay, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    self.reconnect().await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ZomeClient for HamZomeClient {
+    async fn get_global_definition(&self) -> Result<GlobalDefinitionExt> {
+        self.call("get_current_global_definition", ()).await
+    }
+
+    /// Not idempotent, so a failed create is only retried once the latest
+    /// table shows it did not land. If the check itself fails, or the
+    /// latest table already matches, the error is returned as-is.
+    async fn create_table(&self, table: ConversionTable) -> Result<ActionHash> {
+        let mut delay = self.hc.retry_backoff;
+        let mut attempt = 1;
+        loop {
+            let error = match self
+                .call_once("create_conversion_table", table.clone())
+                .await
+            {
+                Ok(action_hash) => return Ok(action_hash),
+                Err(e) if attempt < self.hc.retry_attempts && is_transient(&e) => e,
+                Err(e) => return Err(e),
+            };
+            warn!(
+                "create_conversion_table failed (attempt {}/{}), checking whether it landed: {:#}",
+                attempt, self.hc.retry_attempts, error
+            );
+            tokio::time::sleep(delay).await;
+            self.reconnect().await;
+            let latest: Option<ConversionTable> = self
+                .call("get_latest_conversion_table", ())
+                .await
+                .map_err(|check| {
+                    error.context(format!(
+                        "not retried: could not check whether it landed ({:#})",
+                        check
+                    ))
+                })?;
+            if latest.is_some_and(|latest| same_table(&latest, &table)) {
+                anyhow::bail!(
+                    "create_conversion_table reported an error but the latest published table \
+                     matches the one sent; it most likely landed, so it is not resubmitted"
+                );
+            }
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    async fn get_latest_table(&self) -> Result<Option<ConversionTable>> {
+        self.call("get_latest_conversion_table", ()).await
+    }
+}
+
+fn same_table(a: &ConversionTable, b: &ConversionTable) -> bool {
+    match (serde_json::to_value(a), serde_json::to_value(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+pub async fn fetch_global_definition(client: &dyn ZomeClient) -> Result<ActionHash> {
+    info!("[gd] Calling transactor/get_current_global_definition");
+    let gd = client.get_global_definition().await?;
     let action_hash: ActionHash = gd.id.into();
     info!("[gd] Got GlobalDefinition: {}", action_hash);
     Ok(action_hash)
 }
 
+/// Fetch the active GlobalDefinition, which must match `pinned` when
+/// `--global-definition` is set.
+pub async fn fetch_pinned_global_definition(
+    client: &dyn ZomeClient,
+    pinned: Option<&ActionHash>,
+) -> Result<ActionHash> {
+    let active = fetch_global_definition(client)
+        .await
+        .context("fetching current GlobalDefinition")?;
+    if let Some(pinned) = pinned {
+        if *pinned != active {
+            anyhow::bail!(
+                "--global-definition {} does not match the active GlobalDefinition {}; \
+                 not submitting",
+                pinned,
+                active
+            );
+        }
+    }
+    Ok(active)
+}
+
+pub async fn fetch_latest_conversion_table(
+    client: &dyn ZomeClient,
+) -> Result<Option<ConversionTable>> {
+    info!("[guard] Calling transactor/get_latest_conversion_table");
+    let table = client.get_latest_table().await?;
+    match &table {
+        Some(t) => info!(
+            "[guard] Previous ConversionTable has {} unit(s)",
+            t.data.len()
+        ),
+        None => info!("[guard] No previous ConversionTable"),
+    }
+    Ok(table)
+}
+
+/// [`fetch_latest_conversion_table`], falling back to the `last_table_file`
+/// snapshot when the conductor read fails. A conductor that answers with no
+/// table is believed over the snapshot.
+pub async fn fetch_previous_table(
+    client: &dyn ZomeClient,
+    snapshot: Option<&Path>,
+) -> Result<Option<ConversionTable>> {
+    let error = match fetch_latest_conversion_table(client).await {
+        Ok(table) => return Ok(table),
+        Err(e) => e,
+    };
+    let Some(path) = snapshot.filter(|p| p.exists()) else {
+        return Err(error);
+    };
+    match crate::output::read_json_file(path) {
+        Ok(table) => {
+            warn!(
+                "[guard] Previous ConversionTable unavailable ({:#}); comparing against {}",
+                error,
+                path.display()
+            );
+            Ok(Some(table))
+        }
+        Err(e) => {
+            warn!("[guard] last_table_file not usable: {:#}", e);
+            Err(error)
+        }
+    }
+}
+
 pub async fn submit_conversion_table(
-    hc: &HolochainConfig,
+    client: &dyn ZomeClient,
     table: ConversionTable,
 ) -> Result<ActionHash> {
+    check_msgpack_roundtrip(&table).context("pre-submit MessagePack self-check failed")?;
+
+    info!("[submit] Calling transactor/create_conversion_table");
+    let action_hash = client.create_table(table).await?;
+    info!("[submit] Created ConversionTable: {}", action_hash);
+    Ok(action_hash)
+}
+
+/// Read the latest table back after a submit and fail, listing the
+/// differing fields, unless it matches `sent`. Retries with backoff while
+/// the entry is not yet visible or an older table is still returned.
+pub async fn verify_submission(client: &dyn ZomeClient, sent: &ConversionTable) -> Result<()> {
+    let expected = serde_json::to_value(sent).context("inspecting submitted table")?;
+    let mut delay = VERIFY_BACKOFF;
+    let mut last_problem = String::new();
+    for attempt in 1..=VERIFY_ATTEMPTS {
+        info!(
+            "[verify] Reading back ConversionTable (attempt {})",
+            attempt
+        );
+        match client.get_latest_table().await {
+            Ok(Some(stored)) => {
+                let stored = serde_json::to_value(&stored).context("inspecting stored table")?;
+                let mut diffs = Vec::new();
+                diff_values("", &expected, &stored, &mut diffs);
+                if diffs.is_empty() {
+                    info!("[verify] Stored ConversionTable matches what was sent");
+                    return Ok(());
+                }
+                last_problem = format!(
+                    "stored ConversionTable differs from what was sent:\n  {}",
+                    diffs.join("\n  ")
+                );
+            }
+            Ok(None) => last_problem = "no ConversionTable found".to_string(),
+            Err(e) => last_problem = format!("{:#}", e),
+        }
+        if attempt < VERIFY_ATTEMPTS {
+            warn!(
+                "[verify] {}; retrying in {:?}",
+                last_problem.lines().next().unwrap_or_default(),
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+    anyhow::bail!(
+        "submission not verified after {} attempt(s): {}",
+        VERIFY_ATTEMPTS,
+        last_problem
+    )
+}
+
+/// Encode `table` the way the zome call does (named-field MessagePack), decode
+/// it back, and fail with the differing paths if anything changed. Catches bad
+/// ZFuel values locally instead of as an opaque wasm deserialization error.
+pub fn check_msgpack_roundtrip(table: &ConversionTable) -> Result<()> {
+    let bytes = rmp_serde::to_vec_named(table).context("encoding ConversionTable")?;
+    let decoded: ConversionTable =
+        rmp_serde::from_slice(&bytes).context("decoding ConversionTable")?;
+    compare_roundtrip(table, &decoded)?;
     info!(
-        "[submit] Connecting to Holochain (admin:{}, app:{}, app_id:{})",
-        hc.admin_port, hc.app_port, hc.app_id
+        "[submit] MessagePack self-check passed ({} bytes)",
+        bytes.len()
     );
+    Ok(())
+}
 
-    let ham = Ham::connect(hc.ham_config())
-        .await
-        .context("Failed to connect to Holochain")?;
+/// Fail with the differing paths unless `decoded` is `table` unchanged.
+fn compare_roundtrip(table: &ConversionTable, decoded: &ConversionTable) -> Result<()> {
+    let before = serde_json::to_value(table).context("inspecting encoded table")?;
+    let after = serde_json::to_value(decoded).context("inspecting decoded table")?;
+    let mut diffs = Vec::new();
+    diff_values("", &before, &after, &mut diffs);
+    if !diffs.is_empty() {
+        anyhow::bail!(
+            "ConversionTable changed across MessagePack round-trip:\n  {}",
+            diffs.join("\n  ")
+        );
+    }
+    Ok(())
+}
 
-    info!("[submit] Calling transactor/create_conversion_table");
-    let action_hash: ActionHash = ham
-        .call_zome(
-            &hc.role_name,
-            "transactor",
-            "create_conversion_table",
-            table,
-        )
-        .await
-        .context("create_conversion_table zome call failed")?;
+/// Collect `path: before != after` lines, e.g. `data.3.current_price`, plus
+/// keys `missing` from `after` or `unexpected` in it.
+fn diff_values(path: &str, before: &Value, after: &Value, out: &mut Vec<String>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, va) in a {
+                match b.get(key) {
+                    Some(vb) => diff_values(&child(key), va, vb, out),
+                    None => out.push(format!("{}: missing", child(key))),
+                }
+            }
+            for key in b.keys().filter(|k| !a.contains_key(*k)) {
+                out.push(format!("{}: unexpected", child(key)));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (va, vb)) in a.iter().zip(b).enumerate() {
+                diff_values(&child(&i.to_string()), va, vb, out);
+            }
+        }
+        _ if before != after => out.push(format!("{}: {} != {}", path, before, after)),
+        _ => {}
+    }
+}
 
-    info!("[submit] Created ConversionTable: {}", action_hash);
-    Ok(action_hash)
+#[cfg(test)]
+mod tests {
+    use super::mock::MockZomeClient;
+    use super::*;
+    use crate::types::{ConversionData, ForexRate, ReferenceUnit};
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use zfuel::fuel::ZFuel;
+
+    /// The conductor behind `Connection::Scripted`: answers each zome call
+    /// with the next scripted response and records the call.
+    #[derive(Default)]
+    pub(super) struct Conductor {
+        script: Mutex<VecDeque<Result<Value, String>>>,
+        /// `(role, zome, fn_name, payload)` per call.
+        calls: Mutex<Vec<(String, String, String, Value)>>,
+        reconnects: AtomicU32,
+    }
+
+    impl Conductor {
+        fn new(script: Vec<Result<Value, String>>) -> Arc<Self> {
+            Arc::new(Self {
+                script: Mutex::new(script.into()),
+                ..Default::default()
+            })
+        }
+
+        pub(super) fn call_zome<I: Serialize, O: DeserializeOwned>(
+            &self,
+            role: &str,
+            zome: &str,
+            fn_name: &str,
+            payload: I,
+        ) -> Result<O> {
+            let payload = serde_json::to_value(payload)?;
+            self.calls.lock().unwrap().push((
+                role.to_string(),
+                zome.to_string(),
+                fn_name.to_string(),
+                payload,
+            ));
+            match self.script.lock().unwrap().pop_front() {
+                Some(Ok(response)) => Ok(serde_json::from_value(response)?),
+                Some(Err(message)) => Err(anyhow::anyhow!(message)),
+                None => anyhow::bail!("unscripted call to {}", fn_name),
+            }
+        }
+
+        pub(super) fn reconnected(&self) {
+            self.reconnects.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn fn_names(&self) -> Vec<String> {
+            let calls = self.calls.lock().unwrap();
+            calls.iter().map(|(_, _, f, _)| f.clone()).collect()
+        }
+    }
+
+    fn client(conductor: &Arc<Conductor>) -> HamZomeClient {
+        HamZomeClient {
+            conn: RwLock::new(Connection::Scripted(conductor.clone())),
+            hc: HolochainConfig {
+                connection: HolochainConnection::Admin { admin_port: 0 },
+                app_port: 0,
+                app_id: "bridging-app".to_string(),
+                role_name: "alliance".to_string(),
+                connect_timeout: Duration::from_secs(1),
+                call_timeout: Duration::from_secs(5),
+                retry_attempts: 3,
+                retry_backoff: Duration::from_secs(1),
+            },
+        }
+    }
+
+    fn hash(byte: u8) -> ActionHash {
+        ActionHash::from_raw_36(vec![byte; 36])
+    }
+
+    fn table() -> ConversionTable {
+        let unit = |price: &str, volume: &str| ConversionData {
+            current_price: ZFuel::from_str(price).unwrap(),
+            volume: volume.to_string(),
+            net_change: "0.0125".to_string(),
+            sources: vec!["coingecko".to_string(), "geckoterminal".to_string()],
+            contract: Some("0x6c6ee5e31d828de241282b9606c8e98ea48526e2".to_string()),
+        };
+        ConversionTable {
+            reference_unit: ReferenceUnit {
+                symbol: "$".to_string(),
+                name: "US Dollar".to_string(),
+            },
+            data: HashMap::from([
+                ("3".to_string(), unit("0.00123", "1500.00")),
+                ("10".to_string(), unit("2500.5", "98000.00")),
+            ]),
+            forex_rates: vec![ForexRate {
+                symbol: "EUR".to_string(),
+                name: "Euro".to_string(),
+                rate: ZFuel::from_str("0.92").unwrap(),
+            }],
+            additional_data: Some(b"{}".to_vec()),
+            global_definition: ActionHash::from_raw_36(vec![0; 36]),
+        }
+    }
+
+    #[test]
+    fn msgpack_self_check_passes_on_a_well_formed_table() {
+        check_msgpack_roundtrip(&table()).unwrap();
+    }
+
+    #[test]
+    fn msgpack_self_check_names_each_corrupted_field() {
+        let sent = table();
+        let mut decoded = sent.clone();
+        let unit = decoded.data.get_mut("3").unwrap();
+        unit.volume = "0.00".to_string();
+        unit.contract = None;
+        decoded.forex_rates[0].symbol = "EUX".to_string();
+        decoded.additional_data = None;
+
+        let err = compare_roundtrip(&sent, &decoded).unwrap_err().to_string();
+        let lines: Vec<&str> = err.lines().map(str::trim).collect();
+        assert_eq!(
+            lines,
+            [
+                "ConversionTable changed across MessagePack round-trip:",
+                "additional_data: [123,125] != null",
+                "data.3.contract: \"0x6c6ee5e31d828de241282b9606c8e98ea48526e2\" != null",
+                "data.3.volume: \"1500.00\" != \"0.00\"",
+                "forex_rates.0.symbol: \"EUR\" != \"EUX\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_reports_missing_and_unexpected_keys() {
+        let before = serde_json::json!({ "data": { "3": { "volume": "1" } } });
+        let after = serde_json::json!({ "data": { "4": { "volume": "1" } } });
+        let mut diffs = Vec::new();
+        diff_values("", &before, &after, &mut diffs);
+        assert_eq!(diffs, ["data.3: missing", "data.4: unexpected"]);
+    }
+
+    #[tokio::test]
+    async fn calls_go_to_the_transactor_zome_of_the_configured_role() {
+        let sent = table();
+        let conductor = Conductor::new(vec![
+            Ok(serde_json::json!({ "id": holo_hash::ActionHashB64::from(hash(1)) })),
+            Ok(Value::Null),
+            Ok(serde_json::to_value(hash(7)).unwrap()),
+        ]);
+        let client = client(&conductor);
+
+        assert_eq!(fetch_global_definition(&client).await.unwrap(), hash(1));
+        assert!(fetch_latest_conversion_table(&client)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            submit_conversion_table(&client, sent.clone())
+                .await
+                .unwrap(),
+            hash(7)
+        );
+
+        let calls = conductor.calls.lock().unwrap();
+        let expected = [
+            ("get_current_global_definition", Value::Null),
+            ("get_latest_conversion_table", Value::Null),
+            (
+                "create_conversion_table",
+                serde_json::to_value(&sent).unwrap(),
+            ),
+        ];
+        assert_eq!(calls.len(), expected.len());
+        for ((role, zome, fn_name, payload), (want_fn, want_payload)) in calls.iter().zip(expected)
+        {
+            assert_eq!(role, "alliance");
+            assert_eq!(zome, "transactor");
+            assert_eq!(fn_name, want_fn);
+            assert_eq!(*payload, want_payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn pinned_global_definition_must_match_the_active_one() {
+        let client = MockZomeClient::new().with_global_definition(hash(1));
+
+        assert_eq!(
+            fetch_pinned_global_definition(&client, None).await.unwrap(),
+            hash(1)
+        );
+        assert_eq!(
+            fetch_pinned_global_definition(&client, Some(&hash(1)))
+                .await
+                .unwrap(),
+            hash(1)
+        );
+        let err = fetch_pinned_global_definition(&client, Some(&hash(2)))
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not match the active GlobalDefinition"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn global_definition_errors_name_the_call() {
+        let client = MockZomeClient::new().with_global_definition_error("no GlobalDefinition");
+
+        let err = fetch_pinned_global_definition(&client, None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", err),
+            "fetching current GlobalDefinition: get_current_global_definition zome call \
+             failed: no GlobalDefinition"
+        );
+    }
+
+    #[tokio::test]
+    async fn submitted_table_verifies_once_stored() {
+        let sent = table();
+        let client = MockZomeClient::new()
+            .with_create_result(Ok(hash(7)))
+            .tracking_created();
+
+        assert_eq!(
+            submit_conversion_table(&client, sent.clone())
+                .await
+                .unwrap(),
+            hash(7)
+        );
+        verify_submission(&client, &sent).await.unwrap();
+
+        let calls = client.calls();
+        let names: Vec<_> = calls.iter().map(|c| c.fn_name).collect();
+        assert_eq!(
+            names,
+            ["create_conversion_table", "get_latest_conversion_table"]
+        );
+        assert!(calls[0]
+            .table
+            .as_ref()
+            .is_some_and(|t| same_table(t, &sent)));
+    }
+
+    #[tokio::test]
+    async fn failed_create_is_returned_from_submit() {
+        let client = MockZomeClient::new().with_create_result(Err("guest error".to_string()));
+
+        let err = submit_conversion_table(&client, table()).await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "create_conversion_table zome call failed: guest error"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn verify_fails_when_the_stored_table_differs() {
+        let sent = table();
+        let mut stored = table();
+        stored.data.get_mut("3").unwrap().volume = "0.00".to_string();
+        let client = MockZomeClient::new().with_latest_table(Ok(Some(stored)));
+
+        let err = verify_submission(&client, &sent).await.unwrap_err();
+
+        let text = err.to_string();
+        assert!(
+            text.starts_with(
+                "submission not verified after 5 attempt(s): stored ConversionTable differs"
+            ),
+            "{}",
+            text
+        );
+        assert!(
+            text.contains("data.3.volume: \"1500.00\" != \"0.00\""),
+            "{}",
+            text
+        );
+        assert_eq!(client.calls().len(), VERIFY_ATTEMPTS as usize);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn verify_fails_when_no_table_is_found() {
+        let client = MockZomeClient::new().with_latest_table(Ok(None));
+
+        let err = verify_submission(&client, &table()).await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "submission not verified after 5 attempt(s): no ConversionTable found"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_create_failure_is_retried_after_reconnecting() {
+        let sent = table();
+        let mut older = table();
+        older.data.get_mut("3").unwrap().volume = "900.00".to_string();
+        let conductor = Conductor::new(vec![
+            Err("websocket connection closed".to_string()),
+            Ok(serde_json::to_value(&older).unwrap()),
+            Ok(serde_json::to_value(hash(7)).unwrap()),
+        ]);
+
+        let action_hash = client(&conductor).create_table(sent.clone()).await.unwrap();
+
+        assert_eq!(action_hash, hash(7));
+        assert_eq!(
+            conductor.fn_names(),
+            [
+                "create_conversion_table",
+                "get_latest_conversion_table",
+                "create_conversion_table"
+            ]
+        );
+        assert_eq!(conductor.reconnects.load(Ordering::SeqCst), 1);
+        let calls = conductor.calls.lock().unwrap();
+        assert_eq!(calls[2].3, serde_json::to_value(&sent).unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn create_that_landed_is_not_resubmitted() {
+        let sent = table();
+        let conductor = Conductor::new(vec![
+            Err("connection reset by peer".to_string()),
+            Ok(serde_json::to_value(&sent).unwrap()),
+        ]);
+
+        let err = client(&conductor).create_table(sent).await.unwrap_err();
+
+        assert!(
+            err.to_string().contains("it most likely landed"),
+            "{:#}",
+            err
+        );
+        assert_eq!(
+            conductor.fn_names(),
+            ["create_conversion_table", "get_latest_conversion_table"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn create_is_not_retried_when_the_landing_check_fails() {
+        let conductor = Conductor::new(vec![
+            Err("websocket connection closed".to_string()),
+            Err("guest error: unauthorized".to_string()),
+        ]);
+
+        let err = client(&conductor).create_table(table()).await.unwrap_err();
+
+        let text = format!("{:#}", err);
+        assert!(
+            text.starts_with("not retried: could not check whether it landed"),
+            "{}",
+            text
+        );
+        assert!(text.contains("websocket connection closed"), "{}", text);
+        assert_eq!(
+            conductor.fn_names(),
+            ["create_conversion_table", "get_latest_conversion_table"]
+        );
+    }
+
+    #[tokio::test]
+    async fn zome_errors_are_not_retried() {
+        let conductor = Conductor::new(vec![Err("guest error: invalid table".to_string())]);
+
+        let err = client(&conductor).create_table(table()).await.unwrap_err();
+
+        assert_eq!(
+            format!("{:#}", err),
+            "create_conversion_table zome call failed: guest error: invalid table"
+        );
+        assert_eq!(conductor.fn_names(), ["create_conversion_table"]);
+        assert_eq!(conductor.reconnects.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reads_give_up_after_retry_attempts() {
+        let conductor = Conductor::new(vec![
+            Err("broken pipe".to_string()),
+            Err("broken pipe".to_string()),
+            Err("broken pipe".to_string()),
+        ]);
+
+        let err = client(&conductor).get_latest_table().await.unwrap_err();
+
+        assert!(format!("{:#}", err).contains("broken pipe"));
+        assert_eq!(conductor.fn_names().len(), 3);
+        assert_eq!(conductor.reconnects.load(Ordering::SeqCst), 2);
+    }
+
+    fn snapshot(name: &str, table: Option<&ConversionTable>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pricing-oracle-last-table-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        if let Some(table) = table {
+            crate::output::write_json_file(table, &path).unwrap();
+        }
+        path
+    }
+
+    #[tokio::test]
+    async fn snapshot_stands_in_when_the_conductor_read_fails() {
+        let path = snapshot("fallback", Some(&table()));
+        let client = MockZomeClient::new().with_latest_table(Err("conductor down".to_string()));
+
+        let previous = fetch_previous_table(&client, Some(&path))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut keys: Vec<_> = previous.data.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["10", "3"]);
+        assert_eq!(previous.data["10"].volume, "98000.00");
+        assert_eq!(previous.forex_rates[0].symbol, "EUR");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn conductor_answer_wins_over_the_snapshot() {
+        let path = snapshot("conductor", Some(&table()));
+        let client = MockZomeClient::new().with_latest_table(Ok(None));
+
+        assert!(fetch_previous_table(&client, Some(&path))
+            .await
+            .unwrap()
+            .is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn read_failure_without_a_usable_snapshot_is_returned() {
+        let missing = snapshot("missing", None);
+        let corrupt = snapshot("corrupt", None);
+        std::fs::write(&corrupt, "not json").unwrap();
+
+        for path in [None, Some(missing.as_path()), Some(corrupt.as_path())] {
+            let client = MockZomeClient::new().with_latest_table(Err("conductor down".to_string()));
+            let err = fetch_previous_table(&client, path).await.unwrap_err();
+            assert_eq!(
+                format!("{:#}", err),
+                "get_latest_conversion_table zome call failed: conductor down"
+            );
+        }
+        let _ = std::fs::remove_file(&corrupt);
+    }
 }
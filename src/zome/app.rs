@@ -0,0 +1,144 @@
+//! App-only conductor connection, for hosts without access to the admin
+//! interface: an app websocket authenticated with a pre-issued token, with
+//! zome calls signed by pre-authorized signing credentials.
+
+use anyhow::{Context, Result};
+use holo_hash::AgentPubKey;
+use holochain_client::{
+    AppWebsocket, CellInfo, ClientAgentSigner, SigningCredentials, ZomeCallTarget,
+};
+use holochain_zome_types::prelude::{CapSecret, ExternIO};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// The signing credentials file: key material from the conductor's
+/// `authorize_signing_credentials`, hex-encoded.
+#[derive(Deserialize)]
+struct CredentialsFile {
+    /// Agent the cap grant was issued for, e.g. `uhCAk...`.
+    agent_key: String,
+    /// 32-byte ed25519 secret key.
+    signing_key: String,
+    /// 64-byte cap secret.
+    cap_secret: String,
+}
+
+pub(super) struct AppConnection {
+    ws: AppWebsocket,
+}
+
+impl AppConnection {
+    /// Connect to `app_port` with `token`, check the token belongs to
+    /// `app_id`, and register the credentials for the `role_name` cell.
+    pub async fn connect(
+        app_port: u16,
+        app_id: &str,
+        role_name: &str,
+        token: &[u8],
+        signing_key_path: &Path,
+    ) -> Result<Self> {
+        let credentials = load_credentials(signing_key_path)?;
+        let signer = Arc::new(ClientAgentSigner::default());
+        let ws = AppWebsocket::connect(
+            ("localhost", app_port),
+            token.to_vec(),
+            signer.clone(),
+            None,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("app websocket connection failed: {:?}", e))?;
+
+        let info = ws
+            .app_info()
+            .await
+            .map_err(|e| anyhow::anyhow!("app_info failed: {:?}", e))?
+            .context("the conductor returned no app info for HOLOCHAIN_APP_TOKEN")?;
+        if info.installed_app_id != app_id {
+            anyhow::bail!(
+                "HOLOCHAIN_APP_TOKEN is for app '{}', expected '{}'",
+                info.installed_app_id,
+                app_id
+            );
+        }
+        let cell_id = info
+            .cell_info
+            .get(role_name)
+            .and_then(|cells| {
+                cells.iter().find_map(|cell| match cell {
+                    CellInfo::Provisioned(cell) => Some(cell.cell_id.clone()),
+                    _ => None,
+                })
+            })
+            .with_context(|| {
+                format!(
+                    "app '{}' has no provisioned cell for role '{}'",
+                    app_id, role_name
+                )
+            })?;
+        signer.add_credentials(cell_id, credentials);
+        Ok(Self { ws })
+    }
+
+    pub async fn call_zome<I, O>(
+        &self,
+        role_name: &str,
+        zome_name: &str,
+        fn_name: &str,
+        payload: I,
+    ) -> Result<O>
+    where
+        I: Serialize,
+        O: DeserializeOwned,
+    {
+        let payload = ExternIO(rmp_serde::to_vec_named(&payload).context("encoding payload")?);
+        let response = self
+            .ws
+            .call_zome(
+                ZomeCallTarget::RoleName(role_name.to_string()),
+                zome_name.into(),
+                fn_name.into(),
+                payload,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        rmp_serde::from_slice(&response.0).context("decoding response")
+    }
+}
+
+fn load_credentials(path: &Path) -> Result<SigningCredentials> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("reading signing credentials {}", path.display()))?;
+    let file: CredentialsFile = serde_json::from_str(&contents)
+        .with_context(|| format!("parsing signing credentials {}", path.display()))?;
+
+    let signing_agent_key = AgentPubKey::try_from(file.agent_key.clone())
+        .map_err(|e| anyhow::anyhow!("invalid agent_key '{}': {:?}", file.agent_key, e))?;
+    let signing_key: [u8; 32] = decode_hex(&file.signing_key)
+        .context("invalid signing_key")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("signing_key must be 32 bytes"))?;
+    let cap_secret: [u8; 64] = decode_hex(&file.cap_secret)
+        .context("invalid cap_secret")?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("cap_secret must be 64 bytes"))?;
+
+    Ok(SigningCredentials {
+        signing_agent_key,
+        keypair: ed25519_dalek::SigningKey::from_bytes(&signing_key),
+        cap_secret: CapSecret::from(cap_secret),
+    })
+}
+
+/// Bytes from a hex string, with or without a `0x` prefix.
+pub(super) fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.trim().trim_start_matches("0x");
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("not an even-length hex string");
+    }
+    Ok((0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("checked hex digits"))
+        .collect())
+}
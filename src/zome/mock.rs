@@ -0,0 +1,106 @@
+//! In-memory `ZomeClient` for exercising the submit path without a
+//! conductor. Built for tests and with the `testing` feature.
+
+use super::ZomeClient;
+use crate::types::{ConversionTable, GlobalDefinitionExt};
+use anyhow::Result;
+use async_trait::async_trait;
+use holo_hash::ActionHash;
+use std::sync::Mutex;
+
+/// One call made through a `MockZomeClient`.
+#[derive(Debug, Clone)]
+pub struct RecordedCall {
+    /// Zome function name, e.g. `create_conversion_table`.
+    pub fn_name: &'static str,
+    /// The table passed to `create_conversion_table`; `None` for reads.
+    pub table: Option<ConversionTable>,
+}
+
+/// Answers each transactor call with a canned response and records every
+/// call. Unset responses fail with `no canned response for ...`.
+#[derive(Default)]
+pub struct MockZomeClient {
+    global_definition: Mutex<Option<Result<ActionHash, String>>>,
+    create: Mutex<Option<Result<ActionHash, String>>>,
+    latest: Mutex<Option<Result<Option<ConversionTable>, String>>>,
+    /// Tables created so far become the latest table when set.
+    track_created: bool,
+    calls: Mutex<Vec<RecordedCall>>,
+}
+
+impl MockZomeClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_global_definition(self, id: ActionHash) -> Self {
+        *self.global_definition.lock().unwrap() = Some(Ok(id));
+        self
+    }
+
+    pub fn with_create_result(self, result: Result<ActionHash, String>) -> Self {
+        *self.create.lock().unwrap() = Some(result);
+        self
+    }
+
+    pub fn with_latest_table(self, result: Result<Option<ConversionTable>, String>) -> Self {
+        *self.latest.lock().unwrap() = Some(result);
+        self
+    }
+
+    pub fn with_global_definition_error(self, message: &str) -> Self {
+        *self.global_definition.lock().unwrap() = Some(Err(message.to_string()));
+        self
+    }
+
+    /// Make a successful create the table `get_latest_table` returns, as a
+    /// conductor would.
+    pub fn tracking_created(mut self) -> Self {
+        self.track_created = true;
+        self
+    }
+
+    /// Every call made so far, in order.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, fn_name: &'static str, table: Option<ConversionTable>) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(RecordedCall { fn_name, table });
+    }
+}
+
+fn canned<T: Clone>(slot: &Mutex<Option<Result<T, String>>>, fn_name: &str) -> Result<T> {
+    match slot.lock().unwrap().as_ref() {
+        Some(Ok(value)) => Ok(value.clone()),
+        Some(Err(message)) => anyhow::bail!("{} zome call failed: {}", fn_name, message),
+        None => anyhow::bail!("no canned response for {}", fn_name),
+    }
+}
+
+#[async_trait]
+impl ZomeClient for MockZomeClient {
+    async fn get_global_definition(&self) -> Result<GlobalDefinitionExt> {
+        self.record("get_current_global_definition", None);
+        let id = canned(&self.global_definition, "get_current_global_definition")?;
+        Ok(GlobalDefinitionExt { id: id.into() })
+    }
+
+    async fn create_table(&self, table: ConversionTable) -> Result<ActionHash> {
+        self.record("create_conversion_table", Some(table.clone()));
+        let action_hash = canned(&self.create, "create_conversion_table")?;
+        if self.track_created {
+            *self.latest.lock().unwrap() = Some(Ok(Some(table)));
+        }
+        Ok(action_hash)
+    }
+
+    async fn get_latest_table(&self) -> Result<Option<ConversionTable>> {
+        self.record("get_latest_conversion_table", None);
+        canned(&self.latest, "get_latest_conversion_table")
+    }
+}
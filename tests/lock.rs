@@ -0,0 +1,94 @@
+//! Two overlapping invocations of the binary sharing one `--lock-file`:
+//! the second exits 4 at once while the first is still fetching.
+
+use pricing_oracle::lock::RunLock;
+use pricing_oracle::sources::BUILTIN_SOURCE_NAMES;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use wiremock::matchers::any;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "pricing-oracle-lock-{}-{}",
+        name,
+        std::process::id()
+    ))
+}
+
+/// Unit 1 (HOT) with every source sent to `server`.
+fn write_config(server: &MockServer) -> PathBuf {
+    let endpoints: String = BUILTIN_SOURCE_NAMES
+        .iter()
+        .filter(|name| **name != "chainlink")
+        .map(|name| format!("  \"{}\": \"{}\"\n", name, server.uri()))
+        .collect();
+    let yaml = format!(
+        r#"units:
+  - unit_index: 1
+    name: HOT
+    chain: ethereum
+    contract: "0x6c6ee5e31d828de241282b9606c8e98ea48526e2"
+min_sources: 1
+http_retries: 0
+source_endpoints:
+{}"#,
+        endpoints
+    );
+    let path = temp_path("config.yaml");
+    std::fs::write(&path, yaml).unwrap();
+    path
+}
+
+fn oracle(config: &Path, lock: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pricing-oracle"));
+    command
+        .arg("--config")
+        .arg(config)
+        .arg("--lock-file")
+        .arg(lock)
+        .arg("--skip-forex")
+        .stdout(Stdio::null());
+    command
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overlapping_run_exits_locked() {
+    // Slow enough that the first run is still fetching when the second starts.
+    let server = MockServer::start().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(503).set_delay(Duration::from_secs(3)))
+        .mount(&server)
+        .await;
+    let config = write_config(&server);
+    let lock = temp_path("run.lock");
+
+    let mut first = oracle(&config, &lock)
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // A request reaching the server means the first run is past the lock.
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while server.received_requests().await.unwrap().is_empty() {
+        assert!(Instant::now() < deadline, "first run never fetched");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let second = oracle(&config, &lock).output().unwrap();
+
+    assert_eq!(second.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&second.stderr);
+    assert!(stderr.contains("another run holds"), "{}", stderr);
+    assert!(
+        first.try_wait().unwrap().is_none(),
+        "first run already ended"
+    );
+
+    // Killed mid-run, the first leaves no stale lock behind.
+    first.kill().unwrap();
+    first.wait().unwrap();
+    assert!(RunLock::acquire(&lock, Duration::ZERO).unwrap().is_some());
+    let _ = std::fs::remove_file(&config);
+    let _ = std::fs::remove_file(&lock);
+}
@@ -0,0 +1,184 @@
+//! The full fetch → aggregate → table pipeline against a wiremock server,
+//! with every source pointed at it through `source_endpoints`.
+
+use pricing_oracle::config::Config;
+use pricing_oracle::forex::BUILTIN_FOREX_NAMES;
+use pricing_oracle::sources::BUILTIN_SOURCE_NAMES;
+use pricing_oracle::{run_pipeline, PipelineOutput};
+use serde_json::json;
+use std::path::PathBuf;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+
+/// A config for unit 1 (HOT) and EUR/JPY, with every source and forex
+/// provider sent to `server`. Keyed sources stay off unless their key is
+/// set in the environment, and then they hit the mock too. Chainlink has
+/// no base URL, and unit 1 has no feed for it to read.
+fn write_config(server: &MockServer, name: &str) -> PathBuf {
+    let endpoints: String = BUILTIN_SOURCE_NAMES
+        .iter()
+        .chain(BUILTIN_FOREX_NAMES)
+        .filter(|name| **name != "chainlink")
+        .map(|name| format!("  \"{}\": \"{}\"\n", name, server.uri()))
+        .collect();
+    let yaml = format!(
+        r#"units:
+  - unit_index: 1
+    name: HOT
+    chain: ethereum
+    contract: "0x6c6EE5e31d828De241282B9606C8e98Ea48526E2"
+forex:
+  symbols: [EUR, JPY]
+  use_twelve_data: false
+  use_coinapi: false
+  use_open_exchange_rates: false
+  use_currencyapi: false
+  use_alpha_vantage: false
+  use_frankfurter: true
+min_sources: 1
+http_retries: 0
+source_endpoints:
+{}"#,
+        endpoints
+    );
+    let path = std::env::temp_dir().join(format!(
+        "pricing-oracle-pipeline-{}-{}.yaml",
+        name,
+        std::process::id()
+    ));
+    std::fs::write(&path, yaml).unwrap();
+    path
+}
+
+async fn run(server: &MockServer, name: &str) -> PipelineOutput {
+    let path = write_config(server, name);
+    let cfg = Config::load(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    run_pipeline(cfg, Default::default()).await.unwrap()
+}
+
+async fn mount_geckoterminal(server: &MockServer, price: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v2/networks/eth/tokens/{}", HOT)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "attributes": {
+                "price_usd": price,
+                "volume_usd": { "h24": "150000" },
+                "symbol": "HOT",
+                "name": "Holo",
+            } }
+        })))
+        .mount(server)
+        .await;
+}
+
+async fn mount_dexscreener(server: &MockServer, response: ResponseTemplate) {
+    Mock::given(method("GET"))
+        .and(path(format!("/latest/dex/tokens/{}", HOT)))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+async fn mount_frankfurter(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/latest"))
+        .and(query_param("from", "USD"))
+        .and(query_param("to", "EUR,JPY"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "rates": { "EUR": 0.92, "JPY": 150.1 } })),
+        )
+        .mount(server)
+        .await;
+}
+
+fn dexscreener_pair(price: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "pairs": [{
+            "chainId": "ethereum",
+            "baseToken": { "address": HOT, "symbol": "HOT", "name": "Holo" },
+            "priceUsd": price,
+            "liquidity": { "usd": 500000.0 },
+            "volume": { "h24": 90000.0 },
+        }]
+    }))
+}
+
+#[tokio::test]
+async fn pipeline_prices_units_and_forex_from_the_mock_server() {
+    let server = MockServer::start().await;
+    mount_geckoterminal(&server, "0.00123").await;
+    mount_dexscreener(&server, dexscreener_pair("0.00124")).await;
+    mount_frankfurter(&server).await;
+
+    let output = run(&server, "ok").await;
+
+    assert_eq!(output.aggregated.len(), 1);
+    let hot = &output.aggregated[0];
+    assert!(hot.valid, "{:?}", hot.reason);
+    assert_eq!(hot.sources, ["geckoterminal", "dexscreener"]);
+    assert!((0.00123..=0.00124).contains(&hot.avg_price_usd));
+    assert!(
+        output.source_errors.is_empty(),
+        "{:?}",
+        output.source_errors
+    );
+
+    let rates: Vec<_> = output
+        .forex
+        .iter()
+        .map(|r| (r.symbol.as_str(), r.foreign_per_usd))
+        .collect();
+    assert_eq!(rates, [("EUR", 0.92), ("JPY", 150.1)]);
+
+    assert_eq!(output.table.data.keys().collect::<Vec<_>>(), ["1"]);
+    let entry = &output.table.data["1"];
+    assert_eq!(entry.sources, ["geckoterminal", "dexscreener"]);
+    assert_eq!(entry.contract.as_deref(), Some(HOT));
+    let symbols: Vec<_> = output
+        .table
+        .forex_rates
+        .iter()
+        .map(|r| r.symbol.as_str())
+        .collect();
+    assert_eq!(symbols, ["EUR", "JPY"]);
+}
+
+#[tokio::test]
+async fn failing_source_is_reported_and_the_others_still_price_the_unit() {
+    let server = MockServer::start().await;
+    mount_geckoterminal(&server, "0.00123").await;
+    mount_dexscreener(&server, ResponseTemplate::new(503)).await;
+    mount_frankfurter(&server).await;
+
+    let output = run(&server, "failing").await;
+
+    let hot = &output.aggregated[0];
+    assert!(hot.valid, "{:?}", hot.reason);
+    assert_eq!(hot.sources, ["geckoterminal"]);
+    assert_eq!(hot.avg_price_usd, 0.00123);
+    assert_eq!(output.source_errors.len(), 1);
+    let error = &output.source_errors[0];
+    assert_eq!(error.source, "dexscreener");
+    assert_eq!(error.subject, "unit 1");
+    assert!(error.error.contains("503"), "{}", error.error);
+}
+
+#[tokio::test]
+async fn sources_that_disagree_leave_the_unit_out_of_the_table() {
+    let server = MockServer::start().await;
+    mount_geckoterminal(&server, "0.00123").await;
+    mount_dexscreener(&server, dexscreener_pair("0.00150")).await;
+    mount_frankfurter(&server).await;
+
+    let output = run(&server, "disagree").await;
+
+    let hot = &output.aggregated[0];
+    assert!(!hot.valid);
+    assert!(output.table.data.is_empty());
+    assert_eq!(output.stats.units_invalid, 1);
+    assert_eq!(output.table.forex_rates.len(), 2);
+}
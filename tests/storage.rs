@@ -0,0 +1,187 @@
+//! The artifact uploader against a wiremock server standing in for an
+//! S3-compatible store (path-style `PUT /<bucket>/<key>`).
+
+use chrono::Utc;
+use pricing_oracle::config::{Config, StorageConfig};
+use pricing_oracle::sources::BUILTIN_SOURCE_NAMES;
+use pricing_oracle::storage::{SubmissionReceipt, Uploader};
+use pricing_oracle::{run_pipeline, OracleSettings, PipelineOutput};
+use serde_json::json;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+const HOT: &str = "0x6c6ee5e31d828de241282b9606c8e98ea48526e2";
+const ACCESS_KEY_ENV: &str = "PRICING_ORACLE_TEST_S3_ACCESS_KEY";
+const SECRET_KEY_ENV: &str = "PRICING_ORACLE_TEST_S3_SECRET_KEY";
+
+/// A run pricing HOT from a mocked GeckoTerminal; every other source is
+/// sent to the same server and finds nothing. No forex.
+async fn pipeline_output() -> PipelineOutput {
+    let server = &MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v2/networks/eth/tokens/{}", HOT)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "attributes": { "price_usd": "0.00123", "symbol": "HOT" } }
+        })))
+        .mount(server)
+        .await;
+    let endpoints: String = BUILTIN_SOURCE_NAMES
+        .iter()
+        .filter(|name| **name != "chainlink")
+        .map(|name| format!("  \"{}\": \"{}\"\n", name, server.uri()))
+        .collect();
+    let config: Config = serde_yaml::from_str(&format!(
+        r#"units:
+  - unit_index: 1
+    name: HOT
+    chain: ethereum
+    contract: "{}"
+min_sources: 1
+http_retries: 0
+source_endpoints:
+{}"#,
+        HOT, endpoints
+    ))
+    .unwrap();
+    let settings = OracleSettings {
+        skip_forex: true,
+        ..Default::default()
+    };
+    run_pipeline(config, settings).await.unwrap()
+}
+
+fn uploader(server: &MockServer, max_attempts: u32) -> Uploader {
+    std::env::set_var(ACCESS_KEY_ENV, "TESTKEY");
+    std::env::set_var(SECRET_KEY_ENV, "testsecret");
+    let config: StorageConfig = serde_yaml::from_str(&format!(
+        "endpoint: {}\nbucket: oracle-artifacts\nprefix: runs/\naccess_key_env: {}\nsecret_key_env: {}\nmax_attempts: {}",
+        server.uri(),
+        ACCESS_KEY_ENV,
+        SECRET_KEY_ENV,
+        max_attempts
+    ))
+    .unwrap();
+    Uploader::from_config(&config).unwrap()
+}
+
+/// Path of any artifact of a run: bucket, prefix, then `<start time>-<table hash>/`.
+const OBJECT: &str = r"^/oracle-artifacts/runs/\d{8}T\d{6}Z-[0-9a-f]{8}/";
+
+fn key(request: &Request) -> String {
+    request
+        .url
+        .path()
+        .trim_start_matches("/oracle-artifacts/")
+        .to_string()
+}
+
+#[tokio::test]
+async fn run_artifacts_are_put_under_a_timestamped_run_directory() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path_regex(OBJECT))
+        .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"abc\""))
+        .mount(&server)
+        .await;
+    let output = pipeline_output().await;
+    let receipt = SubmissionReceipt {
+        action_hash: "uhCkkTEST".to_string(),
+        submitted_at: Utc::now(),
+        ipfs_cid: Some("bafkreitest".to_string()),
+    };
+
+    let urls = uploader(&server, 1)
+        .upload_run(&output, Some(&receipt))
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let keys: Vec<String> = requests.iter().map(key).collect();
+    let run_dir = format!("runs/{}-", output.stats.started_at.format("%Y%m%dT%H%M%SZ"));
+    assert_eq!(keys.len(), 3);
+    assert!(keys.iter().all(|k| k.starts_with(&run_dir)), "{:?}", keys);
+    let names: Vec<&str> = keys.iter().map(|k| k.rsplit('/').next().unwrap()).collect();
+    assert_eq!(
+        names,
+        ["conversion_table.json", "report.json", "receipt.json"]
+    );
+    assert_eq!(
+        urls,
+        keys.iter()
+            .map(|k| format!("{}/oracle-artifacts/{}", server.uri(), k))
+            .collect::<Vec<_>>()
+    );
+
+    for request in &requests {
+        let auth = request.headers["authorization"].to_str().unwrap();
+        assert!(
+            auth.starts_with("AWS4-HMAC-SHA256 Credential=TESTKEY/"),
+            "{}",
+            auth
+        );
+        assert_eq!(request.headers["content-type"], "application/json");
+    }
+    let body = |name: &str| -> serde_json::Value {
+        let request = &requests[names.iter().position(|n| *n == name).unwrap()];
+        serde_json::from_slice(&request.body).unwrap()
+    };
+    assert_eq!(
+        body("conversion_table.json"),
+        serde_json::to_value(&output.table).unwrap()
+    );
+    assert_eq!(body("report.json")["ipfs_cid"], "bafkreitest");
+    assert_eq!(body("report.json")["stats"]["units_valid"], 1);
+    assert_eq!(body("receipt.json")["action_hash"], "uhCkkTEST");
+}
+
+#[tokio::test]
+async fn transient_store_error_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path_regex(OBJECT))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path_regex(OBJECT))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+    let output = pipeline_output().await;
+
+    let urls = uploader(&server, 3)
+        .upload_run(&output, None)
+        .await
+        .unwrap();
+
+    // Table and report; the table took two attempts.
+    assert_eq!(urls.len(), 2);
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn failed_upload_names_the_object() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .respond_with(ResponseTemplate::new(403).set_body_string(
+            "<Error><Code>AccessDenied</Code><Message>Access Denied</Message></Error>",
+        ))
+        .mount(&server)
+        .await;
+    let output = pipeline_output().await;
+
+    let error = uploader(&server, 1)
+        .upload_run(&output, None)
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(
+        error.starts_with("uploading s3://oracle-artifacts/runs/"),
+        "{}",
+        error
+    );
+    assert!(error.ends_with("/conversion_table.json"), "{}", error);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}